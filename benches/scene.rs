@@ -0,0 +1,143 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use glyphon::{Attrs, Buffer, Family, FontSystem, Metrics, Shaping};
+use wgpu::util::DeviceExt;
+use wgpu_test::{
+    rect::{build_geometry, Fill, Rect},
+    Camera, DrawList,
+};
+
+fn rects(count: usize) -> Vec<Rect> {
+    (0..count)
+        .map(|i| Rect {
+            position: [(i % 100) as f32 * 12.0, (i / 100) as f32 * 12.0],
+            size: [10.0, 10.0],
+            border_radius: [3.0; 4],
+            fill: Some(Fill::Solid {
+                color: [1.0, 0.0, 0.0, 1.0],
+            }),
+            ..Default::default()
+        })
+        .collect()
+}
+
+fn vertex_generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build_geometry");
+    for count in [100, 1_000, 10_000] {
+        let rects = rects(count);
+        group.bench_with_input(BenchmarkId::from_parameter(count), &rects, |b, rects| {
+            b.iter(|| build_geometry(black_box(rects), &Camera::default()))
+        });
+    }
+    group.finish();
+}
+
+fn draw_list(c: &mut Criterion) {
+    let mut group = c.benchmark_group("draw_list");
+    for count in [100, 1_000, 10_000] {
+        let rects = rects(count);
+        group.bench_with_input(BenchmarkId::new("push", count), &rects, |b, rects| {
+            b.iter(|| {
+                let mut draw_list = DrawList::new();
+                for rect in rects {
+                    draw_list.push_rect(*black_box(rect));
+                }
+                draw_list
+            })
+        });
+
+        let mut draw_list = DrawList::new();
+        draw_list.extend(rects);
+        // Moves a tenth of the rects, half to the front and half to the back.
+        group.bench_function(BenchmarkId::new("reorder", count), |b| {
+            b.iter(|| {
+                for index in (0..count).step_by(20) {
+                    draw_list.bring_to_front(black_box(index));
+                    draw_list.send_to_back(black_box(index + 10));
+                }
+            })
+        });
+        // What the renderer does with a changed draw list before writing the instance buffer.
+        group.bench_function(BenchmarkId::new("upload", count), |b| {
+            b.iter(|| build_geometry(&black_box(&draw_list).ordered_rects(), &Camera::default()))
+        });
+    }
+    group.finish();
+}
+
+fn text_shaping(c: &mut Criterion) {
+    let mut font_system = FontSystem::new();
+    let mut buffer = Buffer::new(&mut font_system, Metrics::new(30.0, 42.0));
+    buffer.set_size(&mut font_system, 800.0, 600.0);
+    let text = "The quick brown fox jumps over the lazy dog. ".repeat(20);
+
+    let mut group = c.benchmark_group("text_shaping");
+    group.bench_function("miss", |b| {
+        b.iter(|| {
+            buffer.set_text(
+                &mut font_system,
+                black_box(&text),
+                Attrs::new().family(Family::SansSerif),
+                Shaping::Advanced,
+            );
+            buffer.shape_until_scroll(&mut font_system);
+        })
+    });
+    group.bench_function("hit", |b| {
+        b.iter(|| buffer.shape_until_scroll(&mut font_system))
+    });
+    group.finish();
+}
+
+fn buffer_upload(c: &mut Criterion) {
+    let instance = wgpu::Instance::default();
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let Some(adapter) =
+        runtime.block_on(instance.request_adapter(&wgpu::RequestAdapterOptions::default()))
+    else {
+        eprintln!("No adapter available, skipping buffer upload benchmarks");
+        return;
+    };
+    let (device, queue) = runtime
+        .block_on(adapter.request_device(&wgpu::DeviceDescriptor::default(), None))
+        .unwrap();
+
+    let geometry = build_geometry(&rects(10_000), &Camera::default());
+    let contents: &[u8] = bytemuck::cast_slice(&geometry.instances);
+
+    let mut group = c.benchmark_group("buffer_upload");
+    group.bench_function("create_buffer_init", |b| {
+        b.iter(|| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Instance Buffer"),
+                contents,
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+            queue.submit(None);
+            device.poll(wgpu::Maintain::Wait);
+        })
+    });
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("Instance Buffer"),
+        size: contents.len() as wgpu::BufferAddress,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    });
+    group.bench_function("write_buffer", |b| {
+        b.iter(|| {
+            queue.write_buffer(&buffer, 0, contents);
+            queue.submit(None);
+            device.poll(wgpu::Maintain::Wait);
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    vertex_generation,
+    draw_list,
+    text_shaping,
+    buffer_upload
+);
+criterion_main!(benches);
@@ -0,0 +1,37 @@
+use wgpu_test::{
+    rect::{Fill, Rect},
+    run_with, RendererConfig, Scene,
+};
+
+const COLUMNS: usize = 100;
+const ROWS: usize = 100;
+
+#[tokio::main]
+async fn main() {
+    let rects: Vec<Rect> = (0..COLUMNS * ROWS)
+        .map(|i| {
+            let (column, row) = (i % COLUMNS, i / COLUMNS);
+            Rect {
+                position: [8.0 + column as f32 * 10.0, 8.0 + row as f32 * 10.0],
+                size: [8.0, 8.0],
+                border_radius: [2.0; 4],
+                fill: Some(Fill::Solid {
+                    color: [
+                        column as f32 / COLUMNS as f32,
+                        row as f32 / ROWS as f32,
+                        0.5,
+                        1.0,
+                    ],
+                }),
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    let scene = Scene {
+        rects,
+        text: "10 000 rects".to_string(),
+        ..Default::default()
+    };
+    run_with(RendererConfig::default(), scene).await.unwrap();
+}
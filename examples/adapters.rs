@@ -0,0 +1,28 @@
+use wgpu_test::{capabilities, AdapterConfig, Headless, RendererConfig, Scene};
+
+/// Lists the graphics adapters, then renders a frame offscreen on the one whose name contains
+/// the first argument, e.g. `cargo run --example adapters -- intel`.
+#[tokio::main]
+async fn main() {
+    for info in capabilities::adapters(wgpu::Backends::all()) {
+        println!("{} ({:?}, {:?})", info.name, info.backend, info.device_type);
+    }
+
+    let config = RendererConfig {
+        adapter: AdapterConfig {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            name: std::env::args().nth(1),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let scene = Scene {
+        text: "Rendered offscreen".to_string(),
+        ..Default::default()
+    };
+    let mut renderer = Headless::new(config, scene, [320, 120])
+        .await
+        .expect("setting up the renderer");
+    let screenshot = renderer.render().expect("rendering offscreen");
+    println!("Rendered {}x{}", screenshot.width, screenshot.height);
+}
@@ -0,0 +1,65 @@
+use wgpu_test::{
+    geo::{GeoLayer, Projection, Region},
+    heatmap::{Colormap, ValueRange},
+    run_with, RendererConfig, Scene,
+};
+
+/// A lon/lat rectangle, with an optional hole in the middle.
+fn block(west: f64, south: f64, east: f64, north: f64, hole: bool) -> Vec<Vec<[f64; 2]>> {
+    let mut rings = vec![vec![
+        [west, south],
+        [east, south],
+        [east, north],
+        [west, north],
+        [west, south],
+    ]];
+    if hole {
+        let (dx, dy) = ((east - west) / 4.0, (north - south) / 4.0);
+        rings.push(vec![
+            [west + dx, south + dy],
+            [west + dx, north - dy],
+            [east - dx, north - dy],
+            [east - dx, south + dy],
+        ]);
+    }
+    rings
+}
+
+#[tokio::main]
+async fn main() {
+    let regions = (0..24)
+        .map(|i| {
+            let (column, row) = ((i % 6) as f64, (i / 6) as f64);
+            let west = -30.0 + column * 10.0;
+            let south = 30.0 + row * 8.0;
+            Region {
+                name: format!("Region {}", i + 1),
+                value: if i == 7 {
+                    f32::NAN
+                } else {
+                    (i * 37 % 24) as f32
+                },
+                polygons: vec![block(west, south, west + 10.0, south + 8.0, i % 5 == 0)],
+            }
+        })
+        .collect();
+
+    let layer = GeoLayer {
+        regions,
+        projection: Projection::Mercator,
+        origin: [400.0, 700.0],
+        scale: 10.0,
+        colormap: Colormap::Viridis,
+        range: ValueRange::Auto,
+        missing_color: [0.3, 0.3, 0.3, 1.0],
+        outline_color: [1.0, 1.0, 1.0, 0.8],
+        outline_width: 1.0,
+    };
+
+    let scene = Scene {
+        geo: vec![layer],
+        text: "Hover a region to log its value".to_string(),
+        ..Default::default()
+    };
+    run_with(RendererConfig::default(), scene).await.unwrap();
+}
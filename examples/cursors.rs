@@ -0,0 +1,50 @@
+use wgpu_test::{
+    rect::{Fill, Rect},
+    AppBuilder, Cursor, Scene,
+};
+
+const CURSORS: [Cursor; 8] = [
+    Cursor::Pointer,
+    Cursor::Text,
+    Cursor::Crosshair,
+    Cursor::Grab,
+    Cursor::NotAllowed,
+    Cursor::ResizeHorizontal,
+    Cursor::ResizeNwse,
+    Cursor::Hidden,
+];
+
+/// A row of buttons, each tagged with the cursor shown while hovering it.
+#[tokio::main]
+async fn main() {
+    let rects = (0..CURSORS.len())
+        .map(|i| Rect {
+            position: [70.0 + i as f32 * 90.0, 120.0],
+            size: [76.0, 76.0],
+            border_radius: [10.0; 4],
+            fill: Some(Fill::Solid {
+                color: [0.25, 0.45 + i as f32 * 0.05, 0.8, 1.0],
+            }),
+            ..Default::default()
+        })
+        .collect();
+    let scene = Scene {
+        rects,
+        tags: (0..CURSORS.len()).map(|i| (i, i as u64)).collect(),
+        text: "Hover the buttons".to_string(),
+        ..Default::default()
+    };
+    AppBuilder::new()
+        .with_title("Cursors")
+        .with_inner_size(760.0, 240.0)
+        .with_scene(scene)
+        .run_with_frame(|frame| {
+            let cursor = frame
+                .hovered_rect
+                .and_then(|i| frame.draw_list.tag(i))
+                .map_or(Cursor::Default, |tag| CURSORS[tag as usize]);
+            frame.set_cursor(cursor);
+        })
+        .await
+        .unwrap();
+}
@@ -0,0 +1,80 @@
+use std::{
+    f64::consts::TAU,
+    time::{Duration, Instant},
+};
+
+use wgpu_test::{
+    custom::{CustomDrawable, CustomGeometry},
+    line::Polyline,
+    path::{Path, Shape},
+    AppBuilder,
+};
+
+/// A rose curve sampled finely enough to be slow to build, standing in for a plugin's
+/// geometry. Its petal count is its version, so it's built again only when that changes.
+struct Rose {
+    petals: u32,
+}
+
+impl CustomDrawable for Rose {
+    fn cache_key(&self) -> u64 {
+        self.petals as u64
+    }
+
+    fn build(&self) -> CustomGeometry {
+        let k = self.petals as f64;
+        let points: Vec<[f64; 2]> = (0..200_000)
+            .map(|i| {
+                let angle = i as f64 / 200_000.0 * TAU;
+                let radius = 250.0 * (k * angle).cos();
+                [400.0 + radius * angle.cos(), 300.0 + radius * angle.sin()]
+            })
+            .collect();
+        let disc = (0..64).fold(Path::new(), |path, i| {
+            let angle = i as f64 / 64.0 * TAU;
+            let point = [400.0 + 40.0 * angle.cos(), 300.0 + 40.0 * angle.sin()];
+            if i == 0 {
+                path.move_to(point)
+            } else {
+                path.line_to(point)
+            }
+        });
+        CustomGeometry {
+            shapes: vec![Shape {
+                path: disc.close(),
+                fill: Some([0.95, 0.8, 0.3, 1.0]),
+                ..Default::default()
+            }],
+            lines: vec![Polyline {
+                points,
+                width: 1.5,
+                color: [0.85, 0.3, 0.45, 1.0],
+                closed: true,
+                ..Default::default()
+            }],
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let mut uploads = 0;
+    AppBuilder::new()
+        .with_title("Custom drawable")
+        .with_inner_size(800.0, 600.0)
+        .run_with_frame(move |frame| {
+            // Drawn every frame, but only built when the petal count steps every two seconds.
+            let rose = Rose {
+                petals: 2 + (frame.time / 2.0) as u32 % 6,
+            };
+            frame.draw_custom(0, &rose);
+            if frame.stats.custom_uploads != uploads {
+                uploads = frame.stats.custom_uploads;
+                println!("Built the rose {} times", uploads);
+            }
+            let next = Duration::from_secs_f64(2.0 - frame.time % 2.0);
+            frame.redraw_at(Instant::now() + next);
+        })
+        .await
+        .unwrap();
+}
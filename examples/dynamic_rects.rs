@@ -0,0 +1,72 @@
+use wgpu_test::{
+    quality::{GovernorConfig, Quality},
+    rect::{Fill, Rect},
+    run_with_frame, CachedLayer, Layer, RendererConfig, Scene,
+};
+
+#[tokio::main]
+async fn main() {
+    let scene = Scene {
+        text: "Rects rebuilt every frame".to_string(),
+        ..Default::default()
+    };
+    let config = RendererConfig {
+        quality_governor: Some(GovernorConfig::default()),
+        ..Default::default()
+    };
+    run_with_frame(config, scene, move |frame| {
+        let time = frame.time as f32;
+        let draw_list = &mut *frame.draw_list;
+        draw_list.clear();
+        // A grid of dots that never changes, drawn once into a texture instead of every frame.
+        if draw_list.cached_layer(0).is_none() {
+            draw_list.set_cached_layer(0, dot_grid());
+        }
+        // The count changes over time, so the buffers have to grow and shrink with it. Fewer
+        // dots are drawn while the GPU can't keep up.
+        let detail = match frame.quality {
+            Quality::High => 1.0,
+            Quality::Medium => 0.5,
+            Quality::Low => 0.25,
+        };
+        let count = 20 + ((time * 0.5).sin().abs() * 200.0 * detail) as usize;
+        for i in 0..count {
+            let angle = time + i as f32 * 0.1;
+            let radius = 50.0 + i as f32 * 1.5;
+            draw_list.push_rect(Rect {
+                position: [400.0 + angle.cos() * radius, 300.0 + angle.sin() * radius],
+                size: [12.0, 12.0],
+                border_radius: [6.0; 4],
+                fill: Some(Fill::Solid {
+                    color: [i as f32 / count as f32, 0.5, 1.0, 1.0],
+                }),
+                ..Default::default()
+            });
+        }
+    })
+    .await
+    .unwrap();
+}
+
+fn dot_grid() -> CachedLayer {
+    let mut rects = Vec::new();
+    for x in 0..80 {
+        for y in 0..60 {
+            rects.push(Rect {
+                position: [x as f32 * 10.0 + 5.0, y as f32 * 10.0 + 5.0],
+                size: [2.0, 2.0],
+                border_radius: [1.0; 4],
+                fill: Some(Fill::Solid {
+                    color: [0.3, 0.3, 0.35, 1.0],
+                }),
+                ..Default::default()
+            });
+        }
+    }
+    CachedLayer {
+        position: [400.0, 300.0],
+        size: [800.0, 600.0],
+        layer: Layer::Background,
+        rects,
+    }
+}
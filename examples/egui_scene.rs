@@ -0,0 +1,42 @@
+use std::{cell::Cell, rc::Rc};
+
+use wgpu_test::{
+    rect::{Fill, Rect},
+    AppBuilder,
+};
+
+/// egui hosting the scene: a side panel of egui widgets sets the size of a rect drawn in the
+/// scene, which the central panel shows as an image.
+#[tokio::main]
+async fn main() {
+    let size = Rc::new(Cell::new(160.0));
+    let slider_size = size.clone();
+    AppBuilder::new()
+        .with_title("egui scene")
+        .with_inner_size(800.0, 600.0)
+        .with_egui_scene(move |ctx, scene| {
+            egui::SidePanel::left("controls").show(ctx, |ui| {
+                let mut value = slider_size.get();
+                ui.add(egui::Slider::new(&mut value, 20.0..=400.0).text("size"));
+                slider_size.set(value);
+            });
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui.image(scene.id, ui.available_size().min(scene.size));
+            });
+        })
+        .run_with_frame(move |frame| {
+            let side = size.get();
+            frame.draw_list.clear();
+            frame.draw_list.push_rect(Rect {
+                position: [400.0, 300.0],
+                size: [side, side],
+                border_radius: [16.0; 4],
+                fill: Some(Fill::Solid {
+                    color: [0.3, 0.6, 0.9, 1.0],
+                }),
+                ..Default::default()
+            });
+        })
+        .await
+        .unwrap();
+}
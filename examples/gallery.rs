@@ -0,0 +1,72 @@
+use wgpu_test::{
+    image::Image,
+    rect::{Fill, Rect},
+    AppBuilder, Scene,
+};
+
+const COLUMNS: u32 = 8;
+const ROWS: u32 = 6;
+const THUMBNAIL: u32 = 512;
+
+#[tokio::main]
+async fn main() {
+    let scene = Scene {
+        text: "A page of images arriving at once".to_string(),
+        ..Default::default()
+    };
+    let mut added = false;
+    let app = AppBuilder::new()
+        .with_title("Gallery")
+        .with_inner_size(800.0, 620.0)
+        .with_background([0.05, 0.05, 0.06, 1.0])
+        .with_scene(scene);
+    app.run_with_frame(move |frame| {
+        // 48 images of 1 MiB each, uploaded over a dozen frames instead of all in the first.
+        if !added {
+            for id in 0..(COLUMNS * ROWS) as u64 {
+                frame.add_image(id, thumbnail(id));
+            }
+            added = true;
+        }
+        let draw_list = &mut *frame.draw_list;
+        draw_list.clear();
+        for row in 0..ROWS {
+            for column in 0..COLUMNS {
+                draw_list.push_rect(Rect {
+                    position: [column as f32 * 100.0 + 50.0, row as f32 * 100.0 + 60.0],
+                    size: [90.0, 90.0],
+                    border_radius: [6.0; 4],
+                    fill: Some(Fill::Image {
+                        id: (row * COLUMNS + column) as u64,
+                        tint: [1.0; 4],
+                    }),
+                    ..Default::default()
+                });
+            }
+        }
+    })
+    .await
+    .unwrap();
+}
+
+/// Diagonal stripes in a color of their own.
+fn thumbnail(id: u64) -> Image {
+    let hue = id as f32 / (COLUMNS * ROWS) as f32;
+    let mut rgba = Vec::with_capacity((THUMBNAIL * THUMBNAIL * 4) as usize);
+    for y in 0..THUMBNAIL {
+        for x in 0..THUMBNAIL {
+            let stripe = ((x + y) / 32) % 2 == 0;
+            let shade = if stripe { 255.0 } else { 160.0 };
+            rgba.extend_from_slice(&[
+                (shade * hue) as u8,
+                (shade * (1.0 - hue)) as u8,
+                shade as u8,
+                255,
+            ]);
+        }
+    }
+    Image {
+        size: [THUMBNAIL, THUMBNAIL],
+        rgba,
+    }
+}
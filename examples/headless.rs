@@ -0,0 +1,39 @@
+use wgpu_test::{
+    rect::{Fill, Rect},
+    Headless, RendererConfig, Scene,
+};
+
+/// Renders a few rects without opening a window and saves them as `headless.png`, then
+/// renders again after moving one.
+#[tokio::main]
+async fn main() {
+    let rects = (0..5)
+        .map(|i| Rect {
+            position: [80.0 + i as f32 * 120.0, 120.0],
+            size: [100.0, 100.0],
+            border_radius: [16.0; 4],
+            fill: Some(Fill::Solid {
+                color: [0.2 * i as f32, 0.5, 1.0 - 0.2 * i as f32, 1.0],
+            }),
+            ..Default::default()
+        })
+        .collect();
+    let scene = Scene {
+        rects,
+        text: "Rendered offscreen".to_string(),
+        ..Default::default()
+    };
+
+    let mut renderer = Headless::new(RendererConfig::default(), scene, [640, 240])
+        .await
+        .expect("setting up the renderer");
+    let screenshot = renderer.render().expect("rendering offscreen");
+    screenshot.save_png("headless.png").unwrap();
+
+    let mut moved = renderer.draw_list().rects()[0];
+    moved.position[1] += 40.0;
+    renderer.draw_list().set_rect(0, moved);
+    let screenshot = renderer.render().expect("rendering offscreen");
+    screenshot.save_png("headless_moved.png").unwrap();
+    println!("Rendered {} frames", renderer.stats().frames);
+}
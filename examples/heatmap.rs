@@ -0,0 +1,57 @@
+use wgpu_test::{
+    heatmap::{Colormap, Heatmap, ValueRange},
+    run_with, RendererConfig, Scene,
+};
+
+const COLUMNS: u32 = 64;
+const ROWS: u32 = 48;
+
+fn field(x: f32, y: f32) -> f32 {
+    (x * 6.0).sin() * (y * 4.0).cos() + 0.5 * ((x - 0.5).powi(2) + (y - 0.5).powi(2)).sqrt()
+}
+
+#[tokio::main]
+async fn main() {
+    let values: Vec<f32> = (0..COLUMNS * ROWS)
+        .map(|i| {
+            let (x, y) = (i % COLUMNS, i / COLUMNS);
+            field(x as f32 / COLUMNS as f32, y as f32 / ROWS as f32)
+        })
+        .collect();
+
+    let heatmap = Heatmap {
+        position: [220.0, 260.0],
+        size: [320.0, 240.0],
+        columns: COLUMNS,
+        rows: ROWS,
+        values,
+        range: ValueRange::Auto,
+        colormap: Colormap::Viridis,
+        smooth: true,
+        z_index: 0.0,
+    };
+    let magma = Heatmap {
+        position: [580.0, 260.0],
+        colormap: Colormap::Magma,
+        range: ValueRange::Fixed {
+            min: -0.5,
+            max: 0.5,
+        },
+        smooth: false,
+        ..heatmap.clone()
+    };
+
+    let lines = [-0.5, 0.0, 0.5]
+        .into_iter()
+        .flat_map(|level| heatmap.isolines(level))
+        .map(|line| line.polyline(1.5, [1.0, 1.0, 1.0, 0.8]))
+        .collect();
+
+    let scene = Scene {
+        lines,
+        heatmaps: vec![heatmap, magma],
+        text: "Viridis, smooth / Magma, fixed range".to_string(),
+        ..Default::default()
+    };
+    run_with(RendererConfig::default(), scene).await.unwrap();
+}
@@ -0,0 +1,57 @@
+use wgpu_test::{
+    rect::{Fill, GradientStop, GradientStops, Rect},
+    AppBuilder, Layer, Scene,
+};
+
+#[tokio::main]
+async fn main() {
+    // A faint paper grain behind everything, and a stone panel over it.
+    let paper = Fill::Noise {
+        scale: 6.0,
+        octaves: 2,
+        offset: [0.0, 0.0],
+        stops: GradientStops::new(&[
+            stop(0.0, [0.86, 0.84, 0.8, 1.0]),
+            stop(1.0, [0.93, 0.92, 0.89, 1.0]),
+        ]),
+    };
+    let stone = Fill::Noise {
+        scale: 120.0,
+        octaves: 6,
+        offset: [0.0, 0.0],
+        stops: GradientStops::new(&[
+            stop(0.2, [0.25, 0.26, 0.3, 1.0]),
+            stop(0.55, [0.45, 0.45, 0.48, 1.0]),
+            stop(0.8, [0.62, 0.6, 0.58, 1.0]),
+        ]),
+    };
+    let scene = Scene {
+        rects: vec![
+            rect([400.0, 300.0], [800.0, 600.0], 0.0, paper),
+            rect([400.0, 300.0], [420.0, 280.0], 28.0, stone),
+        ],
+        layers: vec![(0, Layer::Background)],
+        ..Default::default()
+    };
+    AppBuilder::new()
+        .with_title("Noise")
+        .with_inner_size(800.0, 600.0)
+        .with_scene(scene)
+        .run()
+        .await
+        .unwrap();
+}
+
+fn stop(offset: f32, color: [f32; 4]) -> GradientStop {
+    GradientStop { offset, color }
+}
+
+fn rect(position: [f32; 2], size: [f32; 2], radius: f32, fill: Fill) -> Rect {
+    Rect {
+        position,
+        size,
+        border_radius: [radius; 4],
+        fill: Some(fill),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,78 @@
+use std::{
+    cell::Cell,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use wgpu_test::{
+    rect::{Fill, Rect, Stroke},
+    AppBuilder, Scene, WindowSet,
+};
+
+const SWATCHES: [[f32; 4]; 6] = [
+    [0.9, 0.3, 0.3, 1.0],
+    [0.95, 0.6, 0.2, 1.0],
+    [0.9, 0.85, 0.3, 1.0],
+    [0.35, 0.8, 0.4, 1.0],
+    [0.3, 0.55, 0.95, 1.0],
+    [0.6, 0.4, 0.9, 1.0],
+];
+
+/// A main window and a tool palette, drawn with one device. The palette steps through its
+/// swatches every second, and the main window paints with the one it's on.
+#[tokio::main]
+async fn main() {
+    let chosen = Rc::new(Cell::new(0));
+    let palette_choice = chosen.clone();
+    let main = AppBuilder::new()
+        .with_title("Canvas")
+        .with_inner_size(640.0, 480.0)
+        .with_scene(Scene {
+            text: "Painting with the palette's color".to_string(),
+            ..Default::default()
+        });
+    let palette = AppBuilder::new()
+        .with_title("Palette")
+        .with_inner_size(120.0, 420.0)
+        .with_resizable(false);
+    WindowSet::new()
+        .with_window(main, move |frame| {
+            let draw_list = &mut *frame.draw_list;
+            draw_list.clear();
+            draw_list.push_rect(swatch(
+                [120.0, 120.0],
+                [400.0, 280.0],
+                SWATCHES[chosen.get()],
+            ));
+        })
+        .with_window(palette, move |frame| {
+            palette_choice.set(frame.time as usize % SWATCHES.len());
+            let draw_list = &mut *frame.draw_list;
+            draw_list.clear();
+            for (i, color) in SWATCHES.into_iter().enumerate() {
+                let mut rect = swatch([20.0, 20.0 + i as f32 * 65.0], [80.0, 55.0], color);
+                if i == palette_choice.get() {
+                    rect.stroke = Some(Stroke {
+                        width: 3.0,
+                        color: [1.0; 3],
+                    });
+                }
+                draw_list.push_rect(rect);
+            }
+            let next = Duration::from_secs_f64(1.0 - frame.time.fract());
+            frame.redraw_at(Instant::now() + next);
+        })
+        .run()
+        .await
+        .unwrap();
+}
+
+fn swatch(position: [f32; 2], size: [f32; 2], color: [f32; 4]) -> Rect {
+    Rect {
+        position,
+        size,
+        border_radius: [8.0; 4],
+        fill: Some(Fill::Solid { color }),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,85 @@
+use std::f32::consts::FRAC_PI_4;
+
+use wgpu_test::{
+    image::Image,
+    rect::{Fill, Rect},
+    AppBuilder, Scene,
+};
+
+const STRIPES: u64 = 0;
+const CHECKER: u64 = 1;
+
+#[tokio::main]
+async fn main() {
+    let scene = Scene {
+        images: vec![(STRIPES, stripes()), (CHECKER, checker())],
+        rects: vec![
+            // Hatching: horizontal stripes turned by 45 degrees.
+            panel(
+                [180.0, 200.0],
+                Fill::Pattern {
+                    id: STRIPES,
+                    tile_size: [12.0, 12.0],
+                    offset: [0.0, 0.0],
+                    rotation: FRAC_PI_4,
+                    tint: [0.9, 0.4, 0.3, 1.0],
+                },
+            ),
+            // A textured panel, its checks shifted half a tile.
+            panel(
+                [460.0, 200.0],
+                Fill::Pattern {
+                    id: CHECKER,
+                    tile_size: [40.0, 40.0],
+                    offset: [20.0, 20.0],
+                    rotation: 0.0,
+                    tint: [0.4, 0.6, 0.9, 1.0],
+                },
+            ),
+        ],
+        text: "Pattern fills".to_string(),
+        ..Default::default()
+    };
+    AppBuilder::new()
+        .with_title("Patterns")
+        .with_inner_size(640.0, 400.0)
+        .with_background([0.08, 0.08, 0.1, 1.0])
+        .with_scene(scene)
+        .run()
+        .await
+        .unwrap();
+}
+
+fn panel(position: [f32; 2], fill: Fill) -> Rect {
+    Rect {
+        position,
+        size: [240.0, 240.0],
+        border_radius: [40.0, 12.0, 40.0, 12.0],
+        fill: Some(fill),
+        ..Default::default()
+    }
+}
+
+/// A white band over a transparent one, tiled into stripes.
+fn stripes() -> Image {
+    let rgba = (0..8)
+        .flat_map(|y| {
+            let alpha = if y < 3 { 255 } else { 0 };
+            [[255, 255, 255, alpha]; 8]
+        })
+        .flatten()
+        .collect();
+    Image { size: [8, 8], rgba }
+}
+
+/// Two light and two dark squares.
+fn checker() -> Image {
+    let rgba = (0..16)
+        .flat_map(|y| (0..16).map(move |x| if (x < 8) == (y < 8) { 255 } else { 140 }))
+        .flat_map(|shade| [shade, shade, shade, 255])
+        .collect();
+    Image {
+        size: [16, 16],
+        rgba,
+    }
+}
@@ -0,0 +1,99 @@
+use std::{
+    f64::consts::TAU,
+    time::{Duration, Instant},
+};
+
+use wgpu_test::{
+    custom::{CustomDrawable, CustomGeometry},
+    line::{LineCap, LineJoin, Polyline},
+    pen::{PenSample, PenStroke},
+    AppBuilder,
+};
+
+/// How often the replayed stylus reports its position, in hertz.
+const SAMPLE_RATE: f64 = 240.0;
+/// Seconds to trace the figure eight once, after which the stroke starts over.
+const LOOP: f64 = 4.0;
+
+/// Where the replayed stylus is `t` seconds into a loop.
+fn pen_position(t: f64) -> [f64; 2] {
+    let angle = t / LOOP * TAU;
+    [
+        400.0 + 250.0 * angle.sin(),
+        300.0 + 150.0 * (2.0 * angle).sin(),
+    ]
+}
+
+/// The confirmed stroke in white and its predicted tail in orange. `samples` counts every
+/// sample pushed since the start, so it's built again for each new one.
+struct Stroke<'a> {
+    stroke: &'a PenStroke,
+    samples: u64,
+}
+
+impl CustomDrawable for Stroke<'_> {
+    fn cache_key(&self) -> u64 {
+        self.samples
+    }
+
+    fn build(&self) -> CustomGeometry {
+        let line = |points: Vec<[f64; 2]>, color| Polyline {
+            points,
+            width: 4.0,
+            color,
+            cap: LineCap::Round,
+            join: LineJoin::Round,
+            closed: false,
+        };
+        let confirmed = self.stroke.confirmed();
+        let tail = confirmed.last().into_iter().chain(self.stroke.predicted());
+        CustomGeometry {
+            shapes: vec![],
+            lines: vec![
+                line(confirmed.to_vec(), [0.95, 0.95, 0.95, 1.0]),
+                line(tail.copied().collect(), [1.0, 0.55, 0.1, 1.0]),
+            ],
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let start = Instant::now();
+    let max_prediction = Duration::from_millis(50);
+    let mut stroke = PenStroke::new(max_prediction);
+    let mut samples = 0;
+    AppBuilder::new()
+        .with_title("Pen prediction")
+        .with_inner_size(800.0, 600.0)
+        .run_with_frame(move |frame| {
+            // Push the samples the stylus would have reported by the time this frame is shown,
+            // starting a new stroke with each loop.
+            let due = (frame.time * SAMPLE_RATE) as u64;
+            while samples <= due {
+                let time = samples as f64 / SAMPLE_RATE;
+                if samples % (LOOP * SAMPLE_RATE) as u64 == 0 {
+                    stroke = PenStroke::new(max_prediction);
+                }
+                stroke.push(PenSample {
+                    position: pen_position(time % LOOP),
+                    time: start + Duration::from_secs_f64(time),
+                });
+                samples += 1;
+            }
+            // Extends the stroke to where the pen should be two frames from now, hiding the
+            // latency between the stylus moving and the frame showing it.
+            let frame_time = Duration::from_secs_f64(1.0 / frame.refresh_rate.unwrap_or(60.0));
+            stroke.predict(frame_time, 2);
+            frame.draw_custom(
+                0,
+                &Stroke {
+                    stroke: &stroke,
+                    samples,
+                },
+            );
+            frame.redraw_at(Instant::now());
+        })
+        .await
+        .unwrap();
+}
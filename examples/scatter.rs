@@ -0,0 +1,54 @@
+use wgpu_test::{
+    run_with,
+    scatter::{Marker, ScatterPlot, ScatterPoint},
+    RendererConfig, Scene,
+};
+
+const POINTS: usize = 2_000_000;
+
+/// xorshift64, so the example doesn't need a rand dependency.
+fn next(state: &mut u64) -> f32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    (*state >> 40) as f32 / (1u64 << 24) as f32
+}
+
+#[tokio::main]
+async fn main() {
+    let mut state = 0x2545_f491_4f6c_dd1d;
+    let clusters = [
+        ([-150.0, -80.0], 60.0, Marker::Circle, [0.3, 0.7, 1.0, 0.6]),
+        ([120.0, 40.0], 90.0, Marker::Square, [1.0, 0.6, 0.2, 0.6]),
+        ([0.0, 160.0], 40.0, Marker::Cross, [0.6, 1.0, 0.4, 0.6]),
+    ];
+
+    let points = (0..POINTS)
+        .map(|i| {
+            let (center, spread, marker, color) = clusters[i % clusters.len()];
+            // Sum of uniforms, roughly normal
+            let offset = [
+                (next(&mut state) + next(&mut state) + next(&mut state) - 1.5) * spread,
+                (next(&mut state) + next(&mut state) + next(&mut state) - 1.5) * spread,
+            ];
+            ScatterPoint::new(
+                [center[0] + offset[0], center[1] + offset[1]],
+                4.0,
+                marker,
+                color,
+            )
+        })
+        .collect();
+
+    let scene = Scene {
+        scatters: vec![ScatterPlot {
+            origin: [400.0, 300.0],
+            points,
+            max_visible_points: 200_000,
+            tags: Vec::new(),
+        }],
+        text: "2M points: scroll to zoom, middle drag to pan".to_string(),
+        ..Default::default()
+    };
+    run_with(RendererConfig::default(), scene).await.unwrap();
+}
@@ -0,0 +1,50 @@
+use wgpu_test::{
+    rect::{Fill, Rect, Shadow},
+    AppBuilder, Scene, WindowAlpha,
+};
+
+/// A splash screen shaped by its rects: the window has no decorations and a transparent
+/// background, so only the rounded card and the badge over its corner are seen.
+#[tokio::main]
+async fn main() {
+    let card = Rect {
+        position: [240.0, 170.0],
+        size: [400.0, 240.0],
+        border_radius: [24.0; 4],
+        fill: Some(Fill::Solid {
+            color: [0.12, 0.13, 0.17, 0.95],
+        }),
+        shadow: Some(Shadow {
+            offset: [0.0, 8.0],
+            blur: 24.0,
+            spread: 0.0,
+            color: [0.0, 0.0, 0.0, 0.5],
+        }),
+        ..Default::default()
+    };
+    let badge = Rect {
+        position: [420.0, 70.0],
+        size: [96.0, 96.0],
+        border_radius: [48.0; 4],
+        fill: Some(Fill::Solid {
+            color: [0.95, 0.55, 0.2, 1.0],
+        }),
+        shadow: None,
+        ..card
+    };
+    let scene = Scene {
+        rects: vec![card, badge],
+        ..Default::default()
+    };
+    AppBuilder::new()
+        .with_title("Splash")
+        .with_inner_size(480.0, 320.0)
+        .with_decorations(false)
+        .with_resizable(false)
+        .with_window_alpha(WindowAlpha::Premultiplied)
+        .with_background([0.0; 4])
+        .with_scene(scene)
+        .run()
+        .await
+        .unwrap();
+}
@@ -0,0 +1,28 @@
+use wgpu_test::{
+    rect::{Fill, Rect},
+    terminal_preview, RendererConfig, Scene,
+};
+
+/// Prints a few rects to a terminal that shows Kitty or Sixel graphics, e.g. over SSH.
+#[tokio::main]
+async fn main() {
+    let rects = (0..4)
+        .map(|i| Rect {
+            position: [20.0 + i as f32 * 90.0, 50.0],
+            size: [70.0, 70.0],
+            border_radius: [12.0; 4],
+            fill: Some(Fill::Solid {
+                color: [0.25 * i as f32, 0.6, 1.0 - 0.25 * i as f32, 1.0],
+            }),
+            ..Default::default()
+        })
+        .collect();
+    let scene = Scene {
+        rects,
+        text: "Terminal preview".to_string(),
+        ..Default::default()
+    };
+    if let Err(e) = terminal_preview::preview(scene, RendererConfig::default(), [380, 140]).await {
+        eprintln!("{}", e);
+    }
+}
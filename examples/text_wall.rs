@@ -0,0 +1,19 @@
+use wgpu_test::{run_with, RendererConfig, Scene};
+
+const PARAGRAPH: &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do \
+eiusmod tempor incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis \
+nostrud exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat. ";
+
+#[tokio::main]
+async fn main() {
+    let text = format!(
+        "{}\n\nŠī ir teksta siena. 日本語のテキスト. Ελληνικά. العربية.",
+        PARAGRAPH.repeat(12)
+    );
+
+    let scene = Scene {
+        text,
+        ..Default::default()
+    };
+    run_with(RendererConfig::default(), scene).await.unwrap();
+}
@@ -0,0 +1,320 @@
+use std::{
+    f32::consts::{FRAC_PI_4, TAU},
+    time::Instant,
+};
+
+use wgpu_test::{
+    rect::{Fill, GradientStop, GradientStops, HitRegion, Rect, Shadow, Stroke},
+    AppBuilder, Cursor, DrawList, Scene, Theme,
+};
+
+/// Tags of the widgets that light up under the cursor: three buttons, the slider's knob and
+/// three cards.
+const BUTTONS: [u64; 3] = [0, 1, 2];
+const SLIDER: u64 = 3;
+const CARDS: [u64; 3] = [4, 5, 6];
+
+const ACCENT: [f32; 4] = [0.25, 0.45, 0.9, 1.0];
+
+/// Common widgets drawn from rects alone in the theme's colors: buttons, a checkbox, a toggle,
+/// a slider, a progress bar, a spinner and cards. Hovered widgets light up and the rest animate
+/// on their own, covering gradients, noise, strokes, shadows, rotation and hit regions.
+#[tokio::main]
+async fn main() {
+    let scene = Scene {
+        text: "Hover the buttons, the slider and the cards".to_string(),
+        ..Default::default()
+    };
+    AppBuilder::new()
+        .with_title("Widget gallery")
+        .with_inner_size(800.0, 600.0)
+        .with_background([0.11, 0.11, 0.13, 1.0])
+        .with_scene(scene)
+        .run_with_frame(|frame| {
+            // The draw list is rebuilt in the same order every frame, so the hovered index
+            // still points at the widget it was hit tested against.
+            let hovered = frame.hovered_rect.and_then(|i| frame.draw_list.tag(i));
+            frame.set_cursor(match hovered {
+                Some(_) => Cursor::Pointer,
+                None => Cursor::Default,
+            });
+            frame.draw_list.clear();
+            let mut gallery = Gallery {
+                draw_list: &mut *frame.draw_list,
+                theme: frame.theme,
+                hovered,
+                time: frame.time as f32,
+            };
+            gallery.buttons();
+            gallery.checkbox([560.0, 100.0]);
+            gallery.toggle([660.0, 100.0]);
+            gallery.slider(200.0);
+            gallery.progress_bar(260.0);
+            gallery.spinner([120.0, 360.0]);
+            gallery.avatar([240.0, 360.0]);
+            gallery.cards(490.0);
+            frame.redraw_at(Instant::now());
+        })
+        .await
+        .unwrap();
+}
+
+struct Gallery<'a> {
+    draw_list: &'a mut DrawList,
+    theme: Theme,
+    hovered: Option<u64>,
+    time: f32,
+}
+
+impl Gallery<'_> {
+    /// Adds `rect` tagged with `tag`, for telling which widget is hovered.
+    fn push_tagged(&mut self, rect: Rect, tag: u64) -> usize {
+        let index = self.draw_list.push_rect(rect);
+        self.draw_list.set_tag(index, tag);
+        index
+    }
+
+    /// `color` with the theme's hover color over it while the widget tagged `tag` is hovered.
+    fn lit(&self, tag: u64, color: [f32; 4]) -> [f32; 4] {
+        if self.hovered != Some(tag) {
+            return color;
+        }
+        let hover = self.theme.hover;
+        [0, 1, 2, 3].map(|i| match i {
+            3 => color[3],
+            _ => color[i] * (1.0 - hover[3]) + hover[i] * hover[3],
+        })
+    }
+
+    fn buttons(&mut self) {
+        for (i, tag) in BUTTONS.into_iter().enumerate() {
+            // The first is the default action.
+            let color = if i == 0 { ACCENT } else { self.theme.button };
+            let rect = Rect {
+                position: [120.0 + i as f32 * 140.0, 100.0],
+                size: [120.0, 40.0],
+                border_radius: [8.0; 4],
+                fill: Some(Fill::Solid {
+                    color: self.lit(tag, color),
+                }),
+                shadow: Some(shadow(3.0)),
+                ..Default::default()
+            };
+            self.push_tagged(rect, tag);
+        }
+    }
+
+    /// Ticks itself every second and a half.
+    fn checkbox(&mut self, position: [f32; 2]) {
+        let checked = (self.time / 1.5) as u32 % 2 == 0;
+        let foreground = self.theme.foreground;
+        self.draw_list.push_rect(Rect {
+            position,
+            size: [24.0, 24.0],
+            border_radius: [5.0; 4],
+            fill: checked.then_some(Fill::Solid { color: ACCENT }),
+            stroke: Some(Stroke {
+                color: [foreground[0], foreground[1], foreground[2]],
+                width: 2.0,
+            }),
+            ..Default::default()
+        });
+        if checked {
+            // A check mark of two turned bars.
+            for (offset, length, rotation) in [
+                ([-4.0, 2.0], 8.0, FRAC_PI_4),
+                ([3.0, -1.0], 14.0, -FRAC_PI_4),
+            ] {
+                self.draw_list.push_rect(Rect {
+                    position: [position[0] + offset[0], position[1] + offset[1]],
+                    size: [length, 3.0],
+                    border_radius: [1.5; 4],
+                    fill: Some(Fill::Solid { color: foreground }),
+                    rotation,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    /// Slides on and off, easing in and out.
+    fn toggle(&mut self, position: [f32; 2]) {
+        let on = 0.5 - 0.5 * (self.time * 2.0).cos();
+        let off = self.theme.button;
+        self.draw_list.push_rect(Rect {
+            position,
+            size: [52.0, 28.0],
+            border_radius: [14.0; 4],
+            fill: Some(Fill::Solid {
+                color: [0, 1, 2, 3].map(|i| off[i] + (ACCENT[i] - off[i]) * on),
+            }),
+            ..Default::default()
+        });
+        self.draw_list.push_rect(Rect {
+            position: [position[0] - 12.0 + 24.0 * on, position[1]],
+            size: [22.0; 2],
+            border_radius: [11.0; 4],
+            fill: Some(Fill::Solid {
+                color: self.theme.foreground,
+            }),
+            shadow: Some(shadow(1.0)),
+            ..Default::default()
+        });
+    }
+
+    /// A knob small enough to miss, so it's grabbed from a 44 pixel square around it.
+    fn slider(&mut self, y: f32) {
+        let value = 0.5 + 0.5 * (self.time * 0.7).sin();
+        let [left, width] = [120.0, 560.0];
+        self.draw_list
+            .push_rect(Rect::solid([400.0, y], [width, 6.0], self.theme.button));
+        self.draw_list.push_rect(Rect {
+            position: [left + width * value / 2.0, y],
+            size: [width * value, 6.0],
+            border_radius: [3.0; 4],
+            fill: Some(Fill::Solid { color: ACCENT }),
+            ..Default::default()
+        });
+        let knob = Rect {
+            position: [left + width * value, y],
+            size: [18.0; 2],
+            border_radius: [9.0; 4],
+            fill: Some(Fill::Solid {
+                color: self.lit(SLIDER, self.theme.foreground),
+            }),
+            stroke: Some(Stroke {
+                color: [ACCENT[0], ACCENT[1], ACCENT[2]],
+                width: 2.0,
+            }),
+            shadow: Some(shadow(1.0)),
+            ..Default::default()
+        };
+        let index = self.push_tagged(knob, SLIDER);
+        self.draw_list
+            .set_hit_region(index, HitRegion::MinSize([44.0, 44.0]));
+    }
+
+    /// Fills up over five seconds, then starts over.
+    fn progress_bar(&mut self, y: f32) {
+        let progress = self.time / 5.0 % 1.0;
+        let [left, width] = [120.0, 560.0];
+        self.draw_list.push_rect(Rect {
+            position: [400.0, y],
+            size: [width, 12.0],
+            border_radius: [6.0; 4],
+            fill: Some(Fill::Solid {
+                color: self.theme.button,
+            }),
+            ..Default::default()
+        });
+        self.draw_list.push_rect(Rect {
+            position: [left + width * progress / 2.0, y],
+            size: [width * progress, 12.0],
+            border_radius: [6.0; 4],
+            fill: Some(Fill::Noise {
+                scale: 24.0,
+                octaves: 3,
+                offset: [-self.time * 40.0, 0.0],
+                stops: stops(&[(0.0, ACCENT), (1.0, [0.45, 0.7, 1.0, 1.0])]),
+            }),
+            ..Default::default()
+        });
+    }
+
+    /// A ring fading around its circle, turning once a second and a half.
+    fn spinner(&mut self, position: [f32; 2]) {
+        let transparent = [ACCENT[0], ACCENT[1], ACCENT[2], 0.0];
+        self.draw_list.push_rect(Rect {
+            position,
+            size: [64.0; 2],
+            border_radius: [32.0; 4],
+            fill: Some(Fill::Conic {
+                center: [0.0, 0.0],
+                start_angle: self.time * TAU / 1.5,
+                stops: stops(&[(0.0, transparent), (1.0, ACCENT)]),
+            }),
+            ..Default::default()
+        });
+        self.draw_list.push_rect(Rect {
+            border_radius: [24.0; 4],
+            ..Rect::solid(position, [48.0; 2], [0.11, 0.11, 0.13, 1.0])
+        });
+    }
+
+    /// A round placeholder picture, lit from the top-left.
+    fn avatar(&mut self, position: [f32; 2]) {
+        self.draw_list.push_rect(Rect {
+            position,
+            size: [64.0; 2],
+            border_radius: [32.0; 4],
+            fill: Some(Fill::Radial {
+                center: [-12.0, -12.0],
+                radius: 56.0,
+                stops: stops(&[
+                    (0.0, [0.95, 0.75, 0.55, 1.0]),
+                    (0.6, [0.8, 0.45, 0.3, 1.0]),
+                    (1.0, [0.4, 0.2, 0.15, 1.0]),
+                ]),
+            }),
+            stroke: Some(Stroke {
+                color: [0.9, 0.9, 0.9],
+                width: 2.0,
+            }),
+            ..Default::default()
+        });
+    }
+
+    /// Panels that rise off the page under the cursor.
+    fn cards(&mut self, y: f32) {
+        for (i, tag) in CARDS.into_iter().enumerate() {
+            let hovered = self.hovered == Some(tag);
+            let position = [
+                180.0 + i as f32 * 220.0,
+                y - if hovered { 4.0 } else { 0.0 },
+            ];
+            let card = Rect {
+                position,
+                size: [200.0, 140.0],
+                border_radius: [12.0; 4],
+                fill: Some(Fill::Solid {
+                    color: self.theme.panel,
+                }),
+                stroke: Some(Stroke {
+                    color: [0.3, 0.3, 0.35],
+                    width: 1.0,
+                }),
+                shadow: Some(shadow(if hovered { 10.0 } else { 4.0 })),
+                ..Default::default()
+            };
+            self.push_tagged(card, tag);
+            // A header band with its top corners rounded like the card's.
+            self.draw_list.push_rect(Rect {
+                position: [position[0], position[1] - 50.0],
+                size: [200.0, 40.0],
+                border_radius: [12.0, 12.0, 0.0, 0.0],
+                fill: Some(Fill::Solid {
+                    color: self.lit(tag, self.theme.bar),
+                }),
+                ..Default::default()
+            });
+        }
+    }
+}
+
+/// A shadow below a widget raised `height` pixels off the page.
+fn shadow(height: f32) -> Shadow {
+    Shadow {
+        offset: [0.0, height],
+        blur: 2.0 * height + 4.0,
+        spread: 0.0,
+        color: [0.0, 0.0, 0.0, 0.35],
+    }
+}
+
+fn stops(stops: &[(f32, [f32; 4])]) -> GradientStops {
+    let stops: Vec<GradientStop> = stops
+        .iter()
+        .map(|&(offset, color)| GradientStop { offset, color })
+        .collect();
+    GradientStops::new(&stops)
+}
@@ -0,0 +1,41 @@
+use wgpu_test::{
+    rect::{Fill, Rect},
+    run_with, Camera, RendererConfig, Scene,
+};
+
+/// Far enough from the origin that f32 world-to-screen math would visibly jitter.
+const ORIGIN: [f32; 2] = [1_000_000.0, 1_000_000.0];
+const GRID: usize = 40;
+
+#[tokio::main]
+async fn main() {
+    let rects: Vec<Rect> = (0..GRID * GRID)
+        .map(|i| {
+            let (column, row) = ((i % GRID) as f32, (i / GRID) as f32);
+            Rect {
+                position: [ORIGIN[0] + column * 40.0, ORIGIN[1] + row * 40.0],
+                size: [30.0, 30.0],
+                border_radius: [6.0; 4],
+                fill: Some(Fill::Solid {
+                    color: [0.9, 0.6 + 0.01 * column, 0.2 + 0.01 * row, 1.0],
+                }),
+                ..Default::default()
+            }
+        })
+        .collect();
+
+    let config = RendererConfig {
+        camera: Camera {
+            offset: [ORIGIN[0] as f64 - 40.0, ORIGIN[1] as f64 - 40.0],
+            zoom: 1.0,
+        },
+        ..Default::default()
+    };
+
+    let scene = Scene {
+        rects,
+        text: "Scroll to zoom, middle-drag to pan".to_string(),
+        ..Default::default()
+    };
+    run_with(config, scene).await.unwrap();
+}
@@ -0,0 +1,190 @@
+//! Setting up the window along with the renderer, for apps that aren't the demo.
+
+use winit::{dpi::LogicalSize, window::WindowBuilder};
+
+#[cfg(feature = "egui")]
+use crate::egui_layer::{EguiUi, SceneTexture};
+use crate::{run_app, Frame, RendererConfig, RendererError, Scene, WindowAlpha};
+
+/// The window `run_with_frame` opens, and what it draws.
+#[derive(Clone, Default)]
+pub struct AppBuilder {
+    window: WindowConfig,
+    renderer_config: RendererConfig,
+    scene: Scene,
+    #[cfg(feature = "egui")]
+    egui: Option<EguiUi>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct WindowConfig {
+    title: Option<String>,
+    /// In logical pixels.
+    inner_size: Option<[f64; 2]>,
+    resizable: bool,
+    decorations: bool,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            title: None,
+            inner_size: None,
+            resizable: true,
+            decorations: true,
+        }
+    }
+}
+
+impl WindowConfig {
+    /// Applies these settings to `builder`. Without decorations, the renderer's title bar
+    /// isn't drawn either.
+    pub fn apply(
+        &self,
+        builder: WindowBuilder,
+        renderer_config: &mut RendererConfig,
+    ) -> WindowBuilder {
+        renderer_config.client_decorations &= self.decorations;
+        let mut builder = builder
+            .with_resizable(self.resizable)
+            .with_decorations(self.decorations && !renderer_config.client_decorations);
+        if let Some(title) = &self.title {
+            builder = builder.with_title(title);
+        }
+        if let Some([width, height]) = self.inner_size {
+            builder = builder.with_inner_size(LogicalSize::new(width, height));
+        }
+        builder
+    }
+}
+
+impl AppBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.window.title = Some(title.into());
+        self
+    }
+
+    /// The size of the window's contents, in logical pixels. The system picks one by default.
+    pub fn with_inner_size(mut self, width: f64, height: f64) -> Self {
+        self.window.inner_size = Some([width, height]);
+        self
+    }
+
+    /// Whether the user can resize the window. On by default.
+    pub fn with_resizable(mut self, resizable: bool) -> Self {
+        self.window.resizable = resizable;
+        self
+    }
+
+    /// Whether the window has a title bar and borders, the system's or with
+    /// `RendererConfig::client_decorations` the renderer's. On by default.
+    pub fn with_decorations(mut self, decorations: bool) -> Self {
+        self.window.decorations = decorations;
+        self
+    }
+
+    /// What the frame is cleared to; see `RendererConfig::background`.
+    pub fn with_background(mut self, background: [f32; 4]) -> Self {
+        self.renderer_config.background = background;
+        self
+    }
+
+    /// Whether the window shows what is behind it where its pixels are translucent; see
+    /// `RendererConfig::window_alpha`. The default background is opaque, so a translucent
+    /// window also wants a background with alpha, fully transparent for shaped windows such
+    /// as splash screens.
+    pub fn with_window_alpha(mut self, window_alpha: WindowAlpha) -> Self {
+        self.renderer_config.window_alpha = window_alpha;
+        self
+    }
+
+    /// Replaces the renderer's config, including a background or window alpha set before.
+    pub fn with_renderer_config(mut self, renderer_config: RendererConfig) -> Self {
+        self.renderer_config = renderer_config;
+        self
+    }
+
+    pub fn with_scene(mut self, scene: Scene) -> Self {
+        self.scene = scene;
+        self
+    }
+
+    /// Draws an egui ui over the scene, in the same pass, for apps moving to or from egui that
+    /// mix both meanwhile. `ui` is called every frame, and egui gets pointer and keyboard
+    /// input first: what it uses doesn't reach the scene.
+    #[cfg(feature = "egui")]
+    pub fn with_egui(mut self, ui: impl FnMut(&egui::Context) + 'static) -> Self {
+        self.egui = Some(EguiUi::overlay(ui));
+        self
+    }
+
+    /// Has egui host the scene: it is drawn into a texture that `ui` shows in its widgets,
+    /// such as an `egui::Image` in a panel, and only egui is drawn to the window. The texture
+    /// has the window's size and camera, and the scene's hover and camera input still use
+    /// window coordinates, so it fits best in a panel filling most of the window.
+    #[cfg(feature = "egui")]
+    pub fn with_egui_scene(
+        mut self,
+        ui: impl FnMut(&egui::Context, SceneTexture) + 'static,
+    ) -> Self {
+        self.egui = Some(EguiUi::scene(ui));
+        self
+    }
+
+    /// Opens the window and draws the scene. Returns only if the renderer can't be set up.
+    pub async fn run(self) -> Result<(), RendererError> {
+        self.run_with_frame(|_| {}).await
+    }
+
+    /// Opens the window and draws the scene, calling `frame` as `run_with_frame` does.
+    pub async fn run_with_frame(
+        self,
+        frame: impl FnMut(&mut Frame) + 'static,
+    ) -> Result<(), RendererError> {
+        run_app(vec![(self, Box::new(frame))]).await
+    }
+
+    pub(crate) fn into_parts(self) -> (WindowConfig, RendererConfig, Scene) {
+        (self.window, self.renderer_config, self.scene)
+    }
+
+    #[cfg(feature = "egui")]
+    pub(crate) fn take_egui(mut self) -> (Self, Option<EguiUi>) {
+        let egui = self.egui.take();
+        (self, egui)
+    }
+}
+
+/// Several windows run by one event loop, such as a main window and a tool palette. Each has
+/// its own surface, config and scene, drawn with the device the first window asked for.
+///
+/// Closing a window closes only that one; the app exits with the last. Each window has its
+/// own native menu, but only the first one's tray icon is created, since tray events don't
+/// say which window they're for.
+#[derive(Default)]
+pub struct WindowSet {
+    windows: Vec<(AppBuilder, Box<dyn FnMut(&mut Frame)>)>,
+}
+
+impl WindowSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a window, opened after the ones added before. `frame` is called for this window
+    /// only, as `run_with_frame` calls it.
+    pub fn with_window(mut self, app: AppBuilder, frame: impl FnMut(&mut Frame) + 'static) -> Self {
+        self.windows.push((app, Box::new(frame)));
+        self
+    }
+
+    /// Opens the windows and draws them until the last one closes. Returns right away without
+    /// windows, or if the renderer can't be set up for one of them.
+    pub async fn run(self) -> Result<(), RendererError> {
+        run_app(self.windows).await
+    }
+}
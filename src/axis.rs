@@ -0,0 +1,240 @@
+use glyphon::{FontSystem, Metrics};
+
+use crate::{
+    rect::{Fill, Rect},
+    text,
+};
+
+/// How tick values are turned into labels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum NumberFormat {
+    /// As many decimals as the tick step needs, switching to SI suffixes for large values.
+    #[default]
+    Auto,
+    /// A fixed number of decimals.
+    Fixed(usize),
+    /// Scaled to an SI prefix, such as `1.5k` or `20µ`.
+    Si,
+}
+
+const SI_PREFIXES: [&str; 9] = ["p", "n", "µ", "m", "", "k", "M", "G", "T"];
+
+impl NumberFormat {
+    /// Formats `value`, a multiple of `step`, with just enough decimals to tell it apart from
+    /// its neighbours.
+    pub fn format(&self, value: f64, step: f64) -> String {
+        match *self {
+            NumberFormat::Fixed(decimals) => format!("{:.*}", decimals, value + 0.0),
+            NumberFormat::Auto if value.abs() < 1e6 => {
+                format!("{:.*}", decimals_for(step), value + 0.0)
+            }
+            NumberFormat::Auto | NumberFormat::Si => {
+                if value == 0.0 {
+                    return "0".to_string();
+                }
+                let group = ((value.abs().log10() / 3.0).floor() as i32).clamp(-4, 4);
+                let scale = 10f64.powi(group * 3);
+                format!(
+                    "{:.*}{}",
+                    decimals_for(step / scale),
+                    value / scale,
+                    SI_PREFIXES[(group + 4) as usize]
+                )
+            }
+        }
+    }
+}
+
+fn decimals_for(step: f64) -> usize {
+    (-(step.log10() + 1e-9).floor()).max(0.0) as usize
+}
+
+/// The smallest step of the form 1, 2 or 5 times a power of ten that splits `range` into at
+/// most `max_ticks` intervals.
+pub fn nice_step(range: f64, max_ticks: usize) -> f64 {
+    let raw = range.abs() / max_ticks.max(1) as f64;
+    if !(raw > 0.0 && raw.is_finite()) {
+        return 1.0;
+    }
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let mantissa = [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .find(|&m| m * magnitude >= raw)
+        .unwrap_or(10.0);
+    mantissa * magnitude
+}
+
+/// The next nice step after `step`: 1 → 2 → 5 → 10.
+fn coarser(step: f64) -> f64 {
+    let magnitude = 10f64.powf((step.log10() + 1e-9).floor());
+    match (step / magnitude).round() as u32 {
+        1 => 2.0 * magnitude,
+        2 => 5.0 * magnitude,
+        _ => 10.0 * magnitude,
+    }
+}
+
+/// Every multiple of `step` between `min` and `max`, inclusive.
+pub fn ticks(min: f64, max: f64, step: f64) -> Vec<f64> {
+    if !(step > 0.0 && min.is_finite() && max.is_finite()) {
+        return vec![];
+    }
+    let first = (min / step - 1e-9).ceil() as i64;
+    let last = (max / step + 1e-9).floor() as i64;
+    (first..=last.min(first + 10_000))
+        .map(|i| i as f64 * step)
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LabelAngle {
+    Horizontal,
+    /// Rotated 45° counter-clockwise, anchored at the tick.
+    Diagonal,
+}
+
+#[derive(Clone, Debug)]
+pub struct Tick {
+    pub value: f64,
+    /// Distance from the axis's `position` along the axis, in pixels.
+    pub offset: f32,
+    pub label: String,
+    /// Measured size of the unrotated label.
+    pub label_size: [f32; 2],
+}
+
+#[derive(Clone, Debug)]
+pub struct AxisLayout {
+    pub ticks: Vec<Tick>,
+    pub step: f64,
+    pub angle: LabelAngle,
+}
+
+/// A chart axis mapping `range` onto `length` pixels.
+#[derive(Clone, Debug)]
+pub struct Axis {
+    /// Screen position of the `range[0]` end.
+    pub position: [f32; 2],
+    pub length: f32,
+    /// Vertical axes grow upwards from `position`, horizontal ones to the right.
+    pub vertical: bool,
+    pub range: [f64; 2],
+    pub format: NumberFormat,
+    pub metrics: Metrics,
+    /// Minimum space between neighbouring labels, in pixels.
+    pub label_gap: f32,
+    /// Lets a crowded horizontal axis rotate its labels before dropping any.
+    pub rotate_labels: bool,
+}
+
+impl Axis {
+    /// Picks the densest nice tick step whose labels don't overlap, rotating or thinning them
+    /// as needed.
+    pub fn layout(&self, font_system: &mut FontSystem) -> AxisLayout {
+        let span = self.range[1] - self.range[0];
+        if !(span != 0.0 && span.is_finite()) {
+            return AxisLayout {
+                ticks: vec![],
+                step: 1.0,
+                angle: LabelAngle::Horizontal,
+            };
+        }
+        // A label every 40px is about as dense as an axis stays readable.
+        let mut step = nice_step(span, (self.length / 40.0).max(1.0) as usize);
+
+        loop {
+            let ticks: Vec<Tick> = ticks(self.range[0], self.range[1], step)
+                .into_iter()
+                .map(|value| {
+                    let label = self.format.format(value, step);
+                    Tick {
+                        value,
+                        offset: ((value - self.range[0]) / span * self.length as f64) as f32,
+                        label_size: text::measure(font_system, &label, self.metrics),
+                        label,
+                    }
+                })
+                .collect();
+            let spacing = (step / span.abs() * self.length as f64) as f32;
+
+            let extent = |tick: &Tick| {
+                if self.vertical {
+                    tick.label_size[1]
+                } else {
+                    tick.label_size[0]
+                }
+            };
+            let widest = ticks.iter().map(extent).fold(0.0, f32::max);
+            if widest + self.label_gap <= spacing {
+                return AxisLayout {
+                    ticks,
+                    step,
+                    angle: LabelAngle::Horizontal,
+                };
+            }
+
+            // Parallel 45° labels are spacing / √2 apart, whatever their length.
+            let tallest = ticks.iter().map(|t| t.label_size[1]).fold(0.0, f32::max);
+            if !self.vertical
+                && self.rotate_labels
+                && tallest + self.label_gap <= spacing * std::f32::consts::FRAC_1_SQRT_2
+            {
+                return AxisLayout {
+                    ticks,
+                    step,
+                    angle: LabelAngle::Diagonal,
+                };
+            }
+
+            if ticks.len() <= 2 || !spacing.is_finite() {
+                return AxisLayout {
+                    ticks,
+                    step,
+                    angle: LabelAngle::Horizontal,
+                };
+            }
+            step = coarser(step);
+        }
+    }
+
+    /// Screen position of the tick at `offset` along the axis.
+    pub fn point_at(&self, offset: f32) -> [f32; 2] {
+        if self.vertical {
+            [self.position[0], self.position[1] - offset]
+        } else {
+            [self.position[0] + offset, self.position[1]]
+        }
+    }
+
+    /// The axis line plus a mark `tick_length` long at each tick, on the side labels go.
+    pub fn rects(&self, layout: &AxisLayout, tick_length: f32, color: [f32; 4]) -> Vec<Rect> {
+        let line = |position: [f32; 2], size: [f32; 2]| Rect {
+            position,
+            size,
+            fill: Some(Fill::Solid { color }),
+            softness: 0.5,
+            ..Default::default()
+        };
+
+        let mut rects = vec![if self.vertical {
+            line(
+                [self.position[0], self.position[1] - self.length / 2.0],
+                [1.0, self.length],
+            )
+        } else {
+            line(
+                [self.position[0] + self.length / 2.0, self.position[1]],
+                [self.length, 1.0],
+            )
+        }];
+        rects.extend(layout.ticks.iter().map(|tick| {
+            let [x, y] = self.point_at(tick.offset);
+            if self.vertical {
+                line([x - tick_length / 2.0, y], [tick_length, 1.0])
+            } else {
+                line([x, y + tick_length / 2.0], [1.0, tick_length])
+            }
+        }));
+        rects
+    }
+}
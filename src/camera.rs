@@ -0,0 +1,116 @@
+use crate::rect::{ClipRect, Rect, Stroke};
+
+/// Maps world coordinates to screen pixels: `screen = (world - offset) * zoom`.
+///
+/// The offset is kept in f64 and subtracted on the CPU before anything is converted to f32,
+/// so the vertices the GPU sees stay small and precise however far the view is panned. Rect
+/// positions are f32 themselves, though: for content far from `[0, 0]`, place the rects
+/// relative to an origin near it with `DrawList::set_origin`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    /// World position shown at the screen origin.
+    pub offset: [f64; 2],
+    pub zoom: f64,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            offset: [0.0, 0.0],
+            zoom: 1.0,
+        }
+    }
+}
+
+impl Camera {
+    pub const MIN_ZOOM: f64 = 1e-4;
+    pub const MAX_ZOOM: f64 = 1e4;
+
+    pub fn to_screen(&self, world: [f64; 2]) -> [f64; 2] {
+        [
+            (world[0] - self.offset[0]) * self.zoom,
+            (world[1] - self.offset[1]) * self.zoom,
+        ]
+    }
+
+    pub fn to_world(&self, screen: [f64; 2]) -> [f64; 2] {
+        [
+            screen[0] / self.zoom + self.offset[0],
+            screen[1] / self.zoom + self.offset[1],
+        ]
+    }
+
+    /// Moves the view by `delta` screen pixels.
+    pub fn pan(&mut self, delta: [f64; 2]) {
+        self.offset[0] -= delta[0] / self.zoom;
+        self.offset[1] -= delta[1] / self.zoom;
+    }
+
+    /// Multiplies the zoom by `factor` while keeping the world point under `screen` in place.
+    pub fn zoom_at(&mut self, screen: [f64; 2], factor: f64) {
+        let anchor = self.to_world(screen);
+        self.zoom = (self.zoom * factor).clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+        self.offset = [
+            anchor[0] - screen[0] / self.zoom,
+            anchor[1] - screen[1] / self.zoom,
+        ];
+    }
+
+    /// Whether any of the world box from `min` to `max`, grown by `pad` pixels, is on a
+    /// screen of `view` pixels.
+    pub fn sees(&self, min: [f64; 2], max: [f64; 2], pad: f64, view: [f32; 2]) -> bool {
+        let [min, max] = [self.to_screen(min), self.to_screen(max)];
+        (0..2).all(|i| min[i] - pad < view[i] as f64 && max[i] + pad > 0.0)
+    }
+
+    /// Returns `rect` moved into screen space.
+    pub fn apply(&self, rect: &Rect) -> Rect {
+        let position = self.to_screen([rect.position[0] as f64, rect.position[1] as f64]);
+        Rect {
+            position: [position[0] as f32, position[1] as f32],
+            size: [
+                (rect.size[0] as f64 * self.zoom) as f32,
+                (rect.size[1] as f64 * self.zoom) as f32,
+            ],
+            border_radius: rect
+                .border_radius
+                .map(|radius| (radius as f64 * self.zoom) as f32),
+            softness: (rect.softness as f64 * self.zoom) as f32,
+            fill: rect.fill.map(|fill| fill.scaled(self.zoom as f32)),
+            stroke: rect.stroke.map(|stroke| Stroke {
+                width: (stroke.width as f64 * self.zoom) as f32,
+                ..stroke
+            }),
+            clip: rect.clip.map(|clip| {
+                let position = self.to_screen(clip.position.map(f64::from));
+                ClipRect {
+                    position: position.map(|v| v as f32),
+                    size: clip.size.map(|side| (side as f64 * self.zoom) as f32),
+                    border_radius: clip
+                        .border_radius
+                        .map(|radius| (radius as f64 * self.zoom) as f32),
+                }
+            }),
+            ..*rect
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cameras_see_boxes_on_screen() {
+        let camera = Camera {
+            offset: [100.0, 0.0],
+            zoom: 2.0,
+        };
+        let view = [200.0, 100.0];
+        assert!(camera.sees([150.0, 10.0], [160.0, 20.0], 0.0, view));
+        // Right of the screen, unless a stroke reaches back onto it.
+        assert!(!camera.sees([200.0, 10.0], [210.0, 20.0], 0.0, view));
+        assert!(camera.sees([200.0, 10.0], [210.0, 20.0], 1.0, view));
+        assert!(!camera.sees([0.0, 10.0], [99.0, 20.0], 0.0, view));
+    }
+}
@@ -0,0 +1,145 @@
+use crate::{chart::PlotArea, rect::Rect};
+
+/// One period of price data. `time` is the bar's center on the x axis.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bar {
+    pub time: f64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+}
+
+impl Bar {
+    pub fn rising(&self) -> bool {
+        self.close >= self.open
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BarStyle {
+    /// A filled body from open to close with a wick from low to high.
+    Candle,
+    /// A low-high line with ticks for open on the left and close on the right.
+    Ohlc,
+}
+
+impl BarStyle {
+    fn rects_per_bar(&self) -> usize {
+        match self {
+            BarStyle::Candle => 2,
+            BarStyle::Ohlc => 3,
+        }
+    }
+}
+
+/// A candlestick or OHLC series that keeps its rects between frames and only rebuilds the
+/// bars that changed, so streaming in a new bar or updating the live one is cheap.
+#[derive(Clone, Debug)]
+pub struct CandlestickSeries {
+    pub style: BarStyle,
+    pub up_color: [f32; 4],
+    pub down_color: [f32; 4],
+    /// Body width in pixels.
+    pub width: f32,
+    bars: Vec<Bar>,
+    rects: Vec<Rect>,
+    /// Area the cached rects were built for.
+    area: Option<PlotArea>,
+    /// Bars before this index have up to date rects.
+    built: usize,
+}
+
+impl CandlestickSeries {
+    pub fn new(style: BarStyle, up_color: [f32; 4], down_color: [f32; 4], width: f32) -> Self {
+        Self {
+            style,
+            up_color,
+            down_color,
+            width,
+            bars: vec![],
+            rects: vec![],
+            area: None,
+            built: 0,
+        }
+    }
+
+    pub fn bars(&self) -> &[Bar] {
+        &self.bars
+    }
+
+    /// Appends a bar. Bars are expected in time order.
+    pub fn push(&mut self, bar: Bar) {
+        self.bars.push(bar);
+    }
+
+    /// Replaces the newest bar, as happens while its period is still open.
+    pub fn update_last(&mut self, bar: Bar) {
+        match self.bars.last_mut() {
+            Some(last) => {
+                *last = bar;
+                self.built = self.built.min(self.bars.len() - 1);
+            }
+            None => self.bars.push(bar),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.bars.clear();
+        self.built = 0;
+    }
+
+    /// Rebuilds every bar on the next `rects` call. Needed after changing the style, colors
+    /// or width.
+    pub fn invalidate(&mut self) {
+        self.built = 0;
+    }
+
+    /// Rects for every bar in `area`. Only new or changed bars are rebuilt unless the area
+    /// changed since the last call.
+    pub fn rects(&mut self, area: &PlotArea) -> &[Rect] {
+        if self.area != Some(*area) {
+            self.area = Some(*area);
+            self.built = 0;
+        }
+        self.rects.truncate(self.built * self.style.rects_per_bar());
+        for bar in &self.bars[self.built..] {
+            let color = if bar.rising() {
+                self.up_color
+            } else {
+                self.down_color
+            };
+            let [x, high] = area.to_screen([bar.time, bar.high]);
+            let [_, low] = area.to_screen([bar.time, bar.low]);
+            let [_, open] = area.to_screen([bar.time, bar.open]);
+            let [_, close] = area.to_screen([bar.time, bar.close]);
+            let line = (self.width / 6.0).max(1.0);
+
+            let wick = Rect::solid(
+                [x, (high + low) / 2.0],
+                [line, (low - high).abs().max(1.0)],
+                color,
+            );
+            match self.style {
+                BarStyle::Candle => {
+                    let body = Rect::solid(
+                        [x, (open + close) / 2.0],
+                        [self.width, (open - close).abs().max(1.0)],
+                        color,
+                    );
+                    self.rects.extend([wick, body]);
+                }
+                BarStyle::Ohlc => {
+                    let tick = self.width / 2.0;
+                    self.rects.extend([
+                        wick,
+                        Rect::solid([x - tick / 2.0, open], [tick, line], color),
+                        Rect::solid([x + tick / 2.0, close], [tick, line], color),
+                    ]);
+                }
+            }
+        }
+        self.built = self.bars.len();
+        &self.rects
+    }
+}
@@ -0,0 +1,132 @@
+use crate::config::AdapterConfig;
+
+/// A feature the renderer scaled back because the adapter couldn't support it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Degradation {
+    /// Rendering runs on a software adapter, because no hardware one was available or
+    /// `AdapterConfig::force_fallback` asked for it.
+    SoftwareAdapter,
+    /// The adapter can't meet wgpu's default limits, so the device runs with lower ones. This
+    /// also caps the size of the text atlas.
+    ReducedLimits,
+    /// Vertex shaders can't read storage buffers or compute shaders are missing, so scatter
+    /// plots aren't drawn.
+    NoScatter,
+}
+
+/// What the renderer runs with on the current adapter, and what it had to give up.
+#[derive(Clone, Debug)]
+pub struct Capabilities {
+    pub adapter_name: String,
+    pub backend: wgpu::Backend,
+    /// Limits to request the device with.
+    pub limits: wgpu::Limits,
+    pub degradations: Vec<Degradation>,
+}
+
+impl Capabilities {
+    /// Picks the highest limits `adapter` supports and the features that fit within them.
+    pub fn detect(adapter: &wgpu::Adapter) -> Self {
+        let info = adapter.get_info();
+        let supported = adapter.limits();
+        let mut degradations = vec![];
+        if info.device_type == wgpu::DeviceType::Cpu {
+            degradations.push(Degradation::SoftwareAdapter);
+        }
+
+        let limits = [
+            wgpu::Limits::default(),
+            wgpu::Limits::downlevel_defaults(),
+            wgpu::Limits::downlevel_webgl2_defaults(),
+        ]
+        .into_iter()
+        .find(|limits| limits.check_limits(&supported))
+        // Even WebGL2 limits are too high; nothing is lower, so take what there is.
+        .unwrap_or_else(|| supported.clone());
+        if !wgpu::Limits::default().check_limits(&limits) {
+            degradations.push(Degradation::ReducedLimits);
+        }
+        // Higher texture sizes are free to use where the adapter has them.
+        let limits = limits.using_resolution(supported);
+
+        let flags = adapter.get_downlevel_capabilities().flags;
+        // Scatter points are read from a storage buffer when drawing, and picking them reads
+        // that buffer and writes a result buffer from a compute shader.
+        let scatter = flags.contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+            && flags.contains(wgpu::DownlevelFlags::VERTEX_STORAGE)
+            && limits.max_storage_buffers_per_shader_stage >= 2
+            && limits.max_compute_invocations_per_workgroup >= 64
+            && limits.max_compute_workgroup_size_x >= 64;
+        if !scatter {
+            degradations.push(Degradation::NoScatter);
+        }
+
+        Self {
+            adapter_name: info.name,
+            backend: info.backend,
+            limits,
+            degradations,
+        }
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        !self.degradations.is_empty()
+    }
+
+    pub fn scatter(&self) -> bool {
+        !self.degradations.contains(&Degradation::NoScatter)
+    }
+}
+
+/// The adapters available on `backends`, for choosing one by name in `AdapterConfig`.
+pub fn adapters(backends: wgpu::Backends) -> Vec<wgpu::AdapterInfo> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends,
+        dx12_shader_compiler: Default::default(),
+    });
+    instance
+        .enumerate_adapters(backends)
+        .map(|adapter| adapter.get_info())
+        .collect()
+}
+
+/// Picks the adapter `config` asks for that can draw to `surface`, if there is one.
+pub(crate) async fn request_adapter(
+    instance: &wgpu::Instance,
+    config: &AdapterConfig,
+    surface: Option<&wgpu::Surface>,
+) -> Option<wgpu::Adapter> {
+    if let Some(name) = &config.name {
+        let name = name.to_lowercase();
+        let named = instance
+            .enumerate_adapters(config.backends)
+            .find(|adapter| {
+                adapter.get_info().name.to_lowercase().contains(&name)
+                    && surface.is_none_or(|surface| adapter.is_surface_supported(surface))
+            });
+        if named.is_some() {
+            return named;
+        }
+        log::warn!("no adapter named like {:?}, picking another", name);
+    }
+
+    // Without a hardware adapter, a software one is still better than no window at all.
+    let fallbacks: &[bool] = if config.force_fallback {
+        &[true]
+    } else {
+        &[false, true]
+    };
+    for &force_fallback_adapter in fallbacks {
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: config.power_preference,
+                compatible_surface: surface,
+                force_fallback_adapter,
+            })
+            .await;
+        if adapter.is_some() {
+            return adapter;
+        }
+    }
+    None
+}
@@ -0,0 +1,380 @@
+//! Reading frames back from the window, for screenshots and recordings.
+
+use std::{
+    fmt, fs,
+    io::{self, Read, Write},
+    path::Path,
+    sync::mpsc,
+};
+
+use crate::resources::{ResourceScope, Tracked};
+
+/// A frame read back from the window with `Frame::capture_frame`, as tightly packed RGBA8
+/// pixels, row by row from the top.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Screenshot {
+    pub fn save_png(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.write_png(io::BufWriter::new(fs::File::create(path)?))
+    }
+
+    /// Writes the pixels as a PNG. They are stored without compression, which is quick to
+    /// write and large on disk.
+    pub fn write_png(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(b"\x89PNG\r\n\x1a\n")?;
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&self.width.to_be_bytes());
+        header.extend_from_slice(&self.height.to_be_bytes());
+        // 8 bits per channel, RGBA, and the only compression, filtering and interlacing
+        // methods there are, or none.
+        header.extend_from_slice(&[8, 6, 0, 0, 0]);
+        write_chunk(&mut writer, b"IHDR", &header)?;
+
+        // Every row starts with its filter, here none.
+        let row_bytes = self.width as usize * 4;
+        let mut scanlines = Vec::with_capacity((row_bytes + 1) * self.height as usize);
+        for row in self.pixels.chunks_exact(row_bytes.max(1)) {
+            scanlines.push(0);
+            scanlines.extend_from_slice(row);
+        }
+        write_chunk(&mut writer, b"IDAT", &zlib_stored(&scanlines))?;
+        write_chunk(&mut writer, b"IEND", &[])?;
+        writer.flush()
+    }
+
+    pub fn load_png(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read_png(io::BufReader::new(fs::File::open(path)?))
+    }
+
+    /// Reads a PNG the way `write_png` writes it: 8-bit RGBA without interlacing, filters or
+    /// compression. Other PNGs are refused as `InvalidData`, so an image touched by an image
+    /// editor has to be written again by the renderer. Checksums aren't verified.
+    pub fn read_png(mut reader: impl Read) -> io::Result<Self> {
+        let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidData, message);
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let mut rest = data
+            .strip_prefix(b"\x89PNG\r\n\x1a\n")
+            .ok_or_else(|| invalid("not a PNG"))?;
+
+        let mut header = None;
+        let mut stream = Vec::new();
+        // Each chunk is its length, kind, data and CRC.
+        while rest.len() >= 12 {
+            let len = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            let body = rest
+                .get(8..8 + len)
+                .ok_or_else(|| invalid("truncated chunk"))?;
+            match &rest[4..8] {
+                b"IHDR" => header = Some(body),
+                b"IDAT" => stream.extend_from_slice(body),
+                b"IEND" => break,
+                _ => {}
+            }
+            rest = rest.get(12 + len..).unwrap_or_default();
+        }
+
+        let header = header.ok_or_else(|| invalid("no IHDR chunk"))?;
+        if header.len() != 13 || header[8..] != [8, 6, 0, 0, 0] {
+            return Err(invalid(
+                "only 8-bit RGBA PNGs without interlacing are supported",
+            ));
+        }
+        let width = u32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        let height = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+        let scanlines = zlib_unstored(&stream)
+            .ok_or_else(|| invalid("only PNGs with uncompressed image data are supported"))?;
+
+        let row_bytes = width as usize * 4;
+        if scanlines.len() != (row_bytes + 1) * height as usize {
+            return Err(invalid("image data doesn't match the image size"));
+        }
+        let mut pixels = Vec::with_capacity(row_bytes * height as usize);
+        for row in scanlines.chunks_exact(row_bytes + 1) {
+            if row[0] != 0 {
+                return Err(invalid("only PNGs without filters are supported"));
+            }
+            pixels.extend_from_slice(&row[1..]);
+        }
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+}
+
+/// Why `Frame::capture_frame` didn't produce a screenshot.
+#[derive(Debug)]
+pub enum CaptureError {
+    Surface(wgpu::SurfaceError),
+    Readback(wgpu::BufferAsyncError),
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::Surface(e) => write!(f, "can't draw the frame: {}", e),
+            CaptureError::Readback(e) => write!(f, "can't read the frame back: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+impl From<wgpu::SurfaceError> for CaptureError {
+    fn from(e: wgpu::SurfaceError) -> Self {
+        CaptureError::Surface(e)
+    }
+}
+
+impl From<wgpu::BufferAsyncError> for CaptureError {
+    fn from(e: wgpu::BufferAsyncError) -> Self {
+        CaptureError::Readback(e)
+    }
+}
+
+fn write_chunk(writer: &mut impl Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+    let crc = !crc32(crc32(!0, kind), data);
+    writer.write_all(&crc.to_be_bytes())
+}
+
+/// Updates a CRC-32 as PNG chunks use it, bit by bit rather than with a table since it only
+/// runs once per screenshot.
+fn crc32(mut crc: u32, data: &[u8]) -> u32 {
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+/// A zlib stream of `data` in uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // Blocks hold at most 65535 bytes, each after 5 bytes of header.
+    let blocks = data.len().div_ceil(0xffff).max(1);
+    let mut stream = Vec::with_capacity(data.len() + 5 * blocks + 6);
+    stream.extend_from_slice(&[0x78, 0x01]);
+    let mut chunks = data.chunks(0xffff).peekable();
+    if chunks.peek().is_none() {
+        // An empty final block.
+        stream.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(chunk) = chunks.next() {
+        let last = chunks.peek().is_none();
+        let len = chunk.len() as u16;
+        stream.push(last as u8);
+        stream.extend_from_slice(&len.to_le_bytes());
+        stream.extend_from_slice(&(!len).to_le_bytes());
+        stream.extend_from_slice(chunk);
+    }
+
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in data {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    stream.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    stream
+}
+
+/// The data of a zlib stream of uncompressed deflate blocks, as `zlib_stored` writes it, or
+/// `None` if it holds compressed blocks or ends early.
+fn zlib_unstored(stream: &[u8]) -> Option<Vec<u8>> {
+    let mut rest = stream.get(2..)?;
+    let mut data = Vec::new();
+    loop {
+        let (&header, block) = rest.split_first()?;
+        // The lowest bit marks the last block, the next two are its type, 0 for stored.
+        if header & 0b110 != 0 {
+            return None;
+        }
+        let len = u16::from_le_bytes([*block.first()?, *block.get(1)?]) as usize;
+        data.extend_from_slice(block.get(4..4 + len)?);
+        rest = &block[4 + len..];
+        if header & 1 == 1 {
+            return Some(data);
+        }
+    }
+}
+
+/// Staging buffer that a rendered texture is copied into so it can be read on the CPU.
+pub(crate) struct Readback {
+    buffer: Tracked<wgpu::Buffer>,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    format: wgpu::TextureFormat,
+}
+
+impl Readback {
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        // Rows in a texture-to-buffer copy must be aligned to 256 bytes.
+        let unpadded_bytes_per_row = width * 4;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(align) * align;
+
+        let buffer = Tracked::new(
+            ResourceScope::Renderer,
+            "Readback Buffer",
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Readback Buffer"),
+                size: (padded_bytes_per_row * height) as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            }),
+        );
+
+        Self {
+            buffer,
+            width,
+            height,
+            padded_bytes_per_row,
+            format,
+        }
+    }
+
+    pub fn size(&self) -> [u32; 2] {
+        [self.width, self.height]
+    }
+
+    pub fn copy_from(&self, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture) {
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(self.padded_bytes_per_row),
+                    rows_per_image: Some(self.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// Blocks until the copy has finished and returns tightly packed RGBA8 pixels.
+    pub fn read(&self, device: &wgpu::Device) -> Result<Vec<u8>, wgpu::BufferAsyncError> {
+        let slice = self.buffer.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        device.poll(wgpu::Maintain::Wait);
+        receiver.recv().map_err(|_| wgpu::BufferAsyncError)??;
+
+        let swap_red_blue = matches!(
+            self.format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+        let row_bytes = (self.width * 4) as usize;
+        let mut pixels = Vec::with_capacity(row_bytes * self.height as usize);
+        {
+            let data = slice.get_mapped_range();
+            for row in data.chunks(self.padded_bytes_per_row as usize) {
+                pixels.extend_from_slice(&row[..row_bytes]);
+            }
+        }
+        self.buffer.unmap();
+
+        if swap_red_blue {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        Ok(pixels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(width: u32, height: u32) -> Screenshot {
+        Screenshot {
+            width,
+            height,
+            pixels: (0..width * height * 4)
+                .map(|i| (i * 7 % 251) as u8)
+                .collect(),
+        }
+    }
+
+    fn round_trip(image: &Screenshot) -> Screenshot {
+        let mut png = Vec::new();
+        image.write_png(&mut png).unwrap();
+        Screenshot::read_png(png.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn png_round_trips() {
+        let image = gradient(3, 2);
+        assert_eq!(round_trip(&image), image);
+    }
+
+    #[test]
+    fn png_round_trips_across_several_deflate_blocks() {
+        // 200 rows of 401 bytes each, past the 65535 a block holds.
+        let image = gradient(100, 200);
+        assert_eq!(round_trip(&image), image);
+    }
+
+    #[test]
+    fn empty_png_round_trips() {
+        let image = gradient(0, 0);
+        assert_eq!(round_trip(&image), image);
+    }
+
+    #[test]
+    fn png_ends_with_the_standard_iend_chunk() {
+        let mut png = Vec::new();
+        gradient(1, 1).write_png(&mut png).unwrap();
+        assert!(png.ends_with(b"\0\0\0\0IEND\xae\x42\x60\x82"));
+    }
+
+    #[test]
+    fn crc32_matches_the_check_value() {
+        assert_eq!(!crc32(!0, b"123456789"), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn zlib_stored_ends_with_the_adler32() {
+        assert!(zlib_stored(b"Wikipedia").ends_with(&0x11e6_0398u32.to_be_bytes()));
+    }
+
+    #[test]
+    fn read_png_refuses_other_files() {
+        let error = Screenshot::read_png(&b"GIF89a"[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
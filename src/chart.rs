@@ -0,0 +1,300 @@
+use std::time::Duration;
+
+use glyphon::{FontSystem, Metrics};
+
+use crate::{axis::NumberFormat, rect::Rect, text};
+
+/// The screen rectangle a chart's data range is drawn into.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlotArea {
+    /// Top-left corner.
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    pub x_range: [f64; 2],
+    /// Drawn bottom to top.
+    pub y_range: [f64; 2],
+}
+
+impl PlotArea {
+    pub fn to_screen(&self, data: [f64; 2]) -> [f32; 2] {
+        let x = (data[0] - self.x_range[0]) / (self.x_range[1] - self.x_range[0]);
+        let y = (data[1] - self.y_range[0]) / (self.y_range[1] - self.y_range[0]);
+        [
+            self.position[0] + (x * self.size[0] as f64) as f32,
+            self.position[1] + ((1.0 - y) * self.size[1] as f64) as f32,
+        ]
+    }
+
+    pub fn to_data(&self, screen: [f32; 2]) -> [f64; 2] {
+        let x = ((screen[0] - self.position[0]) / self.size[0]) as f64;
+        let y = 1.0 - ((screen[1] - self.position[1]) / self.size[1]) as f64;
+        [
+            self.x_range[0] + x * (self.x_range[1] - self.x_range[0]),
+            self.y_range[0] + y * (self.y_range[1] - self.y_range[0]),
+        ]
+    }
+
+    pub fn contains(&self, screen: [f32; 2]) -> bool {
+        (0.0..=self.size[0]).contains(&(screen[0] - self.position[0]))
+            && (0.0..=self.size[1]).contains(&(screen[1] - self.position[1]))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Series {
+    pub name: String,
+    pub color: [f32; 4],
+    pub points: Vec<[f64; 2]>,
+    visible: bool,
+    /// Fades towards 1 when visible and 0 when hidden.
+    opacity: f32,
+}
+
+impl Series {
+    pub fn new(name: impl Into<String>, color: [f32; 4], points: Vec<[f64; 2]>) -> Self {
+        Self {
+            name: name.into(),
+            color,
+            points,
+            visible: true,
+            opacity: 1.0,
+        }
+    }
+
+    pub fn visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    pub fn opacity(&self) -> f32 {
+        self.opacity
+    }
+
+    fn faded(&self, color: [f32; 4]) -> [f32; 4] {
+        [color[0], color[1], color[2], color[3] * self.opacity]
+    }
+
+    /// Round markers at each point inside `area`. Nothing once the series has faded out.
+    pub fn dots(&self, area: &PlotArea, size: f32) -> Vec<Rect> {
+        if self.opacity <= 0.0 {
+            return vec![];
+        }
+        self.points
+            .iter()
+            .map(|&point| area.to_screen(point))
+            .filter(|&point| area.contains(point))
+            .map(|position| Rect {
+                border_radius: [size / 2.0; 4],
+                softness: 1.0,
+                ..Rect::solid(position, [size, size], self.faded(self.color))
+            })
+            .collect()
+    }
+}
+
+/// The data point closest to the cursor.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hover {
+    pub series: usize,
+    pub index: usize,
+    /// Screen position of the point.
+    pub position: [f32; 2],
+}
+
+/// A label box placed next to a hovered point.
+#[derive(Clone, Debug)]
+pub struct Tooltip {
+    pub text: String,
+    /// Top-left corner of the text.
+    pub text_position: [f32; 2],
+    pub background: Rect,
+}
+
+/// A vertical list of series names with colored swatches, toggling series when clicked.
+#[derive(Clone, Copy, Debug)]
+pub struct Legend {
+    /// Top-left corner.
+    pub position: [f32; 2],
+    pub row_height: f32,
+    pub width: f32,
+}
+
+impl Legend {
+    /// Which entry `point` is over, if any.
+    pub fn entry_at(&self, point: [f32; 2], entries: usize) -> Option<usize> {
+        let x = point[0] - self.position[0];
+        let y = point[1] - self.position[1];
+        if !(0.0..self.width).contains(&x) || y < 0.0 {
+            return None;
+        }
+        let row = (y / self.row_height) as usize;
+        (row < entries).then_some(row)
+    }
+
+    /// Top-left corner of entry `i`'s name, right of its swatch.
+    pub fn label_position(&self, i: usize) -> [f32; 2] {
+        [
+            self.position[0] + self.row_height,
+            self.position[1] + i as f32 * self.row_height,
+        ]
+    }
+
+    /// One swatch per series. Hidden series keep a dim swatch so they can be turned back on.
+    pub fn swatches(&self, series: &[Series]) -> Vec<Rect> {
+        let size = self.row_height * 0.6;
+        series
+            .iter()
+            .enumerate()
+            .map(|(i, series)| {
+                let dim = 0.25 + 0.75 * series.opacity;
+                let color = [
+                    series.color[0],
+                    series.color[1],
+                    series.color[2],
+                    series.color[3] * dim,
+                ];
+                let center = [
+                    self.position[0] + self.row_height / 2.0,
+                    self.position[1] + (i as f32 + 0.5) * self.row_height,
+                ];
+                Rect::solid(center, [size, size], color)
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Chart {
+    pub area: PlotArea,
+    pub series: Vec<Series>,
+    pub legend: Legend,
+    pub x_format: NumberFormat,
+    pub y_format: NumberFormat,
+    /// How long a series takes to fade in or out when toggled.
+    pub fade: Duration,
+}
+
+impl Chart {
+    /// Advances fade animations. Returns `true` while any are still running.
+    pub fn update(&mut self, dt: Duration) -> bool {
+        let step = if self.fade.is_zero() {
+            1.0
+        } else {
+            dt.as_secs_f32() / self.fade.as_secs_f32()
+        };
+        let mut animating = false;
+        for series in &mut self.series {
+            let target = if series.visible { 1.0 } else { 0.0 };
+            series.opacity = if series.opacity < target {
+                (series.opacity + step).min(target)
+            } else {
+                (series.opacity - step).max(target)
+            };
+            animating |= series.opacity != target;
+        }
+        animating
+    }
+
+    /// Toggles the series whose legend entry is under `point`. Returns whether one was hit.
+    pub fn click(&mut self, point: [f32; 2]) -> bool {
+        let Some(i) = self.legend.entry_at(point, self.series.len()) else {
+            return false;
+        };
+        let series = &mut self.series[i];
+        series.visible = !series.visible;
+        true
+    }
+
+    /// The visible point nearest to `cursor`, within `max_distance` pixels.
+    pub fn nearest(&self, cursor: [f32; 2], max_distance: f32) -> Option<Hover> {
+        if !self.area.contains(cursor) {
+            return None;
+        }
+        let mut best: Option<(f32, Hover)> = None;
+        for (s, series) in self.series.iter().enumerate().filter(|(_, s)| s.visible) {
+            for (index, &point) in series.points.iter().enumerate() {
+                let position = self.area.to_screen(point);
+                let distance = (position[0] - cursor[0]).hypot(position[1] - cursor[1]);
+                if distance <= max_distance && !matches!(best, Some((d, _)) if d <= distance) {
+                    best = Some((
+                        distance,
+                        Hover {
+                            series: s,
+                            index,
+                            position,
+                        },
+                    ));
+                }
+            }
+        }
+        best.map(|(_, hover)| hover)
+    }
+
+    /// Vertical and horizontal lines through `cursor`, spanning the plot area.
+    pub fn crosshair(&self, cursor: [f32; 2], color: [f32; 4]) -> Vec<Rect> {
+        if !self.area.contains(cursor) {
+            return vec![];
+        }
+        let [left, top] = self.area.position;
+        let [width, height] = self.area.size;
+        vec![
+            Rect::solid([cursor[0], top + height / 2.0], [1.0, height], color),
+            Rect::solid([left + width / 2.0, cursor[1]], [width, 1.0], color),
+        ]
+    }
+
+    /// The hovered point's series and values, kept inside the plot area.
+    pub fn tooltip(
+        &self,
+        font_system: &mut FontSystem,
+        metrics: Metrics,
+        hover: Hover,
+        background: [f32; 4],
+    ) -> Tooltip {
+        let series = &self.series[hover.series];
+        let [x, y] = series.points[hover.index];
+        // Enough decimals to tell apart values a thousandth of the visible range apart.
+        let x_step = (self.area.x_range[1] - self.area.x_range[0]).abs() / 1000.0;
+        let y_step = (self.area.y_range[1] - self.area.y_range[0]).abs() / 1000.0;
+        let text = format!(
+            "{}\nx: {}\ny: {}",
+            series.name,
+            self.x_format.format(x, x_step),
+            self.y_format.format(y, y_step),
+        );
+
+        let padding = 6.0;
+        let [text_width, text_height] = text::measure(font_system, &text, metrics);
+        let size = [text_width + padding * 2.0, text_height + padding * 2.0];
+
+        // Below and to the right of the point, flipped when that would leave the area.
+        let area_end = [
+            self.area.position[0] + self.area.size[0],
+            self.area.position[1] + self.area.size[1],
+        ];
+        let corner = [0, 1].map(|axis| {
+            let corner = hover.position[axis] + 12.0;
+            if corner + size[axis] > area_end[axis] {
+                hover.position[axis] - 12.0 - size[axis]
+            } else {
+                corner
+            }
+        });
+
+        Tooltip {
+            text,
+            text_position: [corner[0] + padding, corner[1] + padding],
+            background: Rect {
+                border_radius: [4.0; 4],
+                ..Rect::solid(
+                    [corner[0] + size[0] / 2.0, corner[1] + size[1] / 2.0],
+                    size,
+                    background,
+                )
+            },
+        }
+    }
+}
@@ -0,0 +1,453 @@
+use std::{borrow::Cow, path::PathBuf};
+
+use crate::{camera::Camera, quality::GovernorConfig, snapshot::SnapshotConfig, theme::Theme};
+
+/// Where `(0, 0)` lies in the coordinates rects are positioned with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Origin {
+    /// `(0, 0)` is the top-left corner of the window and y grows downwards.
+    #[default]
+    TopLeft,
+    /// `(0, 0)` is the bottom-left corner of the window and y grows upwards.
+    BottomLeft,
+}
+
+impl Origin {
+    /// Sign applied to the y axis when converting to normalized device coordinates.
+    pub(crate) fn y_direction(self) -> f32 {
+        match self {
+            Origin::TopLeft => 1.0,
+            Origin::BottomLeft => -1.0,
+        }
+    }
+
+    /// Flipping the y axis mirrors the quads, which reverses their winding.
+    pub(crate) fn front_face(self) -> wgpu::FrontFace {
+        match self {
+            Origin::TopLeft => wgpu::FrontFace::Ccw,
+            Origin::BottomLeft => wgpu::FrontFace::Cw,
+        }
+    }
+}
+
+/// How glyphs are placed relative to the pixel grid.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextPositioning {
+    /// Glyphs snap to whole pixels. Crisp, but moving text steps from pixel to pixel and
+    /// shimmers.
+    Snap,
+    /// Glyphs keep their fractional positions and are rasterized at subpixel offsets, which
+    /// keeps motion smooth but stores several variants of each glyph in the atlas.
+    Subpixel,
+    /// Subpixel while text moves, snapped once it comes to rest.
+    #[default]
+    Auto,
+}
+
+impl TextPositioning {
+    /// Whether glyphs should snap to whole pixels this frame.
+    pub fn snaps(self, moving: bool) -> bool {
+        match self {
+            TextPositioning::Snap => true,
+            TextPositioning::Subpixel => false,
+            TextPositioning::Auto => !moving,
+        }
+    }
+}
+
+/// When the renderer frees cached resources it may not need again: rasterized glyphs, rect
+/// buffers that grew for a larger scene than the current one, room in the image atlas left
+/// by replaced images, and the textures of cached layers and meshes of custom nodes that are
+/// off screen. The glyph atlas itself is trimmed every frame, since glyphon relies on that to
+/// know which glyphs are still in use.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CachePolicy {
+    EveryFrame,
+    EveryNFrames(u32),
+    /// Whenever the GPU memory in `resources::stats` exceeds this many bytes, at most once
+    /// every `MEMORY_PRESSURE_COOLDOWN` frames: what is on screen may stay above the limit
+    /// however often it's collected.
+    MemoryPressure {
+        bytes: u64,
+    },
+    /// Only when the frame callback asks for it with `Frame::gc`.
+    Manual,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        CachePolicy::EveryNFrames(600)
+    }
+}
+
+impl CachePolicy {
+    /// The fewest frames between two collections for `MemoryPressure`.
+    pub const MEMORY_PRESSURE_COOLDOWN: u32 = 60;
+
+    /// Whether to collect now, `frames` frames after the last collection, with `bytes` of GPU
+    /// memory in use.
+    pub(crate) fn due(self, frames: u32, bytes: u64) -> bool {
+        match self {
+            CachePolicy::EveryFrame => true,
+            CachePolicy::EveryNFrames(n) => frames >= n,
+            CachePolicy::MemoryPressure { bytes: limit } => {
+                bytes > limit && frames >= Self::MEMORY_PRESSURE_COOLDOWN
+            }
+            CachePolicy::Manual => false,
+        }
+    }
+}
+
+/// How the window's pixels combine with whatever is behind it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WindowAlpha {
+    /// Alpha is ignored and the window covers what is behind it.
+    #[default]
+    Opaque,
+    /// Translucent, with colors the compositor expects multiplied by alpha already.
+    Premultiplied,
+    /// Translucent, with colors the compositor multiplies by alpha itself. Costs an extra pass
+    /// per frame, since everything is blended premultiplied first.
+    Postmultiplied,
+}
+
+impl WindowAlpha {
+    /// Composite modes to try in order of preference. A compositor that only offers the other
+    /// translucent mode still gets a translucent window, since both are handled.
+    pub(crate) fn composite_modes(self) -> &'static [wgpu::CompositeAlphaMode] {
+        use wgpu::CompositeAlphaMode::*;
+        match self {
+            WindowAlpha::Opaque => &[Opaque],
+            WindowAlpha::Premultiplied => &[PreMultiplied, PostMultiplied],
+            WindowAlpha::Postmultiplied => &[PostMultiplied, PreMultiplied],
+        }
+    }
+}
+
+/// Whether the window fills its monitor; see `Frame::set_window_mode`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WindowMode {
+    #[default]
+    Windowed,
+    /// Covers the monitor the window is on, in the monitor's current video mode. Switching is
+    /// quick, and other windows can still be brought in front.
+    Borderless,
+    /// Takes the monitor over in its largest video mode, with the highest refresh rate at
+    /// that size. Falls back to `Borderless` where the monitor's modes aren't known, as on
+    /// Wayland and the web.
+    Exclusive,
+}
+
+/// The mouse cursor shown over the window; see `Frame::set_cursor`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Cursor {
+    #[default]
+    Default,
+    /// A pointing hand, for links and buttons.
+    Pointer,
+    /// An I-beam, for text that can be selected or edited.
+    Text,
+    Crosshair,
+    /// For things that move in any direction when dragged.
+    Move,
+    /// For things that can be picked up and dragged.
+    Grab,
+    /// While something is being dragged.
+    Grabbing,
+    NotAllowed,
+    Wait,
+    /// Arrows left and right, for edges that resize sideways.
+    ResizeHorizontal,
+    /// Arrows up and down.
+    ResizeVertical,
+    /// Arrows from the top-left to the bottom-right corner.
+    ResizeNwse,
+    /// Arrows from the top-right to the bottom-left corner.
+    ResizeNesw,
+    /// No cursor at all, as while the application draws its own.
+    Hidden,
+}
+
+impl Cursor {
+    /// The system cursor to show, none when hidden.
+    pub(crate) fn icon(self) -> Option<winit::window::CursorIcon> {
+        use winit::window::CursorIcon;
+        Some(match self {
+            Cursor::Default => CursorIcon::Default,
+            Cursor::Pointer => CursorIcon::Hand,
+            Cursor::Text => CursorIcon::Text,
+            Cursor::Crosshair => CursorIcon::Crosshair,
+            Cursor::Move => CursorIcon::Move,
+            Cursor::Grab => CursorIcon::Grab,
+            Cursor::Grabbing => CursorIcon::Grabbing,
+            Cursor::NotAllowed => CursorIcon::NotAllowed,
+            Cursor::Wait => CursorIcon::Wait,
+            Cursor::ResizeHorizontal => CursorIcon::EwResize,
+            Cursor::ResizeVertical => CursorIcon::NsResize,
+            Cursor::ResizeNwse => CursorIcon::NwseResize,
+            Cursor::ResizeNesw => CursorIcon::NeswResize,
+            Cursor::Hidden => return None,
+        })
+    }
+}
+
+/// When finished frames replace the one on screen. A mode the display doesn't offer falls
+/// back to the next in line, and in the end to `Fifo`, which every display supports.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PresentMode {
+    /// Vsync on: frames queue up and are shown at vertical blanks. Never tears, but input can
+    /// lag a frame or two behind.
+    #[default]
+    Fifo,
+    /// Shows the newest frame at each vertical blank, dropping older ones. Never tears and
+    /// lags less than `Fifo`, at the cost of drawing frames nobody sees.
+    Mailbox,
+    /// Vsync off: frames are shown as soon as they are done, which can tear. Falls back to
+    /// `Mailbox`.
+    Immediate,
+}
+
+impl PresentMode {
+    /// `Fifo` for vsync, `Immediate` without.
+    pub fn vsync(vsync: bool) -> Self {
+        if vsync {
+            PresentMode::Fifo
+        } else {
+            PresentMode::Immediate
+        }
+    }
+
+    /// The first mode of the fallback chain that is in `supported`.
+    pub(crate) fn choose(self, supported: &[wgpu::PresentMode]) -> wgpu::PresentMode {
+        let chain: &[wgpu::PresentMode] = match self {
+            PresentMode::Fifo => &[],
+            PresentMode::Mailbox => &[wgpu::PresentMode::Mailbox],
+            PresentMode::Immediate => &[wgpu::PresentMode::Immediate, wgpu::PresentMode::Mailbox],
+        };
+        chain
+            .iter()
+            .copied()
+            .find(|mode| supported.contains(mode))
+            .unwrap_or(wgpu::PresentMode::Fifo)
+    }
+}
+
+/// Removes what varies between machines from the rendered frames, so golden images recorded on
+/// one match on every other: text uses only the given fonts and a fixed locale, glyphs snap to
+/// whole pixels, and animations advance by a fixed step per frame instead of by the clock.
+///
+/// GPUs still round gradients and antialiased edges slightly differently, which the golden
+/// tolerance absorbs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Deterministic {
+    /// TrueType or OpenType font data to lay text out with, in place of the system's fonts,
+    /// e.g. from `include_bytes!`. The first font's family is used as the sans-serif family.
+    pub fonts: Vec<Cow<'static, [u8]>>,
+    /// The locale text is shaped for and fallback fonts are chosen by.
+    pub locale: String,
+    /// How many seconds animations advance per rendered frame.
+    pub frame_time: f32,
+}
+
+impl Deterministic {
+    pub fn new(fonts: Vec<Cow<'static, [u8]>>) -> Self {
+        Self {
+            fonts,
+            locale: "en-US".to_string(),
+            frame_time: 1.0 / 60.0,
+        }
+    }
+}
+
+/// Which graphics adapter the renderer runs on. `capabilities::adapters` lists the ones there
+/// are.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AdapterConfig {
+    pub backends: wgpu::Backends,
+    pub power_preference: wgpu::PowerPreference,
+    /// Runs on a software adapter even when a hardware one exists. Without it, a software
+    /// adapter is only used when there is no other.
+    pub force_fallback: bool,
+    /// Runs on the first adapter whose name contains this, ignoring case, e.g. `"nvidia"`.
+    /// When none does, one is picked as if it was unset.
+    pub name: Option<String>,
+}
+
+impl Default for AdapterConfig {
+    fn default() -> Self {
+        Self {
+            backends: wgpu::Backends::all(),
+            power_preference: wgpu::PowerPreference::LowPower,
+            force_fallback: false,
+            name: None,
+        }
+    }
+}
+
+/// Platform effects behind a translucent window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Backdrop {
+    #[default]
+    None,
+    /// Prepares the window for the platform's blurred vibrancy or acrylic backdrop: it is
+    /// created transparent, and on macOS its content extends under a transparent title bar.
+    /// winit has no API for the blur itself, so the application enables it on the raw window
+    /// handle. Needs a translucent `window_alpha`, and only shows through where `background`
+    /// and the scene are translucent.
+    Vibrancy,
+}
+
+/// When the event loop draws a frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RedrawMode {
+    /// Waits for events and draws only when something changed, the frame callback invalidated
+    /// the window with `Frame::invalidate`, or a deadline from `Frame::redraw_at` passed.
+    #[default]
+    OnChange,
+    /// Draws as fast as the present mode allows, whether anything changed or not.
+    Continuous,
+}
+
+#[derive(Clone, Debug)]
+pub struct RendererConfig {
+    pub adapter: AdapterConfig,
+    /// Coordinate convention for rects. Text is always laid out from the top-left.
+    pub origin: Origin,
+    /// Camera the view starts with. Middle-drag pans it and the scroll wheel zooms.
+    pub camera: Camera,
+    /// How many submitted frames may be unfinished on the GPU before rendering the next one
+    /// blocks. Lower values reduce input-to-display latency at the cost of throughput.
+    ///
+    /// wgpu doesn't expose the swapchain image count, so this is enforced by waiting on older
+    /// submissions; the surface may still buffer images on top of it depending on the
+    /// present mode.
+    pub max_frames_in_flight: usize,
+    /// Ignored in favor of `TextPositioning::Snap` when `deterministic` is set.
+    pub text_positioning: TextPositioning,
+    /// Renders the same frames on every machine, for golden images. Off by default.
+    pub deterministic: Option<Deterministic>,
+    /// Overlays the physical pixel grid and outlines rects whose edges fall between pixels.
+    /// F2 toggles it while running.
+    pub pixel_grid: bool,
+    /// Shows a panel listing the draw list's rects by layer, which outlines the one under the
+    /// cursor and edits the selected one's fill color, radius, z index and softness. F12
+    /// toggles it while running.
+    pub inspector: bool,
+    /// The colors of the title bar, the inspector, the theme editor and the menu bar widget.
+    pub theme: Theme,
+    /// Shows a panel along the left edge with a slider for each channel of each color of
+    /// `theme`, which change it live, and a button that saves it to `theme_path`. F9 toggles
+    /// it while running.
+    pub theme_editor: bool,
+    /// Where the theme editor saves the theme, for `Theme::load` to read back. `theme.txt` in
+    /// the working directory by default.
+    pub theme_path: PathBuf,
+    /// Checks that every GPU resource owned by the scene was freed when the window closes,
+    /// logging and asserting on any that weren't. See `resources::stats` for live counts.
+    pub leak_check: bool,
+    pub cache_policy: CachePolicy,
+    /// How far outside a rect's visible edge the cursor still hovers it, in pixels.
+    pub hit_tolerance: f32,
+    /// Periodically saves the rects, text and camera so a crashed run can be resumed. Off by
+    /// default.
+    pub snapshot: Option<SnapshotConfig>,
+    /// Starts over on a new device when the GPU resets or its driver is updated, uploading
+    /// the scene again. This keeps a copy of the scene's images, heatmaps, scatter plots, geo
+    /// layers, shapes and lines; without it, a lost device ends the app. On by default, and
+    /// ignored on wasm32, where a new device can't be waited for without returning to the
+    /// browser.
+    pub device_recovery: bool,
+    /// Lowers the quality while frames miss their budget and raises it again once there's
+    /// headroom; see `quality`. Off by default.
+    pub quality_governor: Option<GovernorConfig>,
+    /// How many bytes of the images added with `Frame::add_image` go up to the GPU each frame.
+    /// A page of images arriving at once is spread over frames instead of stalling one, and
+    /// each image shows once all of it arrived. 4 MiB by default.
+    pub upload_budget: usize,
+    /// Uploads rect instances with half floats and line and geo vertices with 8-bit colors,
+    /// which saves memory bandwidth on integrated GPUs drawing very large scenes. Colors lose
+    /// precision in dark shades and rounded corners and soft edges snap to about three
+    /// significant digits.
+    pub packed_vertices: bool,
+    /// Draws the title bar with the renderer instead of asking the system for decorations, for
+    /// Wayland compositors that leave them to the application. The bar lies over the top 32
+    /// logical pixels of the scene. Off by default.
+    pub client_decorations: bool,
+    /// Creates the window hidden and shows it once the first frame has been presented, so it
+    /// never flashes white or leftover memory before the scene appears. Off by default.
+    pub hidden_until_rendered: bool,
+    pub redraw_mode: RedrawMode,
+    /// Vsync and its alternatives. `Frame::set_present_mode` and `Frame::set_vsync` change it
+    /// while running.
+    pub present_mode: PresentMode,
+    pub window_alpha: WindowAlpha,
+    /// What the frame is cleared to, with straight alpha. Alpha only matters when
+    /// `window_alpha` is translucent.
+    pub background: [f32; 4],
+    pub backdrop: Backdrop,
+    /// The window's menu bar. None by default.
+    #[cfg(feature = "menu")]
+    pub menu_bar: Option<crate::menu::MenuBar>,
+    /// A tray icon with a menu, created when the window opens. None by default.
+    #[cfg(feature = "tray")]
+    pub tray: Option<crate::tray::TrayConfig>,
+    /// The id of the canvas in the page to draw to. Without one, or when the page has no
+    /// canvas with that id, one is added to the page's body. None by default.
+    #[cfg(target_arch = "wasm32")]
+    pub canvas: Option<String>,
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            adapter: AdapterConfig::default(),
+            origin: Origin::default(),
+            camera: Camera::default(),
+            max_frames_in_flight: 2,
+            text_positioning: TextPositioning::default(),
+            deterministic: None,
+            pixel_grid: false,
+            inspector: false,
+            theme: Theme::default(),
+            theme_editor: false,
+            theme_path: PathBuf::from("theme.txt"),
+            leak_check: cfg!(debug_assertions),
+            cache_policy: CachePolicy::default(),
+            hit_tolerance: 1.0,
+            snapshot: None,
+            device_recovery: true,
+            quality_governor: None,
+            upload_budget: 4 << 20,
+            packed_vertices: false,
+            hidden_until_rendered: false,
+            client_decorations: false,
+            redraw_mode: RedrawMode::default(),
+            present_mode: PresentMode::default(),
+            window_alpha: WindowAlpha::default(),
+            background: [0.1, 0.2, 0.3, 1.0],
+            backdrop: Backdrop::default(),
+            #[cfg(feature = "menu")]
+            menu_bar: None,
+            #[cfg(feature = "tray")]
+            tray: None,
+            #[cfg(target_arch = "wasm32")]
+            canvas: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_pressure_waits_between_collections() {
+        let policy = CachePolicy::MemoryPressure { bytes: 1000 };
+        let cooldown = CachePolicy::MEMORY_PRESSURE_COOLDOWN;
+        assert!(!policy.due(cooldown, 1000));
+        assert!(policy.due(cooldown, 1001));
+        // Still over the limit right after collecting.
+        assert!(!policy.due(1, 1001));
+        assert!(!policy.due(cooldown - 1, 1001));
+    }
+}
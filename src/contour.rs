@@ -0,0 +1,216 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::{
+    heatmap::Heatmap,
+    line::{LineJoin, Polyline},
+};
+
+/// Identifies the grid edge a contour point lies on: the edge starting at `(x, y)` and going
+/// right (`false`) or down (`true`). Neighbouring cells share edges, which is how segments
+/// are joined.
+type EdgeKey = (u32, u32, bool);
+
+/// A contour line at one level, in the coordinates of whatever it was extracted from.
+#[derive(Clone, Debug)]
+pub struct Isoline {
+    pub level: f32,
+    pub points: Vec<[f32; 2]>,
+    /// Whether the last point connects back to the first.
+    pub closed: bool,
+}
+
+impl Isoline {
+    /// Where a value label fits best: the middle of the line, measured along its length.
+    pub fn label_position(&self) -> Option<[f32; 2]> {
+        let length: f32 = self.segments().map(|(a, b)| distance(a, b)).sum();
+        let mut remaining = length / 2.0;
+        for (a, b) in self.segments() {
+            let segment = distance(a, b);
+            if segment >= remaining && segment > 0.0 {
+                let t = remaining / segment;
+                return Some([a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t]);
+            }
+            remaining -= segment;
+        }
+        self.points.first().copied()
+    }
+
+    /// The line as a stroke `width` pixels wide, for `Scene::lines` or a custom node.
+    pub fn polyline(&self, width: f32, color: [f32; 4]) -> Polyline {
+        Polyline {
+            points: self
+                .points
+                .iter()
+                .map(|point| point.map(f64::from))
+                .collect(),
+            width,
+            color,
+            join: LineJoin::Round,
+            closed: self.closed,
+            ..Default::default()
+        }
+    }
+
+    fn segments(&self) -> impl Iterator<Item = ([f32; 2], [f32; 2])> + '_ {
+        let closing = if self.closed {
+            self.points.last().zip(self.points.first())
+        } else {
+            None
+        };
+        self.points
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .chain(closing.map(|(&a, &b)| (a, b)))
+    }
+}
+
+fn distance(a: [f32; 2], b: [f32; 2]) -> f32 {
+    ((b[0] - a[0]).powi(2) + (b[1] - a[1]).powi(2)).sqrt()
+}
+
+/// Extracts the contour lines at `level` from a row-major grid with marching squares.
+///
+/// Points are in grid coordinates, where sample `(x, y)` sits at `[x, y]`. Cells touching a
+/// NaN sample are skipped, and saddle cells are resolved with the average of their corners.
+pub fn isolines(values: &[f32], columns: u32, rows: u32, level: f32) -> Vec<Isoline> {
+    let value = |x: u32, y: u32| values[(y * columns + x) as usize];
+
+    let mut points: HashMap<EdgeKey, [f32; 2]> = HashMap::new();
+    let mut segments: Vec<(EdgeKey, EdgeKey)> = vec![];
+
+    for y in 0..rows.saturating_sub(1) {
+        for x in 0..columns.saturating_sub(1) {
+            let corners = [
+                value(x, y),
+                value(x + 1, y),
+                value(x + 1, y + 1),
+                value(x, y + 1),
+            ];
+            if corners.iter().any(|v| v.is_nan()) {
+                continue;
+            }
+
+            // Edges in order top, right, bottom, left, each with the corners it connects.
+            let edges: [(EdgeKey, usize, usize); 4] = [
+                ((x, y, false), 0, 1),
+                ((x + 1, y, true), 1, 2),
+                ((x, y + 1, false), 3, 2),
+                ((x, y, true), 0, 3),
+            ];
+            let mut point_on = |edge: usize| {
+                let (key, from, to) = edges[edge];
+                let (a, b) = (corners[from], corners[to]);
+                let t = if b == a { 0.5 } else { (level - a) / (b - a) };
+                let corner = |i: usize| match i {
+                    0 => [x as f32, y as f32],
+                    1 => [x as f32 + 1.0, y as f32],
+                    2 => [x as f32 + 1.0, y as f32 + 1.0],
+                    _ => [x as f32, y as f32 + 1.0],
+                };
+                let (p, q) = (corner(from), corner(to));
+                points.insert(key, [p[0] + (q[0] - p[0]) * t, p[1] + (q[1] - p[1]) * t]);
+                key
+            };
+
+            let case = corners
+                .iter()
+                .enumerate()
+                .fold(0, |case, (i, &v)| case | ((v >= level) as u8) << i);
+            let center_inside = corners.iter().sum::<f32>() / 4.0 >= level;
+
+            let pairs: &[(usize, usize)] = match case {
+                1 | 14 => &[(3, 0)],
+                2 | 13 => &[(0, 1)],
+                3 | 12 => &[(3, 1)],
+                4 | 11 => &[(1, 2)],
+                6 | 9 => &[(0, 2)],
+                7 | 8 => &[(3, 2)],
+                5 if center_inside => &[(0, 1), (2, 3)],
+                5 => &[(3, 0), (1, 2)],
+                10 if center_inside => &[(3, 0), (1, 2)],
+                10 => &[(0, 1), (2, 3)],
+                _ => &[],
+            };
+            for &(a, b) in pairs {
+                segments.push((point_on(a), point_on(b)));
+            }
+        }
+    }
+
+    join(&segments, &points, level)
+}
+
+/// Chains segments that share an edge into polylines.
+fn join(
+    segments: &[(EdgeKey, EdgeKey)],
+    points: &HashMap<EdgeKey, [f32; 2]>,
+    level: f32,
+) -> Vec<Isoline> {
+    let mut by_edge: HashMap<EdgeKey, Vec<usize>> = HashMap::new();
+    for (i, &(a, b)) in segments.iter().enumerate() {
+        by_edge.entry(a).or_default().push(i);
+        by_edge.entry(b).or_default().push(i);
+    }
+
+    let mut used = vec![false; segments.len()];
+    let next_from = |key: EdgeKey, used: &mut [bool]| {
+        let i = *by_edge.get(&key)?.iter().find(|&&i| !used[i])?;
+        used[i] = true;
+        let (a, b) = segments[i];
+        Some(if a == key { b } else { a })
+    };
+
+    let mut lines = vec![];
+    for (start, &(a, b)) in segments.iter().enumerate() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+        let mut chain = VecDeque::from([a, b]);
+
+        let mut closed = false;
+        while let Some(key) = next_from(*chain.back().unwrap(), &mut used) {
+            if key == chain[0] {
+                closed = true;
+                break;
+            }
+            chain.push_back(key);
+        }
+        if !closed {
+            while let Some(key) = next_from(chain[0], &mut used) {
+                chain.push_front(key);
+            }
+        }
+
+        lines.push(Isoline {
+            level,
+            points: chain.iter().map(|key| points[key]).collect(),
+            closed,
+        });
+    }
+    lines
+}
+
+impl Heatmap {
+    /// Contour lines through the heatmap's values at `level`, in the same coordinates as its
+    /// `position`, so they line up with the drawn cells.
+    pub fn isolines(&self, level: f32) -> Vec<Isoline> {
+        let cell = [
+            self.size[0] / self.columns as f32,
+            self.size[1] / self.rows as f32,
+        ];
+        let origin = [
+            self.position[0] - self.size[0] / 2.0 + cell[0] / 2.0,
+            self.position[1] - self.size[1] / 2.0 + cell[1] / 2.0,
+        ];
+
+        let mut lines = isolines(&self.values, self.columns, self.rows, level);
+        for point in lines.iter_mut().flat_map(|line| &mut line.points) {
+            *point = [
+                origin[0] + point[0] * cell[0],
+                origin[1] + point[1] * cell[1],
+            ];
+        }
+        lines
+    }
+}
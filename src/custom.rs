@@ -0,0 +1,242 @@
+//! Geometry built by the application itself, such as a plugin's, drawn through the geo
+//! pipeline like the scene's shapes and lines. Building and tessellating it can take longer
+//! than a frame, so each node is only built again when its cache key changes or it is
+//! invalidated.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    camera::Camera,
+    geo::{GeoMesh, GeoPipeline},
+    line::Polyline,
+    path::{PathCommand, Shape},
+};
+
+/// Something the application draws with geometry of its own; see `Frame::draw_custom`.
+pub trait CustomDrawable {
+    /// Tells one version of the geometry from another: a hash of what it is built from, or a
+    /// counter bumped whenever that changes. Two builds with the same key are taken to be the
+    /// same, so `build` isn't called for it again.
+    fn cache_key(&self) -> u64;
+
+    /// The geometry to draw, in world space.
+    fn build(&self) -> CustomGeometry;
+}
+
+/// Shapes and lines of a custom node, drawn in that order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CustomGeometry {
+    pub shapes: Vec<Shape>,
+    pub lines: Vec<Polyline>,
+}
+
+impl CustomGeometry {
+    /// The world box around every point of the geometry, curves' control points included,
+    /// and the widest stroke in pixels. `None` without any points.
+    fn bounds(&self) -> Option<([[f64; 2]; 2], f32)> {
+        let paths = self.shapes.iter().flat_map(|shape| {
+            shape
+                .path
+                .commands
+                .iter()
+                .flat_map(|command| match *command {
+                    PathCommand::MoveTo(point) | PathCommand::LineTo(point) => vec![point],
+                    PathCommand::QuadTo(control, point) => vec![control, point],
+                    PathCommand::CubicTo(control_1, control_2, point) => {
+                        vec![control_1, control_2, point]
+                    }
+                    PathCommand::Close => vec![],
+                })
+        });
+        let lines = self
+            .lines
+            .iter()
+            .flat_map(|line| line.points.iter().copied());
+        let mut points = paths
+            .chain(lines)
+            .filter(|point| point.iter().all(|v| v.is_finite()));
+        let first = points.next()?;
+        let bounds = points.fold([first, first], |[min, max], point| {
+            [
+                [min[0].min(point[0]), min[1].min(point[1])],
+                [max[0].max(point[0]), max[1].max(point[1])],
+            ]
+        });
+        let strokes = self.shapes.iter().filter_map(|shape| shape.stroke);
+        let width = strokes
+            .map(|stroke| stroke.width)
+            .chain(self.lines.iter().map(|line| line.width))
+            .fold(0.0, f32::max);
+        Some((bounds, width))
+    }
+}
+
+struct CustomNode {
+    cache_key: u64,
+    /// Set by `invalidate`, so the next draw builds the node whatever its key.
+    stale: bool,
+    /// Kept to upload again on a new device, or once the node is back in view.
+    geometry: CustomGeometry,
+    /// `CustomGeometry::bounds`, worked out once per build.
+    bounds: Option<([[f64; 2]; 2], f32)>,
+    /// `None` until the geometry is uploaded.
+    meshes: Option<Vec<GeoMesh>>,
+}
+
+impl CustomNode {
+    fn visible(&self, camera: &Camera, view: [f32; 2]) -> bool {
+        match self.bounds {
+            Some(([min, max], width)) => camera.sees(min, max, width as f64, view),
+            None => false,
+        }
+    }
+}
+
+/// The custom nodes of a window by key, drawn in key order over the scene's shapes and lines.
+#[derive(Default)]
+pub(crate) struct CustomNodes {
+    nodes: BTreeMap<u64, CustomNode>,
+    /// Whether a node was built or removed since the last upload.
+    changed: bool,
+}
+
+impl CustomNodes {
+    /// Builds `drawable` as the node `key` unless it was built with the same cache key
+    /// before and hasn't been invalidated since.
+    pub fn draw(&mut self, key: u64, drawable: &dyn CustomDrawable) {
+        let cache_key = drawable.cache_key();
+        if let Some(node) = self.nodes.get(&key) {
+            if node.cache_key == cache_key && !node.stale {
+                return;
+            }
+        }
+        let geometry = drawable.build();
+        self.nodes.insert(
+            key,
+            CustomNode {
+                cache_key,
+                stale: false,
+                bounds: geometry.bounds(),
+                geometry,
+                meshes: None,
+            },
+        );
+        self.changed = true;
+    }
+
+    pub fn invalidate(&mut self, key: u64) {
+        if let Some(node) = self.nodes.get_mut(&key) {
+            node.stale = true;
+        }
+    }
+
+    pub fn remove(&mut self, key: u64) {
+        if self.nodes.remove(&key).is_some() {
+            self.changed = true;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.changed |= !self.nodes.is_empty();
+        self.nodes.clear();
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.changed
+    }
+
+    /// Drops the uploaded meshes, so every node is uploaded again; for a new device.
+    pub fn reset(&mut self) {
+        for node in self.nodes.values_mut() {
+            node.meshes = None;
+        }
+        self.changed = !self.nodes.is_empty();
+    }
+
+    /// Drops the uploaded meshes of the nodes off a screen of `view` pixels, which are
+    /// uploaded again once they come into view. Returns whether any were dropped.
+    pub fn evict_offscreen(&mut self, camera: &Camera, view: [f32; 2]) -> bool {
+        let mut evicted = false;
+        for node in self.nodes.values_mut() {
+            if node.meshes.is_some() && !node.visible(camera, view) {
+                node.meshes = None;
+                evicted = true;
+            }
+        }
+        evicted
+    }
+
+    /// Whether `upload` has anything to do: nodes built since the last call, or evicted
+    /// ones back in view.
+    pub fn needs_upload(&self, camera: &Camera, view: [f32; 2]) -> bool {
+        self.changed
+            || self
+                .nodes
+                .values()
+                .any(|node| node.meshes.is_none() && node.visible(camera, view))
+    }
+
+    /// Tessellates and uploads the nodes on a screen of `view` pixels that were built or
+    /// evicted since they were last uploaded. Returns how many there were.
+    pub fn upload(
+        &mut self,
+        device: &wgpu::Device,
+        pipeline: &GeoPipeline,
+        camera: &Camera,
+        view: [f32; 2],
+    ) -> usize {
+        self.changed = false;
+        let mut uploaded = 0;
+        for node in self
+            .nodes
+            .values_mut()
+            .filter(|node| node.meshes.is_none() && node.visible(camera, view))
+        {
+            let geometry = &node.geometry;
+            let shapes = geometry
+                .shapes
+                .iter()
+                .map(|shape| pipeline.prepare_shape(device, shape));
+            let lines = geometry
+                .lines
+                .iter()
+                .map(|line| pipeline.prepare_line(device, line));
+            node.meshes = Some(shapes.chain(lines).collect());
+            uploaded += 1;
+        }
+        uploaded
+    }
+
+    /// The uploaded meshes in draw order.
+    pub fn meshes(&self) -> impl Iterator<Item = &GeoMesh> {
+        self.nodes
+            .values()
+            .filter_map(|node| node.meshes.as_ref())
+            .flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path::{Path, PathStroke};
+
+    #[test]
+    fn bounds_cover_paths_and_lines() {
+        let geometry = CustomGeometry {
+            shapes: vec![Shape {
+                path: Path::new()
+                    .move_to([0.0, 0.0])
+                    .quad_to([5.0, -10.0], [10.0, 0.0]),
+                stroke: Some(PathStroke {
+                    width: 3.0,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }],
+            lines: vec![Polyline::line([-4.0, 2.0], [f64::NAN, 50.0], 2.0, [1.0; 4])],
+        };
+        assert_eq!(geometry.bounds(), Some(([[-4.0, -10.0], [10.0, 2.0]], 3.0)));
+        assert_eq!(CustomGeometry::default().bounds(), None);
+    }
+}
@@ -0,0 +1,191 @@
+//! Native open and save dialogs that don't block the render loop.
+//!
+//! Dialogs are requested from the frame callback through `Frame::dialogs` and open once the
+//! callback returns, attached to the window. The frame keeps drawing while one is open, and
+//! the chosen paths reach the callback in a later frame as `Dialogs::results`.
+
+use std::{
+    future::Future,
+    path::PathBuf,
+    pin::Pin,
+    sync::mpsc::{self, Receiver, Sender},
+};
+
+use rfd::{AsyncFileDialog, FileHandle};
+use winit::{event_loop::EventLoopProxy, window::Window};
+
+/// The title, starting place and file types of a dialog.
+#[derive(Clone, Debug, Default)]
+pub struct FileDialog {
+    title: Option<String>,
+    directory: Option<PathBuf>,
+    file_name: Option<String>,
+    /// Names and their extensions, without dots.
+    filters: Vec<(String, Vec<String>)>,
+}
+
+impl FileDialog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_title(mut self, title: impl Into<String>) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    pub fn with_directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.directory = Some(directory.into());
+        self
+    }
+
+    /// The name a save dialog suggests.
+    pub fn with_file_name(mut self, file_name: impl Into<String>) -> Self {
+        self.file_name = Some(file_name.into());
+        self
+    }
+
+    /// Offers the files with `extensions`, written without dots, under `name`.
+    pub fn with_filter(mut self, name: impl Into<String>, extensions: &[&str]) -> Self {
+        let extensions = extensions.iter().map(|e| e.to_string()).collect();
+        self.filters.push((name.into(), extensions));
+        self
+    }
+
+    fn build(&self, window: &Window) -> AsyncFileDialog {
+        let mut dialog = AsyncFileDialog::new().set_parent(window);
+        if let Some(title) = &self.title {
+            dialog = dialog.set_title(title);
+        }
+        if let Some(directory) = &self.directory {
+            dialog = dialog.set_directory(directory);
+        }
+        if let Some(file_name) = &self.file_name {
+            dialog = dialog.set_file_name(file_name);
+        }
+        for (name, extensions) in &self.filters {
+            dialog = dialog.add_filter(name.as_str(), extensions.as_slice());
+        }
+        dialog
+    }
+}
+
+/// Identifies a dialog's result among others opened at the same time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DialogId(u64);
+
+/// What the user chose in a dialog.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DialogResult {
+    pub id: DialogId,
+    /// Empty when the dialog was cancelled.
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Open,
+    OpenMany,
+    Save,
+}
+
+/// The window's file dialogs, handed to the frame callback as `Frame::dialogs`.
+pub struct Dialogs {
+    next_id: u64,
+    requested: Vec<(DialogId, Kind, FileDialog)>,
+    results: Vec<DialogResult>,
+    sender: Sender<DialogResult>,
+    receiver: Receiver<DialogResult>,
+    /// Wakes the event loop when a dialog closes.
+    proxy: EventLoopProxy<()>,
+}
+
+impl Dialogs {
+    pub(crate) fn new(proxy: EventLoopProxy<()>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            next_id: 0,
+            requested: Vec::new(),
+            results: Vec::new(),
+            sender,
+            receiver,
+            proxy,
+        }
+    }
+
+    /// Asks for one existing file.
+    pub fn open_file(&mut self, dialog: FileDialog) -> DialogId {
+        self.request(Kind::Open, dialog)
+    }
+
+    /// Asks for any number of existing files.
+    pub fn open_files(&mut self, dialog: FileDialog) -> DialogId {
+        self.request(Kind::OpenMany, dialog)
+    }
+
+    /// Asks where to save a file.
+    pub fn save_file(&mut self, dialog: FileDialog) -> DialogId {
+        self.request(Kind::Save, dialog)
+    }
+
+    /// The dialogs closed since the previous frame.
+    pub fn results(&self) -> &[DialogResult] {
+        &self.results
+    }
+
+    fn request(&mut self, kind: Kind, dialog: FileDialog) -> DialogId {
+        let id = DialogId(self.next_id);
+        self.next_id += 1;
+        self.requested.push((id, kind, dialog));
+        id
+    }
+
+    /// Replaces the results with the dialogs closed since the last call.
+    pub(crate) fn poll(&mut self) {
+        self.results.clear();
+        self.results.extend(self.receiver.try_iter());
+    }
+
+    /// Opens the dialogs requested since the last call on `window`. They are shown from the
+    /// event loop's thread as macOS requires, and waited for on their own threads.
+    pub(crate) fn apply(&mut self, window: &Window) {
+        for (id, kind, dialog) in self.requested.drain(..) {
+            let dialog = dialog.build(window);
+            let sender = self.sender.clone();
+            let proxy = self.proxy.clone();
+            // The dialog is shown by these calls, so they stay on this thread, and only
+            // waiting for it moves.
+            let chosen: Pin<Box<dyn Future<Output = Vec<FileHandle>> + Send>> = match kind {
+                Kind::Open => {
+                    let file = dialog.pick_file();
+                    Box::pin(async move { file.await.into_iter().collect::<Vec<_>>() })
+                }
+                Kind::OpenMany => {
+                    let files = dialog.pick_files();
+                    Box::pin(async move { files.await.unwrap_or_default() })
+                }
+                Kind::Save => {
+                    let file = dialog.save_file();
+                    Box::pin(async move { file.await.into_iter().collect::<Vec<_>>() })
+                }
+            };
+            std::thread::spawn(move || {
+                let runtime = match tokio::runtime::Builder::new_current_thread().build() {
+                    Ok(runtime) => runtime,
+                    Err(error) => {
+                        log::error!("Can't wait for a file dialog: {error}");
+                        return;
+                    }
+                };
+                let paths = runtime
+                    .block_on(chosen)
+                    .iter()
+                    .map(|file| file.path().to_path_buf())
+                    .collect();
+                // Both only fail once the window closed, when nobody waits for the result.
+                let _ = sender.send(DialogResult { id, paths });
+                let _ = proxy.send_event(());
+            });
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use std::{cell::RefCell, rc::Rc};
+
+use egui::epaint::ClippedPrimitive;
+use egui_wgpu::renderer::ScreenDescriptor;
+use winit::{event::WindowEvent, event_loop::EventLoopWindowTarget, window::Window};
+
+/// The scene drawn into a texture for egui to show, for windows built with
+/// `AppBuilder::with_egui_scene`. It has the window's size and camera, so an image of `size`
+/// shows it unscaled.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SceneTexture {
+    pub id: egui::TextureId,
+    /// In points.
+    pub size: egui::Vec2,
+}
+
+/// The ui closure of a window's egui layer, shared by clones of its `AppBuilder`.
+#[derive(Clone)]
+pub(crate) struct EguiUi {
+    ui: Rc<RefCell<dyn FnMut(&egui::Context, Option<SceneTexture>)>>,
+    /// Whether the scene is drawn into a texture for the ui rather than under it.
+    scene_texture: bool,
+}
+
+impl EguiUi {
+    pub fn overlay(mut ui: impl FnMut(&egui::Context) + 'static) -> Self {
+        Self {
+            ui: Rc::new(RefCell::new(
+                move |context: &egui::Context, _: Option<SceneTexture>| ui(context),
+            )),
+            scene_texture: false,
+        }
+    }
+
+    pub fn scene(mut ui: impl FnMut(&egui::Context, SceneTexture) + 'static) -> Self {
+        Self {
+            ui: Rc::new(RefCell::new(
+                move |context: &egui::Context, scene: Option<SceneTexture>| {
+                    ui(
+                        context,
+                        scene.expect("the scene texture is made before the ui runs"),
+                    )
+                },
+            )),
+            scene_texture: true,
+        }
+    }
+}
+
+/// The texture the scene is drawn into when egui hosts it.
+struct SceneTarget {
+    view: wgpu::TextureView,
+    id: egui::TextureId,
+    size: [u32; 2],
+}
+
+/// Draws an egui context on top of the rects and text, inside the same render pass, or with
+/// the scene in a texture its widgets show; see `AppBuilder::with_egui`.
+/// With the `primary-selection` feature, its text fields also use the primary selection on
+/// Linux; see `primary_selection`.
+pub struct EguiLayer {
+    context: egui::Context,
+    winit_state: egui_winit::State,
+    renderer: egui_wgpu::Renderer,
+    ui: EguiUi,
+    /// `None` while the scene is drawn under the ui, or until the first frame.
+    scene: Option<SceneTarget>,
+    paint_jobs: Vec<ClippedPrimitive>,
+    textures_to_free: Vec<egui::TextureId>,
+    screen_descriptor: ScreenDescriptor,
+    #[cfg(all(feature = "primary-selection", target_os = "linux"))]
+    primary_selection: Option<crate::primary_selection::PrimarySelection>,
+}
+
+impl EguiLayer {
+    pub(crate) fn new<T>(
+        event_loop: &EventLoopWindowTarget<T>,
+        window: &Window,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        ui: EguiUi,
+    ) -> Self {
+        let mut winit_state = egui_winit::State::new(event_loop);
+        winit_state.set_pixels_per_point(window.scale_factor() as f32);
+
+        Self {
+            context: egui::Context::default(),
+            winit_state,
+            renderer: egui_wgpu::Renderer::new(device, format, None, 1),
+            ui,
+            scene: None,
+            paint_jobs: vec![],
+            textures_to_free: vec![],
+            screen_descriptor: ScreenDescriptor {
+                size_in_pixels: [window.inner_size().width, window.inner_size().height],
+                pixels_per_point: window.scale_factor() as f32,
+            },
+            #[cfg(all(feature = "primary-selection", target_os = "linux"))]
+            primary_selection: crate::primary_selection::PrimarySelection::new(),
+        }
+    }
+
+    pub fn context(&self) -> &egui::Context {
+        &self.context
+    }
+
+    /// Starts over on a new device after the old one was lost. egui sends its font texture
+    /// again, but textures the ui loaded itself are gone.
+    pub(crate) fn recreate(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        self.renderer = egui_wgpu::Renderer::new(device, format, None, 1);
+        self.scene = None;
+        self.paint_jobs.clear();
+        self.textures_to_free.clear();
+        let fonts = self
+            .context
+            .fonts(|fonts| fonts.lock().fonts.definitions().clone());
+        self.context.set_fonts(fonts);
+    }
+
+    /// Forwards a window event to egui. Returns `true` if egui consumed it.
+    pub fn on_event(&mut self, event: &WindowEvent) -> bool {
+        #[cfg(all(feature = "primary-selection", target_os = "linux"))]
+        if let Some(primary_selection) = &mut self.primary_selection {
+            if primary_selection.on_event(&self.context, event) {
+                return true;
+            }
+        }
+        self.winit_state.on_event(&self.context, event).consumed
+    }
+
+    /// The texture to draw the scene into before the layer, if egui hosts the scene.
+    pub(crate) fn scene_view(&self) -> Option<&wgpu::TextureView> {
+        self.scene.as_ref().map(|scene| &scene.view)
+    }
+
+    /// Makes the scene texture `size` pixels large, registering it with egui when it is new.
+    fn resize_scene(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat, size: [u32; 2]) {
+        if self.scene.as_ref().is_some_and(|scene| scene.size == size) {
+            return;
+        }
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("egui Scene Texture"),
+            size: wgpu::Extent3d {
+                width: size[0],
+                height: size[1],
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let id = match &self.scene {
+            Some(scene) => {
+                self.renderer.update_egui_texture_from_wgpu_texture(
+                    device,
+                    &view,
+                    wgpu::FilterMode::Linear,
+                    scene.id,
+                );
+                scene.id
+            }
+            None => self
+                .renderer
+                .register_native_texture(device, &view, wgpu::FilterMode::Linear),
+        };
+        self.scene = Some(SceneTarget { view, id, size });
+    }
+
+    /// Runs the ui closure, uploads textures and records the vertex/index buffer updates.
+    /// The returned command buffers must be submitted before the encoder that renders the layer.
+    ///
+    /// `size` is the surface's, which the scene texture takes so it matches the depth buffer the
+    /// scene is drawn with. It lags behind the window's while minimized or while a new scale
+    /// factor is pending.
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        window: &Window,
+        format: wgpu::TextureFormat,
+        size: [u32; 2],
+    ) -> Vec<wgpu::CommandBuffer> {
+        for id in self.textures_to_free.drain(..) {
+            self.renderer.free_texture(&id);
+        }
+        if self.ui.scene_texture {
+            self.resize_scene(device, format, size);
+        }
+
+        #[allow(unused_mut)]
+        let mut raw_input = self.winit_state.take_egui_input(window);
+        #[cfg(all(feature = "primary-selection", target_os = "linux"))]
+        if let Some(primary_selection) = &mut self.primary_selection {
+            primary_selection.begin_frame(&mut raw_input);
+        }
+        let scene = self.scene.as_ref().map(|scene| SceneTexture {
+            id: scene.id,
+            size: egui::vec2(scene.size[0] as f32, scene.size[1] as f32)
+                / self.winit_state.pixels_per_point(),
+        });
+        let mut ui = self.ui.ui.borrow_mut();
+        #[allow(unused_mut)]
+        let mut full_output = self.context.run(raw_input, |context| (*ui)(context, scene));
+        drop(ui);
+        #[cfg(all(feature = "primary-selection", target_os = "linux"))]
+        if let Some(primary_selection) = &mut self.primary_selection {
+            primary_selection.end_frame(&self.context, &mut full_output.platform_output);
+        }
+        self.winit_state
+            .handle_platform_output(window, &self.context, full_output.platform_output);
+
+        self.paint_jobs = self.context.tessellate(full_output.shapes);
+        self.screen_descriptor = ScreenDescriptor {
+            size_in_pixels: size,
+            pixels_per_point: self.context.pixels_per_point(),
+        };
+
+        for (id, image_delta) in &full_output.textures_delta.set {
+            self.renderer
+                .update_texture(device, queue, *id, image_delta);
+        }
+        self.textures_to_free = full_output.textures_delta.free;
+
+        self.renderer.update_buffers(
+            device,
+            queue,
+            encoder,
+            &self.paint_jobs,
+            &self.screen_descriptor,
+        )
+    }
+
+    pub fn render<'rp>(&'rp self, render_pass: &mut wgpu::RenderPass<'rp>) {
+        self.renderer
+            .render(render_pass, &self.paint_jobs, &self.screen_descriptor);
+    }
+}
@@ -0,0 +1,57 @@
+use std::fmt;
+
+/// Why the renderer couldn't be set up.
+#[derive(Debug)]
+pub enum RendererError {
+    /// The window couldn't be opened.
+    Window(winit::error::OsError),
+    SurfaceCreation(wgpu::CreateSurfaceError),
+    /// No adapter on the configured backends can draw to the window, software ones included.
+    NoAdapter,
+    DeviceRequest(wgpu::RequestDeviceError),
+    /// The adapter reports no texture format or alpha mode the window can be drawn in.
+    UnsupportedFormat,
+}
+
+impl fmt::Display for RendererError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RendererError::Window(e) => write!(f, "can't open the window: {}", e),
+            RendererError::SurfaceCreation(e) => write!(f, "can't create the surface: {}", e),
+            RendererError::NoAdapter => write!(f, "no graphics adapter available"),
+            RendererError::DeviceRequest(e) => write!(f, "can't open the device: {}", e),
+            RendererError::UnsupportedFormat => {
+                write!(f, "the adapter can't draw to the window in any format")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RendererError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RendererError::Window(e) => Some(e),
+            RendererError::SurfaceCreation(e) => Some(e),
+            RendererError::DeviceRequest(e) => Some(e),
+            RendererError::NoAdapter | RendererError::UnsupportedFormat => None,
+        }
+    }
+}
+
+impl From<winit::error::OsError> for RendererError {
+    fn from(e: winit::error::OsError) -> Self {
+        RendererError::Window(e)
+    }
+}
+
+impl From<wgpu::CreateSurfaceError> for RendererError {
+    fn from(e: wgpu::CreateSurfaceError) -> Self {
+        RendererError::SurfaceCreation(e)
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for RendererError {
+    fn from(e: wgpu::RequestDeviceError) -> Self {
+        RendererError::DeviceRequest(e)
+    }
+}
@@ -0,0 +1,143 @@
+//! Reading the color of one pixel of a drawn frame without stalling on the GPU. The pixel is
+//! copied into a small buffer along with the frame and mapped once the GPU is done with it, a
+//! frame or two later, the way scatter picks are read back.
+
+use std::sync::{Arc, Mutex};
+
+use crate::resources::{ResourceScope, Tracked};
+
+pub(crate) struct Eyedropper {
+    /// One row of a texture copy, which is padded to `COPY_BYTES_PER_ROW_ALIGNMENT`.
+    buffer: Tracked<wgpu::Buffer>,
+    request: Option<[u32; 2]>,
+    encoded: bool,
+    in_flight: bool,
+    mapped: Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>,
+    /// The format of the texture the pixel was copied from.
+    format: wgpu::TextureFormat,
+}
+
+impl Eyedropper {
+    pub fn new(device: &wgpu::Device) -> Self {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Eyedropper Buffer"),
+            size: wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        Self {
+            buffer: Tracked::new(ResourceScope::Renderer, "Eyedropper Buffer", buffer),
+            request: None,
+            encoded: false,
+            in_flight: false,
+            mapped: Arc::default(),
+            format: wgpu::TextureFormat::Rgba8Unorm,
+        }
+    }
+
+    /// Reads the pixel at `point` from the next frame, or the one after a read still in
+    /// flight. A later request replaces one that wasn't encoded yet.
+    pub fn request(&mut self, point: [u32; 2]) {
+        self.request = Some(point);
+    }
+
+    /// Whether a read was requested and hasn't been collected by `poll` yet. Frames have to
+    /// keep coming until it is, since the pixel is copied with them.
+    pub fn sampling(&self) -> bool {
+        self.request.is_some() || self.encoded || self.in_flight
+    }
+
+    /// Copies the requested pixel of `texture`, the frame just encoded.
+    pub fn encode(&mut self, encoder: &mut wgpu::CommandEncoder, texture: &wgpu::Texture) {
+        if self.encoded || self.in_flight {
+            return;
+        }
+        let Some([x, y]) = self.request.take() else {
+            return;
+        };
+        let format = texture.format();
+        if !matches!(
+            format,
+            wgpu::TextureFormat::Rgba8Unorm
+                | wgpu::TextureFormat::Rgba8UnormSrgb
+                | wgpu::TextureFormat::Bgra8Unorm
+                | wgpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            log::warn!("the eyedropper can't read {:?} pixels", format);
+            return;
+        }
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: x.min(texture.width() - 1),
+                    y: y.min(texture.height() - 1),
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &self.buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.format = format;
+        self.encoded = true;
+    }
+
+    /// Starts reading back a pixel that was copied this frame. Call after submitting.
+    pub fn after_submit(&mut self) {
+        if !self.encoded {
+            return;
+        }
+        self.encoded = false;
+        self.in_flight = true;
+
+        let mapped = self.mapped.clone();
+        self.buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                *mapped.lock().unwrap() = Some(result);
+            });
+    }
+
+    /// Collects a finished read: the pixel's color in linear RGB like `Fill` colors, with the
+    /// frame's alpha.
+    pub fn poll(&mut self) -> Option<[f32; 4]> {
+        let result = self.mapped.lock().unwrap().take()?;
+        self.in_flight = false;
+        result.ok()?;
+
+        let mut pixel = [0; 4];
+        pixel.copy_from_slice(&self.buffer.slice(..).get_mapped_range()[..4]);
+        self.buffer.unmap();
+
+        if matches!(
+            self.format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            pixel.swap(0, 2);
+        }
+        let srgb = self.format.is_srgb();
+        Some([0, 1, 2, 3].map(|c| {
+            let value = pixel[c] as f32 / 255.0;
+            if c == 3 || !srgb {
+                value
+            } else if value <= 0.04045 {
+                value / 12.92
+            } else {
+                ((value + 0.055) / 1.055).powf(2.4)
+            }
+        }))
+    }
+}
@@ -0,0 +1,194 @@
+use std::fmt;
+
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use crate::rect::{
+    Fill, Geometry, GradientStop, GradientStops, Rect, Stroke, MAX_GRADIENT_STOPS,
+    MAX_NOISE_OCTAVES,
+};
+
+/// Generates random but reproducible rect scenes. The same seed always yields the same scene.
+pub struct SceneFuzzer {
+    state: u64,
+    width: f32,
+    height: f32,
+}
+
+impl SceneFuzzer {
+    /// Scenes are spread over (and slightly past) a `width` x `height` viewport.
+    pub fn new(seed: u64, width: f32, height: f32) -> Self {
+        Self {
+            state: seed,
+            width,
+            height,
+        }
+    }
+
+    // SplitMix64
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`.
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn range(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    /// Picks one of the edge values a quarter of the time, otherwise a value in `min..max`.
+    fn range_or_edge(&mut self, min: f32, max: f32, edges: &[f32]) -> f32 {
+        if self.next_u64() % 4 == 0 {
+            edges[(self.next_u64() % edges.len() as u64) as usize]
+        } else {
+            self.range(min, max)
+        }
+    }
+
+    pub fn rect(&mut self) -> Rect {
+        let size = [
+            self.range_or_edge(0.0, 400.0, &[0.0, 1.0, 0.5]),
+            self.range_or_edge(0.0, 400.0, &[0.0, 1.0, 0.5]),
+        ];
+        // Fill only, stroke only, or both.
+        let kind = self.next_u64() % 3;
+        let fill = (kind != 1).then(|| self.fill());
+        let stroke = (kind != 0).then(|| Stroke {
+            color: [self.next_f32(), self.next_f32(), self.next_f32()],
+            width: self.range_or_edge(0.0, 20.0, &[0.0, 1.0, 1000.0]),
+        });
+        Rect {
+            position: [
+                self.range(-0.1 * self.width, 1.1 * self.width),
+                self.range(-0.1 * self.height, 1.1 * self.height),
+            ],
+            size,
+            border_radius: [0; 4].map(|_| self.range_or_edge(0.0, 200.0, &[0.0, 1000.0])),
+            fill,
+            stroke,
+            rotation: self.range_or_edge(-TAU, TAU, &[0.0, FRAC_PI_2, PI]),
+            z_index: self.range_or_edge(0.0, 1.0, &[0.0, 1.0]),
+            softness: self.range_or_edge(0.0, 50.0, &[0.0, 1000.0]),
+            ..Default::default()
+        }
+    }
+
+    /// Any kind of fill. Images are picked by small ids, which a scene may or may not have.
+    fn fill(&mut self) -> Fill {
+        match self.next_u64() % 6 {
+            0 => Fill::Solid {
+                color: self.color(),
+            },
+            1 => Fill::Radial {
+                center: [self.range(-200.0, 200.0), self.range(-200.0, 200.0)],
+                radius: self.range_or_edge(0.0, 400.0, &[0.0, 1e-6]),
+                stops: self.stops(),
+            },
+            2 => Fill::Conic {
+                center: [self.range(-200.0, 200.0), self.range(-200.0, 200.0)],
+                start_angle: self.range_or_edge(-TAU, TAU, &[0.0, PI]),
+                stops: self.stops(),
+            },
+            3 => Fill::Image {
+                id: self.next_u64() % 4,
+                tint: self.color(),
+            },
+            4 => Fill::Pattern {
+                id: self.next_u64() % 4,
+                tile_size: [0; 2].map(|_| self.range_or_edge(0.0, 64.0, &[0.0, 1.0])),
+                offset: [self.range(-100.0, 100.0), self.range(-100.0, 100.0)],
+                rotation: self.range_or_edge(-TAU, TAU, &[0.0, FRAC_PI_2]),
+                tint: self.color(),
+            },
+            _ => Fill::Noise {
+                scale: self.range_or_edge(0.0, 200.0, &[0.0, 1e-6]),
+                // Past both ends of the octaves the shader supports.
+                octaves: (self.next_u64() % (MAX_NOISE_OCTAVES as u64 + 2)) as u32,
+                offset: [self.range(-100.0, 100.0), self.range(-100.0, 100.0)],
+                stops: self.stops(),
+            },
+        }
+    }
+
+    fn color(&mut self) -> [f32; 4] {
+        [
+            self.next_f32(),
+            self.next_f32(),
+            self.next_f32(),
+            self.range_or_edge(0.0, 1.0, &[0.0, 1.0]),
+        ]
+    }
+
+    /// None up to the most a gradient holds, at offsets that may coincide.
+    fn stops(&mut self) -> GradientStops {
+        let count = (self.next_u64() % (MAX_GRADIENT_STOPS as u64 + 1)) as usize;
+        let stops: Vec<GradientStop> = (0..count)
+            .map(|_| GradientStop {
+                offset: self.range_or_edge(0.0, 1.0, &[0.0, 1.0]),
+                color: self.color(),
+            })
+            .collect();
+        GradientStops::new(&stops)
+    }
+
+    pub fn rects(&mut self, count: usize) -> Vec<Rect> {
+        (0..count).map(|_| self.rect()).collect()
+    }
+}
+
+#[derive(Debug)]
+pub enum GeometryError {
+    NonFiniteInstance { instance: usize },
+    NonFiniteStyle { style: usize },
+    StyleOutOfRange { instance: usize, value: u32 },
+}
+
+impl fmt::Display for GeometryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeometryError::NonFiniteInstance { instance } => {
+                write!(f, "instance {} has a non-finite attribute", instance)
+            }
+            GeometryError::NonFiniteStyle { style } => {
+                write!(f, "style {} has a non-finite field", style)
+            }
+            GeometryError::StyleOutOfRange { instance, value } => {
+                write!(
+                    f,
+                    "instance {} points past the style table ({})",
+                    instance, value
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for GeometryError {}
+
+/// Checks generated geometry for the problems that render as garbage instead of failing loudly.
+pub fn check_geometry(geometry: &Geometry) -> Result<(), GeometryError> {
+    let Geometry { instances, styles } = geometry;
+    let styles = styles.styles();
+    if let Some(instance) = instances.iter().position(|i| !i.is_finite()) {
+        return Err(GeometryError::NonFiniteInstance { instance });
+    }
+    if let Some(style) = styles.iter().position(|s| !s.is_finite()) {
+        return Err(GeometryError::NonFiniteStyle { style });
+    }
+    if let Some(instance) = instances
+        .iter()
+        .position(|i| i.style() as usize >= styles.len())
+    {
+        return Err(GeometryError::StyleOutOfRange {
+            instance,
+            value: instances[instance].style(),
+        });
+    }
+    Ok(())
+}
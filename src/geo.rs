@@ -0,0 +1,595 @@
+use std::{borrow::Cow, f64::consts::PI};
+
+use wgpu::util::{DeviceExt, RenderEncoder};
+
+use crate::{
+    axis::NumberFormat,
+    camera::Camera,
+    heatmap::{Colormap, ValueRange},
+    line::{self, Polyline},
+    pack::{f16_bits, unorm8},
+    path::{self, Shape},
+    resources::{ResourceScope, Tracked},
+    tessellate,
+};
+
+/// A polygon's outline followed by its holes, as `[longitude, latitude]` in degrees.
+pub type Polygon = Vec<Vec<[f64; 2]>>;
+
+/// How longitude and latitude map onto the flat world.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Projection {
+    #[default]
+    Equirectangular,
+    /// Web Mercator. Latitudes are clamped to ±85.05°.
+    Mercator,
+}
+
+impl Projection {
+    /// Projects to degrees of longitude on both axes, with north towards smaller y.
+    pub fn project(&self, lon_lat: [f64; 2]) -> [f64; 2] {
+        let [lon, lat] = lon_lat;
+        match self {
+            Projection::Equirectangular => [lon, -lat],
+            Projection::Mercator => {
+                let lat = lat.clamp(-85.05113, 85.05113).to_radians();
+                [lon, -(PI / 4.0 + lat / 2.0).tan().ln().to_degrees()]
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Region {
+    pub name: String,
+    /// Mapped through the layer's colormap. NaN means no data.
+    pub value: f32,
+    pub polygons: Vec<Polygon>,
+}
+
+/// A choropleth: regions filled by value and outlined, projected into world space.
+#[derive(Clone, Debug)]
+pub struct GeoLayer {
+    pub regions: Vec<Region>,
+    pub projection: Projection,
+    /// World position of longitude and latitude 0.
+    pub origin: [f64; 2],
+    /// World units per projected degree.
+    pub scale: f64,
+    pub colormap: Colormap,
+    pub range: ValueRange,
+    /// Fill for regions whose value is NaN.
+    pub missing_color: [f32; 4],
+    pub outline_color: [f32; 4],
+    /// Outline width in pixels, whatever the zoom. Zero draws no outlines.
+    pub outline_width: f32,
+}
+
+impl GeoLayer {
+    /// Where `lon_lat` ends up in world space.
+    pub fn to_world(&self, lon_lat: [f64; 2]) -> [f64; 2] {
+        let [x, y] = self.projection.project(lon_lat);
+        [
+            self.origin[0] + x * self.scale,
+            self.origin[1] + y * self.scale,
+        ]
+    }
+
+    fn resolved_range(&self) -> [f32; 2] {
+        let [min, max] = match self.range {
+            ValueRange::Fixed { min, max } => [min, max],
+            ValueRange::Auto => self
+                .regions
+                .iter()
+                .map(|region| region.value)
+                .filter(|v| v.is_finite())
+                .fold([f32::INFINITY, f32::NEG_INFINITY], |[min, max], v| {
+                    [min.min(v), max.max(v)]
+                }),
+        };
+        if min.is_finite() && max > min {
+            [min, max]
+        } else if min.is_finite() {
+            [min, min + 1.0]
+        } else {
+            [0.0, 1.0]
+        }
+    }
+
+    /// Fill color of each region.
+    pub fn colors(&self) -> Vec<[f32; 4]> {
+        let [min, max] = self.resolved_range();
+        self.regions
+            .iter()
+            .map(|region| {
+                if region.value.is_nan() {
+                    self.missing_color
+                } else {
+                    self.colormap.sample((region.value - min) / (max - min))
+                }
+            })
+            .collect()
+    }
+
+    /// The region under the world position `world`, if any.
+    pub fn region_at(&self, world: [f64; 2]) -> Option<usize> {
+        self.regions.iter().position(|region| {
+            region.polygons.iter().any(|polygon| {
+                let rings: Vec<Vec<[f64; 2]>> = polygon
+                    .iter()
+                    .map(|ring| ring.iter().map(|&p| self.to_world(p)).collect())
+                    .collect();
+                tessellate::contains(&rings, world)
+            })
+        })
+    }
+
+    /// Name and value of region `i`, for a hover tooltip.
+    pub fn tooltip(&self, i: usize) -> String {
+        let region = &self.regions[i];
+        if region.value.is_nan() {
+            return format!("{}: no data", region.name);
+        }
+        let [min, max] = self.resolved_range();
+        let step = (max - min) as f64 / 1000.0;
+        format!(
+            "{}: {}",
+            region.name,
+            NumberFormat::Auto.format(region.value as f64, step)
+        )
+    }
+}
+
+#[cfg(feature = "geojson")]
+pub use geojson::{regions_from_geojson, GeoJsonError};
+
+#[cfg(feature = "geojson")]
+mod geojson {
+    use std::fmt;
+
+    use serde_json::Value;
+
+    use super::{Polygon, Region};
+
+    #[derive(Debug)]
+    pub enum GeoJsonError {
+        Json(serde_json::Error),
+        /// The document parsed but isn't GeoJSON the layer understands.
+        Format(String),
+    }
+
+    impl fmt::Display for GeoJsonError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                GeoJsonError::Json(e) => write!(f, "invalid JSON: {}", e),
+                GeoJsonError::Format(message) => write!(f, "invalid GeoJSON: {}", message),
+            }
+        }
+    }
+
+    impl std::error::Error for GeoJsonError {}
+
+    fn format_error(message: &str) -> GeoJsonError {
+        GeoJsonError::Format(message.to_string())
+    }
+
+    fn ring(value: &Value) -> Result<Vec<[f64; 2]>, GeoJsonError> {
+        value
+            .as_array()
+            .ok_or_else(|| format_error("ring is not an array"))?
+            .iter()
+            .map(|position| match position.as_array().map(Vec::as_slice) {
+                Some([lon, lat, ..]) => match (lon.as_f64(), lat.as_f64()) {
+                    (Some(lon), Some(lat)) => Ok([lon, lat]),
+                    _ => Err(format_error("position is not numeric")),
+                },
+                _ => Err(format_error("position needs two coordinates")),
+            })
+            .collect()
+    }
+
+    fn polygon(value: &Value) -> Result<Polygon, GeoJsonError> {
+        value
+            .as_array()
+            .ok_or_else(|| format_error("polygon is not an array"))?
+            .iter()
+            .map(ring)
+            .collect()
+    }
+
+    fn polygons(geometry: &Value) -> Result<Vec<Polygon>, GeoJsonError> {
+        let coordinates = &geometry["coordinates"];
+        match geometry["type"].as_str() {
+            Some("Polygon") => Ok(vec![polygon(coordinates)?]),
+            Some("MultiPolygon") => coordinates
+                .as_array()
+                .ok_or_else(|| format_error("MultiPolygon coordinates are not an array"))?
+                .iter()
+                .map(polygon)
+                .collect(),
+            // Points and lines have no area to fill.
+            _ => Ok(vec![]),
+        }
+    }
+
+    /// Reads the Polygon and MultiPolygon features of a FeatureCollection (or a single
+    /// Feature), taking each region's name and value from the given properties.
+    pub fn regions_from_geojson(
+        json: &str,
+        name_property: &str,
+        value_property: &str,
+    ) -> Result<Vec<Region>, GeoJsonError> {
+        let document: Value = serde_json::from_str(json).map_err(GeoJsonError::Json)?;
+        let features = match document["type"].as_str() {
+            Some("FeatureCollection") => document["features"]
+                .as_array()
+                .ok_or_else(|| format_error("features is not an array"))?
+                .iter()
+                .collect(),
+            Some("Feature") => vec![&document],
+            _ => return Err(format_error("expected a Feature or FeatureCollection")),
+        };
+
+        let mut regions = vec![];
+        for feature in features {
+            let polygons = polygons(&feature["geometry"])?;
+            if polygons.is_empty() {
+                continue;
+            }
+            let properties = &feature["properties"];
+            regions.push(Region {
+                name: match &properties[name_property] {
+                    Value::String(name) => name.clone(),
+                    Value::Null => String::new(),
+                    other => other.to_string(),
+                },
+                value: properties[value_property].as_f64().unwrap_or(f64::NAN) as f32,
+                polygons,
+            });
+        }
+        Ok(regions)
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct GeoVertex {
+    /// Relative to the mesh's origin, in world units.
+    pub position: [f32; 2],
+    /// Pixels to move the vertex by after zooming.
+    pub extrude: [f32; 2],
+    pub color: [f32; 4],
+}
+
+impl GeoVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 3] = wgpu::vertex_attr_array![
+        0 => Float32x2,
+        1 => Float32x2,
+        2 => Float32x4,
+    ];
+
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<GeoVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// `GeoVertex` with half float extrusions and 8-bit colors, for
+/// `RendererConfig::packed_vertices`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct PackedGeoVertex {
+    position: [f32; 2],
+    extrude: [u16; 2],
+    color: [u8; 4],
+}
+
+impl PackedGeoVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 3] = wgpu::vertex_attr_array![
+        0 => Float32x2,
+        1 => Float16x2,
+        2 => Unorm8x4,
+    ];
+
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<PackedGeoVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+impl From<&GeoVertex> for PackedGeoVertex {
+    fn from(vertex: &GeoVertex) -> Self {
+        Self {
+            position: vertex.position,
+            extrude: vertex.extrude.map(f16_bits),
+            color: vertex.color.map(unorm8),
+        }
+    }
+}
+
+/// Fills every region, then outlines it with quads extruded in screen space.
+fn build_mesh(layer: &GeoLayer) -> (Vec<GeoVertex>, Vec<u32>) {
+    let mut vertices = vec![];
+    let mut indices = vec![];
+    let local = |p: [f64; 2]| {
+        let [x, y] = layer.projection.project(p);
+        [(x * layer.scale) as f32, (y * layer.scale) as f32]
+    };
+
+    for (region, color) in layer.regions.iter().zip(layer.colors()) {
+        for polygon in &region.polygons {
+            let rings: Vec<Vec<[f64; 2]>> = polygon
+                .iter()
+                .map(|ring| ring.iter().map(|&p| layer.projection.project(p)).collect())
+                .collect();
+            let (points, triangles) = tessellate::fill(&rings);
+            let base = vertices.len() as u32;
+            vertices.extend(points.iter().map(|&[x, y]| GeoVertex {
+                position: [(x * layer.scale) as f32, (y * layer.scale) as f32],
+                extrude: [0.0; 2],
+                color,
+            }));
+            indices.extend(triangles.iter().map(|i| base + i));
+        }
+    }
+
+    if layer.outline_width > 0.0 {
+        let half = layer.outline_width / 2.0;
+        let rings = layer
+            .regions
+            .iter()
+            .flat_map(|region| &region.polygons)
+            .flatten();
+        for ring in rings {
+            for (i, &a) in ring.iter().enumerate() {
+                let (a, b) = (local(a), local(ring[(i + 1) % ring.len()]));
+                let direction = [b[0] - a[0], b[1] - a[1]];
+                let length = direction[0].hypot(direction[1]);
+                if !length.is_normal() {
+                    continue;
+                }
+                let normal = [-direction[1] / length * half, direction[0] / length * half];
+                let base = vertices.len() as u32;
+                for (position, side) in [(a, 1.0), (a, -1.0), (b, -1.0), (b, 1.0)] {
+                    vertices.push(GeoVertex {
+                        position,
+                        extrude: [normal[0] * side, normal[1] * side],
+                        color: layer.outline_color,
+                    });
+                }
+                indices.extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+            }
+        }
+    }
+    (vertices, indices)
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct GeoUniform {
+    translation: [f32; 2],
+    zoom: f32,
+    _padding: f32,
+}
+
+/// Triangles uploaded for the geo pipeline, positioned relative to a world-space origin.
+pub(crate) struct GeoMesh {
+    origin: [f64; 2],
+    vertex_buffer: Tracked<wgpu::Buffer>,
+    index_buffer: Tracked<wgpu::Buffer>,
+    num_indices: u32,
+    uniform_buffer: Tracked<wgpu::Buffer>,
+    bind_group: Tracked<wgpu::BindGroup>,
+}
+
+impl GeoMesh {
+    pub fn update(&self, queue: &wgpu::Queue, camera: &Camera) {
+        let uniform = GeoUniform {
+            translation: [
+                (self.origin[0] - camera.offset[0]) as f32,
+                (self.origin[1] - camera.offset[1]) as f32,
+            ],
+            zoom: camera.zoom as f32,
+            _padding: 0.0,
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+}
+
+/// GPU resources for one geo layer, plus the layer itself for hit testing.
+pub(crate) struct PreparedGeo {
+    pub layer: GeoLayer,
+    pub hovered: Option<usize>,
+    pub mesh: GeoMesh,
+}
+
+impl PreparedGeo {
+    pub fn update(&self, queue: &wgpu::Queue, camera: &Camera) {
+        self.mesh.update(queue, camera);
+    }
+}
+
+pub(crate) struct GeoPipeline {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    packed: bool,
+}
+
+impl GeoPipeline {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        window_bind_group_layout: &wgpu::BindGroupLayout,
+        packed: bool,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("geo.wgsl"));
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+            label: Some("Geo Bind Group Layout"),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Geo Pipeline Layout"),
+            bind_group_layouts: &[window_bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Geo Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                // The packed formats arrive in the shader as the same f32 vectors.
+                buffers: &[if packed {
+                    PackedGeoVertex::desc()
+                } else {
+                    GeoVertex::desc()
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            // Triangulation and projection don't keep a consistent winding, so nothing is
+            // culled.
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            packed,
+        }
+    }
+
+    /// Tessellates and uploads `layer`.
+    pub fn prepare(&self, device: &wgpu::Device, layer: &GeoLayer) -> PreparedGeo {
+        let (vertices, indices) = build_mesh(layer);
+        PreparedGeo {
+            layer: layer.clone(),
+            hovered: None,
+            mesh: self.upload(device, layer.origin, &vertices, &indices),
+        }
+    }
+
+    /// Strokes and uploads `line`.
+    pub fn prepare_line(&self, device: &wgpu::Device, line: &Polyline) -> GeoMesh {
+        let origin = line::origin(line.points.iter().copied());
+        let (mut vertices, mut indices) = (vec![], vec![]);
+        line::stroke(line, origin, &mut vertices, &mut indices);
+        self.upload(device, origin, &vertices, &indices)
+    }
+
+    /// Fills, strokes and uploads `shape`.
+    pub fn prepare_shape(&self, device: &wgpu::Device, shape: &Shape) -> GeoMesh {
+        let (origin, vertices, indices) = path::build_mesh(shape);
+        self.upload(device, origin, &vertices, &indices)
+    }
+
+    /// Uploads triangles whose positions are relative to `origin`.
+    pub fn upload(
+        &self,
+        device: &wgpu::Device,
+        origin: [f64; 2],
+        vertices: &[GeoVertex],
+        indices: &[u32],
+    ) -> GeoMesh {
+        let vertex_data: Cow<[u8]> = if self.packed {
+            let packed: Vec<PackedGeoVertex> = vertices.iter().map(PackedGeoVertex::from).collect();
+            Cow::Owned(bytemuck::cast_slice(&packed).to_vec())
+        } else {
+            Cow::Borrowed(bytemuck::cast_slice(vertices))
+        };
+        let vertex_buffer = Tracked::new(
+            ResourceScope::Scene,
+            "Geo Vertex Buffer",
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Geo Vertex Buffer"),
+                contents: &vertex_data,
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+        );
+        let index_buffer = Tracked::new(
+            ResourceScope::Scene,
+            "Geo Index Buffer",
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Geo Index Buffer"),
+                contents: bytemuck::cast_slice(indices),
+                usage: wgpu::BufferUsages::INDEX,
+            }),
+        );
+        let uniform_buffer = Tracked::new(
+            ResourceScope::Scene,
+            "Geo Uniform",
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Geo Uniform"),
+                size: std::mem::size_of::<GeoUniform>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+        );
+        let bind_group = Tracked::new(
+            ResourceScope::Scene,
+            "Geo Bind Group",
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                }],
+                label: Some("Geo Bind Group"),
+            }),
+        );
+
+        GeoMesh {
+            origin,
+            vertex_buffer,
+            index_buffer,
+            num_indices: indices.len() as u32,
+            uniform_buffer,
+            bind_group,
+        }
+    }
+
+    pub fn render<'rp>(
+        &'rp self,
+        encoder: &mut impl RenderEncoder<'rp>,
+        window_bind_group: &'rp wgpu::BindGroup,
+        meshes: impl IntoIterator<Item = &'rp GeoMesh>,
+    ) {
+        encoder.set_pipeline(&self.pipeline);
+        encoder.set_bind_group(0, window_bind_group, &[]);
+        // Empty buffers can't be bound, so meshes without geometry are skipped.
+        for mesh in meshes.into_iter().filter(|mesh| mesh.num_indices > 0) {
+            encoder.set_bind_group(1, &mesh.bind_group, &[]);
+            encoder.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+            encoder.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+            encoder.draw_indexed(0..mesh.num_indices, 0, 0..1);
+        }
+    }
+}
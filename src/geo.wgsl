@@ -0,0 +1,46 @@
+struct WindowUniform {
+	size: vec2<f32>,
+	scale_factor: f32,
+	y_direction: f32,
+}
+@group(0) @binding(0)
+var<uniform> window: WindowUniform;
+
+struct GeoUniform {
+	// origin - camera offset, computed in f64 on the CPU
+	translation: vec2<f32>,
+	zoom: f32,
+	_padding: f32,
+}
+@group(1) @binding(0)
+var<uniform> geo: GeoUniform;
+
+struct VertexInput {
+	@location(0) position: vec2<f32>,
+	// Pixels to move the vertex by after zooming, so outlines and lines keep their width
+	@location(1) extrude: vec2<f32>,
+	@location(2) color: vec4<f32>,
+};
+
+struct VertexOutput {
+	@builtin(position) clip_position: vec4<f32>,
+	@location(0) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(model: VertexInput) -> VertexOutput {
+	var out: VertexOutput;
+	var screen = (model.position + geo.translation) * geo.zoom + model.extrude;
+	var ndc_position = vec2<f32>(
+		(2.0 * screen.x / window.size.x) - 1.0,
+		window.y_direction * (1.0 - (2.0 * screen.y / window.size.y))
+	);
+	out.clip_position = vec4<f32>(ndc_position, 0.0, 1.0);
+	out.color = model.color;
+	return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+	return in.color;
+}
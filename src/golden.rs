@@ -0,0 +1,274 @@
+//! Golden-image testing: frames rendered with `Headless` are compared against reference PNGs
+//! checked in with the tests, within a tolerance, so a change that alters what gets drawn
+//! fails with a picture of where.
+//!
+//! A missing reference fails the check, with the frame written where the reference belongs
+//! as `<name>.actual.png`. Running with `WGPU_TEST_UPDATE_GOLDEN` set writes the frames as the
+//! new references instead. References are read with `Screenshot::read_png`, so they have to
+//! be written by the renderer.
+//!
+//! Render with `RendererConfig::deterministic` set, so references recorded on one machine
+//! match on the others.
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    capture::{CaptureError, Screenshot},
+    Headless,
+};
+
+/// Set to write rendered frames as the references instead of comparing against them.
+pub const UPDATE_VAR: &str = "WGPU_TEST_UPDATE_GOLDEN";
+
+/// How far a frame may be from its reference and still match. GPUs and drivers round
+/// gradients and antialiased edges a little differently, so an exact match is rarely wanted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tolerance {
+    /// How far any channel of a pixel may be off before the pixel counts as different.
+    pub channel: u8,
+    /// How many different pixels still match.
+    pub pixels: usize,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            channel: 2,
+            pixels: 0,
+        }
+    }
+}
+
+/// The pixels in which two images of the same size differ.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Difference {
+    /// How many pixels differ by more than the channel tolerance.
+    pub pixels: usize,
+    /// The largest difference of any channel, within the tolerance or not.
+    pub max_channel: u8,
+    /// The first pixel that differs, row by row from the top-left.
+    pub first: Option<[u32; 2]>,
+    /// The expected image faded to gray, with the pixels that differ in red.
+    pub image: Screenshot,
+}
+
+/// Compares `actual` with `expected` pixel by pixel, counting the pixels with a channel off by
+/// more than `channel_tolerance`. Returns `None` when their sizes differ.
+pub fn diff(
+    actual: &Screenshot,
+    expected: &Screenshot,
+    channel_tolerance: u8,
+) -> Option<Difference> {
+    if [actual.width, actual.height] != [expected.width, expected.height] {
+        return None;
+    }
+    let mut difference = Difference {
+        pixels: 0,
+        max_channel: 0,
+        first: None,
+        image: Screenshot {
+            width: expected.width,
+            height: expected.height,
+            pixels: Vec::with_capacity(expected.pixels.len()),
+        },
+    };
+    let pairs = actual
+        .pixels
+        .chunks_exact(4)
+        .zip(expected.pixels.chunks_exact(4));
+    for (i, (a, e)) in pairs.enumerate() {
+        let channel = a
+            .iter()
+            .zip(e)
+            .map(|(a, e)| a.abs_diff(*e))
+            .max()
+            .unwrap_or(0);
+        difference.max_channel = difference.max_channel.max(channel);
+        if channel > channel_tolerance {
+            difference.pixels += 1;
+            if difference.first.is_none() {
+                let width = expected.width as usize;
+                difference.first = Some([(i % width) as u32, (i / width) as u32]);
+            }
+            difference.image.pixels.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            let gray = (e[0] as u16 + e[1] as u16 + e[2] as u16) / 6 + 64;
+            difference
+                .image
+                .pixels
+                .extend_from_slice(&[gray as u8, gray as u8, gray as u8, 255]);
+        }
+    }
+    Some(difference)
+}
+
+/// Why a frame didn't match its reference.
+pub enum GoldenError {
+    Render(CaptureError),
+    Io(PathBuf, io::Error),
+    /// There is no reference yet. The frame was written to `actual`.
+    Missing {
+        reference: PathBuf,
+        actual: PathBuf,
+    },
+    SizeMismatch {
+        expected: [u32; 2],
+        actual: [u32; 2],
+    },
+    /// Too many pixels differ. The frame and the difference image were written to `actual`
+    /// and `diff`.
+    Mismatch {
+        difference: Difference,
+        actual: PathBuf,
+        diff: PathBuf,
+    },
+}
+
+impl fmt::Display for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GoldenError::Render(e) => write!(f, "can't render the frame: {}", e),
+            GoldenError::Io(path, e) => write!(f, "{}: {}", path.display(), e),
+            GoldenError::Missing { reference, actual } => write!(
+                f,
+                "no reference at {}, the frame is at {}; set {} to accept it",
+                reference.display(),
+                actual.display(),
+                UPDATE_VAR
+            ),
+            GoldenError::SizeMismatch { expected, actual } => write!(
+                f,
+                "the frame is {}x{} but the reference {}x{}",
+                actual[0], actual[1], expected[0], expected[1]
+            ),
+            GoldenError::Mismatch {
+                difference,
+                actual,
+                diff,
+            } => {
+                write!(
+                    f,
+                    "{} pixels differ by up to {}",
+                    difference.pixels, difference.max_channel
+                )?;
+                if let Some([x, y]) = difference.first {
+                    write!(f, ", the first at {}, {}", x, y)?;
+                }
+                write!(
+                    f,
+                    "; the frame is at {} and the difference at {}",
+                    actual.display(),
+                    diff.display()
+                )
+            }
+        }
+    }
+}
+
+// Written out rather than derived, so that `unwrap` in a test prints the report instead of
+// every pixel of the difference image.
+impl fmt::Debug for GoldenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl std::error::Error for GoldenError {}
+
+impl From<CaptureError> for GoldenError {
+    fn from(e: CaptureError) -> Self {
+        GoldenError::Render(e)
+    }
+}
+
+/// A directory of reference images, each named after the check that compares against it.
+#[derive(Clone, Debug)]
+pub struct Golden {
+    dir: PathBuf,
+    pub tolerance: Tolerance,
+    /// Whether frames replace the references instead of being compared; see `UPDATE_VAR`.
+    pub update: bool,
+}
+
+impl Golden {
+    /// References are `<dir>/<name>.png`, e.g. with `dir` being
+    /// `concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden")`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            tolerance: Tolerance::default(),
+            update: std::env::var_os(UPDATE_VAR).is_some(),
+        }
+    }
+
+    pub fn with_tolerance(mut self, tolerance: Tolerance) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Renders a frame with `renderer` and checks it against the reference `name`.
+    pub fn check_render(&self, name: &str, renderer: &mut Headless) -> Result<(), GoldenError> {
+        let frame = renderer.render()?;
+        self.check(name, &frame)
+    }
+
+    /// Checks `frame` against the reference `name`. On a mismatch the frame and the difference
+    /// image are written next to the reference, as `<name>.actual.png` and `<name>.diff.png`,
+    /// and removed again once it matches.
+    pub fn check(&self, name: &str, frame: &Screenshot) -> Result<(), GoldenError> {
+        let reference = self.dir.join(format!("{name}.png"));
+        let actual = self.dir.join(format!("{name}.actual.png"));
+        let diff_path = self.dir.join(format!("{name}.diff.png"));
+        if self.update {
+            save(&reference, frame)?;
+            remove_outputs(&actual, &diff_path);
+            return Ok(());
+        }
+
+        let expected = match Screenshot::load_png(&reference) {
+            Ok(expected) => expected,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                save(&actual, frame)?;
+                return Err(GoldenError::Missing { reference, actual });
+            }
+            Err(e) => return Err(GoldenError::Io(reference, e)),
+        };
+        let Some(difference) = diff(frame, &expected, self.tolerance.channel) else {
+            save(&actual, frame)?;
+            return Err(GoldenError::SizeMismatch {
+                expected: [expected.width, expected.height],
+                actual: [frame.width, frame.height],
+            });
+        };
+        if difference.pixels <= self.tolerance.pixels {
+            remove_outputs(&actual, &diff_path);
+            return Ok(());
+        }
+        save(&actual, frame)?;
+        save(&diff_path, &difference.image)?;
+        Err(GoldenError::Mismatch {
+            difference,
+            actual,
+            diff: diff_path,
+        })
+    }
+}
+
+fn save(path: &Path, image: &Screenshot) -> Result<(), GoldenError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| GoldenError::Io(dir.to_path_buf(), e))?;
+    }
+    image
+        .save_png(path)
+        .map_err(|e| GoldenError::Io(path.to_path_buf(), e))
+}
+
+/// Removes what an earlier failed check left behind, if anything.
+fn remove_outputs(actual: &Path, diff: &Path) {
+    for path in [actual, diff] {
+        let _ = fs::remove_file(path);
+    }
+}
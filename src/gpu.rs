@@ -0,0 +1,66 @@
+//! The GPU the renderer draws with. Every window of an event loop shares one: a single device
+//! and queue, with a surface of its own per window.
+
+use std::sync::Arc;
+
+use crate::{
+    capabilities::{self, Capabilities},
+    config::AdapterConfig,
+    recovery::DeviceLoss,
+    RendererError,
+};
+
+#[derive(Clone)]
+pub(crate) struct Gpu {
+    /// Kept to create surfaces for more windows, and again when the app resumes.
+    pub instance: Arc<wgpu::Instance>,
+    pub adapter: Arc<wgpu::Adapter>,
+    pub device: Arc<wgpu::Device>,
+    pub queue: Arc<wgpu::Queue>,
+    pub device_loss: DeviceLoss,
+    pub capabilities: Capabilities,
+}
+
+impl Gpu {
+    /// Picks the adapter `config` asks for that can draw to `surface` and opens a device on
+    /// it, with the highest limits it supports.
+    pub async fn request(
+        instance: Arc<wgpu::Instance>,
+        config: &AdapterConfig,
+        surface: Option<&wgpu::Surface>,
+    ) -> Result<Self, RendererError> {
+        let adapter = capabilities::request_adapter(&instance, config, surface)
+            .await
+            .ok_or(RendererError::NoAdapter)?;
+
+        let capabilities = Capabilities::detect(&adapter);
+        for degradation in &capabilities.degradations {
+            log::warn!(
+                "{} ({:?}): running with {:?}",
+                capabilities.adapter_name,
+                capabilities.backend,
+                degradation
+            );
+        }
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    features: wgpu::Features::empty(),
+                    limits: capabilities.limits.clone(),
+                    label: None,
+                },
+                None,
+            )
+            .await?;
+        let device_loss = DeviceLoss::watch(&device);
+        Ok(Self {
+            instance,
+            adapter: Arc::new(adapter),
+            device: Arc::new(device),
+            queue: Arc::new(queue),
+            device_loss,
+            capabilities,
+        })
+    }
+}
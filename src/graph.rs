@@ -0,0 +1,417 @@
+use crate::{
+    line::{LineJoin, Polyline},
+    rect::{Fill, Rect},
+    scatter::{Marker, ScatterPoint},
+};
+
+#[derive(Clone, Debug)]
+pub struct Node {
+    /// World position.
+    pub position: [f64; 2],
+    pub velocity: [f64; 2],
+    pub label: String,
+    pub color: [f32; 4],
+    /// Radius in pixels.
+    pub radius: f32,
+    /// Pinned nodes aren't moved by the simulation.
+    pub pinned: bool,
+}
+
+impl Node {
+    pub fn new(position: [f64; 2], label: impl Into<String>) -> Self {
+        Self {
+            position,
+            velocity: [0.0; 2],
+            label: label.into(),
+            color: [0.9, 0.9, 0.9, 1.0],
+            radius: 6.0,
+            pinned: false,
+        }
+    }
+}
+
+/// Tuning for `Graph::step`.
+#[derive(Clone, Copy, Debug)]
+pub struct ForceLayout {
+    /// How strongly every pair of nodes pushes apart.
+    pub repulsion: f64,
+    /// Rest length of an edge, in world units.
+    pub spring_length: f64,
+    pub spring_strength: f64,
+    /// Pull towards `center`, which keeps disconnected parts from drifting away.
+    pub gravity: f64,
+    pub center: [f64; 2],
+    /// How quickly nodes slow down, per second: velocity falls by a factor of e every
+    /// `1 / damping` seconds, however long the steps are.
+    pub damping: f64,
+    /// The simulation freezes once no node moves faster than this, in world units per second.
+    pub freeze_threshold: f64,
+}
+
+impl Default for ForceLayout {
+    fn default() -> Self {
+        Self {
+            repulsion: 2000.0,
+            spring_length: 60.0,
+            spring_strength: 2.0,
+            gravity: 0.05,
+            center: [400.0, 300.0],
+            // About 0.85 of the velocity kept per frame at 60 Hz.
+            damping: 10.0,
+            freeze_threshold: 1.0,
+        }
+    }
+}
+
+/// Nodes joined by edges, laid out by a force simulation that is stepped once per frame.
+#[derive(Clone, Debug, Default)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<(usize, usize)>,
+    pub layout: ForceLayout,
+    frozen: bool,
+    /// The dragged node, and whether it was pinned before the drag.
+    dragged: Option<(usize, bool)>,
+}
+
+impl Graph {
+    pub fn new(nodes: Vec<Node>, edges: Vec<(usize, usize)>) -> Self {
+        Self {
+            nodes,
+            edges,
+            ..Default::default()
+        }
+    }
+
+    pub fn frozen(&self) -> bool {
+        self.frozen
+    }
+
+    /// Restarts a frozen simulation, e.g. after adding nodes.
+    pub fn wake(&mut self) {
+        self.frozen = false;
+    }
+
+    /// Advances the simulation by `dt` seconds. Returns `false` once it has frozen, so the
+    /// caller can stop redrawing.
+    ///
+    /// Repulsion is computed between every pair of nodes, which keeps a few hundred nodes
+    /// comfortably within a frame.
+    pub fn step(&mut self, dt: f64) -> bool {
+        if self.frozen {
+            return false;
+        }
+        let layout = self.layout;
+        let mut forces = vec![[0.0f64; 2]; self.nodes.len()];
+
+        for i in 0..self.nodes.len() {
+            for j in i + 1..self.nodes.len() {
+                let (a, b) = (self.nodes[i].position, self.nodes[j].position);
+                let delta = [a[0] - b[0], a[1] - b[1]];
+                // Nodes on top of each other are nudged apart in a fixed direction.
+                let distance_squared = (delta[0] * delta[0] + delta[1] * delta[1]).max(0.01);
+                let distance = distance_squared.sqrt();
+                let push = layout.repulsion / distance_squared;
+                let force = if distance > 0.1 {
+                    [delta[0] / distance * push, delta[1] / distance * push]
+                } else {
+                    [push, 0.0]
+                };
+                forces[i][0] += force[0];
+                forces[i][1] += force[1];
+                forces[j][0] -= force[0];
+                forces[j][1] -= force[1];
+            }
+        }
+
+        for &(i, j) in &self.edges {
+            if i >= self.nodes.len() || j >= self.nodes.len() || i == j {
+                continue;
+            }
+            let (a, b) = (self.nodes[i].position, self.nodes[j].position);
+            let delta = [b[0] - a[0], b[1] - a[1]];
+            let distance = delta[0].hypot(delta[1]).max(0.1);
+            let pull = (distance - layout.spring_length) * layout.spring_strength;
+            let force = [delta[0] / distance * pull, delta[1] / distance * pull];
+            forces[i][0] += force[0];
+            forces[i][1] += force[1];
+            forces[j][0] -= force[0];
+            forces[j][1] -= force[1];
+        }
+
+        let kept = (-layout.damping * dt).exp();
+        let mut fastest: f64 = 0.0;
+        for (node, force) in self.nodes.iter_mut().zip(forces) {
+            if node.pinned {
+                node.velocity = [0.0; 2];
+                continue;
+            }
+            let velocity = [0, 1].map(|axis| {
+                let gravity = (layout.center[axis] - node.position[axis]) * layout.gravity;
+                (node.velocity[axis] + (force[axis] + gravity) * dt) * kept
+            });
+            node.position = [
+                node.position[0] + velocity[0] * dt,
+                node.position[1] + velocity[1] * dt,
+            ];
+            node.velocity = velocity;
+            fastest = fastest.max(node.velocity[0].hypot(node.velocity[1]));
+        }
+
+        // A dragged node keeps the rest of the graph reacting to it.
+        self.frozen = fastest < layout.freeze_threshold && self.dragged.is_none();
+        !self.frozen
+    }
+
+    /// The topmost node within its radius of `world`, given the camera's `zoom`.
+    pub fn node_at(&self, world: [f64; 2], zoom: f64) -> Option<usize> {
+        self.nodes.iter().rposition(|node| {
+            let distance = (node.position[0] - world[0]).hypot(node.position[1] - world[1]) * zoom;
+            distance <= node.radius as f64
+        })
+    }
+
+    /// Starts dragging the node under `world`. Returns whether one was grabbed.
+    pub fn begin_drag(&mut self, world: [f64; 2], zoom: f64) -> bool {
+        self.dragged = self.node_at(world, zoom).map(|i| {
+            let pinned = std::mem::replace(&mut self.nodes[i].pinned, true);
+            (i, pinned)
+        });
+        if self.dragged.is_some() {
+            self.frozen = false;
+        }
+        self.dragged.is_some()
+    }
+
+    pub fn drag_to(&mut self, world: [f64; 2]) {
+        if let Some((i, _)) = self.dragged {
+            self.nodes[i].position = world;
+            self.frozen = false;
+        }
+    }
+
+    /// Lets go of the dragged node. With `keep_pinned` it stays where it was dropped, as does
+    /// a node that was pinned before the drag.
+    pub fn end_drag(&mut self, keep_pinned: bool) {
+        if let Some((i, pinned)) = self.dragged.take() {
+            self.nodes[i].pinned = pinned || keep_pinned;
+        }
+    }
+
+    /// Nodes as instanced scatter markers, relative to `origin`, for a `ScatterPlot`.
+    pub fn node_points(&self, origin: [f64; 2]) -> Vec<ScatterPoint> {
+        self.nodes
+            .iter()
+            .map(|node| {
+                ScatterPoint::new(
+                    [
+                        (node.position[0] - origin[0]) as f32,
+                        (node.position[1] - origin[1]) as f32,
+                    ],
+                    node.radius * 2.0,
+                    Marker::Circle,
+                    node.color,
+                )
+            })
+            .collect()
+    }
+
+    /// Nodes as round rects in world space. Unlike `node_points` they scale with the zoom.
+    pub fn node_rects(&self) -> Vec<Rect> {
+        self.nodes
+            .iter()
+            .map(|node| Rect {
+                position: [node.position[0] as f32, node.position[1] as f32],
+                size: [node.radius * 2.0; 2],
+                border_radius: [node.radius; 4],
+                fill: Some(Fill::Solid { color: node.color }),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Where each node's label goes: just right of the node, at world scale.
+    pub fn label_positions(&self) -> Vec<[f64; 2]> {
+        self.nodes
+            .iter()
+            .map(|node| {
+                [
+                    node.position[0] + node.radius as f64 + 4.0,
+                    node.position[1],
+                ]
+            })
+            .collect()
+    }
+
+    /// A line `width` pixels wide along each edge. A nonzero `curvature` bows each edge
+    /// sideways by that fraction of its length, which keeps edges in both directions between
+    /// two nodes apart.
+    pub fn edge_lines(&self, width: f32, curvature: f64, color: [f32; 4]) -> Vec<Polyline> {
+        // Enough points for a bowed edge to look smooth at the zooms graphs are viewed at.
+        let steps = if curvature == 0.0 { 1 } else { 24 };
+        let mut lines = vec![];
+        for &(i, j) in &self.edges {
+            let (Some(a), Some(b)) = (self.nodes.get(i), self.nodes.get(j)) else {
+                continue;
+            };
+            let (a, b) = (a.position, b.position);
+            let delta = [b[0] - a[0], b[1] - a[1]];
+            let control = [
+                (a[0] + b[0]) / 2.0 - delta[1] * curvature,
+                (a[1] + b[1]) / 2.0 + delta[0] * curvature,
+            ];
+            let points = (0..=steps)
+                .map(|step| {
+                    let t = step as f64 / steps as f64;
+                    let u = 1.0 - t;
+                    [
+                        u * u * a[0] + 2.0 * u * t * control[0] + t * t * b[0],
+                        u * u * a[1] + 2.0 * u * t * control[1] + t * t * b[1],
+                    ]
+                })
+                .collect();
+            lines.push(Polyline {
+                points,
+                width,
+                color,
+                join: LineJoin::Round,
+                ..Default::default()
+            });
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A layout with only the forces a test turns on.
+    fn still() -> ForceLayout {
+        ForceLayout {
+            repulsion: 0.0,
+            spring_strength: 0.0,
+            gravity: 0.0,
+            freeze_threshold: 0.0,
+            ..Default::default()
+        }
+    }
+
+    fn graph(positions: &[[f64; 2]], edges: Vec<(usize, usize)>) -> Graph {
+        let nodes = positions
+            .iter()
+            .enumerate()
+            .map(|(i, &position)| Node::new(position, i.to_string()))
+            .collect();
+        Graph::new(nodes, edges)
+    }
+
+    #[test]
+    fn damping_is_the_same_however_the_time_is_stepped() {
+        let speed_after = |steps: u32| {
+            let mut graph = graph(&[[0.0, 0.0]], vec![]);
+            graph.layout = still();
+            graph.nodes[0].velocity = [100.0, 0.0];
+            for _ in 0..steps {
+                graph.step(1.0 / steps as f64);
+            }
+            graph.nodes[0].velocity[0]
+        };
+        let expected = 100.0 * (-ForceLayout::default().damping).exp();
+        for steps in [1, 30, 60, 144] {
+            assert!(
+                (speed_after(steps) - expected).abs() < 1e-9,
+                "{} steps",
+                steps
+            );
+        }
+    }
+
+    #[test]
+    fn springs_settle_at_their_rest_length_and_freeze() {
+        let mut graph = graph(&[[0.0, 0.0], [10.0, 0.0]], vec![(0, 1)]);
+        graph.layout = ForceLayout {
+            spring_strength: 2.0,
+            freeze_threshold: 1e-3,
+            ..still()
+        };
+        let mut steps = 0;
+        while graph.step(1.0 / 60.0) {
+            steps += 1;
+            assert!(steps < 10_000, "never froze");
+        }
+        assert!(graph.frozen());
+        let [a, b] = [graph.nodes[0].position, graph.nodes[1].position];
+        let length = (b[0] - a[0]).hypot(b[1] - a[1]);
+        assert!(
+            (length - graph.layout.spring_length).abs() < 0.1,
+            "{}",
+            length
+        );
+        assert!(!graph.step(1.0 / 60.0));
+    }
+
+    #[test]
+    fn pinned_nodes_stay_put() {
+        let mut graph = graph(&[[0.0, 0.0], [5.0, 0.0]], vec![(0, 1)]);
+        graph.nodes[0].pinned = true;
+        for _ in 0..10 {
+            graph.step(1.0 / 60.0);
+        }
+        assert_eq!(graph.nodes[0].position, [0.0, 0.0]);
+        assert_eq!(graph.nodes[0].velocity, [0.0, 0.0]);
+        assert_ne!(graph.nodes[1].position, [5.0, 0.0]);
+    }
+
+    #[test]
+    fn dragging_moves_the_topmost_node_and_wakes_the_graph() {
+        let mut graph = graph(&[[0.0, 0.0], [2.0, 0.0], [100.0, 0.0]], vec![]);
+        graph.layout = ForceLayout {
+            freeze_threshold: 1.0,
+            ..still()
+        };
+        graph.step(1.0 / 60.0);
+        assert!(graph.frozen());
+
+        assert!(!graph.begin_drag([50.0, 0.0], 1.0));
+        assert!(graph.begin_drag([1.0, 0.0], 1.0));
+        assert!(!graph.frozen());
+        graph.drag_to([30.0, 40.0]);
+        assert_eq!(graph.nodes[1].position, [30.0, 40.0]);
+        assert_eq!(graph.nodes[0].position, [0.0, 0.0]);
+        // The dragged node is held where it's put while the graph is stepped.
+        assert!(graph.step(1.0 / 60.0));
+        assert_eq!(graph.nodes[1].position, [30.0, 40.0]);
+    }
+
+    #[test]
+    fn ending_a_drag_keeps_a_node_pinned_before_it_pinned() {
+        let mut graph = graph(&[[0.0, 0.0], [100.0, 0.0]], vec![]);
+        graph.nodes[1].pinned = true;
+        for (keep_pinned, pinned) in [(false, [false, true]), (true, [true, true])] {
+            for (i, node) in [[0.0, 0.0], [100.0, 0.0]].into_iter().enumerate() {
+                graph.nodes[0].pinned = false;
+                assert!(graph.begin_drag(node, 1.0));
+                assert!(graph.nodes[i].pinned);
+                graph.end_drag(keep_pinned);
+                assert_eq!(graph.nodes[i].pinned, pinned[i], "node {}", i);
+            }
+        }
+    }
+
+    #[test]
+    fn edge_lines_skip_missing_nodes_and_bow_with_curvature() {
+        let graph = graph(&[[0.0, 0.0], [100.0, 0.0]], vec![(0, 1), (0, 5)]);
+        let straight = graph.edge_lines(2.0, 0.0, [1.0; 4]);
+        assert_eq!(straight.len(), 1);
+        assert_eq!(straight[0].points, vec![[0.0, 0.0], [100.0, 0.0]]);
+
+        let curved = graph.edge_lines(2.0, 0.25, [1.0; 4]);
+        let points = &curved[0].points;
+        assert_eq!(points.len(), 25);
+        assert_eq!(points[0], [0.0, 0.0]);
+        assert_eq!(points[24], [100.0, 0.0]);
+        // Halfway along a quadratic bezier is halfway to its control point, 25 to the side.
+        assert_eq!(points[12], [50.0, 12.5]);
+    }
+}
@@ -0,0 +1,62 @@
+use crate::{
+    capture::{CaptureError, Screenshot},
+    Camera, DrawList, FrameStats, RendererConfig, RendererError, Scene, State,
+};
+
+/// The renderer without a window: frames are drawn into a texture and read back, for CI,
+/// servers and tests where no windowing system exists.
+///
+/// The scale factor is 1, and window-only settings such as `hidden_until_rendered`, the menu
+/// bar and the tray are ignored. Translucent `window_alpha` still decides whether the pixels
+/// come back premultiplied.
+pub struct Headless {
+    state: State,
+}
+
+impl Headless {
+    /// Sets up the renderer for `scene`, drawing frames of `size` pixels.
+    pub async fn new(
+        renderer_config: RendererConfig,
+        scene: Scene,
+        size: [u32; 2],
+    ) -> Result<Self, RendererError> {
+        let size = winit::dpi::PhysicalSize::new(size[0].max(1), size[1].max(1));
+        Ok(Self {
+            state: State::new(None, size, renderer_config, scene, None).await?,
+        })
+    }
+
+    /// The rects drawn by the next `render`, starting out as the scene's.
+    pub fn draw_list(&mut self) -> &mut DrawList {
+        &mut self.state.draw_list
+    }
+
+    pub fn camera(&self) -> Camera {
+        self.state.camera
+    }
+
+    pub fn set_camera(&mut self, camera: Camera) {
+        self.state.set_camera(camera);
+    }
+
+    pub fn size(&self) -> [u32; 2] {
+        [self.state.size.width, self.state.size.height]
+    }
+
+    /// Draws the following frames at `size` pixels. Empty sizes are ignored.
+    pub fn resize(&mut self, size: [u32; 2]) {
+        self.state
+            .resize(winit::dpi::PhysicalSize::new(size[0], size[1]));
+    }
+
+    /// Draws a frame and reads it back as RGBA pixels.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn render(&mut self) -> Result<Screenshot, CaptureError> {
+        self.state.capture_frame()
+    }
+
+    /// Counters for the frames rendered so far.
+    pub fn stats(&self) -> FrameStats {
+        self.state.stats
+    }
+}
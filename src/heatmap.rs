@@ -0,0 +1,437 @@
+use wgpu::util::{DeviceExt, RenderEncoder};
+
+use crate::{
+    camera::Camera,
+    resources::{ResourceScope, Tracked},
+};
+
+/// Maps normalized values in `0..=1` to colors.
+#[derive(Clone, Debug)]
+pub enum Colormap {
+    Viridis,
+    Magma,
+    /// sRGB stops spread evenly over `0..=1`. Needs at least one stop.
+    Custom(Vec<[u8; 4]>),
+}
+
+const VIRIDIS: [[u8; 4]; 11] = [
+    [0x44, 0x01, 0x54, 0xff],
+    [0x48, 0x24, 0x75, 0xff],
+    [0x41, 0x44, 0x87, 0xff],
+    [0x35, 0x5f, 0x8d, 0xff],
+    [0x2a, 0x78, 0x8e, 0xff],
+    [0x21, 0x91, 0x8c, 0xff],
+    [0x22, 0xa8, 0x84, 0xff],
+    [0x44, 0xbf, 0x70, 0xff],
+    [0x7a, 0xd1, 0x51, 0xff],
+    [0xbd, 0xdf, 0x26, 0xff],
+    [0xfd, 0xe7, 0x25, 0xff],
+];
+
+const MAGMA: [[u8; 4]; 11] = [
+    [0x00, 0x00, 0x04, 0xff],
+    [0x14, 0x0e, 0x36, 0xff],
+    [0x3b, 0x0f, 0x70, 0xff],
+    [0x64, 0x1a, 0x80, 0xff],
+    [0x8c, 0x29, 0x81, 0xff],
+    [0xb7, 0x37, 0x79, 0xff],
+    [0xde, 0x49, 0x68, 0xff],
+    [0xf7, 0x70, 0x5c, 0xff],
+    [0xfe, 0x9f, 0x6d, 0xff],
+    [0xfe, 0xcf, 0x92, 0xff],
+    [0xfc, 0xfd, 0xbf, 0xff],
+];
+
+impl Colormap {
+    const LUT_SIZE: usize = 256;
+
+    fn stops(&self) -> &[[u8; 4]] {
+        match self {
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Magma => &MAGMA,
+            Colormap::Custom(stops) => stops,
+        }
+    }
+
+    /// Samples the stops into a lookup table with `LUT_SIZE` entries.
+    fn lut(&self) -> Vec<[u8; 4]> {
+        let stops = self.stops();
+        assert!(!stops.is_empty(), "Colormap needs at least one stop");
+
+        (0..Self::LUT_SIZE)
+            .map(|i| {
+                let t = i as f32 / (Self::LUT_SIZE - 1) as f32 * (stops.len() - 1) as f32;
+                let from = stops[t.floor() as usize];
+                let to = stops[(t.ceil() as usize).min(stops.len() - 1)];
+                let f = t.fract();
+                [0, 1, 2, 3].map(|c| (from[c] as f32 + (to[c] as f32 - from[c] as f32) * f) as u8)
+            })
+            .collect()
+    }
+
+    /// The color at `t` in `0..=1`, converted to the linear values rect fills take.
+    pub fn sample(&self, t: f32) -> [f32; 4] {
+        let stops = self.stops();
+        assert!(!stops.is_empty(), "Colormap needs at least one stop");
+
+        let t = t.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let from = stops[t.floor() as usize];
+        let to = stops[(t.ceil() as usize).min(stops.len() - 1)];
+        let f = t.fract();
+        [0, 1, 2, 3].map(|c| {
+            let value = (from[c] as f32 + (to[c] as f32 - from[c] as f32) * f) / 255.0;
+            if c == 3 {
+                value
+            } else if value <= 0.04045 {
+                value / 12.92
+            } else {
+                ((value + 0.055) / 1.055).powf(2.4)
+            }
+        })
+    }
+}
+
+/// Which values map to the two ends of the colormap. Values outside the range are clamped.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum ValueRange {
+    /// The smallest and largest finite value in the grid.
+    #[default]
+    Auto,
+    Fixed {
+        min: f32,
+        max: f32,
+    },
+}
+
+/// A grid of values drawn as colors. NaN values are left transparent.
+#[derive(Clone, Debug)]
+pub struct Heatmap {
+    /// Center of the heatmap, like `Rect::position`.
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    pub columns: u32,
+    pub rows: u32,
+    /// Row-major, `columns * rows` values. Row 0 is at the smaller y coordinate.
+    pub values: Vec<f32>,
+    pub range: ValueRange,
+    pub colormap: Colormap,
+    /// Interpolate between cells instead of drawing each one as a flat block.
+    pub smooth: bool,
+    pub z_index: f32,
+}
+
+impl Heatmap {
+    fn resolved_range(&self) -> [f32; 2] {
+        let [min, max] = match self.range {
+            ValueRange::Fixed { min, max } => [min, max],
+            ValueRange::Auto => self
+                .values
+                .iter()
+                .filter(|v| v.is_finite())
+                .fold([f32::INFINITY, f32::NEG_INFINITY], |[min, max], &v| {
+                    [min.min(v), max.max(v)]
+                }),
+        };
+        if min.is_finite() && max > min {
+            [min, max]
+        } else if min.is_finite() {
+            [min, min + 1.0]
+        } else {
+            [0.0, 1.0]
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct HeatmapUniform {
+    position: [f32; 2],
+    size: [f32; 2],
+    range: [f32; 2],
+    grid: [f32; 2],
+    smooth_values: f32,
+    z_index: f32,
+    _padding: [f32; 2],
+}
+
+/// GPU resources for one heatmap.
+pub(crate) struct PreparedHeatmap {
+    uniform: HeatmapUniform,
+    uniform_buffer: Tracked<wgpu::Buffer>,
+    bind_group: Tracked<wgpu::BindGroup>,
+    // Bound through `bind_group`; kept here so they stay counted while in use.
+    _values: Tracked<wgpu::Texture>,
+    _lut: Tracked<wgpu::Texture>,
+}
+
+impl PreparedHeatmap {
+    /// Moves the heatmap into the screen space of `camera`.
+    pub fn update(&self, queue: &wgpu::Queue, camera: &Camera) {
+        let position = camera.to_screen([
+            self.uniform.position[0] as f64,
+            self.uniform.position[1] as f64,
+        ]);
+        let uniform = HeatmapUniform {
+            position: [position[0] as f32, position[1] as f32],
+            size: [
+                (self.uniform.size[0] as f64 * camera.zoom) as f32,
+                (self.uniform.size[1] as f64 * camera.zoom) as f32,
+            ],
+            ..self.uniform
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+}
+
+pub(crate) struct HeatmapPipeline {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+}
+
+impl HeatmapPipeline {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        window_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("heatmap.wgsl"));
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("Heatmap Bind Group Layout"),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Heatmap Pipeline Layout"),
+            bind_group_layouts: &[window_bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Heatmap Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            // The quad is generated in the shader and never culled, so the origin's winding
+            // doesn't matter here.
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Colormap Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            sampler,
+        }
+    }
+
+    /// Uploads the values and colormap of `heatmap`.
+    pub fn prepare(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        heatmap: &Heatmap,
+    ) -> PreparedHeatmap {
+        assert_eq!(
+            heatmap.values.len(),
+            (heatmap.columns * heatmap.rows) as usize,
+            "Heatmap needs columns * rows values"
+        );
+
+        let values = create_texture(
+            device,
+            queue,
+            "Heatmap Values",
+            [heatmap.columns, heatmap.rows],
+            wgpu::TextureFormat::R32Float,
+            bytemuck::cast_slice(&heatmap.values),
+        );
+        let lut = create_texture(
+            device,
+            queue,
+            "Colormap LUT",
+            [Colormap::LUT_SIZE as u32, 1],
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            bytemuck::cast_slice(&heatmap.colormap.lut()),
+        );
+
+        let uniform = HeatmapUniform {
+            position: heatmap.position,
+            size: heatmap.size,
+            range: heatmap.resolved_range(),
+            grid: [heatmap.columns as f32, heatmap.rows as f32],
+            smooth_values: if heatmap.smooth { 1.0 } else { 0.0 },
+            z_index: heatmap.z_index,
+            _padding: [0.0; 2],
+        };
+        let uniform_buffer = Tracked::new(
+            ResourceScope::Scene,
+            "Heatmap Uniform",
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Heatmap Uniform"),
+                contents: bytemuck::cast_slice(&[uniform]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }),
+        );
+
+        let bind_group = Tracked::new(
+            ResourceScope::Scene,
+            "Heatmap Bind Group",
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(
+                            &values.create_view(&wgpu::TextureViewDescriptor::default()),
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(
+                            &lut.create_view(&wgpu::TextureViewDescriptor::default()),
+                        ),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: wgpu::BindingResource::Sampler(&self.sampler),
+                    },
+                ],
+                label: Some("Heatmap Bind Group"),
+            }),
+        );
+
+        PreparedHeatmap {
+            uniform,
+            uniform_buffer,
+            bind_group,
+            _values: values,
+            _lut: lut,
+        }
+    }
+
+    pub fn render<'rp>(
+        &'rp self,
+        encoder: &mut impl RenderEncoder<'rp>,
+        window_bind_group: &'rp wgpu::BindGroup,
+        heatmaps: &'rp [PreparedHeatmap],
+    ) {
+        if heatmaps.is_empty() {
+            return;
+        }
+        encoder.set_pipeline(&self.pipeline);
+        encoder.set_bind_group(0, window_bind_group, &[]);
+        for heatmap in heatmaps {
+            encoder.set_bind_group(1, &heatmap.bind_group, &[]);
+            encoder.draw(0..6, 0..1);
+        }
+    }
+}
+
+fn create_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    label: &'static str,
+    [width, height]: [u32; 2],
+    format: wgpu::TextureFormat,
+    data: &[u8],
+) -> Tracked<wgpu::Texture> {
+    let size = wgpu::Extent3d {
+        width,
+        height,
+        depth_or_array_layers: 1,
+    };
+    let texture = Tracked::new(
+        ResourceScope::Scene,
+        label,
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        }),
+    );
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        data,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(width * 4),
+            rows_per_image: Some(height),
+        },
+        size,
+    );
+    texture
+}
@@ -0,0 +1,86 @@
+struct WindowUniform {
+	size: vec2<f32>,
+	scale_factor: f32,
+	y_direction: f32,
+}
+@group(0) @binding(0)
+var<uniform> window: WindowUniform;
+
+struct HeatmapUniform {
+	position: vec2<f32>,
+	size: vec2<f32>,
+	range: vec2<f32>,
+	grid: vec2<f32>,
+	smooth_values: f32,
+	z_index: f32,
+	padding: vec2<f32>,
+}
+@group(1) @binding(0)
+var<uniform> heatmap: HeatmapUniform;
+@group(1) @binding(1)
+var values: texture_2d<f32>;
+@group(1) @binding(2)
+var colormap: texture_2d<f32>;
+@group(1) @binding(3)
+var colormap_sampler: sampler;
+
+struct VertexOutput {
+	@builtin(position) clip_position: vec4<f32>,
+	@location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+	var out: VertexOutput;
+
+	var corners = array<vec2<f32>, 6>(
+		vec2<f32>(0.0, 0.0),
+		vec2<f32>(1.0, 0.0),
+		vec2<f32>(1.0, 1.0),
+		vec2<f32>(0.0, 0.0),
+		vec2<f32>(1.0, 1.0),
+		vec2<f32>(0.0, 1.0),
+	);
+	var uv = corners[index];
+	var point = heatmap.position + (uv - 0.5) * heatmap.size;
+	var ndc_position = vec2<f32>(
+		(2.0 * point.x / window.size.x) - 1.0,
+		window.y_direction * (1.0 - (2.0 * point.y / window.size.y))
+	);
+	out.clip_position = vec4<f32>(ndc_position, heatmap.z_index, 1.0);
+	out.uv = uv;
+	return out;
+}
+
+fn load_value(cell: vec2<i32>) -> f32 {
+	var last_cell = vec2<i32>(heatmap.grid) - 1;
+	return textureLoad(values, clamp(cell, vec2<i32>(0), last_cell), 0).r;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+	var cell = in.uv * heatmap.grid;
+	var value: f32;
+
+	if(heatmap.smooth_values > 0.5) {
+		// R32Float can't be filtered by a sampler, so interpolate between cell centers by hand
+		var p = cell - 0.5;
+		var base = vec2<i32>(floor(p));
+		var f = fract(p);
+		var top = mix(load_value(base), load_value(base + vec2<i32>(1, 0)), f.x);
+		var bottom = mix(load_value(base + vec2<i32>(0, 1)), load_value(base + vec2<i32>(1, 1)), f.x);
+		value = mix(top, bottom, f.y);
+	} else {
+		value = load_value(vec2<i32>(cell));
+	}
+
+	// NaN marks missing data
+	if(value != value) {
+		discard;
+	}
+
+	var t = clamp((value - heatmap.range.x) / (heatmap.range.y - heatmap.range.x), 0.0, 1.0);
+	// Sample texel centers so the ends of the range hit the first and last LUT entries
+	var lut_u = (t * 255.0 + 0.5) / 256.0;
+	return textureSampleLevel(colormap, colormap_sampler, vec2<f32>(lut_u, 0.5), 0.0);
+}
@@ -0,0 +1,525 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::resources::{ResourceScope, Tracked};
+
+/// Pixels for `Fill::Image` and `Fill::Pattern`.
+#[derive(Clone, Debug)]
+pub struct Image {
+    pub size: [u32; 2],
+    /// sRGB RGBA8, `size[0] * size[1]` pixels with the top row first.
+    pub rgba: Vec<u8>,
+}
+
+/// Empty texels between packed images, so filtering at an image's edge doesn't pick up its
+/// neighbour.
+const PADDING: u32 = 1;
+const MIN_ATLAS_SIZE: u32 = 256;
+
+/// Where each image lies in the atlas texture.
+#[derive(Clone, Debug, Default)]
+pub struct AtlasLayout {
+    size: u32,
+    /// Top-left corner and size of each image, in texels.
+    entries: HashMap<u64, ([u32; 2], [u32; 2])>,
+    /// Where the next image goes on the last row, and that row's height.
+    cursor: [u32; 2],
+    row_height: u32,
+}
+
+impl AtlasLayout {
+    /// Packs `images` in rows into the smallest power-of-two square that holds all of them, up
+    /// to `max_size`. Images that don't fit even then, or whose pixel data doesn't match their
+    /// size, are left out and logged.
+    pub fn pack(images: &[(u64, Image)], max_size: u32) -> Self {
+        let sizes: Vec<(u64, [u32; 2])> = images
+            .iter()
+            .filter(|(id, image)| {
+                let valid = image.rgba.len() == (image.size[0] * image.size[1] * 4) as usize;
+                if !valid {
+                    log::error!("Image {} has the wrong amount of pixel data", id);
+                }
+                valid
+            })
+            .map(|(id, image)| (*id, image.size))
+            .collect();
+        Self::pack_sizes(&sizes, max_size)
+    }
+
+    /// `pack` for images of the given sizes.
+    fn pack_sizes(images: &[(u64, [u32; 2])], max_size: u32) -> Self {
+        let mut size = MIN_ATLAS_SIZE.min(max_size);
+        loop {
+            let layout = Self::pack_into(images, size);
+            if layout.entries.len() == images.len() || size >= max_size {
+                for (id, _) in images.iter().filter(|(id, _)| layout.uv(*id).is_none()) {
+                    log::error!("Image {} doesn't fit in a {}px atlas", id, size);
+                }
+                return layout;
+            }
+            size = (size * 2).min(max_size);
+        }
+    }
+
+    fn pack_into(images: &[(u64, [u32; 2])], size: u32) -> Self {
+        // Tallest first keeps the rows tight.
+        let mut order: Vec<&(u64, [u32; 2])> = images.iter().collect();
+        order.sort_by_key(|(_, image_size)| std::cmp::Reverse(image_size[1]));
+
+        let mut entries = HashMap::new();
+        let [mut x, mut y, mut row_height] = [0; 3];
+        for &(id, image_size) in order {
+            let [width, height] = image_size.map(|side| side + PADDING);
+            if x + width > size {
+                x = 0;
+                y += row_height;
+                row_height = 0;
+            }
+            if width > size || y + height > size {
+                continue;
+            }
+            entries.insert(id, ([x, y], image_size));
+            x += width;
+            row_height = row_height.max(height);
+        }
+        Self {
+            size,
+            entries,
+            cursor: [x, y],
+            row_height,
+        }
+    }
+
+    /// Finds room for an image of `size` after those packed so far, on the last row or a new
+    /// one below it.
+    fn allocate(&mut self, size: [u32; 2]) -> Option<[u32; 2]> {
+        let [width, height] = size.map(|side| side + PADDING);
+        let [mut x, mut y] = self.cursor;
+        let mut row_height = self.row_height;
+        if x + width > self.size {
+            x = 0;
+            y += row_height;
+            row_height = 0;
+        }
+        if width > self.size || y + height > self.size {
+            return None;
+        }
+        self.cursor = [x + width, y];
+        self.row_height = row_height.max(height);
+        Some([x, y])
+    }
+
+    /// Edge length of the square atlas texture, in texels.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// The image's rect in the atlas as `[left, top, right, bottom]` texture coordinates.
+    pub fn uv(&self, id: u64) -> Option<[f32; 4]> {
+        let ([x, y], [width, height]) = *self.entries.get(&id)?;
+        let size = self.size as f32;
+        Some([
+            x as f32 / size,
+            y as f32 / size,
+            (x + width) as f32 / size,
+            (y + height) as f32 / size,
+        ])
+    }
+}
+
+/// An image added while running, uploaded a band of rows at a time.
+struct PendingImage {
+    id: u64,
+    image: Image,
+    /// Where it goes in the atlas, once there was room for it.
+    origin: Option<[u32; 2]>,
+    rows_uploaded: u32,
+}
+
+/// The images of a scene packed into one texture, bound as group 1 of the rect pipeline.
+pub(crate) struct ImageAtlas {
+    texture: Tracked<wgpu::Texture>,
+    sampler: wgpu::Sampler,
+    pub bind_group: Tracked<wgpu::BindGroup>,
+    pub layout: AtlasLayout,
+    pending: VecDeque<PendingImage>,
+}
+
+impl ImageAtlas {
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("Image Atlas Bind Group Layout"),
+        })
+    }
+
+    /// Packs and uploads `images`. Without any, the atlas is a single texel owned by the
+    /// renderer, so that the rect pipeline always has something bound.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        images: &[(u64, Image)],
+    ) -> Self {
+        let (layout, scope) = if images.is_empty() {
+            (
+                AtlasLayout {
+                    size: 1,
+                    ..Default::default()
+                },
+                ResourceScope::Renderer,
+            )
+        } else {
+            (
+                AtlasLayout::pack(images, device.limits().max_texture_dimension_2d),
+                ResourceScope::Scene,
+            )
+        };
+
+        let texture = create_texture(device, layout.size, scope);
+        for (id, image) in images {
+            let Some(&(origin, [_, height])) = layout.entries.get(id) else {
+                continue;
+            };
+            write_rows(queue, &texture, origin, image, 0..height);
+        }
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Image Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let bind_group = create_bind_group(device, bind_group_layout, &texture, &sampler, scope);
+
+        Self {
+            texture,
+            sampler,
+            bind_group,
+            layout,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Queues `image` for `upload_pending`. One replacing an image of the same size is
+    /// written over it; otherwise it gets new room, and the old image's stays taken until the
+    /// scene is cleared or the atlas is compacted.
+    pub fn add(&mut self, id: u64, image: Image) {
+        if image.rgba.len() != (image.size[0] * image.size[1] * 4) as usize {
+            log::error!("Image {} has the wrong amount of pixel data", id);
+            return;
+        }
+        let origin = self
+            .layout
+            .entries
+            .get(&id)
+            .filter(|(_, size)| *size == image.size)
+            .map(|(origin, _)| *origin);
+        self.pending.retain(|pending| pending.id != id);
+        self.pending.push_back(PendingImage {
+            id,
+            image,
+            origin,
+            rows_uploaded: 0,
+        });
+    }
+
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Uploads the queued images a band of rows at a time, until about `budget` bytes went
+    /// up, so a batch arriving at once is spread over frames instead of stalling one. At least
+    /// a row goes up every call. The texture grows when an image doesn't fit, copying what it
+    /// held on the GPU. Images only show once all their rows arrived.
+    ///
+    /// Returns whether the layout changed, which the rects' texture coordinates come from.
+    pub fn upload_pending(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        budget: usize,
+    ) -> bool {
+        let size = self.layout.size;
+        let mut changed = false;
+        let mut uploaded = 0;
+        while uploaded == 0 || uploaded < budget {
+            let Some(mut pending) = self.pending.pop_front() else {
+                break;
+            };
+            let origin = match pending.origin {
+                Some(origin) => origin,
+                None => {
+                    match self.make_room(device, queue, bind_group_layout, pending.image.size) {
+                        Some(origin) => origin,
+                        None => {
+                            log::error!("Image {} doesn't fit in the atlas", pending.id);
+                            continue;
+                        }
+                    }
+                }
+            };
+            pending.origin = Some(origin);
+
+            let [width, height] = pending.image.size;
+            let row_bytes = (width as usize * 4).max(1);
+            let rows = (budget.saturating_sub(uploaded) / row_bytes)
+                .clamp(1, (height - pending.rows_uploaded).max(1) as usize)
+                as u32;
+            let rows = pending.rows_uploaded..(pending.rows_uploaded + rows).min(height);
+            if width > 0 && !rows.is_empty() {
+                write_rows(queue, &self.texture, origin, &pending.image, rows.clone());
+            }
+            uploaded += rows.len() * row_bytes;
+            pending.rows_uploaded = rows.end;
+
+            if pending.rows_uploaded == height {
+                self.layout
+                    .entries
+                    .insert(pending.id, (origin, pending.image.size));
+                changed = true;
+            } else {
+                self.pending.push_front(pending);
+            }
+        }
+        changed || self.layout.size != size
+    }
+
+    /// Packs the images again into the smallest texture that holds them, copying them over on
+    /// the GPU, when that is smaller than the current one: room left behind by images
+    /// replaced with ones of another size is only given back this way. Waits for queued
+    /// images to finish first.
+    ///
+    /// Returns whether the layout changed, which the rects' texture coordinates come from.
+    pub fn compact(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> bool {
+        if self.has_pending() || self.layout.entries.is_empty() {
+            return false;
+        }
+        let sizes: Vec<(u64, [u32; 2])> = self
+            .layout
+            .entries
+            .iter()
+            .map(|(id, (_, size))| (*id, *size))
+            .collect();
+        let layout = AtlasLayout::pack_sizes(&sizes, device.limits().max_texture_dimension_2d);
+        if layout.size >= self.layout.size || layout.entries.len() != sizes.len() {
+            return false;
+        }
+
+        let texture = create_texture(device, layout.size, ResourceScope::Scene);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Image Atlas Compaction"),
+        });
+        for (id, (origin, [width, height])) in &self.layout.entries {
+            if *width == 0 || *height == 0 {
+                continue;
+            }
+            let to = layout.entries[id].0;
+            encoder.copy_texture_to_texture(
+                wgpu::ImageCopyTexture {
+                    origin: wgpu::Origin3d {
+                        x: origin[0],
+                        y: origin[1],
+                        z: 0,
+                    },
+                    ..self.texture.as_image_copy()
+                },
+                wgpu::ImageCopyTexture {
+                    origin: wgpu::Origin3d {
+                        x: to[0],
+                        y: to[1],
+                        z: 0,
+                    },
+                    ..texture.as_image_copy()
+                },
+                wgpu::Extent3d {
+                    width: *width,
+                    height: *height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+        self.bind_group = create_bind_group(
+            device,
+            bind_group_layout,
+            &texture,
+            &self.sampler,
+            ResourceScope::Scene,
+        );
+        self.texture = texture;
+        self.layout = layout;
+        true
+    }
+
+    /// Room for an image of `size`, doubling the texture until there is some or it reached
+    /// the device's limit.
+    fn make_room(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        size: [u32; 2],
+    ) -> Option<[u32; 2]> {
+        let max_size = device.limits().max_texture_dimension_2d;
+        loop {
+            if let Some(origin) = self.layout.allocate(size) {
+                return Some(origin);
+            }
+            if self.layout.size >= max_size {
+                return None;
+            }
+            let grown = (self.layout.size * 2).clamp(MIN_ATLAS_SIZE.min(max_size), max_size);
+            self.grow(device, queue, bind_group_layout, grown);
+        }
+    }
+
+    /// Moves the images to a texture of `size`, where they keep their texel positions.
+    fn grow(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        size: u32,
+    ) {
+        let texture = create_texture(device, size, ResourceScope::Scene);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Image Atlas Growth"),
+        });
+        encoder.copy_texture_to_texture(
+            self.texture.as_image_copy(),
+            texture.as_image_copy(),
+            wgpu::Extent3d {
+                width: self.layout.size,
+                height: self.layout.size,
+                depth_or_array_layers: 1,
+            },
+        );
+        // Rows written so far are flushed before this copy, and later ones go to the new
+        // texture.
+        queue.submit(std::iter::once(encoder.finish()));
+        self.bind_group = create_bind_group(
+            device,
+            bind_group_layout,
+            &texture,
+            &self.sampler,
+            ResourceScope::Scene,
+        );
+        self.texture = texture;
+        self.layout.size = size;
+    }
+}
+
+fn create_texture(
+    device: &wgpu::Device,
+    size: u32,
+    scope: ResourceScope,
+) -> Tracked<wgpu::Texture> {
+    Tracked::new(
+        scope,
+        "Image Atlas",
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Image Atlas"),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            // Copied from when the atlas grows.
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        }),
+    )
+}
+
+fn create_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    texture: &wgpu::Texture,
+    sampler: &wgpu::Sampler,
+    scope: ResourceScope,
+) -> Tracked<wgpu::BindGroup> {
+    Tracked::new(
+        scope,
+        "Image Atlas Bind Group",
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(
+                        &texture.create_view(&wgpu::TextureViewDescriptor::default()),
+                    ),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+            label: Some("Image Atlas Bind Group"),
+        }),
+    )
+}
+
+/// Writes `rows` of `image` to its place at `origin`.
+fn write_rows(
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    origin: [u32; 2],
+    image: &Image,
+    rows: std::ops::Range<u32>,
+) {
+    let width = image.size[0];
+    let row_bytes = width as usize * 4;
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d {
+                x: origin[0],
+                y: origin[1] + rows.start,
+                z: 0,
+            },
+            aspect: wgpu::TextureAspect::All,
+        },
+        &image.rgba[rows.start as usize * row_bytes..rows.end as usize * row_bytes],
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(width * 4),
+            rows_per_image: Some(rows.len() as u32),
+        },
+        wgpu::Extent3d {
+            width,
+            height: rows.len() as u32,
+            depth_or_array_layers: 1,
+        },
+    );
+}
@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+
+use glyphon::{FontSystem, Metrics};
+
+use crate::{
+    rect::{Fill, Rect},
+    text::{font_metrics, measure},
+};
+
+/// A piece of flowing content: text that may wrap between words, or a widget that moves
+/// as a single unbreakable box.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Inline {
+    Text(String),
+    /// A rounded label such as an @mention.
+    Chip {
+        label: String,
+        color: [f32; 4],
+    },
+    /// A fixed-size box, e.g. an emoji image, identified by `id` for the caller to draw.
+    Image {
+        id: u64,
+        size: [f32; 2],
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlacedKind {
+    /// A word, drawn with its top-left corner at the item's position.
+    Word(String),
+    /// The chip's label goes at `label_position`.
+    Chip {
+        label: String,
+        color: [f32; 4],
+        label_position: [f32; 2],
+    },
+    Image {
+        id: u64,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Placed {
+    pub kind: PlacedKind,
+    /// Top-left corner, relative to the layout's origin.
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    pub line: usize,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct InlineLine {
+    pub top: f32,
+    pub height: f32,
+    pub baseline: f32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct InlineLayout {
+    pub items: Vec<Placed>,
+    pub lines: Vec<InlineLine>,
+}
+
+impl InlineLayout {
+    pub fn height(&self) -> f32 {
+        self.lines.last().map_or(0.0, |line| line.top + line.height)
+    }
+
+    /// Chip backgrounds, offset by `origin`. Words and images are left to the caller.
+    pub fn chip_rects(&self, origin: [f32; 2]) -> Vec<Rect> {
+        self.items
+            .iter()
+            .filter_map(|item| match item.kind {
+                PlacedKind::Chip { color, .. } => Some(Rect {
+                    position: [
+                        origin[0] + item.position[0] + item.size[0] / 2.0,
+                        origin[1] + item.position[1] + item.size[1] / 2.0,
+                    ],
+                    size: item.size,
+                    border_radius: [item.size[1] / 2.0; 4],
+                    fill: Some(Fill::Solid { color }),
+                    ..Default::default()
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// One unbreakable unit waiting to be placed on a line.
+struct Unit {
+    kind: PlacedKind,
+    size: [f32; 2],
+    /// How far the unit reaches above the baseline.
+    ascent: f32,
+    /// Whether a space separates it from the next unit. Widgets only get one from the
+    /// surrounding text.
+    space_after: bool,
+}
+
+/// Flows `content` into lines at most `width` pixels wide, breaking only at spaces between
+/// words and widgets. Widgets are centered on the middle of the lowercase letters, and lines
+/// grow to fit widgets taller than the text.
+pub fn layout_inline(
+    font_system: &mut FontSystem,
+    content: &[Inline],
+    metrics: Metrics,
+    width: f32,
+) -> InlineLayout {
+    // Words repeat a lot in chat and documents, so each is only shaped once.
+    let mut widths: HashMap<String, f32> = HashMap::new();
+    let mut word_width = |font_system: &mut FontSystem, word: &str| {
+        *widths
+            .entry(word.to_string())
+            .or_insert_with(|| measure(font_system, word, metrics)[0])
+    };
+    let space = word_width(font_system, "a a") - word_width(font_system, "aa");
+
+    // Lines center the font's ascent and descent within the line height, which puts the
+    // baseline this far below the top of a word. Without a font to measure, typical
+    // proportions stand in.
+    let (ascent, middle) = match font_metrics(font_system, metrics.font_size) {
+        Some(font) => (
+            (metrics.line_height - font.ascent - font.descent) / 2.0 + font.ascent,
+            font.x_height / 2.0,
+        ),
+        None => (metrics.font_size * 0.8, metrics.font_size * 0.25),
+    };
+    let chip_padding = metrics.font_size * 0.4;
+
+    let mut units: Vec<Unit> = vec![];
+    for inline in content {
+        match inline {
+            Inline::Text(text) => {
+                // Whitespace at either end separates this text from its neighbours.
+                if text.starts_with(char::is_whitespace) {
+                    if let Some(previous) = units.last_mut() {
+                        previous.space_after = true;
+                    }
+                }
+                let ends_with_space = text.ends_with(char::is_whitespace);
+                let mut words = text.split_whitespace().peekable();
+                while let Some(word) = words.next() {
+                    units.push(Unit {
+                        kind: PlacedKind::Word(word.to_string()),
+                        size: [word_width(font_system, word), metrics.line_height],
+                        ascent,
+                        space_after: words.peek().is_some() || ends_with_space,
+                    });
+                }
+            }
+            Inline::Chip { label, color } => {
+                let height = metrics.font_size * 1.4;
+                units.push(Unit {
+                    kind: PlacedKind::Chip {
+                        label: label.clone(),
+                        color: *color,
+                        label_position: [0.0; 2],
+                    },
+                    size: [word_width(font_system, label) + chip_padding * 2.0, height],
+                    ascent: middle + height / 2.0,
+                    space_after: false,
+                });
+            }
+            Inline::Image { id, size } => units.push(Unit {
+                kind: PlacedKind::Image { id: *id },
+                size: *size,
+                ascent: middle + size[1] / 2.0,
+                space_after: false,
+            }),
+        }
+    }
+
+    let mut layout = InlineLayout::default();
+    let mut line_start = 0;
+    let mut x = 0.0;
+    let mut top = 0.0;
+    let mut finish_line = |layout: &mut InlineLayout, units: &[Unit], start: usize, end: usize| {
+        let above = units[start..end]
+            .iter()
+            .map(|unit| unit.ascent)
+            .fold(ascent, f32::max);
+        let below = units[start..end]
+            .iter()
+            .map(|unit| unit.size[1] - unit.ascent)
+            .fold(metrics.line_height - ascent, f32::max);
+        let line = InlineLine {
+            top,
+            height: above + below,
+            baseline: top + above,
+        };
+        for (item, unit) in layout.items[start..end].iter_mut().zip(&units[start..end]) {
+            item.position[1] = line.baseline - unit.ascent;
+            if let PlacedKind::Chip { label_position, .. } = &mut item.kind {
+                *label_position = [
+                    item.position[0] + chip_padding,
+                    item.position[1] + (item.size[1] - metrics.line_height) / 2.0,
+                ];
+            }
+        }
+        top += line.height;
+        layout.lines.push(line);
+    };
+
+    for (i, unit) in units.iter().enumerate() {
+        // Units with no space between them, like a chip followed by a comma, stay together.
+        let can_break = i > 0 && units[i - 1].space_after;
+        if can_break && x + unit.size[0] > width {
+            finish_line(&mut layout, &units, line_start, i);
+            line_start = i;
+            x = 0.0;
+        }
+        layout.items.push(Placed {
+            kind: unit.kind.clone(),
+            position: [x, 0.0],
+            size: unit.size,
+            line: layout.lines.len(),
+        });
+        x += unit.size[0];
+        if unit.space_after {
+            x += space;
+        }
+    }
+    if line_start < units.len() {
+        finish_line(&mut layout, &units, line_start, units.len());
+    }
+    layout
+}
@@ -0,0 +1,572 @@
+//! The scene inspector: a debug panel along the right edge of the window, shown with
+//! `RendererConfig::inspector` or F12.
+//!
+//! It lists the draw list's rects layer by layer in draw order, outlines the one under the
+//! cursor in the view or in the list, and edits the fill color, radius, z index and softness
+//! of the one clicked. The eyedropper button beside the color swatch takes the color of the
+//! next pixel clicked in the view. Edits go straight into the draw list, so they last until
+//! the application replaces the rect. The panel is drawn with the renderer's own rects and
+//! text, over everything but egui.
+
+use glyphon::{FontSystem, Metrics, TextArea, TextBounds};
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+
+use crate::{
+    camera::Camera,
+    rect::{Fill, Rect},
+    scene::{DrawList, Layer},
+    text::TextNode,
+    theme::Theme,
+};
+
+const PANEL_WIDTH: f32 = 300.0;
+const PADDING: f32 = 8.0;
+const FONT_SIZE: f32 = 14.0;
+/// Every row of the panel is one line of its text.
+const LINE_HEIGHT: f32 = 20.0;
+const BUTTON_SIZE: f32 = 16.0;
+/// The title row above the list, and the blank row, heading and properties below it.
+const HEADER_ROWS: usize = 1;
+const DETAIL_ROWS: usize = 2 + Property::ALL.len();
+
+/// A value of the selected rect that the panel edits with a pair of buttons.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Property {
+    Red,
+    Green,
+    Blue,
+    Alpha,
+    Radius,
+    Z,
+    Softness,
+}
+
+impl Property {
+    const ALL: [Property; 7] = [
+        Property::Red,
+        Property::Green,
+        Property::Blue,
+        Property::Alpha,
+        Property::Radius,
+        Property::Z,
+        Property::Softness,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Property::Red => "red",
+            Property::Green => "green",
+            Property::Blue => "blue",
+            Property::Alpha => "alpha",
+            Property::Radius => "radius",
+            Property::Z => "z index",
+            Property::Softness => "softness",
+        }
+    }
+
+    /// What one click of a button adds or takes away.
+    fn step(self) -> f32 {
+        match self {
+            Property::Red | Property::Green | Property::Blue | Property::Alpha => 0.05,
+            Property::Radius | Property::Z => 1.0,
+            Property::Softness => 0.5,
+        }
+    }
+
+    /// The value shown for `rect`, or `None` when it can't be edited: only solid fills have a
+    /// color to edit. Corners that differ show the largest radius.
+    fn value(self, rect: &Rect) -> Option<f32> {
+        match self {
+            Property::Red | Property::Green | Property::Blue | Property::Alpha => match rect.fill {
+                Some(Fill::Solid { color }) => Some(color[self as usize]),
+                _ => None,
+            },
+            Property::Radius => Some(rect.border_radius.into_iter().fold(0.0, f32::max)),
+            Property::Z => Some(rect.z_index),
+            Property::Softness => Some(rect.softness),
+        }
+    }
+
+    /// Moves the value of `rect` by `steps` steps. Radii move together, keeping their
+    /// differences.
+    fn adjust(self, rect: &mut Rect, steps: f32) {
+        let delta = steps * self.step();
+        match self {
+            Property::Red | Property::Green | Property::Blue | Property::Alpha => {
+                if let Some(Fill::Solid { color }) = &mut rect.fill {
+                    let channel = &mut color[self as usize];
+                    *channel = (*channel + delta).clamp(0.0, 1.0);
+                }
+            }
+            Property::Radius => {
+                rect.border_radius = rect.border_radius.map(|radius| (radius + delta).max(0.0));
+            }
+            Property::Z => rect.z_index += delta,
+            Property::Softness => rect.softness = (rect.softness + delta).max(0.0),
+        }
+    }
+}
+
+/// Where the eyedropper is in taking a color from the view.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Dropper {
+    #[default]
+    Off,
+    /// The next click in the view picks the pixel under it.
+    Armed,
+    /// The pixel was asked for and is being read back.
+    Waiting,
+}
+
+/// A line of the scene list.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Entry {
+    /// The heading of the rects of a layer that follow.
+    Layer(Layer),
+    Rect(usize),
+}
+
+/// A box of the panel, by its top-left corner in window pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Slot {
+    position: [f32; 2],
+    size: [f32; 2],
+}
+
+impl Slot {
+    fn contains(&self, point: [f32; 2]) -> bool {
+        (0..2).all(|i| self.position[i] <= point[i] && point[i] < self.position[i] + self.size[i])
+    }
+
+    fn rect(&self, color: [f32; 4]) -> Rect {
+        Rect {
+            position: [0, 1].map(|i| self.position[i] + self.size[i] / 2.0),
+            size: self.size,
+            fill: Some(Fill::Solid { color }),
+            softness: 0.0,
+            ..Default::default()
+        }
+    }
+}
+
+/// The panel's state between frames. Sizes passed in are the window's, in physical pixels.
+pub(crate) struct Inspector {
+    /// In window pixels from the top-left corner, whatever the origin of rects.
+    cursor: [f32; 2],
+    /// The first list entry shown.
+    scroll: usize,
+    selected: Option<usize>,
+    /// The rect whose list entry is under the cursor.
+    hovered_entry: Option<usize>,
+    dropper: Dropper,
+    /// The pixel the eyedropper wants read, in window pixels from the top-left corner.
+    sample_request: Option<[f32; 2]>,
+    /// Every row of the panel, one line each.
+    text: TextNode,
+    changed: bool,
+}
+
+impl Inspector {
+    pub fn new(font_system: &mut FontSystem) -> Self {
+        Self {
+            cursor: [f32::NEG_INFINITY; 2],
+            scroll: 0,
+            selected: None,
+            hovered_entry: None,
+            dropper: Dropper::Off,
+            sample_request: None,
+            // Rows mustn't wrap, or the text would drift from the rows it labels, so long ones
+            // run on and are cut off at the panel's edge instead.
+            text: TextNode::new(
+                font_system,
+                "",
+                Metrics::new(FONT_SIZE, LINE_HEIGHT),
+                f32::MAX,
+            ),
+            changed: false,
+        }
+    }
+
+    /// Handles hovering, clicks and scrolling, returning whether `event` was used. Clicking a
+    /// rect in the view outside the panel selects it too, but leaves the click to the view,
+    /// unless the eyedropper takes it.
+    pub fn on_event(
+        &mut self,
+        event: &WindowEvent,
+        draw_list: &mut DrawList,
+        hovered: Option<usize>,
+        size: [f32; 2],
+    ) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = [position.x as f32, position.y as f32];
+                let entry = self.entry_at(draw_list, size);
+                if entry != self.hovered_entry {
+                    self.hovered_entry = entry;
+                    self.changed = true;
+                }
+                false
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                if !panel(size).contains(self.cursor) {
+                    if self.dropper == Dropper::Armed {
+                        self.dropper = Dropper::Waiting;
+                        self.sample_request = Some(self.cursor);
+                        self.changed = true;
+                        return true;
+                    }
+                    if hovered.is_some() && hovered != self.selected {
+                        self.selected = hovered;
+                        self.changed = true;
+                    }
+                    return false;
+                }
+                if let Some(i) = self.entry_at(draw_list, size) {
+                    self.selected = Some(i);
+                } else if self
+                    .selected(draw_list)
+                    .is_some_and(|i| swatch(&draw_list.rects()[i]).is_some())
+                    && dropper_slot(size).contains(self.cursor)
+                {
+                    self.dropper = match self.dropper {
+                        Dropper::Off => Dropper::Armed,
+                        Dropper::Armed | Dropper::Waiting => Dropper::Off,
+                    };
+                } else if let (Some(i), Some((property, steps))) =
+                    (self.selected(draw_list), self.button_at(size))
+                {
+                    let mut rect = draw_list.rects()[i];
+                    property.adjust(&mut rect, steps);
+                    draw_list.set_rect(i, rect);
+                }
+                self.changed = true;
+                true
+            }
+            WindowEvent::MouseWheel { delta, .. } if panel(size).contains(self.cursor) => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => -y.round() as i64 * 3,
+                    MouseScrollDelta::PixelDelta(position) => {
+                        -(position.y / LINE_HEIGHT as f64).round() as i64
+                    }
+                };
+                let last = entries(draw_list).len().saturating_sub(list_rows(size[1]));
+                self.scroll = (self.scroll as i64 + lines).clamp(0, last as i64) as usize;
+                self.hovered_entry = self.entry_at(draw_list, size);
+                self.changed = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The pixel the eyedropper wants read since the last call, in window pixels from the
+    /// top-left corner.
+    pub fn take_sample_request(&mut self) -> Option<[f32; 2]> {
+        self.sample_request.take()
+    }
+
+    /// Gives the selected rect the color read for the eyedropper, keeping its alpha. Colors
+    /// read for anything else are ignored.
+    pub fn sampled(&mut self, draw_list: &mut DrawList, color: [f32; 4]) {
+        if self.dropper != Dropper::Waiting {
+            return;
+        }
+        self.dropper = Dropper::Off;
+        self.changed = true;
+        let Some(i) = self.selected(draw_list) else {
+            return;
+        };
+        let mut rect = draw_list.rects()[i];
+        if let Some(
+            Fill::Solid { color: fill }
+            | Fill::Image { tint: fill, .. }
+            | Fill::Pattern { tint: fill, .. },
+        ) = &mut rect.fill
+        {
+            fill[..3].copy_from_slice(&color[..3]);
+            draw_list.set_rect(i, rect);
+        }
+    }
+
+    /// Whether the panel has to be laid out again since the last call.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// Writes the rows for the draw list as it is now.
+    pub fn refresh(&mut self, font_system: &mut FontSystem, draw_list: &DrawList, size: [f32; 2]) {
+        let entries = entries(draw_list);
+        let rows = list_rows(size[1]);
+        self.scroll = self.scroll.min(entries.len().saturating_sub(rows));
+
+        let mut lines = vec![format!("Scene inspector: {} rects", draw_list.len())];
+        lines.extend((0..rows).map(|row| match entries.get(self.scroll + row) {
+            Some(Entry::Layer(layer)) => format!("{layer:?}"),
+            Some(Entry::Rect(i)) => {
+                let rect = &draw_list.rects()[*i];
+                let mut line = format!(
+                    "    #{i}  {:.0} x {:.0}",
+                    rect.size[0].abs(),
+                    rect.size[1].abs()
+                );
+                if let Some(key) = draw_list.node_key(*i) {
+                    line.push_str(&format!("  key {key}"));
+                }
+                if let Some(tag) = draw_list.tag(*i) {
+                    line.push_str(&format!("  tag {tag}"));
+                }
+                line
+            }
+            None => String::new(),
+        }));
+        lines.push(String::new());
+        match self.selected(draw_list) {
+            Some(i) => {
+                let rect = &draw_list.rects()[i];
+                lines.push(format!(
+                    "#{i} at {:.1}, {:.1}",
+                    rect.position[0], rect.position[1]
+                ));
+                lines.extend(Property::ALL.map(|property| match property.value(rect) {
+                    Some(value) => format!("{}  {value:.2}", property.label()),
+                    None => format!("{}  -", property.label()),
+                }));
+            }
+            None => lines.push("Click a rect to edit it".to_string()),
+        }
+        self.text.set_text(font_system, &lines.join("\n"));
+    }
+
+    /// The panel's background, row highlights and buttons, in window pixels from the top-left
+    /// corner.
+    pub fn rects(&self, draw_list: &DrawList, size: [f32; 2], theme: &Theme) -> Vec<Rect> {
+        let mut rects = vec![panel(size).rect(theme.panel)];
+        let entries = entries(draw_list);
+        let selected = self.selected(draw_list);
+        for row in 0..list_rows(size[1]) {
+            let Some(Entry::Rect(i)) = entries.get(self.scroll + row) else {
+                continue;
+            };
+            let slot = row_slot(HEADER_ROWS + row, size);
+            if selected == Some(*i) {
+                rects.push(slot.rect(theme.selected));
+            } else if self.hovered_entry == Some(*i) {
+                rects.push(slot.rect(theme.hover));
+            }
+        }
+
+        let Some(i) = selected else {
+            return rects;
+        };
+        let rect = &draw_list.rects()[i];
+        let heading = row_slot(details_row(size[1]), size);
+        if let Some(color) = swatch(rect) {
+            rects.push(button_slot(heading, 1).rect(color));
+            let dropper = dropper_slot(size);
+            let background = match self.dropper {
+                Dropper::Off => theme.button,
+                Dropper::Armed | Dropper::Waiting => theme.selected,
+            };
+            let center = [0, 1].map(|i| dropper.position[i] + dropper.size[i] / 2.0);
+            let tip = Slot {
+                position: [center[0] - 3.0, center[1] - 3.0],
+                size: [6.0, 6.0],
+            };
+            rects.extend([dropper.rect(background), tip.rect(theme.foreground)]);
+        }
+        for (row, property) in Property::ALL.into_iter().enumerate() {
+            if property.value(rect).is_none() {
+                continue;
+            }
+            let slot = row_slot(details_row(size[1]) + 1 + row, size);
+            let [minus, plus] = [0, 1].map(|column| button_slot(slot, column));
+            rects.extend([minus.rect(theme.button), plus.rect(theme.button)]);
+            let center = [minus, plus]
+                .map(|button| [0, 1].map(|i| button.position[i] + button.size[i] / 2.0));
+            let bar = |center: [f32; 2], size: [f32; 2]| Slot {
+                position: [center[0] - size[0] / 2.0, center[1] - size[1] / 2.0],
+                size,
+            };
+            rects.extend(
+                [
+                    bar(center[0], [8.0, 2.0]),
+                    bar(center[1], [8.0, 2.0]),
+                    bar(center[1], [2.0, 8.0]),
+                ]
+                .map(|slot| slot.rect(theme.foreground)),
+            );
+        }
+        rects
+    }
+
+    /// Outlines around the selected rect and the one hovered in the view or the list, in the
+    /// screen space of rects.
+    pub fn outlines(
+        &self,
+        draw_list: &DrawList,
+        hovered: Option<usize>,
+        camera: &Camera,
+        theme: &Theme,
+    ) -> Vec<Rect> {
+        let hovered = self.hovered_entry.or(hovered);
+        let mut outlines = vec![];
+        for (index, color) in [
+            (self.selected(draw_list), theme.selected_outline),
+            (hovered, theme.hover_outline),
+        ] {
+            if let Some(rect) = index.and_then(|i| draw_list.rects().get(i)) {
+                outlines.extend(outline(&camera.apply(rect), color));
+            }
+        }
+        outlines
+    }
+
+    /// The rows' text, cut off at the panel's edges.
+    pub fn text_area(&self, size: [f32; 2], theme: &Theme) -> TextArea<'_> {
+        let panel = panel(size);
+        TextArea {
+            buffer: self.text.buffer(),
+            left: panel.position[0] + PADDING,
+            top: PADDING,
+            scale: 1.0,
+            bounds: TextBounds {
+                left: panel.position[0] as i32,
+                top: 0,
+                right: (panel.position[0] + panel.size[0] - PADDING) as i32,
+                bottom: size[1] as i32,
+            },
+            default_color: theme.text_color(),
+        }
+    }
+
+    /// The selected rect, unless the draw list has since shrunk past it.
+    fn selected(&self, draw_list: &DrawList) -> Option<usize> {
+        self.selected.filter(|&i| i < draw_list.len())
+    }
+
+    /// The rect whose list entry is under the cursor.
+    fn entry_at(&self, draw_list: &DrawList, size: [f32; 2]) -> Option<usize> {
+        let row = row_at(self.cursor, size)?.checked_sub(HEADER_ROWS)?;
+        if row >= list_rows(size[1]) {
+            return None;
+        }
+        match entries(draw_list).get(self.scroll + row) {
+            Some(Entry::Rect(i)) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// The property whose button is under the cursor, with the steps that button moves it.
+    fn button_at(&self, size: [f32; 2]) -> Option<(Property, f32)> {
+        let row = row_at(self.cursor, size)?.checked_sub(details_row(size[1]) + 1)?;
+        let property = *Property::ALL.get(row)?;
+        let slot = row_slot(details_row(size[1]) + 1 + row, size);
+        [(0, -1.0), (1, 1.0)]
+            .into_iter()
+            .find(|(column, _)| button_slot(slot, *column).contains(self.cursor))
+            .map(|(_, steps)| (property, steps))
+    }
+}
+
+/// The list's entries: each layer's rects in draw order, under the layer's name.
+fn entries(draw_list: &DrawList) -> Vec<Entry> {
+    let mut entries = vec![];
+    let mut layer = None;
+    for i in draw_list.draw_order() {
+        if layer != Some(draw_list.layer(i)) {
+            layer = Some(draw_list.layer(i));
+            entries.push(Entry::Layer(draw_list.layer(i)));
+        }
+        entries.push(Entry::Rect(i));
+    }
+    entries
+}
+
+fn panel(size: [f32; 2]) -> Slot {
+    Slot {
+        position: [(size[0] - PANEL_WIDTH).max(0.0), 0.0],
+        size: [PANEL_WIDTH.min(size[0]), size[1]],
+    }
+}
+
+/// How many list entries fit between the title and the details.
+fn list_rows(height: f32) -> usize {
+    let rows = ((height - 2.0 * PADDING) / LINE_HEIGHT).max(0.0) as usize;
+    rows.saturating_sub(HEADER_ROWS + DETAIL_ROWS)
+}
+
+/// The row of the blank line above the selected rect's heading.
+fn details_row(height: f32) -> usize {
+    HEADER_ROWS + list_rows(height) + 1
+}
+
+fn row_slot(row: usize, size: [f32; 2]) -> Slot {
+    let panel = panel(size);
+    Slot {
+        position: [
+            panel.position[0] + PADDING,
+            PADDING + row as f32 * LINE_HEIGHT,
+        ],
+        size: [panel.size[0] - 2.0 * PADDING, LINE_HEIGHT],
+    }
+}
+
+fn row_at(point: [f32; 2], size: [f32; 2]) -> Option<usize> {
+    if !panel(size).contains(point) || point[1] < PADDING {
+        return None;
+    }
+    Some(((point[1] - PADDING) / LINE_HEIGHT) as usize)
+}
+
+/// The minus button of a row in column 0 and the plus button in column 1, at its right end.
+fn button_slot(row: Slot, column: usize) -> Slot {
+    let gap = 4.0;
+    let right = row.position[0] + row.size[0];
+    Slot {
+        position: [
+            right - (2 - column) as f32 * BUTTON_SIZE - (1 - column) as f32 * gap,
+            row.position[1] + (row.size[1] - BUTTON_SIZE) / 2.0,
+        ],
+        size: [BUTTON_SIZE; 2],
+    }
+}
+
+/// The color of a rect's solid fill or image or pattern tint, shown in its swatch.
+fn swatch(rect: &Rect) -> Option<[f32; 4]> {
+    match rect.fill {
+        Some(Fill::Solid { color })
+        | Some(Fill::Image { tint: color, .. })
+        | Some(Fill::Pattern { tint: color, .. }) => Some(color),
+        _ => None,
+    }
+}
+
+/// The eyedropper button, left of the selected rect's color swatch.
+fn dropper_slot(size: [f32; 2]) -> Slot {
+    button_slot(row_slot(details_row(size[1]), size), 0)
+}
+
+/// A frame two pixels wide around the box that `rect` covers on screen, rotation included.
+fn outline(rect: &Rect, color: [f32; 4]) -> [Rect; 4] {
+    let (sin, cos) = rect.rotation.sin_cos();
+    let half = rect.size.map(|side| side.abs() / 2.0);
+    let extent = [
+        half[0] * cos.abs() + half[1] * sin.abs(),
+        half[0] * sin.abs() + half[1] * cos.abs(),
+    ];
+    let width = 2.0;
+    let min = [0, 1].map(|i| rect.position[i] - extent[i] - width);
+    let max = [0, 1].map(|i| rect.position[i] + extent[i] + width);
+    let size = [max[0] - min[0], max[1] - min[1]];
+    [
+        ([min[0], min[1]], [size[0], width]),
+        ([min[0], max[1] - width], [size[0], width]),
+        ([min[0], min[1]], [width, size[1]]),
+        ([max[0] - width, min[1]], [width, size[1]]),
+    ]
+    .map(|(position, size)| Slot { position, size }.rect(color))
+}
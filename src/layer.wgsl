@@ -0,0 +1,59 @@
+// Composites a cached layer, drawn into its own texture once, as one quad.
+
+struct WindowUniform {
+	size: vec2<f32>,
+	scale_factor: f32,
+	// 1.0 when y grows downwards from a top-left origin, -1.0 when it grows upwards
+	y_direction: f32,
+}
+@group(0) @binding(0)
+var<uniform> window: WindowUniform;
+
+struct Placement {
+	// Corner with the smallest coordinates and size, in rect coordinates
+	position: vec2<f32>,
+	size: vec2<f32>,
+}
+@group(1) @binding(0)
+var<uniform> placement: Placement;
+@group(1) @binding(1)
+var layer_texture: texture_2d<f32>;
+@group(1) @binding(2)
+var layer_sampler: sampler;
+
+struct VertexOutput {
+	@builtin(position) clip_position: vec4<f32>,
+	@location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+	// The unit square as two triangles. Nothing is culled, so the winding doesn't matter.
+	var corners = array<vec2<f32>, 6>(
+		vec2<f32>(0.0, 0.0),
+		vec2<f32>(1.0, 0.0),
+		vec2<f32>(1.0, 1.0),
+		vec2<f32>(0.0, 0.0),
+		vec2<f32>(1.0, 1.0),
+		vec2<f32>(0.0, 1.0),
+	);
+	var corner = corners[vertex_index];
+	var position = placement.position + corner * placement.size;
+
+	var out: VertexOutput;
+	out.clip_position = vec4<f32>(
+		(2.0 * position.x / window.size.x) - 1.0,
+		window.y_direction * (1.0 - (2.0 * position.y / window.size.y)),
+		0.0,
+		1.0
+	);
+	// The layer was drawn with the same y direction, so when y grows upwards its first row
+	// holds the largest y.
+	out.uv = vec2<f32>(corner.x, select(1.0 - corner.y, corner.y, window.y_direction > 0.0));
+	return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+	return textureSample(layer_texture, layer_sampler, in.uv);
+}
@@ -0,0 +1,403 @@
+use std::collections::BTreeMap;
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    camera::Camera,
+    config::Origin,
+    image::ImageAtlas,
+    rect::{self, build_geometry_with_atlas, StyleBuffer},
+    resources::{ResourceScope, Tracked},
+    scene::{CachedLayer, DrawList, Layer},
+    WindowUniform, DEPTH_FORMAT,
+};
+
+/// What drawing a cached layer's rects takes from the renderer: the rect pipelines and the
+/// bindings they expect.
+pub(crate) struct RectPipelines<'a> {
+    pub opaque: &'a wgpu::RenderPipeline,
+    pub blended: &'a wgpu::RenderPipeline,
+    pub window_bind_group_layout: &'a wgpu::BindGroupLayout,
+    pub style_bind_group_layout: &'a wgpu::BindGroupLayout,
+    pub image_atlas: &'a ImageAtlas,
+    pub packed_vertices: bool,
+    /// The pipelines cull by winding, which the origin flips.
+    pub origin: Origin,
+    pub scale_factor: f32,
+}
+
+/// Textures holding the draw list's cached layers, and the pipeline that composites them.
+pub(crate) struct LayerCache {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    format: wgpu::TextureFormat,
+    layers: BTreeMap<u64, CachedTexture>,
+}
+
+struct CachedTexture {
+    layer: Layer,
+    /// The `DrawList::cached_layers` number of the rects drawn, and the zoom they were drawn at.
+    set: u64,
+    zoom: f64,
+    /// Whether any of it was on screen at the last `update`.
+    visible: bool,
+    /// Where the texture goes on screen, written every frame.
+    placement: Tracked<wgpu::Buffer>,
+    bind_group: Tracked<wgpu::BindGroup>,
+    _texture: Tracked<wgpu::Texture>,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct Placement {
+    position: [f32; 2],
+    size: [f32; 2],
+}
+
+impl LayerCache {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        window_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("layer.wgsl"));
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+            label: Some("Cached Layer Bind Group Layout"),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Cached Layer Pipeline Layout"),
+            bind_group_layouts: &[window_bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Cached Layer Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    // The layer was blended into a transparent texture, which leaves it
+                    // premultiplied.
+                    blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Cached Layer Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            sampler,
+            format,
+            layers: BTreeMap::new(),
+        }
+    }
+
+    /// Drops every drawn layer, so each is drawn again by the next `update`.
+    pub fn invalidate(&mut self) {
+        self.layers.clear();
+    }
+
+    /// Drops the textures of layers that were off screen at the last `update`. They are drawn
+    /// again once they come into view.
+    pub fn evict_offscreen(&mut self) {
+        self.layers.retain(|_, cached| cached.visible);
+    }
+
+    /// Frees the textures of removed layers, draws the layers on a screen of `view` pixels
+    /// that were set or zoomed since they were last drawn, and places every layer for
+    /// `camera`. Layers off screen are drawn once they come into view.
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        rects: &RectPipelines,
+        draw_list: &DrawList,
+        camera: &Camera,
+        view: [f32; 2],
+    ) {
+        self.layers
+            .retain(|key, _| draw_list.cached_layer(*key).is_some());
+        let max_side = device.limits().max_texture_dimension_2d;
+        for (key, layer, set) in draw_list.cached_layers() {
+            // Layers too large for a texture at this zoom are drawn smaller and stretched.
+            let screen_size = layer.size.map(|side| side.abs() as f64 * camera.zoom);
+            let fit = (max_side as f64 / screen_size[0].max(screen_size[1])).min(1.0);
+            let zoom = camera.zoom * fit;
+            let corner = [
+                layer.position[0] as f64 - layer.size[0].abs() as f64 / 2.0,
+                layer.position[1] as f64 - layer.size[1].abs() as f64 / 2.0,
+            ];
+
+            let far_corner = [
+                corner[0] + layer.size[0].abs() as f64,
+                corner[1] + layer.size[1].abs() as f64,
+            ];
+            let visible = camera.sees(corner, far_corner, 0.0, view);
+
+            let stale = match self.layers.get(&key) {
+                Some(cached) => cached.set != set || cached.zoom != zoom,
+                None => true,
+            };
+            if stale && !visible {
+                self.layers.remove(&key);
+                continue;
+            }
+            if stale {
+                let camera = Camera {
+                    offset: corner,
+                    zoom,
+                };
+                let cached = self.draw(device, queue, rects, layer, set, camera);
+                self.layers.insert(key, cached);
+            }
+
+            // Whole pixels keep the texture as sharp as it was drawn. The texture's size is
+            // rounded up to whole pixels too, so it is placed at that size rather than the
+            // layer's.
+            let position = camera.to_screen(corner).map(|v| v.round() as f32);
+            let size = texture_size(layer, zoom, max_side).map(|side| (side as f64 / fit) as f32);
+            let cached = self.layers.get_mut(&key).unwrap();
+            cached.visible = visible;
+            queue.write_buffer(
+                &cached.placement,
+                0,
+                bytemuck::cast_slice(&[Placement { position, size }]),
+            );
+        }
+    }
+
+    /// Draws `layer`'s rects with `camera`, whose offset is the layer's corner, into a new
+    /// transparent texture.
+    fn draw(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        rects: &RectPipelines,
+        layer: &CachedLayer,
+        set: u64,
+        camera: Camera,
+    ) -> CachedTexture {
+        let size = texture_size(layer, camera.zoom, device.limits().max_texture_dimension_2d);
+        let extent = wgpu::Extent3d {
+            width: size[0],
+            height: size[1],
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Cached Layer"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let depth = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Cached Layer Depth"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let depth_view = depth.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let geometry = build_geometry_with_atlas(&layer.rects, &camera, &rects.image_atlas.layout);
+        let (instances, num_opaque) = geometry.depth_sorted();
+        let num_opaque = num_opaque as u32;
+        let num_instances = geometry.instances.len() as u32;
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Cached Layer Instances"),
+            contents: &rect::instance_data(&instances, rects.packed_vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let styles = StyleBuffer::new(
+            device,
+            queue,
+            rects.style_bind_group_layout,
+            geometry.styles.styles(),
+        );
+        let window_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Cached Layer Window"),
+            contents: bytemuck::cast_slice(&[WindowUniform {
+                size: size.map(|side| side as f32),
+                scale_factor: rects.scale_factor,
+                y_direction: rects.origin.y_direction(),
+            }]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let window_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: rects.window_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: window_buffer.as_entire_binding(),
+            }],
+            label: Some("Cached Layer Window Bind Group"),
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Cached Layer Encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Cached Layer Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(0.0),
+                        store: false,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+            // Empty buffers can't be bound, so a layer without rects is only cleared.
+            if num_instances > 0 {
+                render_pass.set_pipeline(rects.opaque);
+                render_pass.set_bind_group(0, &window_bind_group, &[]);
+                render_pass.set_bind_group(1, &rects.image_atlas.bind_group, &[]);
+                render_pass.set_bind_group(2, &styles.bind_group, &[]);
+                render_pass.set_vertex_buffer(0, instance_buffer.slice(..));
+                render_pass.draw(0..rect::QUAD_VERTICES, 0..num_opaque);
+                render_pass.set_pipeline(rects.blended);
+                render_pass.draw(
+                    0..rect::QUAD_VERTICES,
+                    num_opaque..num_opaque + num_instances,
+                );
+            }
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let placement = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Cached Layer Placement"),
+            size: std::mem::size_of::<Placement>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: placement.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+            label: Some("Cached Layer Bind Group"),
+        });
+
+        CachedTexture {
+            layer: layer.layer,
+            set,
+            zoom: camera.zoom,
+            visible: true,
+            placement: Tracked::new(ResourceScope::Renderer, "Cached Layer Placement", placement),
+            bind_group: Tracked::new(
+                ResourceScope::Renderer,
+                "Cached Layer Bind Group",
+                bind_group,
+            ),
+            _texture: Tracked::new(ResourceScope::Renderer, "Cached Layer", texture),
+        }
+    }
+
+    /// Composites the `Layer::Background` layers when `background` is set, otherwise the
+    /// others, each band in order and the layers within it by key.
+    pub fn render<'rp>(
+        &'rp self,
+        render_pass: &mut wgpu::RenderPass<'rp>,
+        window_bind_group: &'rp wgpu::BindGroup,
+        background: bool,
+    ) {
+        if self.layers.is_empty() {
+            return;
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, window_bind_group, &[]);
+        let bands: &[Layer] = if background {
+            &[Layer::Background]
+        } else {
+            &[Layer::Content, Layer::Overlay, Layer::Debug]
+        };
+        for band in bands {
+            for cached in self.layers.values().filter(|cached| cached.layer == *band) {
+                render_pass.set_bind_group(1, &cached.bind_group, &[]);
+                render_pass.draw(0..6, 0..1);
+            }
+        }
+    }
+}
+
+/// Pixels of the texture `layer` is drawn into at `zoom`.
+fn texture_size(layer: &CachedLayer, zoom: f64, max_side: u32) -> [u32; 2] {
+    layer
+        .size
+        .map(|side| ((side.abs() as f64 * zoom).ceil() as u32).clamp(1, max_side))
+}
@@ -1,277 +1,589 @@
+use std::{sync::Arc, time::Duration};
+
 use glyphon::{
-    Attrs, Buffer, Color, Family, FontSystem, Metrics, Resolution, Shaping, SwashCache, TextArea,
-    TextAtlas, TextBounds, TextRenderer,
+    Color, FontSystem, Metrics, Resolution, SwashCache, TextArea, TextAtlas, TextBounds,
+    TextRenderer,
+};
+use instant::Instant;
+use wgpu::{
+    util::{DeviceExt, RenderEncoder},
+    MultisampleState,
 };
-use wgpu::{util::DeviceExt, MultisampleState};
 use winit::{
     event::*,
     event_loop::{ControlFlow, EventLoop},
     window::Window,
-    window::WindowBuilder,
+    window::{Fullscreen, WindowBuilder},
+};
+
+mod app;
+pub mod axis;
+pub mod camera;
+pub mod candlestick;
+pub mod capabilities;
+pub mod capture;
+pub mod chart;
+mod config;
+pub mod contour;
+pub mod custom;
+#[cfg(feature = "dialog")]
+pub mod dialog;
+#[cfg(feature = "egui")]
+mod egui_layer;
+mod error;
+mod eyedropper;
+pub mod fuzz;
+pub mod geo;
+pub mod golden;
+mod gpu;
+pub mod graph;
+mod headless;
+pub mod heatmap;
+pub mod image;
+pub mod inline;
+mod inspector;
+mod layer_cache;
+pub mod line;
+#[cfg(feature = "menu")]
+pub mod menu;
+mod pacing;
+mod pack;
+pub mod path;
+pub mod pen;
+mod pixel_grid;
+#[cfg(all(feature = "primary-selection", target_os = "linux"))]
+mod primary_selection;
+pub mod print;
+pub mod quality;
+pub mod recorder;
+mod recovery;
+pub mod rect;
+pub mod resources;
+pub mod scatter;
+mod scene;
+pub mod snapshot;
+pub mod snapshot_diff;
+#[cfg(feature = "taskbar")]
+pub mod taskbar;
+pub mod terminal_preview;
+pub mod tessellate;
+pub mod text;
+pub mod text_effects;
+pub mod theme;
+mod theme_editor;
+pub mod time_axis;
+pub mod timeline;
+mod title_bar;
+#[cfg(feature = "tray")]
+pub mod tray;
+mod unpremultiply;
+pub mod visualizer;
+#[cfg(target_arch = "wasm32")]
+pub mod web;
+
+pub use app::{AppBuilder, WindowSet};
+pub use camera::Camera;
+pub use capabilities::{Capabilities, Degradation};
+pub use config::{
+    AdapterConfig, Backdrop, CachePolicy, Cursor, Deterministic, Origin, PresentMode, RedrawMode,
+    RendererConfig, TextPositioning, WindowAlpha, WindowMode,
 };
+#[cfg(feature = "egui")]
+pub use egui_layer::{EguiLayer, SceneTexture};
+pub use error::RendererError;
+pub use headless::Headless;
+pub use scene::{CachedLayer, DrawList, Frame, FrameStats, Layer, NodeOp, Scene, SceneNode};
+pub use snapshot::SnapshotConfig;
+pub use theme::Theme;
+
+use app::WindowConfig;
+use capture::{CaptureError, Readback, Screenshot};
+use custom::CustomNodes;
+use eyedropper::Eyedropper;
+use geo::{GeoMesh, GeoPipeline, PreparedGeo};
+use gpu::Gpu;
+use heatmap::{HeatmapPipeline, PreparedHeatmap};
+use image::{Image, ImageAtlas};
+use inspector::Inspector;
+use layer_cache::{LayerCache, RectPipelines};
+use pacing::FramePacer;
+use pixel_grid::PixelGridPipeline;
+use print::{Page, PrintConfig, PrintJob};
+use quality::{Governor, Quality};
+use recorder::{FrameSink, Recording};
+use recovery::DeviceLoss;
+use rect::{build_geometry_with_atlas, Fill, Rect, StyleBuffer};
+use resources::{ResourceScope, Tracked};
+use scatter::{PreparedScatter, ScatterPipeline};
+use snapshot::Snapshot;
+use text::TextNode;
+use text_effects::{EffectText, GlyphEffect};
+use theme_editor::ThemeEditor;
+use title_bar::TitleBar;
+use unpremultiply::UnpremultiplyPass;
 
 #[repr(C)]
 #[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 struct WindowUniform {
     size: [f32; 2],
     scale_factor: f32,
-    _padding: f32,
+    y_direction: f32,
 }
 
-#[derive(Clone, Copy)]
-struct Fill {
-    color: [f32; 4],
+impl WindowUniform {
+    /// `size` is the size the surface is configured with, which can run ahead of
+    /// `Window::inner_size` while the scale factor changes.
+    fn new(scale_factor: f64, size: winit::dpi::PhysicalSize<u32>, origin: Origin) -> Self {
+        Self {
+            size: [size.width as f32, size.height as f32],
+            scale_factor: scale_factor as f32,
+            y_direction: origin.y_direction(),
+        }
+    }
 }
 
-#[derive(Clone, Copy)]
-struct Stroke {
-    color: [f32; 3],
-    width: f32,
+/// Format of the depth buffer the rect passes test against.
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Largest side of the tiles a print is rendered in, well within every adapter's limits and
+/// small enough to read back without a long stall.
+const PRINT_TILE: u32 = 2048;
+
+fn create_depth_texture(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+) -> (Tracked<wgpu::Texture>, wgpu::TextureView) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Depth Texture"),
+        size: wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: DEPTH_FORMAT,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    (
+        Tracked::new(ResourceScope::Renderer, "Depth Texture", texture),
+        view,
+    )
 }
 
-struct Rect {
-    position: [f32; 2],
-    size: [f32; 2],
-    border_radius: u32,
-    fill: Option<Fill>,
-    stroke: Option<Stroke>,
-    z_index: f32,
-    softness: f32,
+/// What frames are drawn into.
+enum Target {
+    Surface(wgpu::Surface),
+    /// A texture the size of the surface configuration, standing in for the surface when
+    /// there is no window; see `Headless`.
+    Offscreen(Tracked<wgpu::Texture>),
+    /// The window's surface while the app is suspended. Android takes the native window away
+    /// then, and only hands it out once the app is resumed, so no frames are drawn until
+    /// `State::resume` creates the surface again.
+    Suspended,
+}
+
+fn create_offscreen_texture(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+) -> Tracked<wgpu::Texture> {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("Offscreen Texture"),
+        size: wgpu::Extent3d {
+            width: config.width,
+            height: config.height,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: config.format,
+        usage: config.usage,
+        view_formats: &[],
+    });
+    Tracked::new(ResourceScope::Renderer, "Offscreen Texture", texture)
+}
+
+/// The first of the surface's formats that is 8-bit sRGB, which the shaders write linear
+/// colors into and screenshots read back directly. Otherwise any sRGB format, and failing that
+/// the surface's preferred one, in which colors come out wrong. None when there are no formats.
+fn choose_surface_format(formats: &[wgpu::TextureFormat]) -> Option<wgpu::TextureFormat> {
+    let eight_bit = [
+        wgpu::TextureFormat::Bgra8UnormSrgb,
+        wgpu::TextureFormat::Rgba8UnormSrgb,
+    ];
+    if let Some(format) = formats.iter().find(|format| eight_bit.contains(format)) {
+        return Some(*format);
+    }
+    if let Some(format) = formats.iter().find(|format| format.is_srgb()) {
+        log::warn!("no 8-bit sRGB surface format, using {:?}", format);
+        return Some(*format);
+    }
+    let format = *formats.first()?;
+    log::warn!("no sRGB surface format, using {:?} with colors off", format);
+    Some(format)
 }
 
+/// The system's fonts, or only the given ones in deterministic mode.
+fn create_font_system(deterministic: Option<&Deterministic>) -> FontSystem {
+    let Some(deterministic) = deterministic else {
+        return FontSystem::new();
+    };
+    let mut db = glyphon::fontdb::Database::new();
+    for font in &deterministic.fonts {
+        db.load_font_data(font.to_vec());
+    }
+    let family = db
+        .faces()
+        .next()
+        .and_then(|face| face.families.first())
+        .map(|(family, _)| family.clone());
+    if let Some(family) = family {
+        db.set_sans_serif_family(family);
+    }
+    FontSystem::new_with_locale_and_db(deterministic.locale.clone(), db)
+}
+
+/// Space between the window edges and the scene text, in pixels.
+const TEXT_MARGIN: f32 = 10.0;
+
+/// How close, in pixels, the cursor has to be to a scatter point to hover it.
+const PICK_RADIUS: f64 = 8.0;
+
 const RECTANGLES: &[Rect] = &[
     Rect {
         position: [200.0, 200.0],
         size: [100.0, 100.0],
-        border_radius: 30,
-        fill: Some(Fill {
+        border_radius: [30.0; 4],
+        fill: Some(Fill::Solid {
             color: [0.0, 0.0, 0.0, 0.7],
         }),
         stroke: None,
+        shadow: None,
+        rotation: 0.0,
         z_index: 0.5,
         softness: 5.0,
+        clip: None,
     },
     Rect {
         position: [198.0, 198.0],
         size: [100.0, 100.0],
-        border_radius: 30,
-        fill: Some(Fill {
+        border_radius: [30.0; 4],
+        fill: Some(Fill::Solid {
             color: [1.0, 0.0, 0.0, 1.0],
         }),
         stroke: None,
+        shadow: None,
+        rotation: 0.0,
         z_index: 0.0,
         softness: 1.0,
+        clip: None,
     },
 ];
 
-#[repr(C)]
-#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-struct RectVertex {
-    position: [f32; 2],
-    z_index: f32,
-    color: [f32; 4],
-    border_radius: f32,
-    rect_pos: [f32; 2],
-    rect_size: [f32; 2],
-    rect_softness: f32,
-}
-
-impl RectVertex {
-    const ATTRIBS: [wgpu::VertexAttribute; 7] = wgpu::vertex_attr_array![
-        0 => Float32x2,
-        1 => Float32,
-        2 => Float32x4,
-        3 => Float32,
-        4 => Float32x2,
-        5 => Float32x2,
-        6 => Float32,
-    ];
-    fn desc() -> wgpu::VertexBufferLayout<'static> {
-        wgpu::VertexBufferLayout {
-            array_stride: std::mem::size_of::<RectVertex>() as wgpu::BufferAddress,
-            step_mode: wgpu::VertexStepMode::Vertex,
-            attributes: &Self::ATTRIBS,
-        }
-    }
-}
-
 struct State {
-    surface: wgpu::Surface,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
+    /// Kept to create the surface again when the app resumes.
+    instance: Arc<wgpu::Instance>,
+    adapter: Arc<wgpu::Adapter>,
+    target: Target,
+    /// The present modes the surface supports, none offscreen.
+    present_modes: Vec<wgpu::PresentMode>,
+    /// Shared with the other windows of the event loop.
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
+    device_loss: DeviceLoss,
+    capabilities: Capabilities,
+    /// Whether the last frame found the surface lost, so losing it again means the device is.
+    surface_lost: bool,
     config: wgpu::SurfaceConfiguration,
     size: winit::dpi::PhysicalSize<u32>,
-    window: Window,
+    /// `None` when rendering headless.
+    window: Option<Window>,
+    renderer_config: RendererConfig,
+    /// Draws every rect instance, blending them over the opaque ones already drawn.
     render_pipeline: wgpu::RenderPipeline,
-    vertex_buffer: wgpu::Buffer,
-    num_vertices: u32,
-    index_buffer: wgpu::Buffer,
-    num_indices: u32,
-    window_buffer: wgpu::Buffer,
-    window_bind_group: wgpu::BindGroup,
+    /// Draws the fully opaque pixels of opaque rects, writing depth.
+    opaque_pipeline: wgpu::RenderPipeline,
+    depth_texture: Tracked<wgpu::Texture>,
+    depth_view: wgpu::TextureView,
+    heatmap_pipeline: HeatmapPipeline,
+    pixel_grid_pipeline: PixelGridPipeline,
+    /// Set when the surface wants straight alpha; see `WindowAlpha::Postmultiplied`.
+    unpremultiply: Option<UnpremultiplyPass>,
+    heatmaps: Vec<PreparedHeatmap>,
+    /// `None` when the adapter can't draw scatter plots.
+    scatter_pipeline: Option<ScatterPipeline>,
+    scatters: Vec<PreparedScatter>,
+    geo_pipeline: GeoPipeline,
+    geo_layers: Vec<PreparedGeo>,
+    /// The scene's shapes, then its lines.
+    vectors: Vec<GeoMesh>,
+    /// Drawn over `vectors`; see `Frame::draw_custom`.
+    custom: CustomNodes,
+    draw_list: DrawList,
+    /// The images, heatmaps, scatter plots, geo layers, shapes and lines of the scene as
+    /// given, to upload again on a new device; see `RendererConfig::device_recovery`.
+    assets: Option<Scene>,
+    image_bind_group_layout: wgpu::BindGroupLayout,
+    image_atlas: ImageAtlas,
+    style_bind_group_layout: wgpu::BindGroupLayout,
+    /// Fills of the rect quads, indexed by their instances.
+    style_buffer: StyleBuffer,
+    hovered_rect: Option<usize>,
+    /// Shown over the window; see `Frame::set_cursor`.
+    cursor: Cursor,
+    /// Shown while `RendererConfig::inspector` is set.
+    inspector: Inspector,
+    /// Shown while `RendererConfig::theme_editor` is set.
+    theme_editor: ThemeEditor,
+    /// Present with `RendererConfig::client_decorations`.
+    title_bar: Option<TitleBar>,
+    /// The title bar, put away while the window is fullscreen.
+    stowed_title_bar: Option<TitleBar>,
+    /// Set by the title bar's close button, for the event loop to exit on.
+    close_requested: bool,
+    camera: Camera,
+    cursor_position: [f64; 2],
+    panning: bool,
+    /// One instance per rect quad, each drawn as a unit quad.
+    instance_buffer: Tracked<wgpu::Buffer>,
+    /// Instances drawn by the blending pass, after the `num_opaque` of the opaque pass.
+    num_instances: u32,
+    num_opaque: u32,
+    window_buffer: Tracked<wgpu::Buffer>,
+    window_bind_group_layout: wgpu::BindGroupLayout,
+    window_bind_group: Tracked<wgpu::BindGroup>,
+    layer_cache: LayerCache,
+    /// The heatmap, geo and vector draws, recorded once per scene. Their camera uniforms are
+    /// written every frame, which the bundle picks up without recording it again.
+    background_bundle: Option<wgpu::RenderBundle>,
+    /// The rect draw, recorded again whenever the rects are uploaded.
+    rect_bundle: Option<wgpu::RenderBundle>,
     font_system: FontSystem,
     cache: SwashCache,
     atlas: TextAtlas,
     text_renderer: TextRenderer,
-    buffer: Buffer,
+    text: TextNode,
+    /// Replaces the plain text draw while the scene has glyph effects.
+    text_effects: Option<EffectText>,
+    glyph_effects: Vec<GlyphEffect>,
+    /// The scene's text as given, kept for snapshots.
+    text_source: String,
+    /// Whether the text has to be prepared again before the next draw. Glyph effects animate,
+    /// so they are prepared every frame regardless.
+    text_changed: bool,
+    /// Whether the window, camera or pixel grid changed since the last frame was presented.
+    /// Other changes are tracked where they happen; see `needs_redraw`.
+    damaged: bool,
+    /// When to draw again for an animation of the frame callback; see `Frame::redraw_at`.
+    redraw_deadline: Option<Instant>,
+    stats: FrameStats,
+    #[cfg(feature = "taskbar")]
+    taskbar: taskbar::Taskbar,
+    #[cfg(feature = "menu")]
+    menus: menu::Menus,
+    /// Created once the event loop runs; see `tray::Tray::new`.
+    #[cfg(feature = "tray")]
+    tray: Option<tray::Tray>,
+    started: Instant,
+    #[cfg(feature = "egui")]
+    egui: Option<EguiLayer>,
+    recorder: Option<Box<dyn FrameSink>>,
+    readback: Option<Readback>,
+    /// Whether `render` reads the frame back into `captured`; see `capture_frame`.
+    capturing: bool,
+    captured: Option<Result<Screenshot, wgpu::BufferAsyncError>>,
+    /// Captured for the frame callback, which gets it the next time it runs.
+    screenshot: Option<Screenshot>,
+    eyedropper: Eyedropper,
+    /// Whether the surface was made copyable for the eyedropper, until its color arrives.
+    eyedropper_copies: bool,
+    /// Read by the eyedropper for the frame callback, which gets it the next time it runs.
+    picked_color: Option<[f32; 4]>,
+    frame_pacer: FramePacer,
+    governor: Option<Governor>,
+    frames_since_gc: u32,
+    gc_requested: bool,
+    last_snapshot: Instant,
 }
 
 impl State {
-    async fn new(window: Window) -> Self {
-        let size = window.inner_size();
-        let window_uniform = WindowUniform {
-            size: [size.width as f32, size.height as f32],
-            scale_factor: window.scale_factor() as f32,
-            _padding: 0.0,
-        };
-
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            dx12_shader_compiler: Default::default(),
-        });
+    /// Sets up the renderer to draw into `window`, or without one into a texture of `size`
+    /// at a scale factor of 1. It draws with `gpu` when given one, shared with other windows.
+    async fn new(
+        window: Option<Window>,
+        size: winit::dpi::PhysicalSize<u32>,
+        renderer_config: RendererConfig,
+        scene: Scene,
+        gpu: Option<Gpu>,
+    ) -> Result<Self, RendererError> {
+        let scale_factor = window.as_ref().map_or(1.0, Window::scale_factor);
+        let window_uniform = WindowUniform::new(scale_factor, size, renderer_config.origin);
 
-        let surface = unsafe { instance.create_surface(&window) }.unwrap();
+        let instance = match &gpu {
+            Some(gpu) => gpu.instance.clone(),
+            None => Arc::new(wgpu::Instance::new(wgpu::InstanceDescriptor {
+                backends: renderer_config.adapter.backends,
+                dx12_shader_compiler: Default::default(),
+            })),
+        };
 
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::LowPower,
-                compatible_surface: Some(&surface),
-                force_fallback_adapter: false,
-            })
-            .await
-            .unwrap();
+        // Android has no native window to draw into before the app is resumed.
+        let surface = window
+            .as_ref()
+            .filter(|_| !cfg!(target_os = "android"))
+            .map(|window| unsafe { instance.create_surface(window) })
+            .transpose()?;
 
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    features: wgpu::Features::empty(),
-                    limits: wgpu::Limits::default(),
-                    label: None,
-                },
-                None,
-            )
-            .await
-            .unwrap();
-        let surface_caps = surface.get_capabilities(&adapter);
-        let surface_format = wgpu::TextureFormat::Bgra8UnormSrgb;
+        let gpu = match gpu {
+            // Another window's adapter may not reach this window's display.
+            Some(gpu) => match &surface {
+                Some(surface) if !gpu.adapter.is_surface_supported(surface) => {
+                    return Err(RendererError::NoAdapter);
+                }
+                _ => gpu,
+            },
+            None => Gpu::request(instance, &renderer_config.adapter, surface.as_ref()).await?,
+        };
+        let Gpu {
+            instance,
+            adapter,
+            device,
+            queue,
+            device_loss,
+            capabilities,
+        } = gpu;
+        // Without a window, the offscreen texture can take any format and takes the preferred
+        // alpha mode, and is always read back.
+        let (surface_format, alpha_mode, present_modes, usage) = match (&surface, &window) {
+            (Some(surface), _) => {
+                let surface_caps = surface.get_capabilities(&adapter);
+                let surface_format = choose_surface_format(&surface_caps.formats)
+                    .ok_or(RendererError::UnsupportedFormat)?;
+                let fallback_alpha_mode = *surface_caps
+                    .alpha_modes
+                    .first()
+                    .ok_or(RendererError::UnsupportedFormat)?;
+                let alpha_mode = renderer_config
+                    .window_alpha
+                    .composite_modes()
+                    .iter()
+                    .copied()
+                    .find(|mode| surface_caps.alpha_modes.contains(mode))
+                    .unwrap_or_else(|| {
+                        log::warn!(
+                            "{:?} window alpha isn't supported, using {:?}",
+                            renderer_config.window_alpha,
+                            fallback_alpha_mode
+                        );
+                        fallback_alpha_mode
+                    });
+                (
+                    surface_format,
+                    alpha_mode,
+                    surface_caps.present_modes,
+                    wgpu::TextureUsages::RENDER_ATTACHMENT,
+                )
+            }
+            // The surface comes on resume, in the format Android's surfaces all offer. Its
+            // windows are opaque, so any alpha mode does.
+            (None, Some(_)) => (
+                wgpu::TextureFormat::Rgba8UnormSrgb,
+                wgpu::CompositeAlphaMode::Auto,
+                Vec::new(),
+                wgpu::TextureUsages::RENDER_ATTACHMENT,
+            ),
+            (None, None) => (
+                wgpu::TextureFormat::Bgra8UnormSrgb,
+                renderer_config.window_alpha.composite_modes()[0],
+                Vec::new(),
+                wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            ),
+        };
+        let present_mode = renderer_config.present_mode.choose(&present_modes);
         let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage,
             format: surface_format,
             width: size.width,
             height: size.height,
-            present_mode: surface_caps.present_modes[0],
-            alpha_mode: surface_caps.alpha_modes[0],
+            present_mode,
+            alpha_mode,
             view_formats: vec![],
         };
 
-        let shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
+        let shader = rect::create_shader(&device);
 
-        let num_vertices: u32 = (RECTANGLES.len() * 4) as u32;
-        let num_indices: u32 = (RECTANGLES.len() * 6) as u32;
-        let mut vertices: Vec<RectVertex> = vec![];
-        let mut indices: Vec<u16> = vec![];
-
-        for i in 0..RECTANGLES.len() {
-            let rect_i = &RECTANGLES[i];
-            vertices.push(RectVertex {
-                position: [
-                    rect_i.position[0] + rect_i.size[0] / 2.0,
-                    rect_i.position[1] - rect_i.size[1] / 2.0,
-                ],
-                z_index: rect_i.z_index as f32,
-                color: rect_i.fill.unwrap().color,
-                border_radius: rect_i.border_radius as f32,
-                rect_pos: rect_i.position,
-                rect_size: rect_i.size,
-                rect_softness: rect_i.softness,
-            });
-            vertices.push(RectVertex {
-                position: [
-                    rect_i.position[0] + rect_i.size[0] / 2.0,
-                    rect_i.position[1] + rect_i.size[1] / 2.0,
-                ],
-                z_index: rect_i.z_index as f32,
-                color: rect_i.fill.unwrap().color,
-                border_radius: rect_i.border_radius as f32,
-                rect_pos: rect_i.position,
-                rect_size: rect_i.size,
-                rect_softness: rect_i.softness,
-            });
-            vertices.push(RectVertex {
-                position: [
-                    rect_i.position[0] - rect_i.size[0] / 2.0,
-                    rect_i.position[1] + rect_i.size[1] / 2.0,
-                ],
-                z_index: rect_i.z_index as f32,
-                color: rect_i.fill.unwrap().color,
-                border_radius: rect_i.border_radius as f32,
-                rect_pos: rect_i.position,
-                rect_size: rect_i.size,
-                rect_softness: rect_i.softness,
-            });
-            vertices.push(RectVertex {
-                position: [
-                    rect_i.position[0] - rect_i.size[0] / 2.0,
-                    rect_i.position[1] - rect_i.size[1] / 2.0,
-                ],
-                z_index: rect_i.z_index as f32,
-                color: rect_i.fill.unwrap().color,
-                border_radius: rect_i.border_radius as f32,
-                rect_pos: rect_i.position,
-                rect_size: rect_i.size,
-                rect_softness: rect_i.softness,
-            });
+        let image_bind_group_layout = ImageAtlas::bind_group_layout(&device);
+        let image_atlas = ImageAtlas::new(&device, &queue, &image_bind_group_layout, &scene.images);
 
-            indices.push((i * 4) as u16);
-            indices.push((i * 4 + 2) as u16);
-            indices.push((i * 4 + 1) as u16);
-
-            indices.push((i * 4) as u16);
-            indices.push((i * 4 + 3) as u16);
-            indices.push((i * 4 + 2) as u16);
-        }
-
-        // vertices = vertices
-        //     .iter()
-        //     .map(|v| RectVertex {
-        //         position: [
-        //             (2.0 * v.position[0] / window_uniform.size[0]) - 1.0,
-        //             1.0 - (2.0 * v.position[1] / window_uniform.size[1]),
-        //         ],
-        //         z_index: v.z_index,
-        //         color: v.color,
-        //         border_radius: v.border_radius,
-        //         rect_pos: v.rect_pos,
-        //         rect_size: v.rect_size,
-        //     })
-        //     .collect();
-
-        println!("{:#?}", vertices);
-        println!("{:#?}", window_uniform);
-
-        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Vertex Buffer"),
-            contents: bytemuck::cast_slice(&vertices),
-            usage: wgpu::BufferUsages::VERTEX,
+        let recoverable = renderer_config.device_recovery && cfg!(not(target_arch = "wasm32"));
+        let assets = recoverable.then(|| Scene {
+            images: scene.images.clone(),
+            heatmaps: scene.heatmaps.clone(),
+            scatters: scene.scatters.clone(),
+            geo: scene.geo.clone(),
+            shapes: scene.shapes.clone(),
+            lines: scene.lines.clone(),
+            ..Default::default()
         });
+        let mut draw_list = DrawList::from(scene.rects);
+        draw_list.set_origin(scene.rect_origin);
+        for (i, region) in scene.hit_regions {
+            draw_list.set_hit_region(i, region);
+        }
+        for (i, tag) in scene.tags {
+            draw_list.set_tag(i, tag);
+        }
+        // Each move goes to the front of the layer, so going by index keeps the scene's order.
+        let mut layers = scene.layers;
+        layers.sort_by_key(|(i, _)| *i);
+        for (i, layer) in layers {
+            draw_list.set_layer(i, layer);
+        }
 
-        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Index Buffer"),
-            contents: bytemuck::cast_slice(&indices),
-            usage: wgpu::BufferUsages::INDEX,
-        });
+        let camera = draw_list.local_camera(&renderer_config.camera);
+        let geometry =
+            build_geometry_with_atlas(&draw_list.ordered_rects(), &camera, &image_atlas.layout);
+        let num_instances = geometry.instances.len() as u32;
+        let (instances, num_opaque) = geometry.depth_sorted();
+        let num_opaque = num_opaque as u32;
 
-        let window_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Window uniform"),
-            contents: bytemuck::cast_slice(&[window_uniform]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-        });
+        let instance_buffer = Tracked::new(
+            ResourceScope::Renderer,
+            "Instance Buffer",
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Instance Buffer"),
+                contents: &rect::instance_data(&instances, renderer_config.packed_vertices),
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            }),
+        );
+
+        let style_bind_group_layout = StyleBuffer::bind_group_layout(&device);
+        let style_buffer = StyleBuffer::new(
+            &device,
+            &queue,
+            &style_bind_group_layout,
+            geometry.styles.styles(),
+        );
+
+        let window_buffer = Tracked::new(
+            ResourceScope::Renderer,
+            "Window uniform",
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Window uniform"),
+                contents: bytemuck::cast_slice(&[window_uniform]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            }),
+        );
 
         let window_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
                         has_dynamic_offset: false,
@@ -282,241 +594,2357 @@ impl State {
                 label: Some("Window Bind Group Layout"),
             });
 
-        let window_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &window_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: window_buffer.as_entire_binding(),
-            }],
-            label: Some("Window Bind Group"),
-        });
+        let window_bind_group = Tracked::new(
+            ResourceScope::Renderer,
+            "Window Bind Group",
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &window_bind_group_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: window_buffer.as_entire_binding(),
+                }],
+                label: Some("Window Bind Group"),
+            }),
+        );
 
         let render_pipeline_layout =
             device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
                 label: Some("Render Pipeline Layout"),
-                bind_group_layouts: &[&window_bind_group_layout],
+                bind_group_layouts: &[
+                    &window_bind_group_layout,
+                    &image_bind_group_layout,
+                    &style_bind_group_layout,
+                ],
                 push_constant_ranges: &[],
             });
 
-        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[RectVertex::desc()],
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: config.format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-        });
+        let rect_pipeline = |label: &'static str,
+                             fragment_entry: &'static str,
+                             blend: Option<wgpu::BlendState>,
+                             depth_write_enabled: bool| {
+            device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&render_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: if renderer_config.packed_vertices {
+                        "vs_packed"
+                    } else {
+                        "vs_main"
+                    },
+                    buffers: &[rect::instance_layout(renderer_config.packed_vertices)],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: fragment_entry,
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: config.format,
+                        blend,
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    strip_index_format: None,
+                    front_face: renderer_config.origin.front_face(),
+                    cull_mode: Some(wgpu::Face::Back),
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                // Every instance has its own depth, so the strict test keeps the paint order
+                // and skips the blending pass's pixels that the opaque pass already drew.
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DEPTH_FORMAT,
+                    depth_write_enabled,
+                    depth_compare: wgpu::CompareFunction::Greater,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState {
+                    count: 1,
+                    mask: !0,
+                    alpha_to_coverage_enabled: false,
+                },
+                multiview: None,
+            })
+        };
+        let render_pipeline = rect_pipeline(
+            "Render Pipline",
+            "fs_main",
+            Some(wgpu::BlendState::ALPHA_BLENDING),
+            false,
+        );
+        let opaque_pipeline = rect_pipeline("Opaque Rect Pipeline", "fs_opaque", None, true);
+        let (depth_texture, depth_view) = create_depth_texture(&device, &config);
 
-        surface.configure(&device, &config);
+        let heatmap_pipeline =
+            HeatmapPipeline::new(&device, config.format, &window_bind_group_layout);
+        let pixel_grid_pipeline = PixelGridPipeline::new(&device, config.format);
+        let layer_cache = LayerCache::new(&device, config.format, &window_bind_group_layout);
+        let unpremultiply = (alpha_mode == wgpu::CompositeAlphaMode::PostMultiplied)
+            .then(|| UnpremultiplyPass::new(&device, config.format));
+        let heatmaps = scene
+            .heatmaps
+            .iter()
+            .map(|heatmap| heatmap_pipeline.prepare(&device, &queue, heatmap))
+            .collect();
+        let scatter_pipeline = capabilities
+            .scatter()
+            .then(|| ScatterPipeline::new(&device, config.format, &window_bind_group_layout));
+        let scatters = match &scatter_pipeline {
+            Some(pipeline) => scene
+                .scatters
+                .iter()
+                .map(|scatter| pipeline.prepare(&device, scatter))
+                .collect(),
+            None => vec![],
+        };
+        let geo_pipeline = GeoPipeline::new(
+            &device,
+            config.format,
+            &window_bind_group_layout,
+            renderer_config.packed_vertices,
+        );
+        let geo_layers = scene
+            .geo
+            .iter()
+            .map(|layer| geo_pipeline.prepare(&device, layer))
+            .collect();
+        let vectors = scene
+            .shapes
+            .iter()
+            .map(|shape| geo_pipeline.prepare_shape(&device, shape))
+            .chain(
+                scene
+                    .lines
+                    .iter()
+                    .map(|line| geo_pipeline.prepare_line(&device, line)),
+            )
+            .collect();
+
+        let target = match surface {
+            Some(surface) => {
+                surface.configure(&device, &config);
+                Target::Surface(surface)
+            }
+            None if window.is_some() => Target::Suspended,
+            None => Target::Offscreen(create_offscreen_texture(&device, &config)),
+        };
 
-        let mut font_system = FontSystem::new();
+        let mut font_system = create_font_system(renderer_config.deterministic.as_ref());
         let cache = SwashCache::new();
         let mut atlas = TextAtlas::new(&device, &queue, surface_format);
         let text_renderer =
             TextRenderer::new(&mut atlas, &device, MultisampleState::default(), None);
-        let mut buffer = Buffer::new(&mut font_system, Metrics::new(30.0, 42.0));
-
-        buffer.set_size(&mut font_system, size.width as f32, size.height as f32);
-        buffer.set_text(
+        // Text is laid out in logical pixels and drawn scaled up to physical ones.
+        let text_scale = scale_factor as f32;
+        let text = TextNode::new(
             &mut font_system,
-            "This is sample text",
-            Attrs::new().family(Family::SansSerif),
-            Shaping::Advanced,
+            &scene.text,
+            Metrics::new(30.0, 42.0),
+            size.width as f32 / text_scale - 2.0 * TEXT_MARGIN,
         );
-        buffer.shape_until_scroll(&mut font_system);
+        let text_effects = (!scene.text_effects.is_empty()).then(|| {
+            let mut effects = EffectText::new(&mut font_system, &text, scene.text_effects.clone());
+            effects.scale = text_scale;
+            effects
+        });
+        let inspector = Inspector::new(&mut font_system);
+        let theme_editor = ThemeEditor::new(&mut font_system, &renderer_config.theme);
+        let title_bar = window
+            .as_ref()
+            .filter(|_| renderer_config.client_decorations)
+            .map(|window| TitleBar::new(&mut font_system, &window.title()));
 
-        Self {
+        let mut frame_pacer = FramePacer::new(renderer_config.max_frames_in_flight);
+        frame_pacer.set_refresh_rate(window.as_ref().and_then(pacing::monitor_refresh_rate));
+        let governor = renderer_config.quality_governor.clone().map(Governor::new);
+        let eyedropper = Eyedropper::new(&device);
+        #[cfg(feature = "menu")]
+        let menus = menu::Menus::new(renderer_config.menu_bar.clone().unwrap_or_default());
+
+        Ok(Self {
             window,
-            surface,
+            renderer_config,
+            instance,
+            adapter,
+            target,
+            present_modes,
             device,
             queue,
+            device_loss,
+            capabilities,
+            surface_lost: false,
             config,
             size,
             render_pipeline,
-            vertex_buffer,
-            num_vertices,
-            index_buffer,
-            num_indices,
+            opaque_pipeline,
+            depth_texture,
+            depth_view,
+            heatmap_pipeline,
+            pixel_grid_pipeline,
+            unpremultiply,
+            heatmaps,
+            scatter_pipeline,
+            scatters,
+            geo_pipeline,
+            geo_layers,
+            vectors,
+            custom: CustomNodes::default(),
+            draw_list,
+            assets,
+            image_bind_group_layout,
+            image_atlas,
+            style_bind_group_layout,
+            style_buffer,
+            hovered_rect: None,
+            cursor: Cursor::Default,
+            inspector,
+            theme_editor,
+            title_bar,
+            stowed_title_bar: None,
+            close_requested: false,
+            camera,
+            cursor_position: [0.0, 0.0],
+            panning: false,
+            instance_buffer,
+            num_instances,
+            num_opaque,
             window_buffer,
+            window_bind_group_layout,
             window_bind_group,
+            layer_cache,
+            background_bundle: None,
+            rect_bundle: None,
             font_system,
             cache,
             atlas,
             text_renderer,
-            buffer,
-        }
+            text,
+            text_effects,
+            glyph_effects: scene.text_effects,
+            text_source: scene.text,
+            text_changed: true,
+            damaged: true,
+            redraw_deadline: None,
+            stats: FrameStats::default(),
+            #[cfg(feature = "taskbar")]
+            taskbar: taskbar::Taskbar::default(),
+            #[cfg(feature = "menu")]
+            menus,
+            #[cfg(feature = "tray")]
+            tray: None,
+            started: Instant::now(),
+            #[cfg(feature = "egui")]
+            egui: None,
+            recorder: None,
+            readback: None,
+            capturing: false,
+            captured: None,
+            screenshot: None,
+            eyedropper,
+            eyedropper_copies: false,
+            picked_color: None,
+            frame_pacer,
+            governor,
+            frames_since_gc: 0,
+            gc_requested: false,
+            last_snapshot: Instant::now(),
+        })
     }
 
     pub fn window(&self) -> &Window {
-        &self.window
+        self.window
+            .as_ref()
+            .expect("a headless renderer has no window")
+    }
+
+    fn scale_factor(&self) -> f64 {
+        self.window.as_ref().map_or(1.0, Window::scale_factor)
+    }
+
+    /// Switches to `mode`, or its fallback, from the next frame on.
+    fn set_present_mode(&mut self, mode: PresentMode) {
+        self.renderer_config.present_mode = mode;
+        let present_mode = mode.choose(&self.present_modes);
+        if present_mode != self.config.present_mode {
+            log::info!("Presenting with {:?}", present_mode);
+            self.config.present_mode = present_mode;
+            self.configure();
+            self.damaged = true;
+        }
+    }
+
+    /// Applies `config` to the surface, or creates an offscreen texture to match it.
+    fn configure(&mut self) {
+        match &mut self.target {
+            Target::Surface(surface) => surface.configure(&self.device, &self.config),
+            Target::Offscreen(texture) => {
+                *texture = create_offscreen_texture(&self.device, &self.config);
+            }
+            // Configured once it's created again.
+            Target::Suspended => {}
+        }
+    }
+
+    /// Drops the surface when the app is suspended, as Android requires before it takes the
+    /// native window away.
+    fn suspend(&mut self) {
+        if let Target::Surface(_) = self.target {
+            log::info!("Suspended, dropping the surface");
+            self.target = Target::Suspended;
+        }
+    }
+
+    /// Creates the surface again when the app resumes, or for the first time on Android.
+    /// Other platforms report resuming once at startup, with the surface already there.
+    fn resume(&mut self) -> Result<(), RendererError> {
+        let (Target::Suspended, Some(window)) = (&self.target, &self.window) else {
+            return Ok(());
+        };
+        let surface = unsafe { self.instance.create_surface(window) }?;
+        let capabilities = surface.get_capabilities(&self.adapter);
+        // The pipelines are built for the format, so a surface without it can't be drawn to.
+        if !capabilities.formats.contains(&self.config.format) {
+            return Err(RendererError::UnsupportedFormat);
+        }
+        if !capabilities.alpha_modes.contains(&self.config.alpha_mode) {
+            self.config.alpha_mode = wgpu::CompositeAlphaMode::Auto;
+        }
+        self.present_modes = capabilities.present_modes;
+        self.config.present_mode = self
+            .renderer_config
+            .present_mode
+            .choose(&self.present_modes);
+        surface.configure(&self.device, &self.config);
+        self.target = Target::Surface(surface);
+        self.damaged = true;
+        Ok(())
     }
 
     fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
+        // Going fullscreen or back resizes the window, whoever asked for it.
+        self.sync_title_bar();
         if new_size.width > 0 && new_size.height > 0 {
             self.size = new_size;
             self.config.width = new_size.width;
             self.config.height = new_size.height;
-            self.surface.configure(&self.device, &self.config);
+            self.configure();
+            #[cfg(feature = "menu")]
+            self.menus.resize(new_size.width);
+            (self.depth_texture, self.depth_view) =
+                create_depth_texture(&self.device, &self.config);
+            self.damaged = true;
+            // The resolution is part of what was prepared, rewrapped or not.
+            self.text_changed = true;
+            let text_scale = self.text_scale();
+            let rewrapped = self.text.set_container_width(
+                &mut self.font_system,
+                new_size.width as f32 / text_scale - 2.0 * TEXT_MARGIN,
+            );
+            // Glyph positions come from the wrapped layout, so the effects need them again.
+            if rewrapped && self.text_effects.is_some() {
+                self.text_effects = Some(EffectText::new(
+                    &mut self.font_system,
+                    &self.text,
+                    self.glyph_effects.clone(),
+                ));
+            }
+            // The scale factor may have changed along with the size.
+            if let Some(effects) = &mut self.text_effects {
+                effects.scale = text_scale;
+            }
+            if self.renderer_config.inspector {
+                self.refresh_inspector();
+            } else if self.title_bar.is_some() || self.renderer_config.theme_editor {
+                self.upload_rects();
+            }
         }
     }
 
-    fn input(&mut self) -> bool {
-        false
+    #[cfg(feature = "egui")]
+    fn attach_egui<T>(
+        &mut self,
+        event_loop: &winit::event_loop::EventLoopWindowTarget<T>,
+        ui: egui_layer::EguiUi,
+    ) {
+        self.egui = Some(EguiLayer::new(
+            event_loop,
+            self.window(),
+            &self.device,
+            self.config.format,
+            ui,
+        ));
     }
 
-    fn update(&mut self) {
-        self.queue.write_buffer(
-            &self.window_buffer,
-            0,
-            bytemuck::cast_slice(&[WindowUniform {
-                size: [
-                    self.window.inner_size().width as f32,
-                    self.window.inner_size().height as f32,
-                ],
-                scale_factor: self.window.scale_factor() as f32,
-                _padding: 0.0,
-            }]),
-        );
+    /// Streams every following frame into `sink` until `stop_recording` is called, finishing
+    /// the recording that was running.
+    fn start_recording(&mut self, sink: Box<dyn FrameSink>) {
+        self.stop_recording();
+        self.config.usage |= wgpu::TextureUsages::COPY_SRC;
+        self.configure();
+        self.recorder = Some(sink);
     }
 
-    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        self.text_renderer
-            .prepare(
-                &self.device,
-                &self.queue,
-                &mut self.font_system,
-                &mut self.atlas,
-                Resolution {
-                    width: self.size.width,
-                    height: self.size.height,
-                },
-                [TextArea {
-                    buffer: &self.buffer,
-                    left: 10.0,
-                    top: 10.0,
-                    scale: 1.0,
-                    bounds: TextBounds {
-                        left: 0,
-                        top: 0,
-                        right: 400,
-                        bottom: 100,
-                    },
-                    default_color: Color::rgb(255, 255, 255),
-                }],
-                &mut self.cache,
-            )
-            .unwrap();
-        let output = self.surface.get_current_texture()?;
+    fn stop_recording(&mut self) {
+        let Some(mut recorder) = self.recorder.take() else {
+            return;
+        };
+        if let Err(e) = recorder.finish() {
+            log::error!("Failed to finish the recording: {}", e);
+        }
+        self.readback = None;
+        // Offscreen textures are always read back, and a pixel the eyedropper still has to copy
+        // keeps the surface copyable until it's read.
+        if self.eyedropper.sampling() {
+            self.eyedropper_copies = true;
+        } else if !matches!(self.target, Target::Offscreen(_)) {
+            self.config.usage = wgpu::TextureUsages::RENDER_ATTACHMENT;
+            self.configure();
+        }
+    }
 
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+    /// Draws a frame and reads it back from the surface as RGBA pixels.
+    fn capture_frame(&mut self) -> Result<Screenshot, CaptureError> {
+        if let Target::Suspended = self.target {
+            return Err(CaptureError::Surface(wgpu::SurfaceError::Lost));
+        }
+        // Surfaces can only be copied from when configured for it, which recordings already
+        // are.
+        let copyable = self.config.usage.contains(wgpu::TextureUsages::COPY_SRC);
+        if !copyable {
+            self.config.usage |= wgpu::TextureUsages::COPY_SRC;
+            self.configure();
+        }
+        self.update();
+        self.capturing = true;
+        let rendered = self.render();
+        self.capturing = false;
+        if !copyable {
+            self.readback = None;
+            self.config.usage = wgpu::TextureUsages::RENDER_ATTACHMENT;
+            self.configure();
+        }
+        rendered?;
+        self.captured
+            .take()
+            .expect("a rendered capture reads the frame back")
+            .map_err(CaptureError::from)
+    }
 
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
-            });
+    /// Frees everything the scene put on the GPU, then checks that nothing it owned is left.
+    fn clear_scene(&mut self) {
+        self.heatmaps.clear();
+        self.scatters.clear();
+        self.geo_layers.clear();
+        self.vectors.clear();
+        self.custom.clear();
+        self.background_bundle = None;
+        self.draw_list.clear();
+        if let Some(assets) = &mut self.assets {
+            *assets = Scene::default();
+        }
+        self.image_atlas = ImageAtlas::new(
+            &self.device,
+            &self.queue,
+            &self.image_bind_group_layout,
+            &[],
+        );
+        self.upload_rects();
+        if self.renderer_config.leak_check {
+            let leaked = resources::report_leaks();
+            debug_assert_eq!(leaked, 0, "GPU resources leaked after scene teardown");
+        }
+    }
+
+    /// Starts over on a new device after the old one was lost: the device, pipelines, buffers
+    /// and text atlas are created again, and the draw list, text, camera and the scene's
+    /// assets uploaded to them. What doesn't live on the GPU carries over, apart from the
+    /// inspector's and title bar's hover. Only returns an error once the window is gone.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn recover(&mut self) -> Result<(), RendererError> {
+        let mut renderer_config = self.renderer_config.clone();
+        renderer_config.camera = self.camera;
+        let scene = Scene {
+            text: self.text_source.clone(),
+            text_effects: self.glyph_effects.clone(),
+            ..self.assets.take().unwrap_or_default()
+        };
+        let window = self.window.take();
+        let suspended = matches!(self.target, Target::Suspended);
+        // The other windows still have the old device, and each starts over on its own.
+        let mut state =
+            recovery::block_on(State::new(window, self.size, renderer_config, scene, None))?;
 
+        // Android's new state waits for a resume that already happened.
+        if !suspended {
+            state.resume()?;
+        }
+        state.draw_list = std::mem::take(&mut self.draw_list);
+        state.custom = std::mem::take(&mut self.custom);
+        state.custom.reset();
+        state.governor = self.governor.take();
+        // The new title bar has to be put away again if the window stayed fullscreen.
+        state.sync_title_bar();
+        state.upload_rects();
+        state.hovered_rect = self.hovered_rect;
+        state.cursor = self.cursor;
+        state.cursor_position = self.cursor_position;
+        state.panning = self.panning;
+        state.stats = self.stats;
+        state.started = self.started;
+        state.recorder = self.recorder.take();
+        state.screenshot = self.screenshot.take();
+        state.picked_color = self.picked_color.take();
+        state.last_snapshot = self.last_snapshot;
+        #[cfg(feature = "taskbar")]
+        std::mem::swap(&mut state.taskbar, &mut self.taskbar);
+        #[cfg(feature = "menu")]
+        std::mem::swap(&mut state.menus, &mut self.menus);
+        #[cfg(feature = "tray")]
         {
-            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("Render Pass"),
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
-                        store: true,
-                    },
-                })],
-                depth_stencil_attachment: None,
-            });
-            render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.set_bind_group(0, &self.window_bind_group, &[]);
-            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
-            render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
-            render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
-            self.text_renderer
-                .render(&self.atlas, &mut render_pass)
-                .unwrap();
+            state.tray = self.tray.take();
         }
-        self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
-        self.atlas.trim();
-
+        #[cfg(feature = "egui")]
+        if let Some(mut egui) = self.egui.take() {
+            egui.recreate(&state.device, state.config.format);
+            state.egui = Some(egui);
+        }
+        *self = state;
+        log::warn!("Recovered on a new device");
         Ok(())
     }
-}
 
-pub async fn run() {
-    env_logger::init();
-    let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    /// Saves a snapshot when the configured interval has passed since the last one.
+    fn save_snapshot(&mut self) {
+        let Some(config) = &self.renderer_config.snapshot else {
+            return;
+        };
+        if self.last_snapshot.elapsed() < config.interval {
+            return;
+        }
+        self.last_snapshot = Instant::now();
+        let (rects, layers, hit_regions) = self.draw_list.to_scene_parts();
+        let snapshot = Snapshot {
+            rects,
+            layers,
+            hit_regions,
+            tags: self.draw_list.scene_tags(),
+            text: self.text_source.clone(),
+            text_effects: self.glyph_effects.clone(),
+            camera: self.camera,
+            rect_origin: self.draw_list.origin(),
+            theme: self.renderer_config.theme,
+        };
+        if let Err(e) = snapshot.save(&config.path) {
+            log::error!("Failed to save snapshot {}: {}", config.path.display(), e);
+        }
+    }
+
+    fn set_camera(&mut self, camera: Camera) {
+        self.camera = camera;
+        self.damaged = true;
+        self.upload_rects();
+    }
 
-    let mut state = State::new(window).await;
+    /// Rebuilds the rect geometry, growing the buffers when it no longer fits.
+    fn upload_rects(&mut self) {
+        let mut rects = self.draw_list.ordered_rects();
+        self.quality().apply(&mut rects);
+        let camera = self.draw_list.local_camera(&self.camera);
+        let mut geometry = build_geometry_with_atlas(&rects, &camera, &self.image_atlas.layout);
+        if self.renderer_config.pixel_grid {
+            // The outlines are already in screen space, so they bypass the camera.
+            let outlines = pixel_grid::highlights(self.draw_list.rects(), &camera);
+            geometry.extend(&outlines, &Camera::default(), &self.image_atlas.layout);
+        }
+        if self.renderer_config.inspector {
+            let size = self.window_size();
+            let outlines = self.inspector.outlines(
+                &self.draw_list,
+                self.hovered_rect,
+                &camera,
+                &self.renderer_config.theme,
+            );
+            geometry.extend(&outlines, &Camera::default(), &self.image_atlas.layout);
+            let panel = self
+                .inspector
+                .rects(&self.draw_list, size, &self.renderer_config.theme);
+            geometry.extend(
+                &self.from_top_left(panel),
+                &Camera::default(),
+                &self.image_atlas.layout,
+            );
+        }
+        if self.renderer_config.theme_editor {
+            let panel = self
+                .theme_editor
+                .rects(&self.renderer_config.theme, self.window_size());
+            geometry.extend(
+                &self.from_top_left(panel),
+                &Camera::default(),
+                &self.image_atlas.layout,
+            );
+        }
+        if let Some(title_bar) = &self.title_bar {
+            let bar = title_bar.rects(
+                self.window_size(),
+                self.text_scale(),
+                &self.renderer_config.theme,
+            );
+            geometry.extend(
+                &self.from_top_left(bar),
+                &Camera::default(),
+                &self.image_atlas.layout,
+            );
+        }
+        let (instances, num_opaque) = geometry.depth_sorted();
+        self.num_instances = geometry.instances.len() as u32;
+        self.num_opaque = num_opaque as u32;
+        // The buffers and bind groups may be replaced below, and the instance count has changed.
+        self.rect_bundle = None;
+        if instances.is_empty() {
+            return;
+        }
+        write_growing(
+            &self.device,
+            &self.queue,
+            &mut self.instance_buffer,
+            &rect::instance_data(&instances, self.renderer_config.packed_vertices),
+        );
+        self.style_buffer.write(
+            &self.device,
+            &self.queue,
+            &self.style_bind_group_layout,
+            geometry.styles.styles(),
+        );
+    }
 
-    event_loop.run(move |event, _, control_flow| match event {
-        Event::RedrawRequested(window_id) if window_id == state.window().id() => {
-            state.update();
-            match state.render() {
-                Ok(_) => {}
+    fn window_size(&self) -> [f32; 2] {
+        [self.size.width as f32, self.size.height as f32]
+    }
 
-                Err(wgpu::SurfaceError::Lost) => state.resize(state.size),
-                Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
-                Err(e) => eprintln!("{:?}", e),
+    /// Moves rects laid out from the top-left like text, as the inspector and title bar are,
+    /// to the origin of rects.
+    fn from_top_left(&self, mut rects: Vec<Rect>) -> Vec<Rect> {
+        if self.renderer_config.origin == Origin::BottomLeft {
+            let height = self.window_size()[1];
+            for rect in &mut rects {
+                rect.position[1] = height - rect.position[1];
             }
         }
+        rects
+    }
 
-        Event::MainEventsCleared => {
-            state.window().request_redraw();
+    /// How many times larger text and the title bar are drawn than laid out: the scale
+    /// factor, fractional on Wayland at 125% or 150%.
+    fn text_scale(&self) -> f32 {
+        self.scale_factor() as f32
+    }
+
+    /// Seconds since the animations started, as of when the frame being drawn is shown: in
+    /// whole refresh intervals with vsync, and right away without it, since nothing holds the
+    /// frame back. Counted in frames in deterministic mode.
+    fn animation_time(&self) -> f64 {
+        if let Some(deterministic) = &self.renderer_config.deterministic {
+            return self.stats.frames as f64 * deterministic.frame_time as f64;
+        }
+        let elapsed = self.started.elapsed();
+        match self.config.present_mode {
+            wgpu::PresentMode::Fifo | wgpu::PresentMode::FifoRelaxed => {
+                self.frame_pacer.display_time(elapsed).as_secs_f64()
+            }
+            _ => elapsed.as_secs_f64(),
         }
+    }
 
-        Event::WindowEvent {
-            ref event,
-            window_id,
-        } if window_id == state.window().id() => match event {
-            WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
+    /// What the governor leaves of the scene's detail, all of it without one.
+    fn quality(&self) -> Quality {
+        self.governor
+            .as_ref()
+            .map_or(Quality::High, Governor::quality)
+    }
+
+    /// Feeds a frame's time to the governor, and draws the rects at the quality it settles
+    /// on from the next frame.
+    fn govern(&mut self, frame_time: Duration) {
+        let Some(governor) = &mut self.governor else {
+            return;
+        };
+        let budget = self
+            .frame_pacer
+            .refresh_interval()
+            .unwrap_or(Duration::from_secs(1) / 60);
+        let frame_time = frame_time.max(self.frame_pacer.latency().unwrap_or_default());
+        if let Some(quality) = governor.record(frame_time, budget) {
+            log::info!("Drawing at {:?} quality", quality);
+            self.damaged = true;
+            self.upload_rects();
+        }
+    }
+
+    /// Follows the window to a monitor with another refresh rate.
+    fn update_refresh_rate(&mut self) {
+        if let Some(window) = &self.window {
+            let refresh_rate = pacing::monitor_refresh_rate(window);
+            self.frame_pacer.set_refresh_rate(refresh_rate);
+        }
+    }
+
+    fn text_positioning(&self) -> TextPositioning {
+        if self.renderer_config.deterministic.is_some() {
+            TextPositioning::Snap
+        } else {
+            self.renderer_config.text_positioning
+        }
+    }
 
-            WindowEvent::Resized(physical_size) => {
-                state.resize(*physical_size);
+    /// Lays the inspector out again for the draw list, and the frame with it.
+    fn refresh_inspector(&mut self) {
+        let size = self.window_size();
+        self.inspector
+            .refresh(&mut self.font_system, &self.draw_list, size);
+        self.upload_rects();
+        self.text_changed = true;
+        self.damaged = true;
+    }
+
+    /// Lays the theme editor out again for the theme, and everything drawn in the theme's
+    /// colors with it.
+    fn refresh_theme_editor(&mut self) {
+        self.theme_editor
+            .refresh(&mut self.font_system, &self.renderer_config.theme);
+        self.upload_rects();
+        self.text_changed = true;
+        self.damaged = true;
+    }
+
+    /// Converts a window position into the coordinate space rects are positioned in.
+    fn to_rect_space(&self, position: winit::dpi::PhysicalPosition<f64>) -> [f64; 2] {
+        match self.renderer_config.origin {
+            Origin::TopLeft => [position.x, position.y],
+            Origin::BottomLeft => [position.x, self.size.height as f64 - position.y],
+        }
+    }
+
+    /// Hands `event` to the title bar and does what its buttons ask, returning whether the bar
+    /// used it.
+    fn title_bar_event(&mut self, event: &WindowEvent) -> bool {
+        let (size, scale) = (self.window_size(), self.text_scale());
+        let Some(title_bar) = &mut self.title_bar else {
+            return false;
+        };
+        let (used, action) = title_bar.on_event(event, size, scale);
+        if title_bar.take_changed() {
+            self.upload_rects();
+            self.damaged = true;
+        }
+        let window = self.window();
+        match action {
+            Some(title_bar::Action::Drag) => {
+                if let Err(e) = window.drag_window() {
+                    log::warn!("Can't move the window: {}", e);
+                }
+            }
+            Some(title_bar::Action::Minimize) => window.set_minimized(true),
+            Some(title_bar::Action::ToggleMaximized) => {
+                window.set_maximized(!window.is_maximized());
             }
+            Some(title_bar::Action::Close) => self.close_requested = true,
+            None => {}
+        }
+        used
+    }
 
-            WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                state.resize(**new_inner_size);
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        #[cfg(feature = "egui")]
+        if let Some(egui) = &mut self.egui {
+            if egui.on_event(event) {
+                return true;
             }
-            _ => {}
-        },
-        _ => {}
-    });
+        }
+
+        #[cfg(feature = "menu")]
+        if self.menus.on_event(event) {
+            return true;
+        }
+
+        if self.title_bar_event(event) {
+            return true;
+        }
+
+        if self.renderer_config.theme_editor {
+            let size = self.window_size();
+            let used = self.theme_editor.on_event(
+                event,
+                &mut self.renderer_config.theme,
+                &self.renderer_config.theme_path,
+                size,
+            );
+            if self.theme_editor.take_changed() {
+                self.refresh_theme_editor();
+            }
+            if used {
+                return true;
+            }
+        }
+
+        if self.renderer_config.inspector {
+            let size = self.window_size();
+            let used = self
+                .inspector
+                .on_event(event, &mut self.draw_list, self.hovered_rect, size);
+            if self.inspector.take_changed() {
+                self.refresh_inspector();
+            }
+            if let Some(point) = self.inspector.take_sample_request() {
+                self.pick_color(point);
+            }
+            if used {
+                return true;
+            }
+        }
+
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                let position = self.to_rect_space(*position);
+                if self.panning {
+                    let mut camera = self.camera;
+                    camera.pan([
+                        position[0] - self.cursor_position[0],
+                        position[1] - self.cursor_position[1],
+                    ]);
+                    self.set_camera(camera);
+                }
+                self.cursor_position = position;
+                // Hover within a few pixels, whatever the zoom.
+                let world = self.camera.to_world(position);
+                let radius = (PICK_RADIUS / self.camera.zoom) as f32;
+                for scatter in &mut self.scatters {
+                    scatter.request_pick(world, radius);
+                }
+                let hovered = self.draw_list.rect_at(
+                    self.draw_list.to_local(world),
+                    (1.0 / self.camera.zoom) as f32,
+                    (self.renderer_config.hit_tolerance as f64 / self.camera.zoom) as f32,
+                );
+                if hovered != self.hovered_rect {
+                    self.hovered_rect = hovered;
+                    if self.renderer_config.inspector {
+                        self.damaged = true;
+                        self.upload_rects();
+                    }
+                    if let Some(i) = hovered {
+                        match self.draw_list.tag(i) {
+                            Some(tag) => log::info!("Hovered rect {} tagged {}", i, tag),
+                            None => log::info!("Hovered rect {}", i),
+                        }
+                    }
+                }
+                for layer in &mut self.geo_layers {
+                    let hovered = layer.layer.region_at(world);
+                    if hovered != layer.hovered {
+                        layer.hovered = hovered;
+                        if let Some(i) = hovered {
+                            log::info!("{}", layer.layer.tooltip(i));
+                        }
+                    }
+                }
+                self.panning
+            }
+            WindowEvent::CursorLeft { .. } => {
+                // Nothing in the window is hovered while the cursor is outside it.
+                if self.hovered_rect.take().is_some() && self.renderer_config.inspector {
+                    self.damaged = true;
+                    self.upload_rects();
+                }
+                false
+            }
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Middle,
+                ..
+            } => {
+                self.panning = *state == ElementState::Pressed;
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F2),
+                        ..
+                    },
+                ..
+            } => {
+                self.renderer_config.pixel_grid = !self.renderer_config.pixel_grid;
+                self.damaged = true;
+                self.upload_rects();
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F11),
+                        ..
+                    },
+                ..
+            } => {
+                let mode = match self.window_mode() {
+                    WindowMode::Windowed => WindowMode::Borderless,
+                    _ => WindowMode::Windowed,
+                };
+                self.set_window_mode(mode);
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F12),
+                        ..
+                    },
+                ..
+            } => {
+                self.renderer_config.inspector = !self.renderer_config.inspector;
+                self.refresh_inspector();
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F9),
+                        ..
+                    },
+                ..
+            } => {
+                self.renderer_config.theme_editor = !self.renderer_config.theme_editor;
+                self.refresh_theme_editor();
+                true
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let steps = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y as f64,
+                    MouseScrollDelta::PixelDelta(position) => position.y / 50.0,
+                };
+                let mut camera = self.camera;
+                camera.zoom_at(self.cursor_position, 1.1f64.powf(steps));
+                self.set_camera(camera);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Shows `cursor` over the window, telling the system only when it changes.
+    fn set_cursor(&mut self, cursor: Cursor) {
+        if cursor == self.cursor {
+            return;
+        }
+        self.cursor = cursor;
+        let Some(window) = &self.window else {
+            return;
+        };
+        match cursor.icon() {
+            Some(icon) => {
+                window.set_cursor_icon(icon);
+                window.set_cursor_visible(true);
+            }
+            None => window.set_cursor_visible(false),
+        }
+    }
+
+    fn window_mode(&self) -> WindowMode {
+        match self.window.as_ref().and_then(Window::fullscreen) {
+            None => WindowMode::Windowed,
+            Some(Fullscreen::Borderless(_)) => WindowMode::Borderless,
+            Some(Fullscreen::Exclusive(_)) => WindowMode::Exclusive,
+        }
+    }
+
+    /// Switches the window in or out of fullscreen. The surface takes the new size once the
+    /// window reports it, as after any resize; without a window there's nothing to switch.
+    fn set_window_mode(&mut self, mode: WindowMode) {
+        let Some(window) = &self.window else {
+            return;
+        };
+        let monitor = window.current_monitor();
+        let fullscreen = match mode {
+            WindowMode::Windowed => None,
+            WindowMode::Borderless => Some(Fullscreen::Borderless(monitor)),
+            WindowMode::Exclusive => {
+                let video_mode = monitor.as_ref().and_then(|monitor| {
+                    monitor.video_modes().max_by_key(|video_mode| {
+                        let size = video_mode.size();
+                        (
+                            size.width * size.height,
+                            video_mode.refresh_rate_millihertz(),
+                            video_mode.bit_depth(),
+                        )
+                    })
+                });
+                match video_mode {
+                    Some(video_mode) => Some(Fullscreen::Exclusive(video_mode)),
+                    None => {
+                        log::warn!("The monitor's video modes are unknown, going borderless");
+                        Some(Fullscreen::Borderless(monitor))
+                    }
+                }
+            }
+        };
+        window.set_fullscreen(fullscreen);
+        self.sync_title_bar();
+    }
+
+    /// Puts the title bar away while the window is fullscreen, where there's nothing to move
+    /// or maximize, and brings it back after.
+    fn sync_title_bar(&mut self) {
+        let fullscreen = self.window_mode() != WindowMode::Windowed;
+        let (from, to) = if fullscreen {
+            (&mut self.title_bar, &mut self.stowed_title_bar)
+        } else {
+            (&mut self.stowed_title_bar, &mut self.title_bar)
+        };
+        if let Some(title_bar) = from.take() {
+            *to = Some(title_bar);
+            self.upload_rects();
+            self.damaged = true;
+        }
+    }
+
+    /// The GPU this window draws with, for other windows to share.
+    fn gpu(&self) -> Gpu {
+        Gpu {
+            instance: self.instance.clone(),
+            adapter: self.adapter.clone(),
+            device: self.device.clone(),
+            queue: self.queue.clone(),
+            device_loss: self.device_loss.clone(),
+            capabilities: self.capabilities.clone(),
+        }
+    }
+
+    /// Whether minimizing or closing the window hides it in the tray instead.
+    #[cfg(feature = "tray")]
+    fn minimizes_to_tray(&self) -> bool {
+        self.tray.as_ref().is_some_and(tray::Tray::minimize_to_tray)
+    }
+
+    /// Whether to draw another frame: always with `RedrawMode::Continuous`, otherwise when it
+    /// would differ from the last one presented. Text effects, egui and recordings change or
+    /// consume every frame, and scatter picks are encoded with frames, so any of those keeps
+    /// the frames coming. Nothing is drawn while the app is suspended.
+    fn needs_redraw(&self) -> bool {
+        if let Target::Suspended = self.target {
+            return false;
+        }
+        if self.renderer_config.redraw_mode == RedrawMode::Continuous {
+            return true;
+        }
+        #[cfg(feature = "egui")]
+        if self.egui.is_some() {
+            return true;
+        }
+        self.damaged
+            || self.text_changed
+            || self.draw_list.is_changed()
+            || self.text_effects.is_some()
+            || self.recorder.is_some()
+            || self.scatters.iter().any(|scatter| scatter.picking())
+            || self.image_atlas.has_pending()
+            || self.custom.is_changed()
+            || self.eyedropper.sampling()
+    }
+
+    /// Reads the color under `point`, in window pixels from the top-left corner, from the
+    /// next frame. It arrives a frame or two later in `update`.
+    fn pick_color(&mut self, point: [f32; 2]) {
+        // Surfaces can only be copied from when configured for it.
+        if matches!(self.target, Target::Surface(_))
+            && !self.config.usage.contains(wgpu::TextureUsages::COPY_SRC)
+        {
+            self.config.usage |= wgpu::TextureUsages::COPY_SRC;
+            self.configure();
+            self.eyedropper_copies = true;
+        }
+        self.eyedropper.request(point.map(|v| v.max(0.0) as u32));
+    }
+
+    /// Hands a color the eyedropper read to the inspector and the frame callback.
+    fn poll_eyedropper(&mut self) {
+        if let Some(color) = self.eyedropper.poll() {
+            if self.renderer_config.inspector {
+                self.inspector.sampled(&mut self.draw_list, color);
+                if self.inspector.take_changed() {
+                    self.refresh_inspector();
+                }
+            }
+            self.picked_color = Some(color);
+            self.damaged = true;
+        }
+        if self.eyedropper_copies && !self.eyedropper.sampling() {
+            self.eyedropper_copies = false;
+            // A recording started since still copies every frame.
+            if self.recorder.is_none() && matches!(self.target, Target::Surface(_)) {
+                self.config.usage = wgpu::TextureUsages::RENDER_ATTACHMENT;
+                self.configure();
+            }
+        }
+    }
+
+    /// Queues an image from `Frame::add_image` for `upload_images`.
+    fn add_image(&mut self, id: u64, image: Image) {
+        if let Some(assets) = &mut self.assets {
+            assets.images.retain(|(existing, _)| *existing != id);
+            assets.images.push((id, image.clone()));
+        }
+        self.image_atlas.add(id, image);
+    }
+
+    /// Uploads this frame's share of the queued images. Rects and cached layers are built
+    /// again once one is complete or the atlas grew, as their texture coordinates moved.
+    fn upload_images(&mut self) {
+        if !self.image_atlas.has_pending() {
+            return;
+        }
+        let changed = self.image_atlas.upload_pending(
+            &self.device,
+            &self.queue,
+            &self.image_bind_group_layout,
+            self.renderer_config.upload_budget,
+        );
+        if changed {
+            self.layer_cache.invalidate();
+            self.upload_rects();
+            self.damaged = true;
+        }
+    }
+
+    /// Tessellates and uploads the custom nodes built since the last frame, and evicted ones
+    /// back in view.
+    fn upload_custom(&mut self) {
+        let view = self.window_size();
+        if !self.custom.needs_upload(&self.camera, view) {
+            return;
+        }
+        let uploaded = self
+            .custom
+            .upload(&self.device, &self.geo_pipeline, &self.camera, view);
+        self.stats.custom_uploads += uploaded as u64;
+        // The background bundle draws the meshes, so it has to be recorded with the new ones.
+        self.background_bundle = None;
+        self.damaged = true;
+    }
+
+    fn update(&mut self) {
+        self.upload_images();
+        self.upload_custom();
+        self.poll_eyedropper();
+        if let (Some(title_bar), Some(window)) = (&mut self.title_bar, &self.window) {
+            title_bar.set_title(&mut self.font_system, window);
+            if title_bar.take_changed() {
+                self.upload_rects();
+                self.text_changed = true;
+            }
+        }
+        if self.draw_list.take_changed() {
+            if self.renderer_config.inspector {
+                self.refresh_inspector();
+            } else {
+                self.upload_rects();
+            }
+        }
+        self.queue.write_buffer(
+            &self.window_buffer,
+            0,
+            bytemuck::cast_slice(&[WindowUniform::new(
+                self.scale_factor(),
+                self.size,
+                self.renderer_config.origin,
+            )]),
+        );
+        for heatmap in &self.heatmaps {
+            heatmap.update(&self.queue, &self.camera);
+        }
+        for layer in &self.geo_layers {
+            layer.update(&self.queue, &self.camera);
+        }
+        for mesh in self.vectors.iter().chain(self.custom.meshes()) {
+            mesh.update(&self.queue, &self.camera);
+        }
+        let viewport = [self.size.width as f32, self.size.height as f32];
+        for (i, scatter) in self.scatters.iter_mut().enumerate() {
+            scatter.update(&self.queue, &self.camera, viewport);
+            if scatter.poll_pick() {
+                log::info!(
+                    "Scatter {} hovered point: {:?}, tagged {:?}",
+                    i,
+                    scatter.hovered(),
+                    scatter.hovered_tag()
+                );
+            }
+        }
+        self.update_cached_layers();
+    }
+
+    /// Draws the cached layers that changed into their textures and places them for the
+    /// camera.
+    fn update_cached_layers(&mut self) {
+        self.draw_list.take_cached_layers_changed();
+        let rects = RectPipelines {
+            opaque: &self.opaque_pipeline,
+            blended: &self.render_pipeline,
+            window_bind_group_layout: &self.window_bind_group_layout,
+            style_bind_group_layout: &self.style_bind_group_layout,
+            image_atlas: &self.image_atlas,
+            packed_vertices: self.renderer_config.packed_vertices,
+            origin: self.renderer_config.origin,
+            scale_factor: self.scale_factor() as f32,
+        };
+        self.layer_cache.update(
+            &self.device,
+            &self.queue,
+            &rects,
+            &self.draw_list,
+            &self.draw_list.local_camera(&self.camera),
+            self.window_size(),
+        );
+    }
+
+    /// Lays the text out for the GPU at the current size, scale factor and time, `scale` times
+    /// larger and moved by `offset` pixels for printing. Glyph effects keep their size.
+    fn prepare_text(&mut self, scale: f32, offset: [f32; 2]) {
+        let bounds = TextBounds {
+            left: 0,
+            top: 0,
+            right: self.size.width as i32,
+            bottom: self.size.height as i32,
+        };
+        let size = self.window_size();
+        let text_scale = self.text_scale();
+        let mut text_areas = match &self.text_effects {
+            Some(effects) => effects.text_areas(
+                TEXT_MARGIN * text_scale + offset[0],
+                TEXT_MARGIN * text_scale + offset[1],
+                self.animation_time() as f32,
+                bounds,
+                self.text_positioning(),
+            ),
+            None => vec![TextArea {
+                buffer: self.text.buffer(),
+                left: TEXT_MARGIN * scale * text_scale + offset[0],
+                top: TEXT_MARGIN * scale * text_scale + offset[1],
+                scale: scale * text_scale,
+                bounds,
+                default_color: Color::rgb(255, 255, 255),
+            }],
+        };
+        let theme = &self.renderer_config.theme;
+        if self.renderer_config.inspector {
+            text_areas.push(self.inspector.text_area(size, theme));
+        }
+        if self.renderer_config.theme_editor {
+            text_areas.push(self.theme_editor.text_area(size, theme));
+        }
+        if let Some(title_bar) = &self.title_bar {
+            text_areas.push(title_bar.text_area(size, text_scale, theme));
+        }
+        self.text_renderer
+            .prepare(
+                &self.device,
+                &self.queue,
+                &mut self.font_system,
+                &mut self.atlas,
+                Resolution {
+                    width: self.size.width,
+                    height: self.size.height,
+                },
+                text_areas,
+                &mut self.cache,
+            )
+            .unwrap();
+        self.text_changed = false;
+    }
+
+    /// `depth` is whether the bundle is replayed in the depth-tested rect pass.
+    fn bundle_encoder(&self, label: &'static str, depth: bool) -> wgpu::RenderBundleEncoder<'_> {
+        self.device
+            .create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
+                label: Some(label),
+                color_formats: &[Some(self.config.format)],
+                depth_stencil: depth.then_some(wgpu::RenderBundleDepthStencil {
+                    format: DEPTH_FORMAT,
+                    depth_read_only: false,
+                    stencil_read_only: true,
+                }),
+                sample_count: 1,
+                multiview: None,
+            })
+    }
+
+    fn record_background(&self) -> wgpu::RenderBundle {
+        let mut encoder = self.bundle_encoder("Background Bundle", false);
+        self.heatmap_pipeline
+            .render(&mut encoder, &self.window_bind_group, &self.heatmaps);
+        self.geo_pipeline.render(
+            &mut encoder,
+            &self.window_bind_group,
+            self.geo_layers
+                .iter()
+                .map(|layer| &layer.mesh)
+                .chain(&self.vectors)
+                .chain(self.custom.meshes()),
+        );
+        encoder.finish(&wgpu::RenderBundleDescriptor {
+            label: Some("Background Bundle"),
+        })
+    }
+
+    fn record_rects(&self) -> wgpu::RenderBundle {
+        let mut encoder = self.bundle_encoder("Rect Bundle", true);
+        // Empty buffers can't be bound, so skip the rect draw when there is nothing in it.
+        if self.num_instances > 0 {
+            // Both pipelines share a layout, so the bindings carry over from one to the other.
+            encoder.set_pipeline(&self.opaque_pipeline);
+            encoder.set_bind_group(0, &self.window_bind_group, &[]);
+            encoder.set_bind_group(1, &self.image_atlas.bind_group, &[]);
+            encoder.set_bind_group(2, &self.style_buffer.bind_group, &[]);
+            encoder.set_vertex_buffer(0, self.instance_buffer.slice(..));
+            encoder.draw(0..rect::QUAD_VERTICES, 0..self.num_opaque);
+            encoder.set_pipeline(&self.render_pipeline);
+            encoder.draw(
+                0..rect::QUAD_VERTICES,
+                self.num_opaque..self.num_opaque + self.num_instances,
+            );
+        }
+        encoder.finish(&wgpu::RenderBundleDescriptor {
+            label: Some("Rect Bundle"),
+        })
+    }
+
+    /// Encodes the background, rect and overlay passes into `target`. `overlays` is whether
+    /// egui and the pixel grid are drawn over the text.
+    fn encode_passes(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+        overlays: bool,
+    ) {
+        // Blending leaves the frame premultiplied, so the background has to start out that way.
+        let [r, g, b, a] = self.renderer_config.background.map(|v| v as f64);
+        let background = wgpu::Color {
+            r: r * a,
+            g: g * a,
+            b: b * a,
+            a,
+        };
+
+        let color_attachment = |load| {
+            Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations { load, store: true },
+            })
+        };
+        // Only the rects are depth tested, so they get a pass of their own between the
+        // background and the overlays.
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[color_attachment(wgpu::LoadOp::Clear(background))],
+                depth_stencil_attachment: None,
+            });
+            render_pass.execute_bundles(&self.background_bundle);
+            // Scatters pick how many points to draw from the camera, so they are encoded anew
+            // every frame.
+            if let Some(scatter_pipeline) = &self.scatter_pipeline {
+                scatter_pipeline.render(&mut render_pass, &self.window_bind_group, &self.scatters);
+            }
+            self.layer_cache
+                .render(&mut render_pass, &self.window_bind_group, true);
+        }
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Rect Pass"),
+                color_attachments: &[color_attachment(wgpu::LoadOp::Load)],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(0.0),
+                        store: false,
+                    }),
+                    stencil_ops: None,
+                }),
+            });
+            render_pass.execute_bundles(&self.rect_bundle);
+        }
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Overlay Pass"),
+                color_attachments: &[color_attachment(wgpu::LoadOp::Load)],
+                depth_stencil_attachment: None,
+            });
+            self.layer_cache
+                .render(&mut render_pass, &self.window_bind_group, false);
+            self.text_renderer
+                .render(&self.atlas, &mut render_pass)
+                .unwrap();
+            if !overlays {
+                return;
+            }
+            #[cfg(feature = "egui")]
+            if let Some(egui) = &self.egui {
+                egui.render(&mut render_pass);
+            }
+            if self.renderer_config.pixel_grid {
+                self.pixel_grid_pipeline.render(&mut render_pass);
+            }
+        }
+    }
+
+    /// Clears `target` to the background and draws egui over it, for windows where egui shows
+    /// the scene in a texture.
+    #[cfg(feature = "egui")]
+    fn encode_egui_host(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        let [r, g, b, a] = self.renderer_config.background.map(|v| v as f64);
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("egui Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color {
+                        r: r * a,
+                        g: g * a,
+                        b: b * a,
+                        a,
+                    }),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        if let Some(egui) = &self.egui {
+            egui.render(&mut render_pass);
+        }
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        if let Target::Suspended = self.target {
+            return Ok(());
+        }
+        let started = Instant::now();
+        self.stats.frames += 1;
+        let prepare_text = self.text_changed || self.text_effects.is_some();
+        if prepare_text {
+            // Counted here only, as printed tiles are laid out without counting as frames.
+            self.prepare_text(1.0, [0.0, 0.0]);
+            self.stats.text_prepares += 1;
+        }
+        if self.background_bundle.is_none() {
+            self.background_bundle = Some(self.record_background());
+        }
+        if self.rect_bundle.is_none() {
+            self.rect_bundle = Some(self.record_rects());
+        }
+        // Waiting for the swapchain is vsync's doing, not the frame's cost.
+        let acquiring = Instant::now();
+        let output = match &self.target {
+            Target::Surface(surface) => Some(surface.get_current_texture()?),
+            Target::Offscreen(_) | Target::Suspended => None,
+        };
+        let acquired = acquiring.elapsed();
+
+        let view = self
+            .target_texture(output.as_ref())
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        #[cfg(feature = "egui")]
+        let egui_commands = match &mut self.egui {
+            Some(egui) => egui.prepare(
+                &self.device,
+                &self.queue,
+                &mut encoder,
+                self.window
+                    .as_ref()
+                    .expect("egui is only attached to windows"),
+                self.config.format,
+                [self.config.width, self.config.height],
+            ),
+            None => vec![],
+        };
+        #[cfg(not(feature = "egui"))]
+        let egui_commands: Vec<wgpu::CommandBuffer> = vec![];
+
+        if let Some(scatter_pipeline) = &self.scatter_pipeline {
+            scatter_pipeline.encode_picks(&self.queue, &mut encoder, &mut self.scatters);
+        }
+
+        if let Some(unpremultiply) = &mut self.unpremultiply {
+            unpremultiply.resize(&self.device, [self.config.width, self.config.height]);
+        }
+        let target = self
+            .unpremultiply
+            .as_ref()
+            .and_then(UnpremultiplyPass::view)
+            .unwrap_or(&view);
+        #[cfg(feature = "egui")]
+        if let Some(scene_view) = self.egui.as_ref().and_then(EguiLayer::scene_view) {
+            self.encode_passes(&mut encoder, scene_view, &self.depth_view, false);
+            self.encode_egui_host(&mut encoder, target);
+        } else {
+            self.encode_passes(&mut encoder, target, &self.depth_view, true);
+        }
+        #[cfg(not(feature = "egui"))]
+        self.encode_passes(&mut encoder, target, &self.depth_view, true);
+
+        if let Some(unpremultiply) = &self.unpremultiply {
+            unpremultiply.render(&mut encoder, &view);
+        }
+
+        if self.recorder.is_some() || self.capturing {
+            let readback = match self.readback.take() {
+                Some(readback) if readback.size() == [self.config.width, self.config.height] => {
+                    readback
+                }
+                _ => Readback::new(
+                    &self.device,
+                    self.config.width,
+                    self.config.height,
+                    self.config.format,
+                ),
+            };
+            readback.copy_from(&mut encoder, self.target_texture(output.as_ref()));
+            self.readback = Some(readback);
+        }
+        self.eyedropper
+            .encode(&mut encoder, self.target_texture(output.as_ref()));
+
+        let submission = self.queue.submit(
+            egui_commands
+                .into_iter()
+                .chain(std::iter::once(encoder.finish())),
+        );
+        for scatter in &mut self.scatters {
+            scatter.after_submit();
+        }
+        self.eyedropper.after_submit();
+        self.frame_pacer
+            .submitted(&self.device, &self.queue, submission);
+        self.stats.gpu_latency = self.frame_pacer.latency();
+        self.govern(started.elapsed().saturating_sub(acquired));
+
+        let frame = match &self.readback {
+            Some(readback) if self.recorder.is_some() || self.capturing => {
+                Some((readback.size(), readback.read(&self.device)))
+            }
+            _ => None,
+        };
+        if let (Some(recorder), Some(([width, height], pixels))) = (&mut self.recorder, &frame) {
+            let result = match pixels {
+                Ok(pixels) => recorder.write_frame(*width, *height, pixels),
+                Err(_) => Err(std::io::Error::other(wgpu::BufferAsyncError)),
+            };
+            if let Err(e) = result {
+                log::error!("Recording stopped: {}", e);
+                self.stop_recording();
+            }
+        }
+        if self.capturing {
+            self.captured = frame.map(|([width, height], pixels)| {
+                pixels.map(|pixels| Screenshot {
+                    width,
+                    height,
+                    pixels,
+                })
+            });
+        }
+
+        if let Some(output) = output {
+            output.present();
+        }
+        self.damaged = false;
+        // Trimming forgets which glyphs the last prepare used, so it has to wait for the next
+        // one. Until then, nothing else allocates in the atlas.
+        if prepare_text {
+            self.atlas.trim();
+        }
+
+        self.frames_since_gc += 1;
+        let stats = resources::stats();
+        if std::mem::take(&mut self.gc_requested)
+            || self.renderer_config.cache_policy.due(
+                self.frames_since_gc,
+                stats.buffer_bytes + stats.texture_bytes,
+            )
+        {
+            self.gc();
+        }
+
+        Ok(())
+    }
+
+    /// The texture a frame is drawn into: the surface's current one in `output`, or the
+    /// offscreen one.
+    fn target_texture<'a>(&'a self, output: Option<&'a wgpu::SurfaceTexture>) -> &'a wgpu::Texture {
+        match (&self.target, output) {
+            (_, Some(output)) => &output.texture,
+            (Target::Offscreen(texture), None) => texture,
+            (Target::Surface(_), None) => unreachable!("surfaces hand out a texture per frame"),
+            (Target::Suspended, None) => unreachable!("suspended windows draw no frames"),
+        }
+    }
+
+    /// Frees rasterized glyphs, shrinks rect buffers that outgrew the current scene and the
+    /// image atlas, and drops the cached layers and custom meshes that are off screen.
+    fn gc(&mut self) {
+        self.frames_since_gc = 0;
+        if self
+            .image_atlas
+            .compact(&self.device, &self.queue, &self.image_bind_group_layout)
+        {
+            self.layer_cache.invalidate();
+            self.upload_rects();
+            self.damaged = true;
+        }
+        self.layer_cache.evict_offscreen();
+        if self
+            .custom
+            .evict_offscreen(&self.camera, self.window_size())
+        {
+            // The bundle holds on to the meshes it draws.
+            self.background_bundle = None;
+        }
+
+        // Glyphs still on screen are rasterized again from the font on the next prepare.
+        self.cache = SwashCache::new();
+
+        let instance_bytes = (self.num_opaque + self.num_instances) as wgpu::BufferAddress
+            * rect::instance_layout(self.renderer_config.packed_vertices).array_stride;
+        // Only shrink when the buffer is well oversized, so a list that fluctuates in size
+        // doesn't reallocate every collection.
+        if self.instance_buffer.size() > 4 * instance_bytes.next_power_of_two().max(1) {
+            self.instance_buffer = Tracked::new(
+                ResourceScope::Renderer,
+                "Rect Buffer",
+                self.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Rect Buffer"),
+                    size: 0,
+                    usage: self.instance_buffer.usage(),
+                    mapped_at_creation: false,
+                }),
+            );
+            self.upload_rects();
+        }
+    }
+
+    /// Writes the view as a PDF to the job's path, or to a temporary file opened in the
+    /// system's viewer to print it from there.
+    fn print(&mut self, job: PrintJob) {
+        let pages = match self.render_pages(&job.config) {
+            Ok(pages) => pages,
+            Err(e) => {
+                log::error!("Failed to render the pages to print: {}", e);
+                return;
+            }
+        };
+        let path = job
+            .path
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join("wgpu_test_print.pdf"));
+        let result = std::fs::File::create(&path)
+            .map(std::io::BufWriter::new)
+            .and_then(|file| print::write_pdf(file, &pages, &job.config));
+        if let Err(e) = result {
+            log::error!("Failed to write {}: {}", path.display(), e);
+            return;
+        }
+        if job.path.is_none() {
+            if let Err(e) = print::open_in_viewer(&path) {
+                log::error!("Failed to open {} to print it: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// Renders the view at `config.dpi`, taking the window's to be 96 times its scale factor,
+    /// and cuts it into pages.
+    fn render_pages(&mut self, config: &PrintConfig) -> Result<Vec<Page>, wgpu::BufferAsyncError> {
+        let scale = config.dpi as f64 / (96.0 * self.scale_factor());
+        let size = [self.size.width, self.size.height].map(|side| (side as f64 * scale) as u32);
+        if size.contains(&0) {
+            return Ok(Vec::new());
+        }
+        let pixels = self.render_scaled(scale, size)?;
+        Ok(print::paginate(&pixels, size, config.page_pixels()))
+    }
+
+    /// Renders the view `scale` times larger into RGB pixels over white, leaving out egui, the
+    /// pixel grid, the inspector, the theme editor and the title bar. The image is drawn in
+    /// tiles of at most `PRINT_TILE` pixels with the camera moved to each, then the window's
+    /// size and camera are put back.
+    fn render_scaled(
+        &mut self,
+        scale: f64,
+        size: [u32; 2],
+    ) -> Result<Vec<u8>, wgpu::BufferAsyncError> {
+        let window_size = self.size;
+        let camera = self.camera;
+        let pixel_grid = std::mem::replace(&mut self.renderer_config.pixel_grid, false);
+        let inspector = std::mem::replace(&mut self.renderer_config.inspector, false);
+        let theme_editor = std::mem::replace(&mut self.renderer_config.theme_editor, false);
+        let title_bar = self.title_bar.take();
+        if self.background_bundle.is_none() {
+            self.background_bundle = Some(self.record_background());
+        }
+
+        let mut pixels = vec![0; size[0] as usize * size[1] as usize * 3];
+        let mut result = Ok(());
+        'tiles: for y in (0..size[1]).step_by(PRINT_TILE as usize) {
+            for x in (0..size[0]).step_by(PRINT_TILE as usize) {
+                let tile = [PRINT_TILE.min(size[0] - x), PRINT_TILE.min(size[1] - y)];
+                // Where the tile starts in the scaled rect space.
+                let origin = match self.renderer_config.origin {
+                    Origin::TopLeft => [x, y],
+                    Origin::BottomLeft => [x, size[1] - y - tile[1]],
+                };
+                let zoom = camera.zoom * scale;
+                self.camera = Camera {
+                    offset: [
+                        camera.offset[0] + origin[0] as f64 / zoom,
+                        camera.offset[1] + origin[1] as f64 / zoom,
+                    ],
+                    zoom,
+                };
+                self.size = winit::dpi::PhysicalSize::new(tile[0], tile[1]);
+                self.upload_rects();
+                self.update();
+                self.prepare_text(scale as f32, [-(x as f32), -(y as f32)]);
+                self.rect_bundle = Some(self.record_rects());
+
+                let rgba = match self.render_tile(tile) {
+                    Ok(rgba) => rgba,
+                    Err(e) => {
+                        result = Err(e);
+                        break 'tiles;
+                    }
+                };
+                // The frame is premultiplied, so a pixel over white only gains what it lets
+                // through.
+                let row_bytes = tile[0] as usize * 4;
+                for (row, line) in rgba.chunks_exact(row_bytes).enumerate() {
+                    let start = ((y as usize + row) * size[0] as usize + x as usize) * 3;
+                    let out = &mut pixels[start..start + tile[0] as usize * 3];
+                    for (pixel, out) in line.chunks_exact(4).zip(out.chunks_exact_mut(3)) {
+                        for (out, &value) in out.iter_mut().zip(&pixel[..3]) {
+                            *out = value.saturating_add(255 - pixel[3]);
+                        }
+                    }
+                }
+            }
+        }
+
+        self.size = window_size;
+        self.camera = camera;
+        self.renderer_config.pixel_grid = pixel_grid;
+        self.renderer_config.inspector = inspector;
+        self.renderer_config.theme_editor = theme_editor;
+        self.title_bar = title_bar;
+        self.upload_rects();
+        self.update();
+        self.text_changed = true;
+        self.damaged = true;
+        result.map(|()| pixels)
+    }
+
+    /// Draws the prepared view into an offscreen texture of `size` and reads it back as RGBA.
+    fn render_tile(&self, size: [u32; 2]) -> Result<Vec<u8>, wgpu::BufferAsyncError> {
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Print Texture"),
+            size: wgpu::Extent3d {
+                width: size[0],
+                height: size[1],
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let (_depth_texture, depth_view) = create_depth_texture(
+            &self.device,
+            &wgpu::SurfaceConfiguration {
+                width: size[0],
+                height: size[1],
+                ..self.config.clone()
+            },
+        );
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Print Encoder"),
+            });
+        self.encode_passes(&mut encoder, &view, &depth_view, false);
+        let readback = Readback::new(&self.device, size[0], size[1], self.config.format);
+        readback.copy_from(&mut encoder, &texture);
+        self.queue.submit(std::iter::once(encoder.finish()));
+        readback.read(&self.device)
+    }
+}
+
+/// Writes `contents` to the start of `buffer`, replacing it with a larger one first if it is
+/// too small. Capacity doubles so a steadily growing list is only reallocated a few times.
+/// Returns whether the buffer was replaced, so bind groups using it can be rebuilt.
+fn write_growing(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    buffer: &mut Tracked<wgpu::Buffer>,
+    contents: &[u8],
+) -> bool {
+    let size = contents.len() as wgpu::BufferAddress;
+    let grow = size > buffer.size();
+    if grow {
+        let grown = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Rect Buffer"),
+            size: size.next_power_of_two(),
+            usage: buffer.usage(),
+            mapped_at_creation: false,
+        });
+        *buffer = Tracked::new(ResourceScope::Renderer, "Rect Buffer", grown);
+    }
+    queue.write_buffer(buffer, 0, contents);
+    grow
+}
+
+pub async fn run() -> Result<(), RendererError> {
+    run_with(
+        RendererConfig::default(),
+        Scene {
+            rects: RECTANGLES.to_vec(),
+            text: "This is sample text".to_string(),
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+/// Opens a window that draws `scene`. Returns only if the renderer can't be set up.
+pub async fn run_with(renderer_config: RendererConfig, scene: Scene) -> Result<(), RendererError> {
+    run_with_frame(renderer_config, scene, |_| {}).await
+}
+
+/// Opens a window that draws `scene`, calling `frame` whenever the event loop wakes to update
+/// the rects. Frames are only drawn when something changed; see `Frame`.
+/// The draw list starts out with the scene's rects, unless `RendererConfig::snapshot` finds a
+/// snapshot left behind by a crashed run, which then replaces the rects, text and camera.
+/// Returns only if the renderer can't be set up. `AppBuilder` sets up the window too.
+pub async fn run_with_frame(
+    renderer_config: RendererConfig,
+    scene: Scene,
+    frame: impl FnMut(&mut Frame) + 'static,
+) -> Result<(), RendererError> {
+    AppBuilder::new()
+        .with_renderer_config(renderer_config)
+        .with_scene(scene)
+        .run_with_frame(frame)
+        .await
+}
+
+/// A window of the event loop, with the callback that fills it.
+struct AppWindow {
+    state: State,
+    frame: Box<dyn FnMut(&mut Frame)>,
+    /// Whether the window still waits for its first frame to be shown.
+    hidden: bool,
+    /// Whether the frame callback ran since the last redraw.
+    #[cfg(target_os = "windows")]
+    stepped: bool,
+    #[cfg(feature = "dialog")]
+    dialogs: dialog::Dialogs,
+}
+
+/// Opens a window for each of `apps`, all drawn with the first one's GPU, and runs the event
+/// loop until the last one closes.
+async fn run_app(apps: Vec<(AppBuilder, Box<dyn FnMut(&mut Frame)>)>) -> Result<(), RendererError> {
+    #[cfg(not(target_arch = "wasm32"))]
+    env_logger::init();
+    #[cfg(target_arch = "wasm32")]
+    web::init_logging();
+    let event_loop = EventLoop::new();
+    let mut windows: Vec<AppWindow> = Vec::new();
+    for (app, frame) in apps {
+        #[cfg(feature = "egui")]
+        let (app, egui) = app.take_egui();
+        let (window_config, mut renderer_config, mut scene) = app.into_parts();
+        let window = build_window(&event_loop, &window_config, &mut renderer_config)?;
+        if let Some(snapshot) = renderer_config
+            .snapshot
+            .as_ref()
+            .and_then(SnapshotConfig::recover)
+        {
+            snapshot.apply(&mut scene, &mut renderer_config);
+        }
+        let size = window.inner_size();
+        let gpu = windows.first().map(|first| first.state.gpu());
+        #[allow(unused_mut)]
+        let mut state = State::new(Some(window), size, renderer_config, scene, gpu).await?;
+        #[cfg(feature = "egui")]
+        if let Some(ui) = egui {
+            state.attach_egui(&event_loop, ui);
+        }
+        windows.push(AppWindow {
+            hidden: state.renderer_config.hidden_until_rendered,
+            state,
+            frame,
+            #[cfg(target_os = "windows")]
+            stepped: false,
+            #[cfg(feature = "dialog")]
+            dialogs: dialog::Dialogs::new(event_loop.create_proxy()),
+        });
+    }
+    let Some(main) = windows.first_mut() else {
+        return Ok(());
+    };
+
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Ok(path) = std::env::var("WGPU_TEST_RECORD") {
+        main.state
+            .start_recording(Box::new(recorder::FfmpegSink::new(path, 60)));
+    }
+
+    event_loop.run(move |event, _, control_flow| match event {
+        Event::RedrawRequested(window_id) => {
+            let Some(app) = windows
+                .iter_mut()
+                .find(|app| app.state.window().id() == window_id)
+            else {
+                return;
+            };
+            // Dragging the title bar runs a modal loop on Windows that never reaches
+            // `MainEventsCleared`, but still delivers redraws requested from here. Running the
+            // frame callback for them keeps animations going until the drag ends.
+            #[cfg(target_os = "windows")]
+            if !std::mem::take(&mut app.stepped) {
+                step(
+                    app,
+                    #[cfg(feature = "tray")]
+                    Vec::new(),
+                );
+            }
+            redraw(&mut app.state, control_flow, &mut app.hidden);
+            #[cfg(target_os = "windows")]
+            if app.state.needs_redraw() {
+                app.state.window().request_redraw();
+            }
+        }
+
+        #[cfg(any(feature = "menu", feature = "tray"))]
+        Event::NewEvents(StartCause::Init) => {
+            #[cfg(feature = "menu")]
+            for app in &mut windows {
+                if let Some(window) = &app.state.window {
+                    app.state.menus.attach(window, &mut app.state.font_system);
+                }
+            }
+            // Tray events don't say which window they're for, so only the first has a tray.
+            #[cfg(feature = "tray")]
+            {
+                let main = &mut windows[0].state;
+                main.tray = main.renderer_config.tray.as_ref().and_then(tray::Tray::new);
+            }
+        }
+
+        Event::Suspended => {
+            for app in &mut windows {
+                app.state.suspend();
+            }
+        }
+        Event::Resumed => {
+            if let Some(e) = windows.iter_mut().find_map(|app| app.state.resume().err()) {
+                log::error!("Can't draw to the window again: {}", e);
+                exit(&mut windows, control_flow);
+            }
+        }
+
+        Event::MainEventsCleared => {
+            #[cfg(any(feature = "menu", feature = "tray"))]
+            #[allow(unused_mut)]
+            let mut menu_events = native_menu_events();
+            #[cfg(feature = "menu")]
+            for app in &mut windows {
+                app.state.menus.poll(&mut menu_events);
+            }
+            let mut wake = ControlFlow::Wait;
+            for app in &mut windows {
+                #[cfg(feature = "tray")]
+                let tray_events = match &app.state.tray {
+                    Some(tray) => {
+                        let polled =
+                            tray.poll(app.state.window(), std::mem::take(&mut menu_events));
+                        if polled.quit {
+                            wake = ControlFlow::Exit;
+                            break;
+                        }
+                        polled.events
+                    }
+                    None => Vec::new(),
+                };
+                wake = earliest_wake(
+                    wake,
+                    step(
+                        app,
+                        #[cfg(feature = "tray")]
+                        tray_events,
+                    ),
+                );
+            }
+            if wake == ControlFlow::Exit {
+                exit(&mut windows, control_flow);
+            } else {
+                *control_flow = wake;
+            }
+        }
+
+        Event::WindowEvent {
+            ref event,
+            window_id,
+        } => {
+            let Some(i) = windows
+                .iter()
+                .position(|app| app.state.window().id() == window_id)
+            else {
+                return;
+            };
+            let last = windows.len() == 1;
+            let app = &mut windows[i];
+            let used = app.state.input(event);
+            let close_requested = std::mem::take(&mut app.state.close_requested)
+                || (!used && matches!(event, WindowEvent::CloseRequested));
+            if close_requested {
+                if close(&mut app.state, last) {
+                    windows.remove(i);
+                    if windows.is_empty() {
+                        *control_flow = ControlFlow::Exit;
+                    }
+                }
+            } else if !used {
+                let state = &mut app.state;
+                match event {
+                    // Windows and macOS block the event loop while the window is dragged to a
+                    // new size, so drawing on the next loop turn would leave the old frame
+                    // stretched over the new size. Draw right away instead.
+                    WindowEvent::Resized(physical_size) => {
+                        #[cfg(feature = "tray")]
+                        if state.minimizes_to_tray() && state.window().is_minimized() == Some(true)
+                        {
+                            state.window().set_visible(false);
+                        }
+                        state.resize(*physical_size);
+                        redraw(state, control_flow, &mut app.hidden);
+                    }
+
+                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+                        // Likely on another monitor, with another refresh rate too.
+                        state.update_refresh_rate();
+                        state.resize(**new_inner_size);
+                        redraw(state, control_flow, &mut app.hidden);
+                    }
+
+                    WindowEvent::Moved(_) => {
+                        state.update_refresh_rate();
+                        // The modal move loop keeps reporting moves even when a redraw is
+                        // pending.
+                        #[cfg(target_os = "windows")]
+                        redraw(state, control_flow, &mut app.hidden);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    });
+}
+
+/// Creates a window with `window_config`, in the way `renderer_config` draws it.
+fn build_window(
+    event_loop: &EventLoop<()>,
+    window_config: &WindowConfig,
+    renderer_config: &mut RendererConfig,
+) -> Result<Window, RendererError> {
+    let translucent = renderer_config.window_alpha != WindowAlpha::Opaque
+        || renderer_config.backdrop != Backdrop::None;
+    let builder = window_config
+        .apply(WindowBuilder::new(), renderer_config)
+        .with_visible(!renderer_config.hidden_until_rendered)
+        .with_transparent(translucent);
+    #[cfg(target_os = "macos")]
+    let builder = if renderer_config.backdrop == Backdrop::Vibrancy {
+        use winit::platform::macos::WindowBuilderExtMacOS;
+        builder
+            .with_titlebar_transparent(true)
+            .with_fullsize_content_view(true)
+    } else {
+        builder
+    };
+    #[cfg(target_arch = "wasm32")]
+    let builder = {
+        use winit::platform::web::WindowBuilderExtWebSys;
+        builder.with_canvas(renderer_config.canvas.as_deref().and_then(web::find_canvas))
+    };
+    let window = builder.build(event_loop)?;
+    #[cfg(target_arch = "wasm32")]
+    web::attach(&window);
+    Ok(window)
+}
+
+/// Runs the frame callback of `app` and does what it asked for, returning when the event loop
+/// has to wake for the window again.
+fn step(
+    app: &mut AppWindow,
+    #[cfg(feature = "tray")] tray_events: Vec<tray::TrayEvent>,
+) -> ControlFlow {
+    #[cfg(target_os = "windows")]
+    {
+        app.stepped = true;
+    }
+    let state = &mut app.state;
+    #[cfg(feature = "dialog")]
+    app.dialogs.poll();
+    let scale_factor = state.scale_factor();
+    let refresh_rate = state.frame_pacer.refresh_rate();
+    let time = state.animation_time();
+    let quality = state.quality();
+    let window_mode = state.window_mode();
+    let hovered_rect = state.hovered_rect;
+    let mut context = Frame {
+        draw_list: &mut state.draw_list,
+        stats: state.stats,
+        scale_factor,
+        refresh_rate,
+        time,
+        quality,
+        window_mode,
+        hovered_rect,
+        theme: state.renderer_config.theme,
+        #[cfg(feature = "taskbar")]
+        taskbar: &mut state.taskbar,
+        #[cfg(feature = "dialog")]
+        dialogs: &mut app.dialogs,
+        #[cfg(feature = "menu")]
+        menu: &mut state.menus,
+        #[cfg(feature = "tray")]
+        tray_events,
+        screenshot: state.screenshot.take(),
+        picked_color: state.picked_color.take(),
+        gc: false,
+        capture: false,
+        print_job: None,
+        recording: None,
+        present_mode: None,
+        window_mode_change: None,
+        cursor: None,
+        invalidated: false,
+        deadline: None,
+        images: Vec::new(),
+        color_pick: None,
+        custom: &mut state.custom,
+    };
+    (app.frame)(&mut context);
+    let print_job = context.print_job.take();
+    let recording = context.recording.take();
+    let images = std::mem::take(&mut context.images);
+    let color_pick = context.color_pick;
+    let present_mode = context.present_mode;
+    let window_mode_change = context.window_mode_change;
+    let cursor = context.cursor;
+    let capture = context.capture;
+    state.gc_requested |= context.gc;
+    state.damaged |= context.invalidated;
+    #[cfg(feature = "taskbar")]
+    if let Some(window) = &state.window {
+        state.taskbar.apply(window);
+    }
+    #[cfg(feature = "dialog")]
+    app.dialogs.apply(state.window());
+    #[cfg(feature = "menu")]
+    state.menus.apply();
+    if let Some(deadline) = context.deadline {
+        state.redraw_deadline = Some(state.redraw_deadline.map_or(deadline, |d| d.min(deadline)));
+    }
+    if state.redraw_deadline.is_some_and(|d| d <= Instant::now()) {
+        state.redraw_deadline = None;
+        state.damaged = true;
+    }
+    if let Some(mode) = present_mode {
+        state.set_present_mode(mode);
+    }
+    if let Some(mode) = window_mode_change {
+        state.set_window_mode(mode);
+    }
+    if let Some(cursor) = cursor {
+        state.set_cursor(cursor);
+    }
+    if let Some(job) = print_job {
+        state.print(job);
+    }
+    match recording {
+        Some(Recording::Start(sink)) => state.start_recording(sink),
+        Some(Recording::Stop) => state.stop_recording(),
+        None => {}
+    }
+    for (id, image) in images {
+        state.add_image(id, image);
+    }
+    if let Some(point) = color_pick {
+        state.pick_color(point);
+    }
+    if capture {
+        match state.capture_frame() {
+            Ok(screenshot) => state.screenshot = Some(screenshot),
+            Err(e) => log::error!("Failed to capture the frame: {}", e),
+        }
+        // Run the callback again to hand it over.
+        state.damaged = true;
+    }
+
+    // Sleep until the next event or deadline when the frame would look the same as the last
+    // one.
+    if state.needs_redraw() {
+        state.window().request_redraw();
+        ControlFlow::Poll
+    } else if let Some(deadline) = state.redraw_deadline {
+        ControlFlow::WaitUntil(deadline)
+    } else {
+        ControlFlow::Wait
+    }
+}
+
+/// The sooner of two wakes the event loop was asked for.
+fn earliest_wake(a: ControlFlow, b: ControlFlow) -> ControlFlow {
+    match (a, b) {
+        (ControlFlow::Poll, _) | (_, ControlFlow::Poll) => ControlFlow::Poll,
+        (ControlFlow::WaitUntil(a), ControlFlow::WaitUntil(b)) => ControlFlow::WaitUntil(a.min(b)),
+        (ControlFlow::WaitUntil(deadline), _) | (_, ControlFlow::WaitUntil(deadline)) => {
+            ControlFlow::WaitUntil(deadline)
+        }
+        _ => ControlFlow::Wait,
+    }
+}
+
+/// The ids of the items chosen in native menus since the last call. The menu bar and the tray
+/// menu share one queue, so it is drained here and each takes its own items.
+#[cfg(any(feature = "menu", feature = "tray"))]
+fn native_menu_events() -> Vec<String> {
+    #[cfg(any(windows, target_os = "macos"))]
+    {
+        #[cfg(feature = "menu")]
+        use muda::MenuEvent;
+        #[cfg(not(feature = "menu"))]
+        use tray_icon::menu::MenuEvent;
+        MenuEvent::receiver()
+            .try_iter()
+            .map(|event| event.id.0)
+            .collect()
+    }
+    #[cfg(not(any(windows, target_os = "macos")))]
+    Vec::new()
+}
+
+/// Shuts the window down, or hides it in the tray when it minimizes there. Returns whether it
+/// was shut down. `last` is whether it's the last window left.
+fn close(state: &mut State, last: bool) -> bool {
+    #[cfg(feature = "tray")]
+    if state.minimizes_to_tray() {
+        state.window().set_visible(false);
+        return false;
+    }
+    // The other windows' scenes are still alive, so only the last teardown can tell a leak.
+    state.renderer_config.leak_check &= last;
+    shut_down(state);
+    true
+}
+
+/// Shuts every window down and ends the event loop.
+fn exit(windows: &mut Vec<AppWindow>, control_flow: &mut ControlFlow) {
+    let count = windows.len();
+    for (i, app) in windows.iter_mut().enumerate() {
+        app.state.renderer_config.leak_check &= i + 1 == count;
+        shut_down(&mut app.state);
+    }
+    windows.clear();
+    *control_flow = ControlFlow::Exit
+}
+
+/// Frees the scene of a window about to close.
+fn shut_down(state: &mut State) {
+    state.stop_recording();
+    state.clear_scene();
+    if let Some(snapshot) = &state.renderer_config.snapshot {
+        snapshot.discard();
+    }
+}
+
+/// Updates and renders one frame. `hidden` is whether the window still waits for its first
+/// frame to be shown.
+fn redraw(state: &mut State, control_flow: &mut ControlFlow, hidden: &mut bool) {
+    state.update();
+    match state.render() {
+        Ok(_) => {
+            state.surface_lost = false;
+            if *hidden {
+                state.window().set_visible(true);
+                *hidden = false;
+            }
+        }
+
+        // Configuring the surface again didn't help, so the device went with it.
+        Err(wgpu::SurfaceError::Lost) if state.surface_lost => {
+            recover_device(state, control_flow);
+            return;
+        }
+        Err(wgpu::SurfaceError::Lost) => {
+            state.surface_lost = true;
+            state.resize(state.size);
+        }
+        Err(wgpu::SurfaceError::Outdated) => state.resize(state.window().inner_size()),
+        Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
+        Err(e) => eprintln!("{:?}", e),
+    }
+    if state.device_loss.is_lost() {
+        recover_device(state, control_flow);
+        return;
+    }
+    state.save_snapshot();
+}
+
+/// Moves to a new device after the old one was lost, or exits when that's turned off. The
+/// snapshot is kept when exiting, as after a crash.
+fn recover_device(state: &mut State, control_flow: &mut ControlFlow) {
+    if !state.renderer_config.device_recovery || cfg!(target_arch = "wasm32") {
+        log::error!("The device was lost");
+        state.stop_recording();
+        *control_flow = ControlFlow::Exit;
+        return;
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Err(e) = state.recover() {
+        // The window went with the old state, so there's nothing left to run the loop for.
+        log::error!("Can't recover from the lost device: {}", e);
+        state.stop_recording();
+        std::process::exit(1);
+    }
+    state.window().request_redraw();
 }
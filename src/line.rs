@@ -0,0 +1,267 @@
+//! Strokes through points, with caps and joins, drawn by the geo pipeline.
+
+use std::f64::consts::PI;
+
+use crate::geo::GeoVertex;
+
+type Point = [f64; 2];
+
+/// How an open line ends.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineCap {
+    /// Stops at the end point.
+    #[default]
+    Butt,
+    /// Ends in a half circle around the end point.
+    Round,
+    /// Goes on for half the width past the end point.
+    Square,
+}
+
+/// How consecutive segments meet.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineJoin {
+    /// Extends the outer edges until they meet, or bevels the corner when they'd meet further
+    /// out than `MITER_LIMIT` allows.
+    #[default]
+    Miter,
+    Round,
+}
+
+/// The longest a miter can be, as a multiple of the line width, before the join is beveled
+/// instead. The same as SVG's default.
+pub const MITER_LIMIT: f64 = 4.0;
+
+/// Furthest a round cap or join may stray from the true circle, in pixels.
+const ROUND_TOLERANCE: f64 = 0.25;
+
+/// A stroke through `points` in world space.
+///
+/// Every segment and join is its own triangles, so where they overlap a translucent color is
+/// blended twice.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Polyline {
+    pub points: Vec<[f64; 2]>,
+    /// In pixels, whatever the zoom.
+    pub width: f32,
+    pub color: [f32; 4],
+    pub cap: LineCap,
+    pub join: LineJoin,
+    /// Joins the last point back to the first instead of capping both ends. Lines with fewer
+    /// than three distinct points are drawn open.
+    pub closed: bool,
+}
+
+impl Polyline {
+    /// A single straight line from `start` to `end`.
+    pub fn line(start: [f64; 2], end: [f64; 2], width: f32, color: [f32; 4]) -> Self {
+        Self {
+            points: vec![start, end],
+            width,
+            color,
+            ..Default::default()
+        }
+    }
+}
+
+fn sub(a: Point, b: Point) -> Point {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+fn scale(v: Point, factor: f64) -> Point {
+    [v[0] * factor, v[1] * factor]
+}
+
+fn unit(v: Point) -> Point {
+    scale(v, 1.0 / v[0].hypot(v[1]))
+}
+
+/// Turns a quarter from the x axis towards the y axis.
+fn perpendicular(v: Point) -> Point {
+    [-v[1], v[0]]
+}
+
+fn rotate(v: Point, angle: f64) -> Point {
+    let (sin, cos) = angle.sin_cos();
+    [v[0] * cos - v[1] * sin, v[0] * sin + v[1] * cos]
+}
+
+/// Steps needed for an arc of `radius` pixels to stay within `ROUND_TOLERANCE` of a circle.
+fn arc_steps(angle: f64, radius: f64) -> usize {
+    let step = if radius > ROUND_TOLERANCE {
+        2.0 * (1.0 - ROUND_TOLERANCE / radius).acos()
+    } else {
+        PI
+    };
+    (angle.abs() / step).ceil().max(1.0) as usize
+}
+
+/// Drops consecutive duplicates and non-finite points, and the repeated first point of a
+/// closed line.
+fn clean(line: &Polyline) -> Vec<Point> {
+    let mut points: Vec<Point> = vec![];
+    for &point in &line.points {
+        if points.last() != Some(&point) && point.iter().all(|v| v.is_finite()) {
+            points.push(point);
+        }
+    }
+    if line.closed && points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+    points
+}
+
+struct Mesh<'a> {
+    origin: Point,
+    half_width: f64,
+    color: [f32; 4],
+    vertices: &'a mut Vec<GeoVertex>,
+    indices: &'a mut Vec<u32>,
+}
+
+impl Mesh<'_> {
+    fn vertex(&mut self, point: Point, extrude: Point) -> u32 {
+        self.vertices.push(GeoVertex {
+            position: sub(point, self.origin).map(|v| v as f32),
+            extrude: extrude.map(|v| v as f32),
+            color: self.color,
+        });
+        (self.vertices.len() - 1) as u32
+    }
+
+    /// Triangles from `point` to each pair of consecutive extrusions around it.
+    fn fan(&mut self, point: Point, extrusions: impl IntoIterator<Item = Point>) {
+        let center = self.vertex(point, [0.0; 2]);
+        let mut previous = None;
+        for extrude in extrusions {
+            let vertex = self.vertex(point, extrude);
+            if let Some(previous) = previous {
+                self.indices.extend([center, previous, vertex]);
+            }
+            previous = Some(vertex);
+        }
+    }
+
+    /// A fan from `point` sweeping `start` by `angle`.
+    fn arc(&mut self, point: Point, start: Point, angle: f64) {
+        let steps = arc_steps(angle, self.half_width);
+        self.fan(
+            point,
+            (0..=steps).map(|i| rotate(start, angle * i as f64 / steps as f64)),
+        );
+    }
+
+    fn segment(&mut self, start: Point, end: Point, direction: Point) {
+        let normal = scale(perpendicular(direction), self.half_width);
+        let base = self.vertices.len() as u32;
+        for (point, side) in [(start, 1.0), (start, -1.0), (end, -1.0), (end, 1.0)] {
+            self.vertex(point, scale(normal, side));
+        }
+        self.indices
+            .extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    /// Fills the gap on the outside of the corner at `point`.
+    fn join(&mut self, point: Point, incoming: Point, outgoing: Point, join: LineJoin) {
+        let turn = incoming[0] * outgoing[1] - incoming[1] * outgoing[0];
+        let dot = incoming[0] * outgoing[0] + incoming[1] * outgoing[1];
+        if turn == 0.0 && dot > 0.0 {
+            return;
+        }
+        // Turning towards positive angles leaves the gap on the negative side.
+        let side = if turn > 0.0 { -1.0 } else { 1.0 };
+        let start = scale(perpendicular(incoming), self.half_width * side);
+        let end = scale(perpendicular(outgoing), self.half_width * side);
+        match join {
+            LineJoin::Round => self.arc(point, start, turn.atan2(dot)),
+            LineJoin::Miter => {
+                // Cosine of half the turn; the miter is this much shorter than the width.
+                let cos_half = ((1.0 + dot) / 2.0).max(0.0).sqrt();
+                if cos_half * MITER_LIMIT < 1.0 {
+                    self.fan(point, [start, end]);
+                } else {
+                    let tip = scale(
+                        unit([start[0] + end[0], start[1] + end[1]]),
+                        self.half_width / cos_half,
+                    );
+                    self.fan(point, [start, tip, end]);
+                }
+            }
+        }
+    }
+
+    /// Caps the end at `point`, with `direction` pointing away from the line.
+    fn cap(&mut self, point: Point, direction: Point, cap: LineCap) {
+        let normal = scale(perpendicular(direction), self.half_width);
+        let ahead = scale(direction, self.half_width);
+        match cap {
+            LineCap::Butt => {}
+            LineCap::Round => self.arc(point, normal, -PI),
+            LineCap::Square => self.fan(
+                point,
+                [
+                    normal,
+                    [normal[0] + ahead[0], normal[1] + ahead[1]],
+                    [ahead[0] - normal[0], ahead[1] - normal[1]],
+                    scale(normal, -1.0),
+                ],
+            ),
+        }
+    }
+}
+
+/// A world position near `points` for mesh vertices to be relative to, so they keep their
+/// precision as f32.
+pub(crate) fn origin(points: impl IntoIterator<Item = Point>) -> Point {
+    points
+        .into_iter()
+        .find(|point| point.iter().all(|v| v.is_finite()))
+        .unwrap_or_default()
+}
+
+/// Triangulates `line` into vertices extruded in screen space, so its width holds at any zoom,
+/// and appends them with three indices per triangle. Vertex positions are relative to
+/// `origin`. A lone point shows only its caps, as in SVG.
+pub(crate) fn stroke(
+    line: &Polyline,
+    origin: Point,
+    vertices: &mut Vec<GeoVertex>,
+    indices: &mut Vec<u32>,
+) {
+    let points = clean(line);
+    let mut mesh = Mesh {
+        origin,
+        half_width: line.width as f64 / 2.0,
+        color: line.color,
+        vertices,
+        indices,
+    };
+    if !(mesh.half_width > 0.0 && mesh.half_width.is_finite()) || points.is_empty() {
+        return;
+    }
+
+    let n = points.len();
+    let closed = line.closed && n > 2;
+    if n == 1 {
+        mesh.cap(points[0], [1.0, 0.0], line.cap);
+        mesh.cap(points[0], [-1.0, 0.0], line.cap);
+        return;
+    }
+
+    let segments = if closed { n } else { n - 1 };
+    let directions: Vec<Point> = (0..segments)
+        .map(|i| unit(sub(points[(i + 1) % n], points[i])))
+        .collect();
+    for (i, &direction) in directions.iter().enumerate() {
+        mesh.segment(points[i], points[(i + 1) % n], direction);
+    }
+    let joins = if closed { 0..n } else { 1..n - 1 };
+    for i in joins {
+        let incoming = directions[(i + segments - 1) % segments];
+        mesh.join(points[i], incoming, directions[i], line.join);
+    }
+    if !closed {
+        mesh.cap(points[0], scale(directions[0], -1.0), line.cap);
+        mesh.cap(points[n - 1], directions[segments - 1], line.cap);
+    }
+}
@@ -0,0 +1,774 @@
+//! A menu bar of menus with items, separators, accelerators and checkmarks.
+//!
+//! Windows and macOS get a native menu bar: in the window on Windows, and in the global menu
+//! bar on macOS. Elsewhere the bar is a `MenuBarWidget` laid out along the top of the window,
+//! which the frame callback draws like the timeline or chart widgets, from `Menus::widget` in
+//! the colors of `Frame::theme`.
+//! Either way, chosen items reach the callback as `Menus::events`.
+
+use std::collections::HashMap;
+
+use glyphon::{FontSystem, Metrics};
+use winit::{
+    event::{
+        ElementState, KeyboardInput, ModifiersState, MouseButton, VirtualKeyCode, WindowEvent,
+    },
+    window::Window,
+};
+
+use crate::{
+    rect::{Fill, Rect},
+    text,
+    theme::Theme,
+};
+
+/// A keyboard shortcut that chooses a menu item.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Accelerator {
+    pub modifiers: ModifiersState,
+    pub key: VirtualKeyCode,
+}
+
+impl Accelerator {
+    pub fn new(modifiers: ModifiersState, key: VirtualKeyCode) -> Self {
+        Self { modifiers, key }
+    }
+
+    /// `key` with Cmd on macOS and Ctrl elsewhere, the usual modifier for menu shortcuts.
+    pub fn primary(key: VirtualKeyCode) -> Self {
+        let modifiers = if cfg!(target_os = "macos") {
+            ModifiersState::LOGO
+        } else {
+            ModifiersState::CTRL
+        };
+        Self::new(modifiers, key)
+    }
+
+    pub fn with_shift(mut self) -> Self {
+        self.modifiers |= ModifiersState::SHIFT;
+        self
+    }
+
+    /// How the shortcut is written next to its item, e.g. "Ctrl+Shift+S".
+    pub fn label(&self) -> String {
+        let logo = if cfg!(target_os = "macos") {
+            "Cmd"
+        } else {
+            "Super"
+        };
+        let mut parts: Vec<String> = [
+            (self.modifiers.ctrl(), "Ctrl"),
+            (self.modifiers.alt(), "Alt"),
+            (self.modifiers.shift(), "Shift"),
+            (self.modifiers.logo(), logo),
+        ]
+        .into_iter()
+        .filter(|(held, _)| *held)
+        .map(|(_, name)| name.to_string())
+        .collect();
+        // Digits are named `Key1` and so on.
+        let key = format!("{:?}", self.key);
+        let key = match key.strip_prefix("Key") {
+            Some(digit) if !digit.is_empty() => digit.to_string(),
+            _ => key,
+        };
+        parts.push(key);
+        parts.join("+")
+    }
+}
+
+/// An entry in a menu.
+#[derive(Clone, Debug, PartialEq)]
+pub enum MenuItem {
+    Item {
+        /// Reported in `Menus::events` when chosen.
+        id: String,
+        label: String,
+        accelerator: Option<Accelerator>,
+        enabled: bool,
+        /// Whether the checkmark is shown, which choosing the item toggles. `None` for items
+        /// without one.
+        checked: Option<bool>,
+    },
+    Separator,
+}
+
+impl MenuItem {
+    pub fn item(id: impl Into<String>, label: impl Into<String>) -> Self {
+        MenuItem::Item {
+            id: id.into(),
+            label: label.into(),
+            accelerator: None,
+            enabled: true,
+            checked: None,
+        }
+    }
+
+    /// An item with a checkmark, shown while `checked`.
+    pub fn check(id: impl Into<String>, label: impl Into<String>, checked: bool) -> Self {
+        let mut item = Self::item(id, label);
+        if let MenuItem::Item { checked: check, .. } = &mut item {
+            *check = Some(checked);
+        }
+        item
+    }
+
+    /// Separators stay unchanged.
+    pub fn with_accelerator(mut self, accelerator: Accelerator) -> Self {
+        if let MenuItem::Item {
+            accelerator: shortcut,
+            ..
+        } = &mut self
+        {
+            *shortcut = Some(accelerator);
+        }
+        self
+    }
+
+    /// Shows the item greyed out, so it can't be chosen. Separators stay unchanged.
+    pub fn disabled(mut self) -> Self {
+        if let MenuItem::Item { enabled, .. } = &mut self {
+            *enabled = false;
+        }
+        self
+    }
+}
+
+/// One menu of the bar, e.g. File.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Menu {
+    pub label: String,
+    pub items: Vec<MenuItem>,
+}
+
+impl Menu {
+    pub fn new(label: impl Into<String>, items: Vec<MenuItem>) -> Self {
+        Self {
+            label: label.into(),
+            items,
+        }
+    }
+}
+
+/// The menus to show in the window's menu bar; see `RendererConfig::menu_bar`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MenuBar {
+    pub menus: Vec<Menu>,
+}
+
+impl MenuBar {
+    fn items(&self) -> impl Iterator<Item = &MenuItem> {
+        self.menus.iter().flat_map(|menu| &menu.items)
+    }
+}
+
+/// The window's menu bar, handed to the frame callback as `Frame::menu`.
+pub struct Menus {
+    bar: MenuBar,
+    /// The checkmarks by item id.
+    checked: HashMap<String, bool>,
+    checks_changed: bool,
+    /// Chosen since the last poll, then handed to the frame.
+    chosen: Vec<String>,
+    events: Vec<String>,
+    modifiers: ModifiersState,
+    widget: Option<MenuBarWidget>,
+    #[cfg(any(windows, target_os = "macos"))]
+    native: Option<native::NativeMenu>,
+}
+
+impl Menus {
+    pub(crate) fn new(bar: MenuBar) -> Self {
+        let checked = bar
+            .items()
+            .filter_map(|item| match item {
+                MenuItem::Item {
+                    id,
+                    checked: Some(checked),
+                    ..
+                } => Some((id.clone(), *checked)),
+                _ => None,
+            })
+            .collect();
+        Self {
+            bar,
+            checked,
+            checks_changed: false,
+            chosen: Vec::new(),
+            events: Vec::new(),
+            modifiers: ModifiersState::empty(),
+            widget: None,
+            #[cfg(any(windows, target_os = "macos"))]
+            native: None,
+        }
+    }
+
+    /// Shows the bar on `window`, natively where possible. macOS only shows a menu bar set up
+    /// once the event loop runs.
+    pub(crate) fn attach(&mut self, window: &Window, font_system: &mut FontSystem) {
+        if self.bar.menus.is_empty() {
+            return;
+        }
+        #[cfg(any(windows, target_os = "macos"))]
+        match native::NativeMenu::new(&self.bar, window) {
+            Ok(native) => {
+                self.native = Some(native);
+                return;
+            }
+            Err(error) => log::warn!("Drawing the menu bar in the window instead: {error}"),
+        }
+        let width = window.inner_size().width as f32;
+        self.widget = Some(MenuBarWidget::new(&self.bar, font_system, width));
+    }
+
+    /// The items chosen since the previous frame, by id.
+    pub fn events(&self) -> &[String] {
+        &self.events
+    }
+
+    pub fn is_checked(&self, id: &str) -> bool {
+        self.checked.get(id).copied().unwrap_or(false)
+    }
+
+    /// Sets the checkmark of the item `id`, if it has one.
+    pub fn set_checked(&mut self, id: &str, checked: bool) {
+        if let Some(check) = self.checked.get_mut(id) {
+            self.checks_changed |= *check != checked;
+            *check = checked;
+        }
+    }
+
+    /// The bar to draw in the window, when it isn't a native one.
+    pub fn widget(&self) -> Option<&MenuBarWidget> {
+        self.widget.as_ref()
+    }
+
+    pub(crate) fn resize(&mut self, width: u32) {
+        if let Some(widget) = &mut self.widget {
+            widget.width = width as f32;
+        }
+    }
+
+    /// Takes the items of this bar out of `native_events`, the ids chosen in native menus,
+    /// and hands everything chosen since the last call to the frame.
+    pub(crate) fn poll(&mut self, native_events: &mut Vec<String>) {
+        let (own, others): (Vec<String>, Vec<String>) = native_events
+            .drain(..)
+            .partition(|id| self.item(id).is_some());
+        *native_events = others;
+        for id in own {
+            self.choose(id);
+        }
+        self.events = std::mem::take(&mut self.chosen);
+    }
+
+    /// Shows what changed since the last call in the native menus.
+    pub(crate) fn apply(&mut self) {
+        if !std::mem::take(&mut self.checks_changed) {
+            return;
+        }
+        #[cfg(any(windows, target_os = "macos"))]
+        if let Some(native) = &self.native {
+            native.set_checked(&self.checked);
+        }
+    }
+
+    /// Handles accelerators and clicks on the widget, returning whether `event` was used.
+    pub(crate) fn on_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = *modifiers;
+                false
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } if self.handles_accelerators() => {
+                let pressed = Accelerator::new(self.modifiers, *key);
+                let id = self.bar.items().find_map(|item| match item {
+                    MenuItem::Item {
+                        id,
+                        accelerator: Some(accelerator),
+                        enabled: true,
+                        ..
+                    } if *accelerator == pressed => Some(id.clone()),
+                    _ => None,
+                });
+                match id {
+                    Some(id) => {
+                        self.choose(id);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                if let Some(widget) = &mut self.widget {
+                    widget.cursor = [position.x as f32, position.y as f32];
+                }
+                false
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                let Some(widget) = &mut self.widget else {
+                    return false;
+                };
+                match widget.click() {
+                    MenuClick::Missed => false,
+                    MenuClick::Handled => true,
+                    MenuClick::Chose(id) => {
+                        self.choose(id);
+                        true
+                    }
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Native menus on macOS see their shortcuts before the window does. Windows leaves them
+    /// to the window.
+    fn handles_accelerators(&self) -> bool {
+        self.widget.is_some() || cfg!(windows)
+    }
+
+    fn item(&self, id: &str) -> Option<&MenuItem> {
+        self.bar
+            .items()
+            .find(|item| matches!(item, MenuItem::Item { id: item_id, .. } if item_id == id))
+    }
+
+    fn choose(&mut self, id: String) {
+        if let Some(check) = self.checked.get_mut(&id) {
+            *check = !*check;
+            self.checks_changed = true;
+        }
+        self.chosen.push(id);
+    }
+}
+
+/// What a click on the widget did.
+enum MenuClick {
+    /// It was outside the bar and its open menu.
+    Missed,
+    /// It opened or closed a menu.
+    Handled,
+    Chose(String),
+}
+
+/// Text for the frame callback to draw over `MenuBarWidget::rects`, in the theme's `text`
+/// color.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MenuLabel {
+    pub text: String,
+    /// Top-left corner.
+    pub position: [f32; 2],
+    pub enabled: bool,
+}
+
+/// A box of the widget, by its top-left corner.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Slot {
+    position: [f32; 2],
+    size: [f32; 2],
+}
+
+impl Slot {
+    fn contains(&self, point: [f32; 2]) -> bool {
+        (0..2).all(|i| self.position[i] <= point[i] && point[i] < self.position[i] + self.size[i])
+    }
+
+    fn rect(&self, color: [f32; 4]) -> Rect {
+        Rect {
+            position: [0, 1].map(|i| self.position[i] + self.size[i] / 2.0),
+            size: self.size,
+            fill: Some(Fill::Solid { color }),
+            softness: 0.0,
+            ..Default::default()
+        }
+    }
+}
+
+/// A menu title in the bar, with its open menu below it.
+#[derive(Clone, Debug)]
+struct PlacedMenu {
+    title: Slot,
+    /// The open menu's background.
+    panel: Slot,
+    /// One slot per item, separators included.
+    items: Vec<Slot>,
+    /// Left edge of the accelerators, relative to the panel.
+    accelerator_x: f32,
+}
+
+/// A menu bar drawn in the window, in physical pixels from the window's top-left corner.
+///
+/// Laid out once when the window opens. Clicking a title opens its menu, clicking an item
+/// chooses it and clicking anywhere else closes the menu.
+#[derive(Clone, Debug)]
+pub struct MenuBarWidget {
+    bar: MenuBar,
+    menus: Vec<PlacedMenu>,
+    open: Option<usize>,
+    width: f32,
+    cursor: [f32; 2],
+}
+
+const FONT_SIZE: f32 = 14.0;
+const LINE_HEIGHT: f32 = 20.0;
+const PADDING: f32 = 8.0;
+const SEPARATOR_HEIGHT: f32 = 9.0;
+/// Room for the checkmark before item labels.
+const CHECK_WIDTH: f32 = 16.0;
+
+impl MenuBarWidget {
+    fn new(bar: &MenuBar, font_system: &mut FontSystem, width: f32) -> Self {
+        let metrics = Metrics::new(FONT_SIZE, LINE_HEIGHT);
+        let mut measure = |text: &str| text::measure(font_system, text, metrics)[0];
+        let item_height = LINE_HEIGHT + PADDING / 2.0;
+        let mut x = 0.0;
+        let menus = bar
+            .menus
+            .iter()
+            .map(|menu| {
+                let title = Slot {
+                    position: [x, 0.0],
+                    size: [measure(&menu.label) + 2.0 * PADDING, LINE_HEIGHT + PADDING],
+                };
+                x += title.size[0];
+
+                let (mut labels, mut accelerators) = (0.0f32, 0.0f32);
+                for item in &menu.items {
+                    if let MenuItem::Item {
+                        label, accelerator, ..
+                    } = item
+                    {
+                        labels = labels.max(measure(label));
+                        if let Some(accelerator) = accelerator {
+                            accelerators = accelerators.max(measure(&accelerator.label()));
+                        }
+                    }
+                }
+                let gap = if accelerators > 0.0 {
+                    3.0 * PADDING
+                } else {
+                    0.0
+                };
+                let panel_width = CHECK_WIDTH + labels + gap + accelerators + 2.0 * PADDING;
+
+                let mut y = title.size[1];
+                let items = menu
+                    .items
+                    .iter()
+                    .map(|item| {
+                        let height = match item {
+                            MenuItem::Item { .. } => item_height,
+                            MenuItem::Separator => SEPARATOR_HEIGHT,
+                        };
+                        let slot = Slot {
+                            position: [title.position[0], y],
+                            size: [panel_width, height],
+                        };
+                        y += height;
+                        slot
+                    })
+                    .collect();
+                PlacedMenu {
+                    title,
+                    panel: Slot {
+                        position: [title.position[0], title.size[1]],
+                        size: [panel_width, y - title.size[1]],
+                    },
+                    items,
+                    accelerator_x: panel_width - PADDING - accelerators,
+                }
+            })
+            .collect();
+        Self {
+            bar: bar.clone(),
+            menus,
+            open: None,
+            width,
+            cursor: [f32::NAN; 2],
+        }
+    }
+
+    /// Height of the bar, which content below it should leave free.
+    pub fn height(&self) -> f32 {
+        LINE_HEIGHT + PADDING
+    }
+
+    /// Whether a menu is open over the content.
+    pub fn is_open(&self) -> bool {
+        self.open.is_some()
+    }
+
+    /// The bar and the open menu in the theme's `bar` color, with the title or item under the
+    /// cursor in `hover`, and checkmarks and separators in `foreground`.
+    pub fn rects(&self, theme: &Theme) -> Vec<Rect> {
+        let bar = Slot {
+            position: [0.0, 0.0],
+            size: [self.width, self.height()],
+        };
+        let mut rects = vec![bar.rect(theme.bar)];
+        for (i, menu) in self.menus.iter().enumerate() {
+            if self.open == Some(i) || (self.open.is_none() && menu.title.contains(self.cursor)) {
+                rects.push(menu.title.rect(theme.hover));
+            }
+        }
+        let Some(open) = self.open else {
+            return rects;
+        };
+        let menu = &self.menus[open];
+        rects.push(menu.panel.rect(theme.bar));
+        for (slot, item) in menu.items.iter().zip(&self.bar.menus[open].items) {
+            match item {
+                MenuItem::Item {
+                    enabled, checked, ..
+                } => {
+                    if *enabled && slot.contains(self.cursor) {
+                        rects.push(slot.rect(theme.hover));
+                    }
+                    if *checked == Some(true) {
+                        let size = CHECK_WIDTH / 2.0;
+                        let check = Slot {
+                            position: [
+                                slot.position[0] + PADDING,
+                                slot.position[1] + (slot.size[1] - size) / 2.0,
+                            ],
+                            size: [size, size],
+                        };
+                        rects.push(Rect {
+                            border_radius: [size / 4.0; 4],
+                            ..check.rect(theme.foreground)
+                        });
+                    }
+                }
+                MenuItem::Separator => {
+                    let line = Slot {
+                        position: [
+                            slot.position[0] + PADDING,
+                            slot.position[1] + slot.size[1] / 2.0,
+                        ],
+                        size: [slot.size[0] - 2.0 * PADDING, 1.0],
+                    };
+                    rects.push(line.rect(theme.foreground));
+                }
+            }
+        }
+        rects
+    }
+
+    /// The menu titles, and the labels and accelerators of the open menu.
+    pub fn labels(&self) -> Vec<MenuLabel> {
+        let mut labels: Vec<MenuLabel> = self
+            .menus
+            .iter()
+            .zip(&self.bar.menus)
+            .map(|(placed, menu)| MenuLabel {
+                text: menu.label.clone(),
+                position: [
+                    placed.title.position[0] + PADDING,
+                    placed.title.position[1] + PADDING / 2.0,
+                ],
+                enabled: true,
+            })
+            .collect();
+        let Some(open) = self.open else {
+            return labels;
+        };
+        let placed = &self.menus[open];
+        for (slot, item) in placed.items.iter().zip(&self.bar.menus[open].items) {
+            let MenuItem::Item {
+                label,
+                accelerator,
+                enabled,
+                ..
+            } = item
+            else {
+                continue;
+            };
+            let y = slot.position[1] + PADDING / 4.0;
+            labels.push(MenuLabel {
+                text: label.clone(),
+                position: [slot.position[0] + PADDING + CHECK_WIDTH, y],
+                enabled: *enabled,
+            });
+            if let Some(accelerator) = accelerator {
+                labels.push(MenuLabel {
+                    text: accelerator.label(),
+                    position: [slot.position[0] + placed.accelerator_x, y],
+                    enabled: *enabled,
+                });
+            }
+        }
+        labels
+    }
+
+    fn click(&mut self) -> MenuClick {
+        let cursor = self.cursor;
+        if let Some(open) = self.open {
+            let placed = &self.menus[open];
+            if placed.panel.contains(cursor) {
+                let chosen = placed
+                    .items
+                    .iter()
+                    .zip(&self.bar.menus[open].items)
+                    .find(|(slot, _)| slot.contains(cursor))
+                    .and_then(|(_, item)| match item {
+                        MenuItem::Item {
+                            id, enabled: true, ..
+                        } => Some(id.clone()),
+                        _ => None,
+                    });
+                // Disabled items and separators leave the menu open.
+                return match chosen {
+                    Some(id) => {
+                        self.open = None;
+                        MenuClick::Chose(id)
+                    }
+                    None => MenuClick::Handled,
+                };
+            }
+        }
+        let title = self
+            .menus
+            .iter()
+            .position(|placed| placed.title.contains(cursor));
+        match title {
+            Some(i) => {
+                self.open = if self.open == Some(i) { None } else { Some(i) };
+                MenuClick::Handled
+            }
+            // Closing the open menu swallows the click, so it doesn't land on the content.
+            None if self.open.take().is_some() => MenuClick::Handled,
+            None => MenuClick::Missed,
+        }
+    }
+}
+
+#[cfg(any(windows, target_os = "macos"))]
+mod native {
+    use std::collections::HashMap;
+
+    use muda::{
+        accelerator::{Accelerator, Code, Modifiers},
+        CheckMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu,
+    };
+    use winit::{event::VirtualKeyCode, window::Window};
+
+    use super::MenuBar;
+
+    /// The menu bar as native menus, which stay up until dropped.
+    pub(super) struct NativeMenu {
+        _menu: Menu,
+        checks: Vec<CheckMenuItem>,
+    }
+
+    impl NativeMenu {
+        pub fn new(bar: &MenuBar, window: &Window) -> muda::Result<Self> {
+            let menu = Menu::new();
+            let mut checks = Vec::new();
+            for entries in &bar.menus {
+                let submenu = Submenu::new(&entries.label, true);
+                for item in &entries.items {
+                    match item {
+                        super::MenuItem::Item {
+                            id,
+                            label,
+                            accelerator,
+                            enabled,
+                            checked,
+                        } => {
+                            let accelerator = accelerator.and_then(convert);
+                            let id = id.as_str();
+                            if let Some(checked) = checked {
+                                let check = CheckMenuItem::with_id(
+                                    id,
+                                    label,
+                                    *enabled,
+                                    *checked,
+                                    accelerator,
+                                );
+                                submenu.append(&check)?;
+                                checks.push(check);
+                            } else {
+                                submenu.append(&MenuItem::with_id(
+                                    id,
+                                    label,
+                                    *enabled,
+                                    accelerator,
+                                ))?;
+                            }
+                        }
+                        super::MenuItem::Separator => {
+                            submenu.append(&PredefinedMenuItem::separator())?
+                        }
+                    }
+                }
+                menu.append(&submenu)?;
+            }
+            #[cfg(windows)]
+            {
+                use winit::platform::windows::WindowExtWindows;
+                menu.init_for_hwnd(window.hwnd())?;
+            }
+            #[cfg(target_os = "macos")]
+            {
+                let _ = window;
+                menu.init_for_nsapp();
+            }
+            Ok(Self {
+                _menu: menu,
+                checks,
+            })
+        }
+
+        pub fn set_checked(&self, checked: &HashMap<String, bool>) {
+            for check in &self.checks {
+                if let Some(&checked) = checked.get(&check.id().0) {
+                    check.set_checked(checked);
+                }
+            }
+        }
+    }
+
+    /// The native form of `accelerator`, or `None` with a warning for keys menus can't show.
+    fn convert(accelerator: super::Accelerator) -> Option<Accelerator> {
+        // Letters and digits are named `A` and `Key1` here, but `KeyA` and `Digit1` natively.
+        let key = format!("{:?}", accelerator.key);
+        let name = match (key.strip_prefix("Key"), accelerator.key) {
+            (Some(digit), _) => format!("Digit{digit}"),
+            (None, _) if key.len() == 1 => format!("Key{key}"),
+            (None, VirtualKeyCode::Back) => "Backspace".to_string(),
+            (None, VirtualKeyCode::Return) => "Enter".to_string(),
+            (None, VirtualKeyCode::Equals) => "Equal".to_string(),
+            (None, _) => key,
+        };
+        let code = name.parse::<Code>().ok();
+        let Some(code) = code else {
+            log::warn!("Menus can't show the shortcut {}", accelerator.label());
+            return None;
+        };
+        let mut modifiers = Modifiers::empty();
+        for (held, modifier) in [
+            (accelerator.modifiers.shift(), Modifiers::SHIFT),
+            (accelerator.modifiers.ctrl(), Modifiers::CONTROL),
+            (accelerator.modifiers.alt(), Modifiers::ALT),
+            (accelerator.modifiers.logo(), Modifiers::SUPER),
+        ] {
+            if held {
+                modifiers |= modifier;
+            }
+        }
+        Some(Accelerator::new(Some(modifiers), code))
+    }
+}
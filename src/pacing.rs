@@ -0,0 +1,98 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use instant::Instant;
+use winit::window::Window;
+
+/// Keeps the CPU from queueing more than a set number of frames ahead of the GPU, and
+/// measures how long each frame's submission takes to complete on the GPU. It also knows the
+/// monitor's refresh rate, to tell when a frame will be shown.
+pub(crate) struct FramePacer {
+    max_frames_in_flight: usize,
+    refresh_interval: Option<Duration>,
+    in_flight: VecDeque<wgpu::SubmissionIndex>,
+    latency: Arc<Mutex<Option<Duration>>>,
+}
+
+impl FramePacer {
+    pub fn new(max_frames_in_flight: usize) -> Self {
+        Self {
+            max_frames_in_flight: max_frames_in_flight.max(1),
+            refresh_interval: None,
+            in_flight: VecDeque::new(),
+            latency: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Call right after submitting a frame. Blocks until the oldest frames have finished if
+    /// too many are queued.
+    pub fn submitted(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        index: wgpu::SubmissionIndex,
+    ) {
+        let submitted_at = Instant::now();
+        let latency = self.latency.clone();
+        queue.on_submitted_work_done(move || {
+            *latency.lock().unwrap() = Some(submitted_at.elapsed());
+        });
+
+        self.in_flight.push_back(index);
+        while self.in_flight.len() > self.max_frames_in_flight {
+            let oldest = self.in_flight.pop_front().unwrap();
+            device.poll(wgpu::Maintain::WaitForSubmissionIndex(oldest));
+        }
+    }
+
+    /// Time between submitting the most recently completed frame and the GPU finishing it.
+    pub fn latency(&self) -> Option<Duration> {
+        *self.latency.lock().unwrap()
+    }
+
+    pub fn refresh_interval(&self) -> Option<Duration> {
+        self.refresh_interval
+    }
+
+    /// In hertz, `None` when the monitor's is unknown.
+    pub fn refresh_rate(&self) -> Option<f64> {
+        self.refresh_interval
+            .map(|interval| 1.0 / interval.as_secs_f64())
+    }
+
+    pub fn set_refresh_rate(&mut self, refresh_rate: Option<f64>) {
+        let interval = refresh_rate
+            .filter(|rate| rate.is_finite() && *rate > 0.0)
+            .map(|rate| Duration::from_secs_f64(1.0 / rate));
+        if interval != self.refresh_interval {
+            match interval {
+                Some(interval) => log::info!("Refreshing every {:?}", interval),
+                None => log::info!("Refresh rate unknown"),
+            }
+            self.refresh_interval = interval;
+        }
+    }
+
+    /// When a frame drawn `elapsed` after the start is shown with vsync: at the end of the
+    /// refresh interval it's drawn in. Animations timed by this advance by whole intervals, so
+    /// a second lasts exactly 120 frames at 120 Hz instead of jittering with when the event
+    /// loop happens to wake. `elapsed` itself while the refresh rate is unknown.
+    pub fn display_time(&self, elapsed: Duration) -> Duration {
+        match self.refresh_interval {
+            Some(interval) => {
+                let intervals = (elapsed.as_secs_f64() / interval.as_secs_f64()).ceil();
+                interval.mul_f64(intervals)
+            }
+            None => elapsed,
+        }
+    }
+}
+
+/// The refresh rate of the monitor `window` is on, in hertz, when the system reports one.
+pub(crate) fn monitor_refresh_rate(window: &Window) -> Option<f64> {
+    let millihertz = window.current_monitor()?.refresh_rate_millihertz()?;
+    Some(millihertz as f64 / 1000.0)
+}
@@ -0,0 +1,44 @@
+//! Conversions into the narrow vertex formats of `RendererConfig::packed_vertices`.
+
+/// The bits of `value` as an IEEE half float, rounded to nearest even. Finite values beyond
+/// the half float range saturate at its largest finite value instead of becoming infinite.
+pub(crate) fn f16_bits(value: f32) -> u16 {
+    const MAX: u16 = 0x7bff;
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+    if exponent == 0xff {
+        let nan = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7c00 | nan;
+    }
+
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | MAX;
+    }
+    let (half, rest, shift) = if exponent > 0 {
+        (
+            ((exponent as u32) << 10) | (mantissa >> 13),
+            mantissa & 0x1fff,
+            13,
+        )
+    } else if exponent >= -10 {
+        // Subnormal: the implicit leading bit becomes part of the mantissa.
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        (mantissa >> shift, mantissa & ((1 << shift) - 1), shift)
+    } else {
+        return sign;
+    };
+    let halfway = 1 << (shift - 1);
+    let round_up = rest > halfway || (rest == halfway && half & 1 == 1);
+    // Rounding can carry into the exponent, which is still the right result unless it
+    // carries all the way to infinity.
+    sign | ((half + round_up as u32) as u16).min(MAX)
+}
+
+/// `value` in 0..=1 as a normalized byte.
+pub(crate) fn unorm8(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
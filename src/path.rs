@@ -0,0 +1,253 @@
+//! Paths of lines and bezier curves, filled and stroked through the geo pipeline.
+
+use crate::{
+    geo::GeoVertex,
+    line::{self, LineCap, LineJoin, Polyline},
+    tessellate,
+};
+
+type Point = [f64; 2];
+
+/// The most segments a single curve is split into, however tight the tolerance.
+const MAX_CURVE_STEPS: usize = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PathCommand {
+    /// Starts a new subpath.
+    MoveTo(Point),
+    LineTo(Point),
+    /// A quadratic bezier: control point, then end point.
+    QuadTo(Point, Point),
+    /// A cubic bezier: two control points, then end point.
+    CubicTo(Point, Point, Point),
+    /// Joins the subpath back to where it started. Drawing on continues from there.
+    Close,
+}
+
+/// Subpaths of lines and bezier curves in world space, in the manner of SVG path data.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Path {
+    pub commands: Vec<PathCommand>,
+}
+
+impl Path {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn move_to(mut self, point: Point) -> Self {
+        self.commands.push(PathCommand::MoveTo(point));
+        self
+    }
+
+    pub fn line_to(mut self, point: Point) -> Self {
+        self.commands.push(PathCommand::LineTo(point));
+        self
+    }
+
+    pub fn quad_to(mut self, control: Point, point: Point) -> Self {
+        self.commands.push(PathCommand::QuadTo(control, point));
+        self
+    }
+
+    pub fn cubic_to(mut self, control_1: Point, control_2: Point, point: Point) -> Self {
+        self.commands
+            .push(PathCommand::CubicTo(control_1, control_2, point));
+        self
+    }
+
+    pub fn close(mut self) -> Self {
+        self.commands.push(PathCommand::Close);
+        self
+    }
+
+    /// Each subpath as points and whether it was closed, with curves split into straight
+    /// segments that stay within `tolerance` of them. Drawing without a `MoveTo` first starts
+    /// at the first point given. A subpath of a single point is left out, but one that only
+    /// draws back to where it started is kept, as in SVG.
+    pub fn flatten(&self, tolerance: f64) -> Vec<(Vec<Point>, bool)> {
+        let mut subpaths = vec![];
+        let mut current: Vec<Point> = vec![];
+        let mut pen: Option<Point> = None;
+        let mut finish = |current: &mut Vec<Point>, closed| {
+            if current.len() > 1 {
+                subpaths.push((std::mem::take(current), closed));
+            }
+            current.clear();
+        };
+        for command in &self.commands {
+            if current.is_empty() && !matches!(command, PathCommand::MoveTo(_)) {
+                // Drawing on after `Close`.
+                current.extend(pen);
+            }
+            match *command {
+                PathCommand::MoveTo(point) => {
+                    finish(&mut current, false);
+                    current.push(point);
+                    pen = Some(point);
+                }
+                PathCommand::LineTo(point) => {
+                    current.push(point);
+                    pen = Some(point);
+                }
+                PathCommand::QuadTo(control, point) => {
+                    let start = pen.unwrap_or(control);
+                    let bend = second_difference(start, control, point);
+                    let steps = curve_steps(bend / 4.0, tolerance);
+                    current.extend((1..=steps).map(|i| {
+                        let t = i as f64 / steps as f64;
+                        let u = 1.0 - t;
+                        bezier([u * u, 2.0 * u * t, t * t], [start, control, point])
+                    }));
+                    pen = Some(point);
+                }
+                PathCommand::CubicTo(control_1, control_2, point) => {
+                    let start = pen.unwrap_or(control_1);
+                    let bend = second_difference(start, control_1, control_2)
+                        .max(second_difference(control_1, control_2, point));
+                    let steps = curve_steps(bend * 3.0 / 4.0, tolerance);
+                    current.extend((1..=steps).map(|i| {
+                        let t = i as f64 / steps as f64;
+                        let u = 1.0 - t;
+                        bezier(
+                            [u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t],
+                            [start, control_1, control_2, point],
+                        )
+                    }));
+                    pen = Some(point);
+                }
+                PathCommand::Close => {
+                    pen = current.first().copied().or(pen);
+                    finish(&mut current, true);
+                }
+            }
+        }
+        finish(&mut current, false);
+        subpaths
+    }
+}
+
+/// Length of `a - 2b + c`, how far a curve's control polygon bends at `b`.
+fn second_difference(a: Point, b: Point, c: Point) -> f64 {
+    (a[0] - 2.0 * b[0] + c[0]).hypot(a[1] - 2.0 * b[1] + c[1])
+}
+
+/// Segments needed to keep a curve within `tolerance`, from the bound on how far a curve
+/// with that much bend strays from its chords.
+fn curve_steps(bend: f64, tolerance: f64) -> usize {
+    let steps = (bend / tolerance).sqrt().ceil();
+    if steps.is_nan() {
+        1
+    } else {
+        (steps as usize).clamp(1, MAX_CURVE_STEPS)
+    }
+}
+
+fn bezier<const N: usize>(weights: [f64; N], points: [Point; N]) -> Point {
+    let mut out = [0.0; 2];
+    for (weight, point) in weights.iter().zip(points) {
+        out[0] += weight * point[0];
+        out[1] += weight * point[1];
+    }
+    out
+}
+
+/// The outline drawn along a shape's path.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PathStroke {
+    /// In pixels, whatever the zoom.
+    pub width: f32,
+    pub color: [f32; 4],
+    pub cap: LineCap,
+    pub join: LineJoin,
+}
+
+/// A path with a fill, a stroke, or both.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shape {
+    pub path: Path,
+    /// Fills every subpath, closed or not, by the even-odd rule. This is exact as long as
+    /// subpaths don't cross each other or themselves.
+    pub fill: Option<[f32; 4]>,
+    /// Drawn over the fill. Only closed subpaths are joined back to their start.
+    pub stroke: Option<PathStroke>,
+    /// How far flattened curves may stray from the true ones, in world units.
+    pub tolerance: f64,
+}
+
+impl Default for Shape {
+    fn default() -> Self {
+        Self {
+            path: Path::default(),
+            fill: None,
+            stroke: None,
+            tolerance: 0.1,
+        }
+    }
+}
+
+/// Triangulates `shape`. Returns the origin the vertex positions are relative to, the
+/// vertices, and three indices per triangle.
+pub(crate) fn build_mesh(shape: &Shape) -> (Point, Vec<GeoVertex>, Vec<u32>) {
+    let subpaths = shape.path.flatten(shape.tolerance);
+    let origin = line::origin(
+        subpaths
+            .iter()
+            .flat_map(|(points, _)| points.iter().copied()),
+    );
+    let mut vertices = vec![];
+    let mut indices = vec![];
+
+    if let Some(color) = shape.fill {
+        let rings: Vec<&Vec<Point>> = subpaths
+            .iter()
+            .map(|(points, _)| points)
+            .filter(|points| points.len() >= 3)
+            .collect();
+        let inside = |ring: &Vec<Point>, other: &Vec<Point>| {
+            !std::ptr::eq(ring, other) && tessellate::contains(std::slice::from_ref(other), ring[0])
+        };
+        // Rings inside an odd number of others are holes in the innermost of those.
+        let depths: Vec<usize> = rings
+            .iter()
+            .map(|ring| rings.iter().filter(|other| inside(ring, other)).count())
+            .collect();
+        for (outline, &depth) in rings
+            .iter()
+            .zip(&depths)
+            .filter(|(_, depth)| *depth % 2 == 0)
+        {
+            let mut polygon = vec![(*outline).clone()];
+            polygon.extend(
+                rings
+                    .iter()
+                    .zip(&depths)
+                    .filter(|(hole, hole_depth)| **hole_depth == depth + 1 && inside(hole, outline))
+                    .map(|(hole, _)| (*hole).clone()),
+            );
+            let (points, triangles) = tessellate::fill(&polygon);
+            let base = vertices.len() as u32;
+            vertices.extend(points.iter().map(|&[x, y]| GeoVertex {
+                position: [(x - origin[0]) as f32, (y - origin[1]) as f32],
+                extrude: [0.0; 2],
+                color,
+            }));
+            indices.extend(triangles.iter().map(|i| base + i));
+        }
+    }
+
+    if let Some(stroke) = shape.stroke {
+        for (points, closed) in subpaths {
+            let line = Polyline {
+                points,
+                width: stroke.width,
+                color: stroke.color,
+                cap: stroke.cap,
+                join: stroke.join,
+                closed,
+            };
+            line::stroke(&line, origin, &mut vertices, &mut indices);
+        }
+    }
+    (origin, vertices, indices)
+}
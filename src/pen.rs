@@ -0,0 +1,231 @@
+use std::{collections::VecDeque, time::Duration};
+
+use instant::Instant;
+
+#[derive(Clone, Copy, Debug)]
+pub struct PenSample {
+    pub position: [f64; 2],
+    pub time: Instant,
+}
+
+/// Extrapolates where the pen will be a short time after the latest input sample.
+pub struct PenPredictor {
+    samples: VecDeque<PenSample>,
+    max_prediction: Duration,
+}
+
+impl PenPredictor {
+    /// How much of the estimated acceleration is used. Raw acceleration from noisy tablet
+    /// samples overshoots badly on direction changes.
+    const ACCELERATION_DAMPING: f64 = 0.5;
+
+    /// `max_prediction` caps how far ahead `predict` will extrapolate.
+    pub fn new(max_prediction: Duration) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(3),
+            max_prediction,
+        }
+    }
+
+    pub fn push(&mut self, sample: PenSample) {
+        if self.samples.len() == 3 {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Predicted position `ahead` after the latest sample, or `None` without enough samples.
+    pub fn predict(&self, ahead: Duration) -> Option<[f64; 2]> {
+        let t = ahead.min(self.max_prediction).as_secs_f64();
+        let latest = *self.samples.back()?;
+        let velocity = self.velocity(self.samples.len() - 1)?;
+        let acceleration = match self.samples.len() {
+            3 => {
+                let previous = self.velocity(1)?;
+                let dt = (latest.time - self.samples[1].time).as_secs_f64();
+                [
+                    (velocity[0] - previous[0]) / dt * Self::ACCELERATION_DAMPING,
+                    (velocity[1] - previous[1]) / dt * Self::ACCELERATION_DAMPING,
+                ]
+            }
+            _ => [0.0, 0.0],
+        };
+
+        Some([
+            latest.position[0] + velocity[0] * t + 0.5 * acceleration[0] * t * t,
+            latest.position[1] + velocity[1] * t + 0.5 * acceleration[1] * t * t,
+        ])
+    }
+
+    /// Velocity between sample `i - 1` and `i`.
+    fn velocity(&self, i: usize) -> Option<[f64; 2]> {
+        let (from, to) = (self.samples.get(i.checked_sub(1)?)?, self.samples.get(i)?);
+        let dt = (to.time - from.time).as_secs_f64();
+        if dt <= 0.0 {
+            return None;
+        }
+        Some([
+            (to.position[0] - from.position[0]) / dt,
+            (to.position[1] - from.position[1]) / dt,
+        ])
+    }
+}
+
+/// A stroke made of confirmed input points followed by a predicted tail. The tail is thrown
+/// away and recomputed whenever a real sample arrives, so mispredictions only live for a frame.
+pub struct PenStroke {
+    points: Vec<[f64; 2]>,
+    predicted: Vec<[f64; 2]>,
+    predictor: PenPredictor,
+}
+
+impl PenStroke {
+    pub fn new(max_prediction: Duration) -> Self {
+        Self {
+            points: vec![],
+            predicted: vec![],
+            predictor: PenPredictor::new(max_prediction),
+        }
+    }
+
+    pub fn push(&mut self, sample: PenSample) {
+        self.points.push(sample.position);
+        self.predicted.clear();
+        self.predictor.push(sample);
+    }
+
+    /// Replaces the predicted tail with `frames` points spaced `frame_time` apart.
+    pub fn predict(&mut self, frame_time: Duration, frames: u32) {
+        self.predicted = (1..=frames)
+            .filter_map(|frame| self.predictor.predict(frame_time * frame))
+            .collect();
+    }
+
+    pub fn confirmed(&self) -> &[[f64; 2]] {
+        &self.points
+    }
+
+    pub fn predicted(&self) -> &[[f64; 2]] {
+        &self.predicted
+    }
+
+    /// Confirmed points followed by the predicted tail.
+    pub fn points(&self) -> impl Iterator<Item = [f64; 2]> + '_ {
+        self.points.iter().chain(&self.predicted).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: Duration = Duration::from_millis(10);
+
+    /// Samples `STEP` apart at `positions`.
+    fn samples(positions: &[[f64; 2]]) -> Vec<PenSample> {
+        let start = Instant::now();
+        positions
+            .iter()
+            .zip(0..)
+            .map(|(&position, i)| PenSample {
+                position,
+                time: start + STEP * i,
+            })
+            .collect()
+    }
+
+    fn predictor(max_prediction: Duration, positions: &[[f64; 2]]) -> PenPredictor {
+        let mut predictor = PenPredictor::new(max_prediction);
+        for sample in samples(positions) {
+            predictor.push(sample);
+        }
+        predictor
+    }
+
+    fn assert_near(actual: [f64; 2], expected: [f64; 2]) {
+        assert!(
+            (actual[0] - expected[0]).abs() < 1e-6 && (actual[1] - expected[1]).abs() < 1e-6,
+            "{:?} isn't {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn constant_velocity_carries_on_in_a_straight_line() {
+        let positions = [[0.0, 0.0], [1.0, 2.0], [2.0, 4.0], [3.0, 6.0]];
+        // From two samples on, and once older ones drop out of the three kept.
+        for count in 2..=positions.len() {
+            let predictor = predictor(Duration::from_secs(1), &positions[..count]);
+            let [x, y] = positions[count - 1];
+            assert_near(predictor.predict(STEP).unwrap(), [x + 1.0, y + 2.0]);
+            assert_near(predictor.predict(STEP * 3).unwrap(), [x + 3.0, y + 6.0]);
+        }
+    }
+
+    #[test]
+    fn predicting_needs_two_samples_apart_in_time() {
+        assert_eq!(PenPredictor::new(STEP).predict(STEP), None);
+        assert_eq!(predictor(STEP, &[[1.0, 1.0]]).predict(STEP), None);
+
+        let mut predictor = predictor(STEP, &[[1.0, 1.0]]);
+        let latest = *predictor.samples.back().unwrap();
+        predictor.push(PenSample {
+            position: [2.0, 2.0],
+            ..latest
+        });
+        assert_eq!(predictor.predict(STEP), None);
+
+        predictor.clear();
+        assert_eq!(predictor.predict(STEP), None);
+    }
+
+    #[test]
+    fn predictions_stop_at_max_prediction() {
+        let predictor = predictor(STEP * 2, &[[0.0, 0.0], [1.0, 0.0], [3.0, 0.0]]);
+        let furthest = predictor.predict(STEP * 2).unwrap();
+        assert_eq!(predictor.predict(STEP * 50), Some(furthest));
+        assert!(predictor.predict(STEP).unwrap()[0] < furthest[0]);
+    }
+
+    #[test]
+    fn acceleration_is_damped() {
+        // Speeding up from 100 to 200 pixels a second: 10000 pixels a second squared.
+        let predictor = predictor(
+            Duration::from_secs(1),
+            &[[0.0, 0.0], [1.0, 0.0], [3.0, 0.0]],
+        );
+        let t = 0.01;
+        let damped = 0.5 * 10_000.0 * PenPredictor::ACCELERATION_DAMPING * t * t;
+        assert_near(predictor.predict(STEP).unwrap(), [3.0 + 2.0 + damped, 0.0]);
+    }
+
+    #[test]
+    fn new_samples_replace_the_predicted_tail() {
+        let mut stroke = PenStroke::new(Duration::from_secs(1));
+        let samples = samples(&[[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [2.0, 1.0]]);
+        for &sample in &samples[..3] {
+            stroke.push(sample);
+        }
+        stroke.predict(STEP, 2);
+        assert_eq!(stroke.predicted().len(), 2);
+        assert_near(stroke.predicted()[1], [4.0, 0.0]);
+        assert_eq!(stroke.points().count(), 5);
+
+        // The pen turned instead of carrying on, so the old tail is dropped and the next one
+        // heads the new way.
+        stroke.push(samples[3]);
+        assert!(stroke.predicted().is_empty());
+        assert_eq!(
+            stroke.confirmed(),
+            &[[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [2.0, 1.0]]
+        );
+        stroke.predict(STEP, 1);
+        let [x, y] = stroke.predicted()[0];
+        assert!(y > 1.0 && x < 2.0, "predicted {:?}", [x, y]);
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{
+    camera::Camera,
+    rect::{Fill, Rect},
+};
+
+/// How far an edge may be from a whole pixel before it counts as misaligned.
+const TOLERANCE: f32 = 1e-3;
+
+/// Draws the physical pixel grid over the frame.
+pub(crate) struct PixelGridPipeline {
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl PixelGridPipeline {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("pixel_grid.wgsl"));
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Pixel Grid Pipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Pixel Grid Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        Self { pipeline }
+    }
+
+    pub fn render<'rp>(&'rp self, render_pass: &mut wgpu::RenderPass<'rp>) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+/// Whether any edge of `rect` falls between physical pixels once `camera` is applied, which
+/// blurs it across two pixels. Layouts in logical pixels hit this at fractional scale factors.
+pub(crate) fn misaligned(rect: &Rect, camera: &Camera) -> bool {
+    let rect = camera.apply(rect).normalized();
+    [0, 1].into_iter().any(|axis| {
+        let half = rect.size[axis] / 2.0;
+        [rect.position[axis] - half, rect.position[axis] + half]
+            .into_iter()
+            .any(|edge| (edge - edge.round()).abs() > TOLERANCE)
+    })
+}
+
+/// Screen-space outlines around every misaligned rect, snapped outwards to whole pixels so
+/// the outlines themselves are sharp.
+pub(crate) fn highlights(rects: &[Rect], camera: &Camera) -> Vec<Rect> {
+    let color = [1.0, 0.0, 1.0, 0.9];
+    let mut outlines = vec![];
+    // Only edges parallel to the pixel grid can line up with it, so rotated rects are left out.
+    for rect in rects
+        .iter()
+        .filter(|rect| !rect.is_empty() && rect.rotation == 0.0)
+    {
+        if !misaligned(rect, camera) {
+            continue;
+        }
+        let rect = camera.apply(rect).normalized();
+        let min = [0, 1].map(|axis| (rect.position[axis] - rect.size[axis] / 2.0).floor() - 1.0);
+        let max = [0, 1].map(|axis| (rect.position[axis] + rect.size[axis] / 2.0).ceil() + 1.0);
+        let size = [max[0] - min[0], max[1] - min[1]];
+        let edges = [
+            ([min[0] + size[0] / 2.0, min[1] + 0.5], [size[0], 1.0]),
+            ([min[0] + size[0] / 2.0, max[1] - 0.5], [size[0], 1.0]),
+            ([min[0] + 0.5, min[1] + size[1] / 2.0], [1.0, size[1]]),
+            ([max[0] - 0.5, min[1] + size[1] / 2.0], [1.0, size[1]]),
+        ];
+        outlines.extend(edges.map(|(position, size)| Rect {
+            position,
+            size,
+            fill: Some(Fill::Solid { color }),
+            softness: 0.0,
+            ..Default::default()
+        }));
+    }
+    outlines
+}
@@ -0,0 +1,21 @@
+// Full-screen overlay marking the physical pixel grid.
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+	// One triangle that covers the whole screen.
+	let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+	return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+	// position.xy is the pixel center, so flooring gives the pixel's index.
+	let pixel = vec2<i32>(floor(position.xy));
+	// A faint checkerboard makes each individual pixel visible.
+	var alpha = select(0.0, 0.06, ((pixel.x + pixel.y) & 1) == 0);
+	// Every 8th row and column is stronger so distances can be counted.
+	if (pixel.x % 8 == 0 || pixel.y % 8 == 0) {
+		alpha = 0.15;
+	}
+	return vec4<f32>(1.0, 1.0, 1.0, alpha);
+}
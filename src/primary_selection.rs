@@ -0,0 +1,100 @@
+//! The primary selection of X11 and Wayland for egui's text fields: selecting text copies it
+//! there, and a middle click pastes it into the focused field at its caret. The regular
+//! clipboard is left to egui-winit, as before.
+//!
+//! Wayland compositors are reached through the data-control protocol, which wlroots-based ones
+//! and KDE offer; elsewhere the selection is shared through XWayland.
+
+use arboard::{Clipboard, GetExtLinux, LinuxClipboardKind, SetExtLinux};
+use winit::event::{ElementState, MouseButton, WindowEvent};
+
+pub(crate) struct PrimarySelection {
+    clipboard: Clipboard,
+    /// The focused text field and its selected character range, as of the last frame.
+    selection: Option<(egui::Id, [usize; 2])>,
+    paste: Option<String>,
+    /// Whether the next frame asks egui for the selected text.
+    copy: bool,
+    /// Whether the current frame did, so the copied text is ours instead of the clipboard's.
+    copying: bool,
+}
+
+impl PrimarySelection {
+    /// `None` without a display server to share the selection with.
+    pub fn new() -> Option<Self> {
+        match Clipboard::new() {
+            Ok(clipboard) => Some(Self {
+                clipboard,
+                selection: None,
+                paste: None,
+                copy: false,
+                copying: false,
+            }),
+            Err(error) => {
+                log::warn!("The primary selection isn't available: {error}");
+                None
+            }
+        }
+    }
+
+    /// Takes a middle click while a text field has focus as a paste. Returns whether `event`
+    /// was used.
+    pub fn on_event(&mut self, context: &egui::Context, event: &WindowEvent) -> bool {
+        let WindowEvent::MouseInput {
+            state: ElementState::Pressed,
+            button: MouseButton::Middle,
+            ..
+        } = event
+        else {
+            return false;
+        };
+        if !context.wants_keyboard_input() || !context.is_pointer_over_area() {
+            return false;
+        }
+        let get = self.clipboard.get().clipboard(LinuxClipboardKind::Primary);
+        match get.text() {
+            Ok(text) if !text.is_empty() => self.paste = Some(text),
+            Ok(_) => {}
+            Err(error) => log::warn!("Can't read the primary selection: {error}"),
+        }
+        true
+    }
+
+    /// Adds the pending paste and the request for the selected text to the frame's input.
+    pub fn begin_frame(&mut self, input: &mut egui::RawInput) {
+        if let Some(text) = self.paste.take() {
+            input.events.push(egui::Event::Paste(text));
+        }
+        // A copy of the user's own goes to the clipboard as usual.
+        let copying = input
+            .events
+            .iter()
+            .any(|event| matches!(event, egui::Event::Copy | egui::Event::Cut));
+        self.copying = std::mem::take(&mut self.copy) && !copying;
+        if self.copying {
+            input.events.push(egui::Event::Copy);
+        }
+    }
+
+    /// Moves the text copied for the selection out of `output`, and notices when the focused
+    /// field's selection changes, to copy it in the next frame.
+    pub fn end_frame(&mut self, context: &egui::Context, output: &mut egui::PlatformOutput) {
+        if std::mem::take(&mut self.copying) && !output.copied_text.is_empty() {
+            let text = std::mem::take(&mut output.copied_text);
+            let set = self.clipboard.set().clipboard(LinuxClipboardKind::Primary);
+            if let Err(error) = set.text(text) {
+                log::warn!("Can't set the primary selection: {error}");
+            }
+        }
+
+        let selection = context.memory(|memory| memory.focus()).and_then(|id| {
+            let range = egui::TextEdit::load_state(context, id)?.ccursor_range()?;
+            let [start, end] = [range.primary.index, range.secondary.index];
+            Some((id, [start.min(end), start.max(end)]))
+        });
+        if selection != self.selection {
+            self.copy = selection.is_some_and(|(_, [start, end])| start < end);
+            self.selection = selection;
+        }
+    }
+}
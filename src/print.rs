@@ -0,0 +1,234 @@
+//! Printing the current view, as a PDF with one image per page.
+//!
+//! The view is rendered again offscreen at the print resolution, in tiles the GPU can hold,
+//! so rects, plots and text stay sharp on paper instead of being the window's pixels scaled
+//! up. A view larger than a page is split over several, row by row. egui, the pixel grid, the
+//! inspector, the theme editor and the title bar aren't printed.
+
+use std::{
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Points per inch, the unit of PDF page sizes.
+const POINTS_PER_INCH: f32 = 72.0;
+
+/// How the view is laid out on paper. Sizes are in points, 1/72 of an inch.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PrintConfig {
+    /// Resolution the view is rendered at.
+    pub dpi: f32,
+    pub page_size: [f32; 2],
+    /// Left blank on every side of the page.
+    pub margin: f32,
+}
+
+impl PrintConfig {
+    pub const A4: [f32; 2] = [595.0, 842.0];
+    pub const LETTER: [f32; 2] = [612.0, 792.0];
+
+    /// Pixels of the printable area of a page.
+    pub(crate) fn page_pixels(&self) -> [u32; 2] {
+        self.page_size
+            .map(|side| ((side - 2.0 * self.margin) / POINTS_PER_INCH * self.dpi).max(1.0) as u32)
+    }
+}
+
+impl Default for PrintConfig {
+    fn default() -> Self {
+        Self {
+            dpi: 300.0,
+            page_size: Self::A4,
+            margin: 36.0,
+        }
+    }
+}
+
+/// A printed page, as tightly packed RGB pixels of at most `PrintConfig::page_pixels`.
+#[derive(Clone, Debug)]
+pub struct Page {
+    pub size: [u32; 2],
+    pub pixels: Vec<u8>,
+}
+
+/// A print requested with `Frame::export_pdf` or `Frame::print`.
+#[derive(Clone, Debug)]
+pub(crate) struct PrintJob {
+    /// Where to write the PDF, or `None` to print it.
+    pub path: Option<PathBuf>,
+    pub config: PrintConfig,
+}
+
+/// Cuts an RGB image of `size` into pages of at most `page` pixels, row by row.
+pub(crate) fn paginate(pixels: &[u8], size: [u32; 2], page: [u32; 2]) -> Vec<Page> {
+    let mut pages = Vec::new();
+    for top in (0..size[1]).step_by(page[1] as usize) {
+        for left in (0..size[0]).step_by(page[0] as usize) {
+            let page_size = [page[0].min(size[0] - left), page[1].min(size[1] - top)];
+            let row_bytes = page_size[0] as usize * 3;
+            let mut page_pixels = Vec::with_capacity(row_bytes * page_size[1] as usize);
+            for row in top..top + page_size[1] {
+                let start = (row as usize * size[0] as usize + left as usize) * 3;
+                page_pixels.extend_from_slice(&pixels[start..start + row_bytes]);
+            }
+            pages.push(Page {
+                size: page_size,
+                pixels: page_pixels,
+            });
+        }
+    }
+    pages
+}
+
+/// Writes `pages` as a PDF, each image in the top-left corner of the printable area.
+pub fn write_pdf(writer: impl Write, pages: &[Page], config: &PrintConfig) -> io::Result<()> {
+    let mut w = Counting {
+        inner: writer,
+        written: 0,
+    };
+    // The comment of high bytes tells readers the file is binary.
+    w.write_all(b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n")?;
+
+    // The catalog and page tree come first, then a page, its content and its image for every
+    // page.
+    let object_count = 2 + 3 * pages.len();
+    let mut offsets = Vec::with_capacity(object_count);
+    let page_id = |i: usize| 3 + 3 * i;
+
+    offsets.push(w.written);
+    w.write_all(b"1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n")?;
+    offsets.push(w.written);
+    let kids: Vec<String> = (0..pages.len())
+        .map(|i| format!("{} 0 R", page_id(i)))
+        .collect();
+    write!(
+        w,
+        "2 0 obj\n<< /Type /Pages /Kids [{}] /Count {} >>\nendobj\n",
+        kids.join(" "),
+        pages.len()
+    )?;
+
+    let [page_width, page_height] = config.page_size;
+    for (i, page) in pages.iter().enumerate() {
+        let id = page_id(i);
+        offsets.push(w.written);
+        write!(
+            w,
+            "{id} 0 obj\n<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {page_width} {page_height}] \
+             /Resources << /XObject << /Im0 {} 0 R >> >> /Contents {} 0 R >>\nendobj\n",
+            id + 2,
+            id + 1,
+        )?;
+
+        let [width, height] = page
+            .size
+            .map(|side| side as f32 * POINTS_PER_INCH / config.dpi);
+        let top = page_height - config.margin - height;
+        let content = format!(
+            "q {width} 0 0 {height} {} {top} cm /Im0 Do Q",
+            config.margin
+        );
+        offsets.push(w.written);
+        write!(
+            w,
+            "{} 0 obj\n<< /Length {} >>\nstream\n{content}\nendstream\nendobj\n",
+            id + 1,
+            content.len()
+        )?;
+
+        let data = run_length(&page.pixels);
+        offsets.push(w.written);
+        write!(
+            w,
+            "{} 0 obj\n<< /Type /XObject /Subtype /Image /Width {} /Height {} \
+             /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /RunLengthDecode \
+             /Length {} >>\nstream\n",
+            id + 2,
+            page.size[0],
+            page.size[1],
+            data.len()
+        )?;
+        w.write_all(&data)?;
+        w.write_all(b"\nendstream\nendobj\n")?;
+    }
+
+    let xref = w.written;
+    write!(w, "xref\n0 {}\n0000000000 65535 f \n", object_count + 1)?;
+    for offset in offsets {
+        // Every entry is exactly 20 bytes, line break included.
+        write!(w, "{offset:010} 00000 n \n")?;
+    }
+    write!(
+        w,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+        object_count + 1
+    )?;
+    w.flush()
+}
+
+/// Opens the PDF at `path` in the system's viewer, whose print dialog takes it from there.
+/// Applications can't open a print dialog for a file of their own on every platform.
+pub(crate) fn open_in_viewer(path: &Path) -> io::Result<()> {
+    let mut command = if cfg!(windows) {
+        let mut command = std::process::Command::new("cmd");
+        // The empty argument is the title `start` would otherwise take the path for.
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    command.arg(path).spawn().map(drop)
+}
+
+/// PDF's `RunLengthDecode` encoding: runs of a repeated byte and stretches of literal bytes,
+/// each at most 128 long. Backgrounds and filled rects shrink to a fraction of their size.
+fn run_length(data: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(data.len() / 4);
+    let mut i = 0;
+    while i < data.len() {
+        let run = data[i..]
+            .iter()
+            .take(128)
+            .take_while(|&&byte| byte == data[i])
+            .count();
+        if run > 1 {
+            encoded.push((257 - run) as u8);
+            encoded.push(data[i]);
+            i += run;
+        } else {
+            // Up to the next repeated byte, which starts a run of its own.
+            let start = i;
+            while i < data.len()
+                && i - start < 128
+                && (i + 1 == data.len() || data[i] != data[i + 1])
+            {
+                i += 1;
+            }
+            encoded.push((i - start - 1) as u8);
+            encoded.extend_from_slice(&data[start..i]);
+        }
+    }
+    // End of data.
+    encoded.push(128);
+    encoded
+}
+
+/// Keeps track of the byte offsets the cross-reference table needs.
+struct Counting<W> {
+    inner: W,
+    written: usize,
+}
+
+impl<W: Write> Write for Counting<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.written += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
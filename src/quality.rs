@@ -0,0 +1,144 @@
+//! Lowering the quality of what is drawn while frames take longer than their budget, and
+//! raising it again once there's headroom, so a throttled or busy GPU keeps up with the display
+//! instead of dropping frames.
+//!
+//! A frame's time is what it costs the renderer: the CPU time spent drawing it, without the
+//! wait for a swapchain image, or the GPU's time to finish it, whichever is longer.
+
+use std::{collections::VecDeque, time::Duration};
+
+use crate::rect::Rect;
+
+/// How much optional detail is drawn. The renderer gives up its rect shadows, which cover
+/// the most pixels for the least visible change; the frame callback sees the level as
+/// `Frame::quality` to scale its own detail, such as particle counts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Quality {
+    /// Rect shadows are left out.
+    Low,
+    /// Rect shadows are drawn with half their blur, and so cover fewer pixels around the rect.
+    Medium,
+    #[default]
+    High,
+}
+
+impl Quality {
+    fn lower(self) -> Option<Self> {
+        match self {
+            Quality::Low => None,
+            Quality::Medium => Some(Quality::Low),
+            Quality::High => Some(Quality::Medium),
+        }
+    }
+
+    fn higher(self) -> Option<Self> {
+        match self {
+            Quality::Low => Some(Quality::Medium),
+            Quality::Medium => Some(Quality::High),
+            Quality::High => None,
+        }
+    }
+
+    /// Takes the detail this level leaves out from `rects`, about to be drawn.
+    pub(crate) fn apply(self, rects: &mut [Rect]) {
+        match self {
+            Quality::High => {}
+            Quality::Medium => {
+                for shadow in rects.iter_mut().filter_map(|rect| rect.shadow.as_mut()) {
+                    shadow.blur /= 2.0;
+                }
+            }
+            Quality::Low => {
+                for rect in rects {
+                    rect.shadow = None;
+                }
+            }
+        }
+    }
+}
+
+/// When the governor changes the quality. Lowering it is quick and raising it slow, so a
+/// level that only just keeps up isn't left again right away.
+#[derive(Clone, Debug)]
+pub struct GovernorConfig {
+    /// The time a frame may take. The monitor's refresh interval by default, or a 60th of a
+    /// second where it's unknown.
+    pub budget: Option<Duration>,
+    /// The lowest quality to go down to.
+    pub min_quality: Quality,
+    /// How many of the latest frames are looked at to lower the quality.
+    pub window: usize,
+    /// How many frames of the window have to miss the budget to lower the quality.
+    pub misses_to_lower: usize,
+    /// How many frames in a row have to take less than `headroom` of the budget to raise the
+    /// quality again.
+    pub frames_to_raise: u32,
+    pub headroom: f32,
+}
+
+impl Default for GovernorConfig {
+    fn default() -> Self {
+        Self {
+            budget: None,
+            min_quality: Quality::Low,
+            window: 60,
+            misses_to_lower: 20,
+            frames_to_raise: 300,
+            headroom: 0.6,
+        }
+    }
+}
+
+pub(crate) struct Governor {
+    config: GovernorConfig,
+    quality: Quality,
+    /// Whether each of the latest frames missed the budget, oldest first.
+    recent: VecDeque<bool>,
+    fast_frames: u32,
+}
+
+impl Governor {
+    pub fn new(config: GovernorConfig) -> Self {
+        Self {
+            config,
+            quality: Quality::High,
+            recent: VecDeque::new(),
+            fast_frames: 0,
+        }
+    }
+
+    pub fn quality(&self) -> Quality {
+        self.quality
+    }
+
+    /// Records a frame's time against the configured budget, or `budget` without one.
+    /// Returns the new quality when it changes.
+    pub fn record(&mut self, frame_time: Duration, budget: Duration) -> Option<Quality> {
+        let budget = self.config.budget.unwrap_or(budget);
+        self.recent.push_back(frame_time > budget);
+        if self.recent.len() > self.config.window {
+            self.recent.pop_front();
+        }
+        if frame_time.as_secs_f32() < budget.as_secs_f32() * self.config.headroom {
+            self.fast_frames = self.fast_frames.saturating_add(1);
+        } else {
+            self.fast_frames = 0;
+        }
+
+        let misses = self.recent.iter().filter(|missed| **missed).count();
+        let quality = if misses >= self.config.misses_to_lower.max(1) {
+            self.quality
+                .lower()
+                .filter(|quality| *quality >= self.config.min_quality)
+        } else if self.fast_frames >= self.config.frames_to_raise {
+            self.quality.higher()
+        } else {
+            None
+        }?;
+        // The frames so far were drawn at the old level, so they say nothing about the new one.
+        self.recent.clear();
+        self.fast_frames = 0;
+        self.quality = quality;
+        Some(quality)
+    }
+}
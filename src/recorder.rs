@@ -0,0 +1,91 @@
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+};
+
+/// Receives every rendered frame while a recording is running.
+pub trait FrameSink {
+    /// `rgba` holds `width * height` tightly packed RGBA8 pixels, top row first.
+    fn write_frame(&mut self, width: u32, height: u32, rgba: &[u8]) -> io::Result<()>;
+
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// What `Frame::start_recording` and `Frame::stop_recording` asked for.
+pub(crate) enum Recording {
+    Start(Box<dyn FrameSink>),
+    Stop,
+}
+
+/// Pipes raw frames into an external `ffmpeg` process that encodes them to a video file.
+pub struct FfmpegSink {
+    output: PathBuf,
+    fps: u32,
+    process: Option<(Child, [u32; 2])>,
+}
+
+impl FfmpegSink {
+    pub fn new(output: impl Into<PathBuf>, fps: u32) -> Self {
+        Self {
+            output: output.into(),
+            fps,
+            process: None,
+        }
+    }
+
+    fn spawn(&self, width: u32, height: u32) -> io::Result<Child> {
+        Command::new("ffmpeg")
+            .args(["-y", "-loglevel", "error"])
+            .args(["-f", "rawvideo", "-pix_fmt", "rgba"])
+            .args(["-s", &format!("{}x{}", width, height)])
+            .args(["-r", &self.fps.to_string()])
+            .args(["-i", "-"])
+            .args(["-pix_fmt", "yuv420p"])
+            .arg(&self.output)
+            .stdin(Stdio::piped())
+            .spawn()
+    }
+}
+
+impl FrameSink for FfmpegSink {
+    fn write_frame(&mut self, width: u32, height: u32, rgba: &[u8]) -> io::Result<()> {
+        if self.process.is_none() {
+            self.process = Some((self.spawn(width, height)?, [width, height]));
+        }
+        let (child, size) = self.process.as_mut().unwrap();
+
+        // Raw video input has a fixed frame size, so frames from a resized window are dropped.
+        if *size != [width, height] {
+            log::warn!(
+                "Skipping {}x{} frame, recording is {}x{}",
+                width,
+                height,
+                size[0],
+                size[1]
+            );
+            return Ok(());
+        }
+
+        match &mut child.stdin {
+            Some(stdin) => stdin.write_all(rgba),
+            None => Err(io::ErrorKind::BrokenPipe.into()),
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if let Some((mut child, _)) = self.process.take() {
+            drop(child.stdin.take());
+            child.wait()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for FfmpegSink {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
@@ -0,0 +1,80 @@
+//! Noticing a lost device, so the renderer can start over on a new one instead of panicking
+//! in wgpu's default error handler. Devices are lost when the GPU resets, its driver is
+//! updated, or an external GPU is unplugged.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    future::Future,
+    pin::pin,
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+};
+
+/// Set once the device reports itself lost.
+#[derive(Clone, Default)]
+pub(crate) struct DeviceLoss(Arc<AtomicBool>);
+
+impl DeviceLoss {
+    /// Watches `device` for its loss. Other errors still panic, as they would without a
+    /// handler.
+    pub fn watch(device: &wgpu::Device) -> Self {
+        let loss = Self::default();
+        let lost = loss.0.clone();
+        device.on_uncaptured_error(Box::new(move |error| {
+            if is_device_lost(&error) {
+                if !lost.swap(true, Ordering::Relaxed) {
+                    log::error!("The device was lost: {}", error);
+                }
+            } else {
+                panic!("wgpu error: {}", error);
+            }
+        }));
+        loss
+    }
+
+    pub fn is_lost(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// wgpu only tells a lost device apart in the message of the errors that follow it.
+fn is_device_lost(error: &wgpu::Error) -> bool {
+    match error {
+        wgpu::Error::Validation { description, .. } => {
+            let description = description.to_lowercase();
+            description.contains("device is lost") || description.contains("device lost")
+        }
+        _ => false,
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+struct ThreadWaker(Thread);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Runs `future` on the current thread. The event loop already runs inside the async
+/// runtime's `block_on`, which can't be entered again. Requesting an adapter and a device
+/// completes right away on native backends anyway. Browsers can't park their one thread, so
+/// there is no recovery on wasm32.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
@@ -1,6 +1,1299 @@
-struct Rectangle {
-    x: u32,
-    y: u32,
-    w: u32,
-    h: u32,
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use bytemuck::Zeroable;
+use wgpu::util::DeviceExt;
+
+use crate::{
+    camera::Camera,
+    image::AtlasLayout,
+    pack::f16_bits,
+    resources::{ResourceScope, Tracked},
+    write_growing,
+};
+
+/// The most stops a gradient can have; the shader interpolates between a fixed number of
+/// colors per style.
+pub const MAX_GRADIENT_STOPS: usize = 4;
+
+/// The most layers of detail `Fill::Noise` adds up; finer ones would be smaller than a pixel
+/// at any sensible scale.
+pub const MAX_NOISE_OCTAVES: u32 = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GradientStop {
+    /// Position along the gradient, from 0 to 1.
+    pub offset: f32,
+    pub color: [f32; 4],
+}
+
+/// Up to `MAX_GRADIENT_STOPS` stops, sorted by offset.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GradientStops {
+    stops: [GradientStop; MAX_GRADIENT_STOPS],
+    len: usize,
+}
+
+impl GradientStops {
+    /// Sorts `stops` by offset, keeping the first `MAX_GRADIENT_STOPS`.
+    pub fn new(stops: &[GradientStop]) -> Self {
+        if stops.len() > MAX_GRADIENT_STOPS && cfg!(debug_assertions) {
+            log::warn!(
+                "Gradient has {} stops, only the first {} are drawn",
+                stops.len(),
+                MAX_GRADIENT_STOPS
+            );
+        }
+        let mut sorted = [GradientStop {
+            offset: 0.0,
+            color: [0.0; 4],
+        }; MAX_GRADIENT_STOPS];
+        let len = stops.len().min(MAX_GRADIENT_STOPS);
+        sorted[..len].copy_from_slice(&stops[..len]);
+        sorted[..len].sort_by(|a, b| a.offset.total_cmp(&b.offset));
+        Self { stops: sorted, len }
+    }
+
+    pub fn as_slice(&self) -> &[GradientStop] {
+        &self.stops[..self.len]
+    }
+
+    /// Every slot filled, repeating the last stop, so the shader can always interpolate
+    /// across all of them. A gradient without stops is transparent.
+    fn padded(&self) -> [GradientStop; MAX_GRADIENT_STOPS] {
+        let mut stops = self.stops;
+        if let Some(last) = self.as_slice().last() {
+            stops[self.len..].fill(*last);
+        }
+        stops
+    }
+}
+
+/// How the inside of a rect is colored. Gradient centers are relative to the rect's center,
+/// and like radii they are in the same units as its size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fill {
+    Solid {
+        color: [f32; 4],
+    },
+    /// Colors by distance from `center`, reaching the last stop at `radius`.
+    Radial {
+        center: [f32; 2],
+        radius: f32,
+        stops: GradientStops,
+    },
+    /// Colors by angle around `center`, sweeping once around from `start_angle`. Angles are
+    /// in radians and turn from the x axis towards the y axis.
+    Conic {
+        center: [f32; 2],
+        start_angle: f32,
+        stops: GradientStops,
+    },
+    /// An image from `Scene::images` or `Frame::add_image`, stretched over the rect and
+    /// multiplied by `tint`. An unknown image draws the tint alone.
+    Image {
+        id: u64,
+        tint: [f32; 4],
+    },
+    /// An image repeated across the rect in tiles of `tile_size`, for hatching, stripes and
+    /// textured panels. The tiles start at the rect's center moved by `offset`, and are turned
+    /// around that point by `rotation` in radians on top of the rect's own. Only whole pixels
+    /// of the image are repeated, so one meant to tile has no padding at its edges.
+    Pattern {
+        id: u64,
+        tile_size: [f32; 2],
+        offset: [f32; 2],
+        rotation: f32,
+        tint: [f32; 4],
+    },
+    /// Value noise from 0 to 1 colored by `stops`, for subtle material textures such as paper
+    /// or stone without an image. `scale` is the size of the coarsest features; each of the
+    /// `octaves`, from 1 to `MAX_NOISE_OCTAVES`, adds detail at half the size and half the
+    /// strength of the one before. The noise is laid out from the rect's center moved by
+    /// `offset`, so rects sharing a fill look alike unless their offsets differ.
+    Noise {
+        scale: f32,
+        octaves: u32,
+        offset: [f32; 2],
+        stops: GradientStops,
+    },
+}
+
+impl Fill {
+    /// The fill with its lengths scaled by `factor`, as when zooming.
+    pub(crate) fn scaled(self, factor: f32) -> Fill {
+        match self {
+            Fill::Solid { .. } | Fill::Image { .. } => self,
+            Fill::Radial {
+                center,
+                radius,
+                stops,
+            } => Fill::Radial {
+                center: center.map(|v| v * factor),
+                radius: radius * factor,
+                stops,
+            },
+            Fill::Conic {
+                center,
+                start_angle,
+                stops,
+            } => Fill::Conic {
+                center: center.map(|v| v * factor),
+                start_angle,
+                stops,
+            },
+            Fill::Pattern {
+                id,
+                tile_size,
+                offset,
+                rotation,
+                tint,
+            } => Fill::Pattern {
+                id,
+                tile_size: tile_size.map(|v| v * factor),
+                offset: offset.map(|v| v * factor),
+                rotation,
+                tint,
+            },
+            Fill::Noise {
+                scale,
+                octaves,
+                offset,
+                stops,
+            } => Fill::Noise {
+                scale: scale * factor,
+                octaves,
+                offset: offset.map(|v| v * factor),
+                stops,
+            },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Stroke {
+    pub color: [f32; 3],
+    pub width: f32,
+}
+
+/// A soft shadow cast by a rect, drawn behind it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Shadow {
+    /// Offset from the rect, in the same units as its position.
+    pub offset: [f32; 2],
+    /// Width of the soft falloff, centered on the shadow's edge.
+    pub blur: f32,
+    /// Grows the shadow on every side, or shrinks it when negative.
+    pub spread: f32,
+    pub color: [f32; 4],
+}
+
+/// A rounded rectangle. `position` is the center, in physical pixels.
+///
+/// `border_radius` holds one radius per corner, clockwise from the corner with the smallest
+/// coordinates: top-left, top-right, bottom-right, bottom-left with `Origin::TopLeft`.
+///
+/// `rotation` turns the rect around its center, in radians from the x axis towards the y axis.
+/// Corners, gradients and images turn with it; the shadow offset doesn't.
+///
+/// A negative size mirrors the rect around its center, so it is drawn with the absolute size.
+/// Rects with a zero or non-finite size or position produce no geometry.
+///
+/// `clip` cuts the rect and its shadow off outside an area, e.g. the bounds of a scrolling
+/// panel, including for hit testing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    pub border_radius: [f32; 4],
+    pub fill: Option<Fill>,
+    pub stroke: Option<Stroke>,
+    pub shadow: Option<Shadow>,
+    pub rotation: f32,
+    /// Rects with a higher `z_index` are drawn over lower ones, whatever their draw order.
+    /// Equal ones keep it.
+    pub z_index: f32,
+    pub softness: f32,
+    pub clip: Option<ClipRect>,
+}
+
+/// An empty rect at the origin, with no fill, stroke or shadow and a pixel of softness, to
+/// spell out only what differs from it.
+impl Default for Rect {
+    fn default() -> Self {
+        Self {
+            position: [0.0, 0.0],
+            size: [0.0, 0.0],
+            border_radius: [0.0; 4],
+            fill: None,
+            stroke: None,
+            shadow: None,
+            rotation: 0.0,
+            z_index: 0.0,
+            softness: 1.0,
+            clip: None,
+        }
+    }
+}
+
+impl Rect {
+    /// A square-cornered rect centered on `position`, filled with `color`, with no stroke or
+    /// shadow and half a pixel of softness.
+    pub fn solid(position: [f32; 2], size: [f32; 2], color: [f32; 4]) -> Rect {
+        Rect {
+            position,
+            size,
+            fill: Some(Fill::Solid { color }),
+            softness: 0.5,
+            ..Default::default()
+        }
+    }
+
+    /// Whether the rect covers no area and should be skipped.
+    pub fn is_empty(&self) -> bool {
+        self.size[0] == 0.0
+            || self.size[1] == 0.0
+            || self
+                .size
+                .iter()
+                .chain(&self.position)
+                .any(|v| !v.is_finite())
+    }
+
+    /// Returns a copy with a non-negative size, and the corner radii and `softness` clamped to
+    /// what the shader can draw: none may exceed half of the shorter side or be negative.
+    pub fn normalized(&self) -> Rect {
+        let mut rect = *self;
+        rect.size = [rect.size[0].abs(), rect.size[1].abs()];
+        let half_extent = rect.size[0].min(rect.size[1]) / 2.0;
+
+        for radius in &mut rect.border_radius {
+            let clamped = if radius.is_nan() {
+                0.0
+            } else {
+                radius.clamp(0.0, half_extent)
+            };
+            if clamped != *radius {
+                warn_clamped("border_radius", &RADIUS_CLAMPED, *radius, clamped);
+                *radius = clamped;
+            }
+        }
+
+        let softness = if rect.softness.is_nan() {
+            0.0
+        } else {
+            rect.softness.clamp(0.0, half_extent)
+        };
+        if softness != rect.softness {
+            warn_clamped("softness", &SOFTNESS_CLAMPED, rect.softness, softness);
+            rect.softness = softness;
+        }
+
+        rect
+    }
+
+    /// Signed distance from `point` to the rect's rounded edge, negative inside, computed the
+    /// same way as in the shader.
+    pub fn signed_distance(&self, point: [f32; 2]) -> f32 {
+        rounded_distance(self.to_local(point), self.size, self.border_radius)
+    }
+
+    /// `point` relative to the center, in the rect's unrotated frame.
+    fn to_local(&self, point: [f32; 2]) -> [f32; 2] {
+        let [x, y] = [0, 1].map(|i| point[i] - self.position[i]);
+        let (sin, cos) = self.rotation.sin_cos();
+        [x * cos + y * sin, y * cos - x * sin]
+    }
+
+    /// How much of the rect's color is drawn at `point`: 1 inside, falling to 0 across the
+    /// soft edge. A stroke is centered on the edge, so half of it counts as inside.
+    pub fn coverage(&self, point: [f32; 2]) -> f32 {
+        let distance = self.edge_distance(point);
+        let softness = self.edge_softness();
+        if distance <= 0.0 {
+            1.0
+        } else if softness <= 0.0 {
+            0.0
+        } else {
+            let t = (distance / softness).min(1.0);
+            1.0 - t * t * (3.0 - 2.0 * t)
+        }
+    }
+
+    /// Whether the rect is drawn anywhere in the pixel of size `pixel` centered on `point`,
+    /// sampled at the four points of a 4x multisample pattern. `tolerance` grows the hit area
+    /// beyond the visible edge, so small rounded rects don't have dead corners.
+    pub fn hit_test(&self, point: [f32; 2], pixel: f32, tolerance: f32) -> bool {
+        (self.fill.is_some() || self.stroke.is_some()) && self.covers(point, pixel, tolerance)
+    }
+
+    /// `hit_test` for the rect's shape alone, whether or not anything is drawn.
+    fn covers(&self, point: [f32; 2], pixel: f32, tolerance: f32) -> bool {
+        if self.is_empty() || self.clip.is_some_and(|clip| !clip.contains(point)) {
+            return false;
+        }
+        let softness = self.edge_softness();
+        HIT_SAMPLES.iter().any(|sample| {
+            let distance =
+                self.edge_distance([0, 1].map(|i| point[i] + sample[i] * pixel)) - tolerance;
+            distance <= 0.0 || distance < softness
+        })
+    }
+
+    fn edge_distance(&self, point: [f32; 2]) -> f32 {
+        let stroke = self
+            .stroke
+            .map_or(0.0, |stroke| stroke.width.max(0.0) / 2.0);
+        self.signed_distance(point) - stroke
+    }
+
+    /// `softness` clamped like `normalized` does, without warning about it.
+    fn edge_softness(&self) -> f32 {
+        let half_extent = self.size[0].abs().min(self.size[1].abs()) / 2.0;
+        self.softness.max(0.0).min(half_extent)
+    }
+
+    /// The rect's shadow as a blurred rect of its own, before any camera is applied.
+    pub fn shadow_rect(&self) -> Option<Rect> {
+        let shadow = self.shadow?;
+        let blur = shadow.blur.max(0.0);
+        // The falloff runs outwards from the edge, so the shape starts half the blur inside
+        // to center the falloff on the edge. A tiny rect under a wide blur still keeps a
+        // sliver of a shape for the falloff to surround.
+        let grow = shadow.spread - blur / 2.0;
+        Some(Rect {
+            position: [
+                self.position[0] + shadow.offset[0],
+                self.position[1] + shadow.offset[1],
+            ],
+            size: self.size.map(|side| (side.abs() + 2.0 * grow).max(1e-3)),
+            border_radius: self.border_radius.map(|radius| (radius + grow).max(0.0)),
+            fill: Some(Fill::Solid {
+                color: shadow.color,
+            }),
+            rotation: self.rotation,
+            z_index: self.z_index,
+            softness: blur,
+            clip: self.clip,
+            ..Default::default()
+        })
+    }
+
+    /// The outer edge of the stroke, which is centered on the rect's edge: the rect grown by
+    /// half the stroke's width, filled inwards for the width. `None` without a stroke, or with
+    /// one that isn't a positive finite width.
+    pub fn stroke_rect(&self) -> Option<Rect> {
+        let stroke = self
+            .stroke
+            .filter(|stroke| stroke.width.is_finite() && stroke.width > 0.0)?;
+        let grow = stroke.width / 2.0;
+        Some(Rect {
+            size: self.size.map(|side| side.abs() + stroke.width),
+            // Sharp corners stay sharp on the outside too.
+            border_radius: self
+                .border_radius
+                .map(|radius| if radius > 0.0 { radius + grow } else { 0.0 }),
+            fill: None,
+            shadow: None,
+            ..*self
+        })
+    }
+}
+
+/// Signed distance from `offset`, relative to the center of a box of `size`, to the box's edge
+/// rounded by `border_radius`, negative inside. The radii are clamped like the shader does.
+fn rounded_distance(offset: [f32; 2], size: [f32; 2], border_radius: [f32; 4]) -> f32 {
+    let half_size = size.map(|side| side.abs() / 2.0);
+    // Each quadrant is rounded by the radius of the corner it contains.
+    let corner = match (offset[0] > 0.0, offset[1] > 0.0) {
+        (false, false) => 0,
+        (true, false) => 1,
+        (true, true) => 2,
+        (false, true) => 3,
+    };
+    let radius = border_radius[corner]
+        .max(0.0)
+        .min(half_size[0].min(half_size[1]));
+    let outside = [0, 1].map(|i| (offset[i].abs() - (half_size[i] - radius)).max(0.0));
+    (outside[0] * outside[0] + outside[1] * outside[1]).sqrt() - radius
+}
+
+/// An axis-aligned area outside of which a rect isn't drawn, in the same coordinates as the
+/// rect. `position` is the center, and `border_radius` rounds the corners in the same order
+/// as `Rect::border_radius`, fading the rect out across a pixel at the rounded edges. Rotating
+/// the rect doesn't turn its clip.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClipRect {
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    pub border_radius: [f32; 4],
+}
+
+impl ClipRect {
+    /// The bounds and corners of `rect`, e.g. a rounded card, to clip its children to. The
+    /// rect's rotation is ignored.
+    pub fn of(rect: &Rect) -> ClipRect {
+        ClipRect {
+            position: rect.position,
+            size: rect.size,
+            border_radius: rect.border_radius,
+        }
+    }
+
+    pub fn contains(&self, point: [f32; 2]) -> bool {
+        let [min, max] = self.bounds();
+        let offset = [0, 1].map(|i| point[i] - self.position[i]);
+        (0..2).all(|i| min[i] <= point[i] && point[i] < max[i])
+            && rounded_distance(offset, self.size, self.border_radius) <= 0.0
+    }
+
+    /// The area inside both clips, for content nested in several clipped containers. Empty
+    /// when they don't overlap. A corner is rounded where it is a corner of either clip, by
+    /// the larger radius when it is one of both, so a clip nested fully inside another keeps
+    /// its shape.
+    pub fn intersect(&self, other: &ClipRect) -> ClipRect {
+        let ([min_a, max_a], [min_b, max_b]) = (self.bounds(), other.bounds());
+        let min = [0, 1].map(|i| min_a[i].max(min_b[i]));
+        let max = [0, 1].map(|i| max_a[i].min(max_b[i]).max(min[i]));
+        let corners = |min: [f32; 2], max: [f32; 2]| {
+            [
+                [min[0], min[1]],
+                [max[0], min[1]],
+                [max[0], max[1]],
+                [min[0], max[1]],
+            ]
+        };
+        let (ours, theirs, result) = (
+            corners(min_a, max_a),
+            corners(min_b, max_b),
+            corners(min, max),
+        );
+        let border_radius = [0, 1, 2, 3].map(|k| {
+            let ours = if ours[k] == result[k] {
+                self.border_radius[k]
+            } else {
+                0.0
+            };
+            let theirs = if theirs[k] == result[k] {
+                other.border_radius[k]
+            } else {
+                0.0
+            };
+            ours.max(theirs)
+        });
+        ClipRect {
+            position: [0, 1].map(|i| (min[i] + max[i]) / 2.0),
+            size: [0, 1].map(|i| max[i] - min[i]),
+            border_radius,
+        }
+    }
+
+    /// The smallest and largest corner.
+    fn bounds(&self) -> [[f32; 2]; 2] {
+        let half = self.size.map(|side| side.abs() / 2.0);
+        [
+            [0, 1].map(|i| self.position[i] - half[i]),
+            [0, 1].map(|i| self.position[i] + half[i]),
+        ]
+    }
+}
+
+/// The area that receives input for a rect, when it should differ from what is drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HitRegion {
+    /// The drawn bounds grown around their center to at least this size, e.g. a 44px touch
+    /// target around a 16px icon.
+    MinSize([f32; 2]),
+    /// The drawn bounds grown by this much on every side.
+    Outset(f32),
+    /// A rounded rect of its own, in the same coordinates as the rect.
+    Bounds {
+        position: [f32; 2],
+        size: [f32; 2],
+        border_radius: [f32; 4],
+    },
+}
+
+impl HitRegion {
+    /// The shape input for `rect` is tested against.
+    pub fn shape(&self, rect: &Rect) -> Rect {
+        match *self {
+            HitRegion::MinSize(min_size) => Rect {
+                size: [0, 1].map(|i| rect.size[i].abs().max(min_size[i])),
+                ..*rect
+            },
+            HitRegion::Outset(outset) => Rect {
+                size: rect.size.map(|side| (side.abs() + 2.0 * outset).max(0.0)),
+                border_radius: rect.border_radius.map(|radius| (radius + outset).max(0.0)),
+                ..*rect
+            },
+            HitRegion::Bounds {
+                position,
+                size,
+                border_radius,
+            } => Rect {
+                position,
+                size,
+                border_radius,
+                stroke: None,
+                softness: 0.0,
+                ..*rect
+            },
+        }
+    }
+
+    /// Like `Rect::hit_test`, but against the region. Regions receive input even around rects
+    /// that draw nothing.
+    pub fn hit_test(&self, rect: &Rect, point: [f32; 2], pixel: f32, tolerance: f32) -> bool {
+        self.shape(rect).covers(point, pixel, tolerance)
+    }
+}
+
+/// Sample offsets within a pixel, in pixels, from the standard 4x multisample pattern.
+const HIT_SAMPLES: [[f32; 2]; 4] = [
+    [-0.125, -0.375],
+    [0.375, -0.125],
+    [-0.375, 0.125],
+    [0.125, 0.375],
+];
+
+/// Whether clamping each field was logged yet. The same rects are normalized again on every
+/// upload, so only the first is.
+static RADIUS_CLAMPED: AtomicBool = AtomicBool::new(false);
+static SOFTNESS_CLAMPED: AtomicBool = AtomicBool::new(false);
+
+fn warn_clamped(field: &str, warned: &AtomicBool, from: f32, to: f32) {
+    if cfg!(debug_assertions) && !warned.swap(true, Ordering::Relaxed) {
+        log::warn!(
+            "Rect {} clamped from {} to {}, further clamping isn't logged",
+            field,
+            from,
+            to
+        );
+    }
+}
+
+/// The fill of a quad, stored once in the style table however many quads share it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct RectStyle {
+    /// Fill kind (0 solid, 1 radial, 2 conic, 3 image, 4 stroke, 5 pattern, 6 noise), gradient
+    /// center, then radius or start angle. Strokes have their width in place of the center,
+    /// patterns their offset and rotation, and noise its offset and scale.
+    gradient: [f32; 4],
+    /// Patterns have their tile size in the first two.
+    stop_offsets: [f32; 4],
+    /// The solid color or tint is the first stop.
+    stop_colors: [[f32; 4]; MAX_GRADIENT_STOPS],
+    /// The image's rect in the atlas, `[left, top, right, bottom]`. Noise has its octaves
+    /// first instead.
+    uv: [f32; 4],
+}
+
+impl RectStyle {
+    fn new(fill: Fill, atlas: &AtlasLayout) -> Self {
+        let solid = |color| [GradientStop { offset: 0.0, color }; MAX_GRADIENT_STOPS];
+        let mut uv = [0.0; 4];
+        let (gradient, stops) = match fill {
+            Fill::Solid { color } => ([0.0; 4], solid(color)),
+            Fill::Image { id, tint } => match atlas.uv(id) {
+                Some(image_uv) => {
+                    uv = image_uv;
+                    ([3.0, 0.0, 0.0, 0.0], solid(tint))
+                }
+                None => ([0.0; 4], solid(tint)),
+            },
+            Fill::Pattern {
+                id,
+                tile_size,
+                offset,
+                rotation,
+                tint,
+            } => match atlas.uv(id) {
+                Some(image_uv) => {
+                    uv = image_uv;
+                    // The tile size takes the place of the first stop offsets.
+                    let [width, height] = tile_size;
+                    let mut stops = solid(tint);
+                    stops[0].offset = width;
+                    stops[1].offset = height;
+                    ([5.0, offset[0], offset[1], rotation], stops)
+                }
+                None => ([0.0; 4], solid(tint)),
+            },
+            Fill::Radial {
+                center,
+                radius,
+                stops,
+            } => ([1.0, center[0], center[1], radius], stops.padded()),
+            Fill::Conic {
+                center,
+                start_angle,
+                stops,
+            } => ([2.0, center[0], center[1], start_angle], stops.padded()),
+            Fill::Noise {
+                scale,
+                octaves,
+                offset,
+                stops,
+            } => {
+                uv[0] = octaves.clamp(1, MAX_NOISE_OCTAVES) as f32;
+                ([6.0, offset[0], offset[1], scale], stops.padded())
+            }
+        };
+        Self {
+            gradient,
+            stop_offsets: stops.map(|stop| stop.offset),
+            stop_colors: stops.map(|stop| stop.color),
+            uv,
+        }
+    }
+
+    /// A stroke's color, drawn over a band of its width inside the quad's edge.
+    fn stroke(stroke: Stroke) -> Self {
+        let [r, g, b] = stroke.color;
+        Self {
+            gradient: [4.0, stroke.width, 0.0, 0.0],
+            stop_offsets: [0.0; 4],
+            stop_colors: [[r, g, b, 1.0]; MAX_GRADIENT_STOPS],
+            uv: [0.0; 4],
+        }
+    }
+
+    /// Whether every pixel of the fill is fully opaque. Images and patterns may have
+    /// transparent pixels, so they never are, while stroke colors have no alpha.
+    pub fn is_opaque(&self) -> bool {
+        match self.gradient[0] as u32 {
+            0 | 4 => self.stop_colors[0][3] >= 1.0,
+            1 | 2 | 6 => self.stop_colors.iter().all(|color| color[3] >= 1.0),
+            _ => false,
+        }
+    }
+
+    pub fn is_finite(&self) -> bool {
+        bytemuck::cast_slice::<_, f32>(&[*self])
+            .iter()
+            .all(|v| v.is_finite())
+    }
+}
+
+/// Every distinct style of a geometry, each stored once.
+#[derive(Clone, Debug, Default)]
+pub struct StyleTable {
+    styles: Vec<RectStyle>,
+    /// Index of each style, by its bits.
+    indices: HashMap<[u32; STYLE_WORDS], u32>,
+}
+
+const STYLE_WORDS: usize = std::mem::size_of::<RectStyle>() / 4;
+
+impl StyleTable {
+    /// Index of `style`, adding it if the table doesn't have it yet.
+    pub fn intern(&mut self, style: RectStyle) -> u32 {
+        let key = bytemuck::cast(style);
+        if let Some(&index) = self.indices.get(&key) {
+            return index;
+        }
+        self.styles.push(style);
+        let index = (self.styles.len() - 1) as u32;
+        self.indices.insert(key, index);
+        index
+    }
+
+    pub fn styles(&self) -> &[RectStyle] {
+        &self.styles
+    }
+}
+
+/// One quad, drawn as an instance of a unit quad whose corners the vertex shader derives from
+/// the vertex index.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct RectInstance {
+    z_index: f32,
+    border_radius: [f32; 4],
+    rect_pos: [f32; 2],
+    rect_size: [f32; 2],
+    rect_softness: f32,
+    rect_rotation: f32,
+    /// Smallest and largest corner of the clip, in screen space.
+    clip: [f32; 4],
+    clip_radius: [f32; 4],
+    /// Index into the style table.
+    style: u32,
+}
+
+impl RectInstance {
+    const ATTRIBS: [wgpu::VertexAttribute; 9] = wgpu::vertex_attr_array![
+        0 => Float32,
+        1 => Float32x4,
+        2 => Float32x2,
+        3 => Float32x2,
+        4 => Float32,
+        5 => Float32,
+        6 => Float32x4,
+        7 => Float32x4,
+        8 => Uint32,
+    ];
+    pub(crate) fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<RectInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+
+    pub fn is_finite(&self) -> bool {
+        // Every attribute before the style index is made of f32s, so those can be checked as a
+        // flat slice.
+        let floats = std::mem::size_of::<RectInstance>() / 4 - 1;
+        bytemuck::cast_slice::<_, f32>(&[*self])[..floats]
+            .iter()
+            .all(|v| v.is_finite())
+    }
+
+    /// Center of the quad, in screen space.
+    pub fn rect_pos(&self) -> [f32; 2] {
+        self.rect_pos
+    }
+
+    /// Index of the instance's style in its geometry's `StyleTable`.
+    pub fn style(&self) -> u32 {
+        self.style
+    }
+}
+
+/// `RectInstance` with half floats where they are precise enough, for
+/// `RendererConfig::packed_vertices`. Positions, sizes, clips and rotation stay full floats,
+/// as rounding them would visibly move edges, and so does the depth, which has to tell every
+/// instance apart.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct PackedRectInstance {
+    rect_pos: [f32; 2],
+    rect_size: [f32; 2],
+    rect_rotation: f32,
+    z_index: f32,
+    border_radius: [u16; 4],
+    /// Softness, then unused.
+    softness: [u16; 2],
+    clip: [f32; 4],
+    clip_radius: [u16; 4],
+    style: u32,
+}
+
+impl PackedRectInstance {
+    const ATTRIBS: [wgpu::VertexAttribute; 9] = wgpu::vertex_attr_array![
+        0 => Float32x2,
+        1 => Float32x2,
+        2 => Float32,
+        3 => Float32,
+        4 => Float16x4,
+        5 => Float16x2,
+        6 => Float32x4,
+        7 => Float16x4,
+        8 => Uint32,
+    ];
+    pub(crate) fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<PackedRectInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+impl From<&RectInstance> for PackedRectInstance {
+    fn from(instance: &RectInstance) -> Self {
+        Self {
+            rect_pos: instance.rect_pos,
+            rect_size: instance.rect_size,
+            rect_rotation: instance.rect_rotation,
+            z_index: instance.z_index,
+            border_radius: instance.border_radius.map(f16_bits),
+            softness: [f16_bits(instance.rect_softness), 0],
+            clip: instance.clip,
+            clip_radius: instance.clip_radius.map(f16_bits),
+            style: instance.style,
+        }
+    }
+}
+
+/// Layout of the rect instance buffer, packed or not.
+pub(crate) fn instance_layout(packed: bool) -> wgpu::VertexBufferLayout<'static> {
+    if packed {
+        PackedRectInstance::desc()
+    } else {
+        RectInstance::desc()
+    }
+}
+
+/// `instances` as they are uploaded, packed or not.
+pub(crate) fn instance_data(instances: &[RectInstance], packed: bool) -> Cow<'_, [u8]> {
+    if packed {
+        let packed: Vec<PackedRectInstance> =
+            instances.iter().map(PackedRectInstance::from).collect();
+        Cow::Owned(bytemuck::cast_slice(&packed).to_vec())
+    } else {
+        Cow::Borrowed(bytemuck::cast_slice(instances))
+    }
+}
+
+/// The clip of instances without one, finite so `RectInstance::is_finite` still holds.
+const UNCLIPPED: [f32; 4] = [f32::MIN, f32::MIN, f32::MAX, f32::MAX];
+
+/// Vertices drawn per instance: the unit quad as two triangles.
+pub(crate) const QUAD_VERTICES: u32 = 6;
+
+/// Quads ready for upload, one instance each, with their styles in a table.
+#[derive(Clone, Debug, Default)]
+pub struct Geometry {
+    pub instances: Vec<RectInstance>,
+    pub styles: StyleTable,
+}
+
+impl Geometry {
+    /// Adds a quad for every non-empty rect, in the screen space of `camera`, with image fills
+    /// looked up in `atlas`. A rect with a shadow gets a second quad for it, just before its
+    /// own, and one with a stroke gets another just after. Without a fill, only those two are
+    /// drawn.
+    ///
+    /// Whether a rect is skipped only depends on the rect itself, so moving the camera never
+    /// changes the number of instances.
+    ///
+    /// The new quads are sorted by `z_index`, keeping the order of `rects` among equal ones,
+    /// and go over everything added before.
+    pub fn extend(&mut self, rects: &[Rect], camera: &Camera, atlas: &AtlasLayout) {
+        self.instances.reserve(rects.len());
+        let start = self.instances.len();
+
+        for rect in rects.iter().filter(|rect| !rect.is_empty()) {
+            if let Some(shadow) = rect.shadow_rect() {
+                let mut shadow = camera.apply(&shadow);
+                // A blur wider than the shadow is fine here, but `normalized` would clamp it.
+                let blur = shadow.softness;
+                shadow.softness = 0.0;
+                let mut shadow = shadow.normalized();
+                shadow.softness = blur;
+                if let Some(fill) = shadow.fill {
+                    self.push_quad(&shadow, RectStyle::new(fill, atlas));
+                }
+            }
+            let rect = camera.apply(rect);
+            if let Some(fill) = rect.fill {
+                self.push_quad(&rect.normalized(), RectStyle::new(fill, atlas));
+            }
+            if let (Some(outline), Some(stroke)) = (rect.stroke_rect(), rect.stroke) {
+                self.push_quad(&outline.normalized(), RectStyle::stroke(stroke));
+            }
+        }
+        // Stable, so shadows stay just under their rects.
+        self.instances[start..].sort_by(|a, b| a.z_index.total_cmp(&b.z_index));
+    }
+
+    /// The instances as the depth-tested rect passes draw them: first the opaque ones front to
+    /// back, then all of them back to front. Each instance's depth comes from its place in the
+    /// paint order instead of its `z_index`, so the depth test reproduces that order exactly.
+    /// Returns the instances and how many are opaque.
+    pub fn depth_sorted(&self) -> (Vec<RectInstance>, usize) {
+        let count = self.instances.len();
+        let painted: Vec<RectInstance> = self
+            .instances
+            .iter()
+            .enumerate()
+            .map(|(i, instance)| RectInstance {
+                // Nearer is greater, and 0 is left for the cleared depth buffer.
+                z_index: (i + 1) as f32 / (count + 1) as f32,
+                ..*instance
+            })
+            .collect();
+        let styles = self.styles.styles();
+        let mut sorted: Vec<RectInstance> = painted
+            .iter()
+            .rev()
+            .filter(|instance| styles[instance.style() as usize].is_opaque())
+            .copied()
+            .collect();
+        let opaque = sorted.len();
+        sorted.extend(painted);
+        (sorted, opaque)
+    }
+
+    fn push_quad(&mut self, rect: &Rect, style: RectStyle) {
+        let style = self.styles.intern(style);
+        self.instances.push(RectInstance {
+            z_index: rect.z_index,
+            border_radius: rect.border_radius,
+            rect_pos: rect.position,
+            rect_size: rect.size,
+            rect_softness: rect.softness,
+            rect_rotation: rect.rotation,
+            clip: rect.clip.map_or(UNCLIPPED, |clip| {
+                let [min, max] = clip.bounds();
+                [min[0], min[1], max[0], max[1]]
+            }),
+            clip_radius: rect.clip.map_or([0.0; 4], |clip| {
+                let half_extent = clip.size[0].abs().min(clip.size[1].abs()) / 2.0;
+                clip.border_radius.map(|radius| {
+                    if radius.is_nan() {
+                        0.0
+                    } else {
+                        radius.clamp(0.0, half_extent)
+                    }
+                })
+            }),
+            style,
+        });
+    }
+}
+
+/// Builds the quads for `rects` in the screen space of `camera`; see `Geometry::extend`.
+pub fn build_geometry(rects: &[Rect], camera: &Camera) -> Geometry {
+    build_geometry_with_atlas(rects, camera, &AtlasLayout::default())
+}
+
+/// `build_geometry` for rects whose image fills are looked up in `atlas`.
+pub fn build_geometry_with_atlas(rects: &[Rect], camera: &Camera, atlas: &AtlasLayout) -> Geometry {
+    let mut geometry = Geometry::default();
+    geometry.extend(rects, camera, atlas);
+    geometry
+}
+
+/// Texels of a `RectStyle` in the style texture, one per `vec4`.
+const STYLE_TEXELS: u32 = (std::mem::size_of::<RectStyle>() / 16) as u32;
+
+/// Whether the device can read styles from a storage buffer. WebGL2 has none, so there they
+/// are read from a texture.
+fn storage_styles(device: &wgpu::Device) -> bool {
+    device.limits().max_storage_buffers_per_shader_stage > 0
+}
+
+/// The rect shader, reading its styles the way `StyleBuffer` stores them on `device`.
+pub(crate) fn create_shader(device: &wgpu::Device) -> wgpu::ShaderModule {
+    let styles = if storage_styles(device) {
+        include_str!("styles_buffer.wgsl")
+    } else {
+        include_str!("styles_texture.wgsl")
+    };
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("shader.wgsl"),
+        source: wgpu::ShaderSource::Wgsl(
+            format!("{}\n{}", include_str!("shader.wgsl"), styles).into(),
+        ),
+    })
+}
+
+/// Where the style table lives.
+enum StyleStorage {
+    Buffer(Tracked<wgpu::Buffer>),
+    Texture(Tracked<wgpu::Texture>),
+}
+
+/// The style table on the GPU, bound as group 2 of the rect pipeline.
+pub(crate) struct StyleBuffer {
+    table: StyleStorage,
+    pub bind_group: Tracked<wgpu::BindGroup>,
+}
+
+impl StyleBuffer {
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        let ty = if storage_styles(device) {
+            wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: true },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            }
+        } else {
+            wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            }
+        };
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty,
+                count: None,
+            }],
+            label: Some("Style Bind Group Layout"),
+        })
+    }
+
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        styles: &[RectStyle],
+    ) -> Self {
+        // Storage buffers and textures can't be empty, so a scene without rects still gets
+        // one style.
+        let placeholder = [RectStyle::zeroed()];
+        let styles = if styles.is_empty() {
+            &placeholder
+        } else {
+            styles
+        };
+        let table = if storage_styles(device) {
+            StyleStorage::Buffer(Tracked::new(
+                ResourceScope::Renderer,
+                "Style Buffer",
+                device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("Style Buffer"),
+                    contents: bytemuck::cast_slice(styles),
+                    usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                }),
+            ))
+        } else {
+            let texture = style_texture(device, styles.len() as u32);
+            write_style_texture(queue, &texture, styles);
+            StyleStorage::Texture(texture)
+        };
+        let bind_group = Self::bind_group(device, bind_group_layout, &table);
+        Self { table, bind_group }
+    }
+
+    /// Uploads `styles`, binding a larger buffer or texture when they no longer fit.
+    pub fn write(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        styles: &[RectStyle],
+    ) {
+        if styles.is_empty() {
+            return;
+        }
+        let grown = match &mut self.table {
+            StyleStorage::Buffer(buffer) => {
+                write_growing(device, queue, buffer, bytemuck::cast_slice(styles))
+            }
+            StyleStorage::Texture(texture) => {
+                let count = styles.len() as u32;
+                let grow = count > texture.width() / STYLE_TEXELS * texture.height();
+                if grow {
+                    *texture = style_texture(device, count.next_power_of_two());
+                }
+                write_style_texture(queue, texture, styles);
+                grow
+            }
+        };
+        if grown {
+            self.bind_group = Self::bind_group(device, bind_group_layout, &self.table);
+        }
+    }
+
+    fn bind_group(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        table: &StyleStorage,
+    ) -> Tracked<wgpu::BindGroup> {
+        let view;
+        let resource = match table {
+            StyleStorage::Buffer(buffer) => buffer.as_entire_binding(),
+            StyleStorage::Texture(texture) => {
+                view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                wgpu::BindingResource::TextureView(&view)
+            }
+        };
+        Tracked::new(
+            ResourceScope::Renderer,
+            "Style Bind Group",
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource,
+                }],
+                label: Some("Style Bind Group"),
+            }),
+        )
+    }
+}
+
+/// A texture with room for `count` styles, in rows as wide as the device allows.
+fn style_texture(device: &wgpu::Device, count: u32) -> Tracked<wgpu::Texture> {
+    let per_row = count.clamp(
+        1,
+        (device.limits().max_texture_dimension_2d / STYLE_TEXELS).max(1),
+    );
+    Tracked::new(
+        ResourceScope::Renderer,
+        "Style Texture",
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Style Texture"),
+            size: wgpu::Extent3d {
+                width: per_row * STYLE_TEXELS,
+                height: count.max(1).div_ceil(per_row),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba32Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        }),
+    )
+}
+
+/// Writes `styles` to the rows of `texture` they take, the last one padded with empty styles.
+fn write_style_texture(queue: &wgpu::Queue, texture: &wgpu::Texture, styles: &[RectStyle]) {
+    let per_row = (texture.width() / STYLE_TEXELS) as usize;
+    let rows = styles.len().div_ceil(per_row);
+    let mut padded = styles.to_vec();
+    padded.resize(rows * per_row, RectStyle::zeroed());
+    queue.write_texture(
+        texture.as_image_copy(),
+        bytemuck::cast_slice(&padded),
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(texture.width() * 16),
+            rows_per_image: Some(rows as u32),
+        },
+        wgpu::Extent3d {
+            width: texture.width(),
+            height: rows as u32,
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(size: [f32; 2], border_radius: [f32; 4], softness: f32) -> Rect {
+        Rect {
+            size,
+            border_radius,
+            fill: Some(Fill::Solid {
+                color: [1.0, 1.0, 1.0, 1.0],
+            }),
+            softness,
+            ..Default::default()
+        }
+    }
+
+    const STROKE: Stroke = Stroke {
+        color: [1.0, 0.0, 0.0],
+        width: 4.0,
+    };
+
+    /// The style kind and size of each quad drawn for `rect`.
+    fn quads(rect: Rect) -> Vec<(u32, [f32; 2])> {
+        let geometry = build_geometry(&[rect], &Camera::default());
+        let styles = geometry.styles.styles();
+        geometry
+            .instances
+            .iter()
+            .map(|instance| {
+                let kind = styles[instance.style() as usize].gradient[0] as u32;
+                (kind, instance.rect_size)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fill_only_draws_one_solid_quad() {
+        let rect = rect([100.0, 40.0], [0.0; 4], 0.0);
+        assert_eq!(quads(rect), [(0, [100.0, 40.0])]);
+    }
+
+    #[test]
+    fn stroke_only_draws_one_stroke_quad() {
+        let rect = Rect {
+            fill: None,
+            stroke: Some(STROKE),
+            ..rect([100.0, 40.0], [0.0; 4], 0.0)
+        };
+        assert_eq!(quads(rect), [(4, [104.0, 44.0])]);
+    }
+
+    #[test]
+    fn stroke_is_drawn_over_the_fill() {
+        let rect = Rect {
+            stroke: Some(STROKE),
+            ..rect([100.0, 40.0], [0.0; 4], 0.0)
+        };
+        assert_eq!(quads(rect), [(0, [100.0, 40.0]), (4, [104.0, 44.0])]);
+    }
+
+    #[test]
+    fn stroke_rect_grows_rounded_corners_only() {
+        let rect = Rect {
+            stroke: Some(STROKE),
+            ..rect([-100.0, 40.0], [0.0, 5.0, 10.0, 0.0], 0.0)
+        };
+        let outline = rect.stroke_rect().unwrap();
+        assert_eq!(outline.size, [104.0, 44.0]);
+        assert_eq!(outline.border_radius, [0.0, 7.0, 12.0, 0.0]);
+        assert_eq!(outline.fill, None);
+    }
+
+    #[test]
+    fn stroke_rect_skips_strokes_without_width() {
+        for width in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            let rect = Rect {
+                stroke: Some(Stroke { width, ..STROKE }),
+                ..rect([100.0, 40.0], [0.0; 4], 0.0)
+            };
+            assert_eq!(rect.stroke_rect(), None, "width {}", width);
+            assert_eq!(quads(rect), [(0, [100.0, 40.0])]);
+        }
+    }
+
+    #[test]
+    fn styles_past_a_u16_get_their_own_index() {
+        let mut table = StyleTable::default();
+        let count = u16::MAX as u32 + 10;
+        for i in 0..count {
+            let style = RectStyle::new(
+                Fill::Solid {
+                    color: [i as f32, 0.0, 0.0, 1.0],
+                },
+                &AtlasLayout::default(),
+            );
+            assert_eq!(table.intern(style), i);
+        }
+        assert_eq!(table.styles().len(), count as usize);
+    }
+
+    #[test]
+    fn normalized_keeps_values_in_range() {
+        let rect = rect([100.0, 40.0], [0.0, 5.0, 10.0, 20.0], 1.0);
+        assert_eq!(rect.normalized(), rect);
+    }
+
+    #[test]
+    fn normalized_zeroes_nan() {
+        let rect = rect([100.0, 40.0], [f32::NAN, 5.0, f32::NAN, 5.0], f32::NAN).normalized();
+        assert_eq!(rect.border_radius, [0.0, 5.0, 0.0, 5.0]);
+        assert_eq!(rect.softness, 0.0);
+    }
+
+    #[test]
+    fn normalized_clamps_infinity_to_half_the_shorter_side() {
+        let rect = rect([100.0, 40.0], [f32::INFINITY; 4], f32::INFINITY).normalized();
+        assert_eq!(rect.border_radius, [20.0; 4]);
+        assert_eq!(rect.softness, 20.0);
+    }
+
+    #[test]
+    fn normalized_clamps_negative_values_to_zero() {
+        let rect = rect([100.0, 40.0], [-5.0, f32::NEG_INFINITY, 3.0, -0.5], -2.0).normalized();
+        assert_eq!(rect.border_radius, [0.0, 0.0, 3.0, 0.0]);
+        assert_eq!(rect.softness, 0.0);
+    }
+
+    #[test]
+    fn normalized_clamps_radius_after_flipping_a_negative_size() {
+        let rect = rect([-30.0, 100.0], [50.0; 4], 1.0).normalized();
+        assert_eq!(rect.size, [30.0, 100.0]);
+        assert_eq!(rect.border_radius, [15.0; 4]);
+    }
 }
@@ -0,0 +1,173 @@
+use std::{
+    collections::HashMap,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ResourceKind {
+    Buffer,
+    Texture,
+    BindGroup,
+}
+
+/// Who owns a resource, which decides when it is expected to be freed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ResourceScope {
+    /// Lives as long as the renderer, e.g. the window uniform.
+    Renderer,
+    /// Belongs to the scene's content and must be freed when the scene is torn down.
+    Scene,
+}
+
+/// A GPU resource that is still alive.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LiveResource {
+    pub kind: ResourceKind,
+    pub scope: ResourceScope,
+    pub label: &'static str,
+    pub bytes: u64,
+}
+
+/// Totals over the live resources the renderer created. Text atlas textures are managed by
+/// glyphon and aren't included.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResourceStats {
+    pub buffers: usize,
+    pub buffer_bytes: u64,
+    pub textures: usize,
+    pub texture_bytes: u64,
+    pub bind_groups: usize,
+    /// Resources created since startup, including freed ones.
+    pub created: u64,
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+static LIVE: Mutex<Option<HashMap<u64, LiveResource>>> = Mutex::new(None);
+
+fn with_live<R>(f: impl FnOnce(&mut HashMap<u64, LiveResource>) -> R) -> R {
+    // Tracking is only bookkeeping, so a panic elsewhere while holding the lock shouldn't
+    // stop it.
+    let mut live = LIVE.lock().unwrap_or_else(|e| e.into_inner());
+    f(live.get_or_insert_with(HashMap::new))
+}
+
+/// Counts and sizes of every live resource.
+pub fn stats() -> ResourceStats {
+    with_live(|live| {
+        let mut stats = ResourceStats {
+            created: NEXT_ID.load(Ordering::Relaxed),
+            ..Default::default()
+        };
+        for resource in live.values() {
+            match resource.kind {
+                ResourceKind::Buffer => {
+                    stats.buffers += 1;
+                    stats.buffer_bytes += resource.bytes;
+                }
+                ResourceKind::Texture => {
+                    stats.textures += 1;
+                    stats.texture_bytes += resource.bytes;
+                }
+                ResourceKind::BindGroup => stats.bind_groups += 1,
+            }
+        }
+        stats
+    })
+}
+
+/// Every live resource of `scope`, oldest first.
+pub fn live(scope: ResourceScope) -> Vec<LiveResource> {
+    with_live(|live| {
+        let mut resources: Vec<(u64, LiveResource)> = live
+            .iter()
+            .filter(|(_, resource)| resource.scope == scope)
+            .map(|(id, resource)| (*id, resource.clone()))
+            .collect();
+        resources.sort_by_key(|(id, _)| *id);
+        resources
+            .into_iter()
+            .map(|(_, resource)| resource)
+            .collect()
+    })
+}
+
+pub(crate) trait GpuResource {
+    const KIND: ResourceKind;
+    fn bytes(&self) -> u64;
+}
+
+impl GpuResource for wgpu::Buffer {
+    const KIND: ResourceKind = ResourceKind::Buffer;
+    fn bytes(&self) -> u64 {
+        self.size()
+    }
+}
+
+impl GpuResource for wgpu::Texture {
+    const KIND: ResourceKind = ResourceKind::Texture;
+    fn bytes(&self) -> u64 {
+        let size = self.size();
+        let texel = self.format().block_size(None).unwrap_or(4) as u64;
+        size.width as u64 * size.height as u64 * size.depth_or_array_layers as u64 * texel
+    }
+}
+
+impl GpuResource for wgpu::BindGroup {
+    const KIND: ResourceKind = ResourceKind::BindGroup;
+    fn bytes(&self) -> u64 {
+        0
+    }
+}
+
+/// A resource that is counted in `stats` until it is dropped.
+pub(crate) struct Tracked<T> {
+    resource: T,
+    id: u64,
+}
+
+impl<T: GpuResource> Tracked<T> {
+    pub fn new(scope: ResourceScope, label: &'static str, resource: T) -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let entry = LiveResource {
+            kind: T::KIND,
+            scope,
+            label,
+            bytes: resource.bytes(),
+        };
+        with_live(|live| live.insert(id, entry));
+        Self { resource, id }
+    }
+}
+
+impl<T> Deref for Tracked<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.resource
+    }
+}
+
+impl<T> Drop for Tracked<T> {
+    fn drop(&mut self) {
+        with_live(|live| live.remove(&self.id));
+    }
+}
+
+/// Logs every scene resource still alive after the scene was torn down. Returns how many
+/// there were.
+pub(crate) fn report_leaks() -> usize {
+    let leaked = live(ResourceScope::Scene);
+    for resource in &leaked {
+        log::error!(
+            "Leaked {:?} \"{}\" ({} bytes) after scene teardown",
+            resource.kind,
+            resource.label,
+            resource.bytes
+        );
+    }
+    leaked.len()
+}
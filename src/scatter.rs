@@ -0,0 +1,525 @@
+use std::sync::{Arc, Mutex};
+
+use wgpu::util::DeviceExt;
+
+use crate::{
+    camera::Camera,
+    resources::{ResourceScope, Tracked},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Marker {
+    Circle = 0,
+    Square = 1,
+    Cross = 2,
+}
+
+/// One marker, laid out as the shader's storage buffer expects it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ScatterPoint {
+    /// Relative to the plot's `origin`, in world units.
+    pub position: [f32; 2],
+    /// Diameter in pixels. Markers keep their size when zooming.
+    pub size: f32,
+    marker: u32,
+    pub color: [f32; 4],
+}
+
+impl ScatterPoint {
+    pub fn new(position: [f32; 2], size: f32, marker: Marker, color: [f32; 4]) -> Self {
+        Self {
+            position,
+            size,
+            marker: marker as u32,
+            color,
+        }
+    }
+}
+
+/// A layer of instanced point markers, meant for very large point counts.
+#[derive(Clone, Debug)]
+pub struct ScatterPlot {
+    /// World position the point positions are relative to. Kept in f64 like the camera
+    /// offset, so large datasets far from the world origin stay precise.
+    pub origin: [f64; 2],
+    pub points: Vec<ScatterPoint>,
+    /// When more points than this would be on screen, an evenly strided subset is drawn.
+    /// Picking always considers every point.
+    pub max_visible_points: u32,
+    /// Application tags by point index, to map a picked point back to what it stands for.
+    /// Points past the end have none.
+    pub tags: Vec<u64>,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct ScatterUniform {
+    translation: [f32; 2],
+    zoom: f32,
+    stride: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+struct PickUniform {
+    cursor: [f32; 2],
+    radius: f32,
+    count: u32,
+}
+
+/// Matches `PickResult` in the pick shader: the best distance's bits and the point index.
+const PICK_RESULT_SIZE: wgpu::BufferAddress = 8;
+const NO_PICK: u32 = u32::MAX;
+
+/// GPU resources for one scatter plot.
+pub(crate) struct PreparedScatter {
+    origin: [f64; 2],
+    count: u32,
+    max_visible_points: u32,
+    bounds: [[f32; 2]; 2],
+    instances: u32,
+    // Bound through both bind groups; kept here so it stays counted while in use.
+    _points_buffer: Tracked<wgpu::Buffer>,
+    uniform_buffer: Tracked<wgpu::Buffer>,
+    bind_group: Tracked<wgpu::BindGroup>,
+    pick_uniform_buffer: Tracked<wgpu::Buffer>,
+    pick_result_buffer: Tracked<wgpu::Buffer>,
+    pick_readback_buffer: Tracked<wgpu::Buffer>,
+    pick_bind_group: Tracked<wgpu::BindGroup>,
+    pick_request: Option<PickUniform>,
+    pick_encoded: bool,
+    pick_in_flight: bool,
+    pick_mapped: Arc<Mutex<Option<Result<(), wgpu::BufferAsyncError>>>>,
+    hovered: Option<usize>,
+    tags: Vec<u64>,
+}
+
+impl PreparedScatter {
+    /// Positions the plot for `camera` and picks how many points to skip at this zoom.
+    pub fn update(&mut self, queue: &wgpu::Queue, camera: &Camera, viewport: [f32; 2]) {
+        let translation = [
+            self.origin[0] - camera.offset[0],
+            self.origin[1] - camera.offset[1],
+        ];
+
+        // Estimate how many points are on screen from how much of their bounding box is.
+        let view_min = camera.to_world([0.0, 0.0]);
+        let view_max = camera.to_world([viewport[0] as f64, viewport[1] as f64]);
+        let fraction: f64 = [0, 1]
+            .iter()
+            .map(|&axis| {
+                let min = self.origin[axis] + self.bounds[0][axis] as f64;
+                let max = self.origin[axis] + self.bounds[1][axis] as f64;
+                if max > min {
+                    let overlap = view_max[axis].min(max) - view_min[axis].max(min);
+                    (overlap / (max - min)).clamp(0.0, 1.0)
+                } else {
+                    1.0
+                }
+            })
+            .product();
+        let visible = (self.count as f64 * fraction).ceil() as u32;
+        let stride = visible.div_ceil(self.max_visible_points.max(1)).max(1);
+        self.instances = self.count.div_ceil(stride);
+
+        let uniform = ScatterUniform {
+            translation: [translation[0] as f32, translation[1] as f32],
+            zoom: camera.zoom as f32,
+            stride,
+        };
+        queue.write_buffer(&self.uniform_buffer, 0, bytemuck::cast_slice(&[uniform]));
+    }
+
+    /// Looks for the nearest point within `radius` world units of `cursor`. The answer shows
+    /// up in `hovered` a frame or two later, once the GPU result has been read back.
+    pub fn request_pick(&mut self, cursor: [f64; 2], radius: f32) {
+        self.pick_request = Some(PickUniform {
+            cursor: [
+                (cursor[0] - self.origin[0]) as f32,
+                (cursor[1] - self.origin[1]) as f32,
+            ],
+            radius,
+            count: self.count,
+        });
+    }
+
+    /// Whether a pick was requested and hasn't been collected by `poll_pick` yet. Frames have
+    /// to keep coming until it is, since the pick is encoded and read back with them.
+    pub fn picking(&self) -> bool {
+        self.pick_request.is_some() || self.pick_encoded || self.pick_in_flight
+    }
+
+    /// Index of the point nearest to the cursor at the last completed pick.
+    pub fn hovered(&self) -> Option<usize> {
+        self.hovered
+    }
+
+    /// Tag of the hovered point, if it has one.
+    pub fn hovered_tag(&self) -> Option<u64> {
+        self.tags.get(self.hovered?).copied()
+    }
+
+    /// Starts reading back a pick that was encoded this frame. Call after submitting.
+    pub fn after_submit(&mut self) {
+        if !self.pick_encoded {
+            return;
+        }
+        self.pick_encoded = false;
+        self.pick_in_flight = true;
+
+        let mapped = self.pick_mapped.clone();
+        self.pick_readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                *mapped.lock().unwrap() = Some(result);
+            });
+    }
+
+    /// Collects a finished pick. Returns `true` if the hovered point changed.
+    pub fn poll_pick(&mut self) -> bool {
+        let Some(result) = self.pick_mapped.lock().unwrap().take() else {
+            return false;
+        };
+        self.pick_in_flight = false;
+        if result.is_err() {
+            return false;
+        }
+
+        let index = {
+            let data = self.pick_readback_buffer.slice(..).get_mapped_range();
+            bytemuck::cast_slice::<_, u32>(&data)[1]
+        };
+        self.pick_readback_buffer.unmap();
+
+        let hovered = (index != NO_PICK).then_some(index as usize);
+        let changed = hovered != self.hovered;
+        self.hovered = hovered;
+        changed
+    }
+}
+
+pub(crate) struct ScatterPipeline {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    find_distance: wgpu::ComputePipeline,
+    find_index: wgpu::ComputePipeline,
+    pick_bind_group_layout: wgpu::BindGroupLayout,
+}
+
+fn layout_entry(
+    binding: u32,
+    visibility: wgpu::ShaderStages,
+    ty: wgpu::BufferBindingType,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty,
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+impl ScatterPipeline {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        window_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("scatter.wgsl"));
+        let pick_shader = device.create_shader_module(wgpu::include_wgsl!("scatter_pick.wgsl"));
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                layout_entry(
+                    0,
+                    wgpu::ShaderStages::VERTEX,
+                    wgpu::BufferBindingType::Uniform,
+                ),
+                layout_entry(
+                    1,
+                    wgpu::ShaderStages::VERTEX,
+                    wgpu::BufferBindingType::Storage { read_only: true },
+                ),
+            ],
+            label: Some("Scatter Bind Group Layout"),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Scatter Pipeline Layout"),
+            bind_group_layouts: &[window_bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Scatter Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let pick_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    layout_entry(
+                        0,
+                        wgpu::ShaderStages::COMPUTE,
+                        wgpu::BufferBindingType::Uniform,
+                    ),
+                    layout_entry(
+                        1,
+                        wgpu::ShaderStages::COMPUTE,
+                        wgpu::BufferBindingType::Storage { read_only: true },
+                    ),
+                    layout_entry(
+                        2,
+                        wgpu::ShaderStages::COMPUTE,
+                        wgpu::BufferBindingType::Storage { read_only: false },
+                    ),
+                ],
+                label: Some("Scatter Pick Bind Group Layout"),
+            });
+
+        let pick_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Scatter Pick Pipeline Layout"),
+            bind_group_layouts: &[&pick_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let compute_pipeline = |entry_point| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                label: Some("Scatter Pick Pipeline"),
+                layout: Some(&pick_layout),
+                module: &pick_shader,
+                entry_point,
+            })
+        };
+
+        Self {
+            pipeline,
+            bind_group_layout,
+            find_distance: compute_pipeline("find_distance"),
+            find_index: compute_pipeline("find_index"),
+            pick_bind_group_layout,
+        }
+    }
+
+    pub fn prepare(&self, device: &wgpu::Device, scatter: &ScatterPlot) -> PreparedScatter {
+        let bounds = scatter.points.iter().fold(
+            [[f32::INFINITY; 2], [f32::NEG_INFINITY; 2]],
+            |[min, max], point| {
+                [
+                    [min[0].min(point.position[0]), min[1].min(point.position[1])],
+                    [max[0].max(point.position[0]), max[1].max(point.position[1])],
+                ]
+            },
+        );
+
+        // Storage buffers can't be empty, so an empty plot still gets one (never drawn) point.
+        let zeroed = [ScatterPoint::new([0.0; 2], 0.0, Marker::Circle, [0.0; 4])];
+        let points: &[ScatterPoint] = if scatter.points.is_empty() {
+            &zeroed
+        } else {
+            &scatter.points
+        };
+        let points_buffer = Tracked::new(
+            ResourceScope::Scene,
+            "Scatter Points",
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Scatter Points"),
+                contents: bytemuck::cast_slice(points),
+                usage: wgpu::BufferUsages::STORAGE,
+            }),
+        );
+        let uniform_buffer = Tracked::new(
+            ResourceScope::Scene,
+            "Scatter Uniform",
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Scatter Uniform"),
+                size: std::mem::size_of::<ScatterUniform>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+        );
+        let bind_group = Tracked::new(
+            ResourceScope::Scene,
+            "Scatter Bind Group",
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: points_buffer.as_entire_binding(),
+                    },
+                ],
+                label: Some("Scatter Bind Group"),
+            }),
+        );
+
+        let pick_uniform_buffer = Tracked::new(
+            ResourceScope::Scene,
+            "Scatter Pick Uniform",
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Scatter Pick Uniform"),
+                size: std::mem::size_of::<PickUniform>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+        );
+        let pick_result_buffer = Tracked::new(
+            ResourceScope::Scene,
+            "Scatter Pick Result",
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Scatter Pick Result"),
+                size: PICK_RESULT_SIZE,
+                usage: wgpu::BufferUsages::STORAGE
+                    | wgpu::BufferUsages::COPY_SRC
+                    | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+        );
+        let pick_readback_buffer = Tracked::new(
+            ResourceScope::Scene,
+            "Scatter Pick Readback",
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Scatter Pick Readback"),
+                size: PICK_RESULT_SIZE,
+                usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+        );
+        let pick_bind_group = Tracked::new(
+            ResourceScope::Scene,
+            "Scatter Pick Bind Group",
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.pick_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: pick_uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: points_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: pick_result_buffer.as_entire_binding(),
+                    },
+                ],
+                label: Some("Scatter Pick Bind Group"),
+            }),
+        );
+
+        PreparedScatter {
+            origin: scatter.origin,
+            count: scatter.points.len() as u32,
+            max_visible_points: scatter.max_visible_points,
+            bounds,
+            instances: 0,
+            _points_buffer: points_buffer,
+            uniform_buffer,
+            bind_group,
+            pick_uniform_buffer,
+            pick_result_buffer,
+            pick_readback_buffer,
+            pick_bind_group,
+            pick_request: None,
+            pick_encoded: false,
+            pick_in_flight: false,
+            pick_mapped: Arc::new(Mutex::new(None)),
+            hovered: None,
+            tags: scatter.tags.clone(),
+        }
+    }
+
+    /// Records the pick passes for every scatter with a pending request whose previous
+    /// readback has finished.
+    pub fn encode_picks(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        scatters: &mut [PreparedScatter],
+    ) {
+        for scatter in scatters {
+            if scatter.pick_in_flight || scatter.count == 0 {
+                continue;
+            }
+            let Some(request) = scatter.pick_request.take() else {
+                continue;
+            };
+
+            queue.write_buffer(
+                &scatter.pick_uniform_buffer,
+                0,
+                bytemuck::cast_slice(&[request]),
+            );
+            queue.write_buffer(
+                &scatter.pick_result_buffer,
+                0,
+                bytemuck::cast_slice(&[NO_PICK, NO_PICK]),
+            );
+
+            // Dispatches are limited to 65535 workgroups per dimension, so large point
+            // counts spill into y.
+            let workgroups = scatter.count.div_ceil(64);
+            let x = workgroups.min(65535);
+            let y = workgroups.div_ceil(x);
+            {
+                let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                    label: Some("Scatter Pick Pass"),
+                });
+                compute_pass.set_bind_group(0, &scatter.pick_bind_group, &[]);
+                compute_pass.set_pipeline(&self.find_distance);
+                compute_pass.dispatch_workgroups(x, y, 1);
+                compute_pass.set_pipeline(&self.find_index);
+                compute_pass.dispatch_workgroups(x, y, 1);
+            }
+            encoder.copy_buffer_to_buffer(
+                &scatter.pick_result_buffer,
+                0,
+                &scatter.pick_readback_buffer,
+                0,
+                PICK_RESULT_SIZE,
+            );
+            scatter.pick_encoded = true;
+        }
+    }
+
+    pub fn render<'rp>(
+        &'rp self,
+        render_pass: &mut wgpu::RenderPass<'rp>,
+        window_bind_group: &'rp wgpu::BindGroup,
+        scatters: &'rp [PreparedScatter],
+    ) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, window_bind_group, &[]);
+        for scatter in scatters.iter().filter(|scatter| scatter.instances > 0) {
+            render_pass.set_bind_group(1, &scatter.bind_group, &[]);
+            render_pass.draw(0..6, 0..scatter.instances);
+        }
+    }
+}
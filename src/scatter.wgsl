@@ -0,0 +1,99 @@
+struct WindowUniform {
+	size: vec2<f32>,
+	scale_factor: f32,
+	y_direction: f32,
+}
+@group(0) @binding(0)
+var<uniform> window: WindowUniform;
+
+struct Point {
+	position: vec2<f32>,
+	size: f32,
+	marker: u32,
+	color: vec4<f32>,
+}
+
+struct ScatterUniform {
+	// origin - camera offset, computed in f64 on the CPU
+	translation: vec2<f32>,
+	zoom: f32,
+	stride: u32,
+}
+@group(1) @binding(0)
+var<uniform> scatter: ScatterUniform;
+@group(1) @binding(1)
+var<storage, read> points: array<Point>;
+
+struct VertexOutput {
+	@builtin(position) clip_position: vec4<f32>,
+	@location(0) local: vec2<f32>,
+	@location(1) color: vec4<f32>,
+	@location(2) size: f32,
+	@location(3) @interpolate(flat) marker: u32,
+};
+
+@vertex
+fn vs_main(
+	@builtin(vertex_index) vertex_index: u32,
+	@builtin(instance_index) instance_index: u32,
+) -> VertexOutput {
+	var out: VertexOutput;
+
+	var corners = array<vec2<f32>, 6>(
+		vec2<f32>(-1.0, -1.0),
+		vec2<f32>(1.0, -1.0),
+		vec2<f32>(1.0, 1.0),
+		vec2<f32>(-1.0, -1.0),
+		vec2<f32>(1.0, 1.0),
+		vec2<f32>(-1.0, 1.0),
+	);
+	var point = points[instance_index * scatter.stride];
+	// Markers keep their pixel size at any zoom, plus a pixel of room for anti-aliasing
+	var half_size = point.size / 2.0 + 1.0;
+	var local = corners[vertex_index] * half_size;
+	var screen = (point.position + scatter.translation) * scatter.zoom + local;
+
+	var ndc_position = vec2<f32>(
+		(2.0 * screen.x / window.size.x) - 1.0,
+		window.y_direction * (1.0 - (2.0 * screen.y / window.size.y))
+	);
+	out.clip_position = vec4<f32>(ndc_position, 0.0, 1.0);
+	out.local = local;
+	out.color = point.color;
+	out.size = point.size;
+	out.marker = point.marker;
+	return out;
+}
+
+fn box_sdf(p: vec2<f32>, half_extent: vec2<f32>) -> f32 {
+	var d = abs(p) - half_extent;
+	return length(max(d, vec2<f32>(0.0))) + min(max(d.x, d.y), 0.0);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+	var radius = in.size / 2.0;
+	var signed_distance: f32;
+
+	switch in.marker {
+		// Square
+		case 1u: {
+			signed_distance = box_sdf(in.local, vec2<f32>(radius));
+		}
+		// Cross
+		case 2u: {
+			var thickness = max(in.size * 0.1, 0.5);
+			signed_distance = min(
+				box_sdf(in.local, vec2<f32>(radius, thickness)),
+				box_sdf(in.local, vec2<f32>(thickness, radius))
+			);
+		}
+		// Circle
+		default: {
+			signed_distance = length(in.local) - radius;
+		}
+	}
+
+	var coverage = clamp(0.5 - signed_distance, 0.0, 1.0);
+	return vec4<f32>(in.color.xyz, in.color.w * coverage);
+}
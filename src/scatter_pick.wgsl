@@ -0,0 +1,68 @@
+struct Point {
+	position: vec2<f32>,
+	size: f32,
+	marker: u32,
+	color: vec4<f32>,
+}
+
+struct PickUniform {
+	// Relative to the scatter plot's origin, like the point positions
+	cursor: vec2<f32>,
+	radius: f32,
+	count: u32,
+}
+
+struct PickResult {
+	distance: atomic<u32>,
+	index: atomic<u32>,
+}
+
+@group(0) @binding(0)
+var<uniform> pick: PickUniform;
+@group(0) @binding(1)
+var<storage, read> points: array<Point>;
+@group(0) @binding(2)
+var<storage, read_write> result: PickResult;
+
+fn point_index(id: vec3<u32>, num_workgroups: vec3<u32>) -> u32 {
+	return id.x + id.y * num_workgroups.x * 64u;
+}
+
+fn distance_bits(i: u32) -> u32 {
+	var d = distance(points[i].position, pick.cursor);
+	if(d > pick.radius) {
+		// Out of range, larger than any real distance
+		return 0xffffffffu;
+	}
+	// Non-negative floats sort the same way as their bit patterns
+	return bitcast<u32>(d);
+}
+
+// First pass: the smallest distance of any point within the radius.
+@compute @workgroup_size(64)
+fn find_distance(
+	@builtin(global_invocation_id) id: vec3<u32>,
+	@builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+	var i = point_index(id, num_workgroups);
+	if(i >= pick.count) {
+		return;
+	}
+	atomicMin(&result.distance, distance_bits(i));
+}
+
+// Second pass: the lowest index with that distance.
+@compute @workgroup_size(64)
+fn find_index(
+	@builtin(global_invocation_id) id: vec3<u32>,
+	@builtin(num_workgroups) num_workgroups: vec3<u32>,
+) {
+	var i = point_index(id, num_workgroups);
+	if(i >= pick.count) {
+		return;
+	}
+	var bits = distance_bits(i);
+	if(bits != 0xffffffffu && bits == atomicLoad(&result.distance)) {
+		atomicMin(&result.index, i);
+	}
+}
@@ -0,0 +1,859 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    time::Duration,
+};
+
+use instant::Instant;
+
+use crate::{
+    camera::Camera,
+    custom::{CustomDrawable, CustomNodes},
+    geo::GeoLayer,
+    heatmap::Heatmap,
+    image::Image,
+    line::Polyline,
+    path::Shape,
+    recorder::Recording,
+    rect::{HitRegion, Rect},
+    scatter::ScatterPlot,
+    text_effects::GlyphEffect,
+};
+
+/// Everything drawn in the window.
+#[derive(Clone, Default)]
+pub struct Scene {
+    pub rects: Vec<Rect>,
+    /// The world position the rects are placed relative to; see `DrawList::set_origin`.
+    pub rect_origin: [f64; 2],
+    /// Input areas for rects, by index into `rects`, where they differ from what is drawn.
+    pub hit_regions: Vec<(usize, HitRegion)>,
+    /// Layers for rects, by index into `rects`. Rects not listed are in `Layer::Content`.
+    pub layers: Vec<(usize, Layer)>,
+    /// Application tags for rects, by index into `rects`; see `DrawList::set_tag`.
+    pub tags: Vec<(usize, u64)>,
+    /// Images for `Fill::Image` and `Fill::Pattern`, by id. They are packed into one texture
+    /// when the renderer starts.
+    pub images: Vec<(u64, Image)>,
+    pub heatmaps: Vec<Heatmap>,
+    pub scatters: Vec<ScatterPlot>,
+    pub geo: Vec<GeoLayer>,
+    /// Filled and stroked paths, drawn over the geo layers.
+    pub shapes: Vec<Shape>,
+    /// Drawn over the shapes, under scatters and rects.
+    pub lines: Vec<Polyline>,
+    /// Shown in the top-left corner.
+    pub text: String,
+    /// Animations applied to each glyph of `text`.
+    pub text_effects: Vec<GlyphEffect>,
+}
+
+impl Scene {
+    /// The operations that turn the rects described by `old` into those described by `new`,
+    /// matching nodes by key. Removals come first, then everything else in `new`'s order.
+    ///
+    /// Nodes that keep their relative order stay where they are; only those after the first
+    /// out-of-order node in each layer are brought to the front again.
+    pub fn diff(old: &[SceneNode], new: &[SceneNode]) -> Vec<NodeOp> {
+        let old_nodes: HashMap<u64, (usize, &SceneNode)> = old
+            .iter()
+            .enumerate()
+            .map(|(position, node)| (node.key, (position, node)))
+            .collect();
+        let new_keys: HashSet<u64> = new.iter().map(|node| node.key).collect();
+
+        let mut ops: Vec<NodeOp> = old
+            .iter()
+            .filter(|node| !new_keys.contains(&node.key))
+            .map(|node| NodeOp::Remove(node.key))
+            .collect();
+        // The old position of the frontmost node kept in place in each layer, or `None` once
+        // a node in that layer had to move.
+        let mut in_place: HashMap<Layer, Option<usize>> = HashMap::new();
+        for node in new {
+            let front = in_place.entry(node.layer).or_insert(Some(0));
+            let Some(&(position, old_node)) = old_nodes.get(&node.key) else {
+                *front = None;
+                ops.push(NodeOp::Insert(*node));
+                continue;
+            };
+            if old_node != node {
+                ops.push(NodeOp::Update(*node));
+            }
+            match *front {
+                Some(frontmost) if old_node.layer == node.layer && position >= frontmost => {
+                    *front = Some(position)
+                }
+                _ => {
+                    *front = None;
+                    ops.push(NodeOp::BringToFront(node.key));
+                }
+            }
+        }
+        ops
+    }
+}
+
+/// A rect in a declarative description of the scene, identified across descriptions by `key`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SceneNode {
+    pub key: u64,
+    pub rect: Rect,
+    pub layer: Layer,
+    pub hit_region: Option<HitRegion>,
+    pub tag: Option<u64>,
+}
+
+/// A change to a keyed rect in a `DrawList`, from `Scene::diff`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NodeOp {
+    /// Adds the node in front of its layer.
+    Insert(SceneNode),
+    /// Replaces the node's rect, hit region and tag in place, or moves it to the front of its
+    /// new layer.
+    Update(SceneNode),
+    /// Removes the node like `DrawList::remove`, so the last rect takes over its index.
+    Remove(u64),
+    BringToFront(u64),
+}
+
+/// Bands that rects are drawn in, from back to front. Within a layer, rects are drawn in the
+/// order they were added until `DrawList::bring_to_front` or `DrawList::send_to_back` moves
+/// them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layer {
+    Background,
+    #[default]
+    Content,
+    Overlay,
+    Debug,
+}
+
+/// Rects drawn once into a texture of their own and composited as a single quad every frame
+/// until they change, which makes detailed static panels nearly free to draw. See
+/// `DrawList::set_cached_layer`.
+///
+/// The rects are positioned like the draw list's and cut off at the layer's bounds. Moving the
+/// camera moves the texture along; zooming draws the rects again at the new zoom. They aren't
+/// hit tested.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CachedLayer {
+    /// Center of the area the layer covers.
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    /// `Layer::Background` layers are drawn under the draw list's rects, the others over them
+    /// and under the text.
+    pub layer: Layer,
+    pub rects: Vec<Rect>,
+}
+
+/// Rects the application rebuilds as often as it likes, e.g. every frame from the callback
+/// passed to `run_with_frame`. The renderer re-uploads the geometry only after a change.
+///
+/// Rects keep the index they were added at, whatever their layer and place in the draw order,
+/// until a rect is removed: `remove` moves the last rect into the removed one's index and
+/// returns the index it moved from. Rects added by `apply_diff` are found again by key.
+#[derive(Clone, Default)]
+pub struct DrawList {
+    rects: Vec<Rect>,
+    /// Each rect's layer and place within it.
+    places: Vec<(Layer, i64)>,
+    /// Rect indices by key, which iterates in draw order without sorting.
+    order: BTreeMap<(Layer, i64), usize>,
+    /// The backmost and frontmost place taken in each layer.
+    ends: [[i64; 2]; 4],
+    hit_regions: HashMap<usize, HitRegion>,
+    tags: HashMap<usize, u64>,
+    /// Index of every rect added by `apply_diff`, by node key, and the key of each rect.
+    nodes: HashMap<u64, usize>,
+    node_keys: Vec<Option<u64>>,
+    /// Cached layers by key, with the number of the `set_cached_layer` call that set each.
+    cached_layers: BTreeMap<u64, (CachedLayer, u64)>,
+    /// Counts `set_cached_layer` calls, which tells a layer set again from the one drawn
+    /// before.
+    cached_layer_sets: u64,
+    changed: bool,
+    cached_layers_changed: bool,
+    /// World position of `[0, 0]` in the rects' coordinates.
+    origin: [f64; 2],
+}
+
+impl DrawList {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `rect` in front of the content layer, returning its index.
+    pub fn push_rect(&mut self, rect: Rect) -> usize {
+        self.push_rect_in(Layer::Content, rect)
+    }
+
+    /// Adds `rect` in front of `layer`, returning its index.
+    pub fn push_rect_in(&mut self, layer: Layer, rect: Rect) -> usize {
+        let index = self.rects.len();
+        let place = self.front_place(layer);
+        self.rects.push(rect);
+        self.places.push(place);
+        self.node_keys.push(None);
+        self.order.insert(place, index);
+        self.changed = true;
+        index
+    }
+
+    pub fn extend(&mut self, rects: impl IntoIterator<Item = Rect>) {
+        for rect in rects {
+            self.push_rect(rect);
+        }
+    }
+
+    /// Removes the rect at `index` and returns it. The last rect takes over `index`, with its
+    /// layer, hit region, tag and node key, so this costs the same wherever the rect is; its old
+    /// index is returned too, or `None` when the removed rect was the last.
+    pub fn remove(&mut self, index: usize) -> (Rect, Option<usize>) {
+        let last = self.rects.len() - 1;
+        self.order.remove(&self.places[index]);
+        self.hit_regions.remove(&index);
+        self.tags.remove(&index);
+        if let Some(key) = self.node_keys[index] {
+            self.nodes.remove(&key);
+        }
+        if index != last {
+            self.order.insert(self.places[last], index);
+            if let Some(region) = self.hit_regions.remove(&last) {
+                self.hit_regions.insert(index, region);
+            }
+            if let Some(tag) = self.tags.remove(&last) {
+                self.tags.insert(index, tag);
+            }
+            if let Some(key) = self.node_keys[last] {
+                self.nodes.insert(key, index);
+            }
+        }
+        self.places.swap_remove(index);
+        self.node_keys.swap_remove(index);
+        self.changed = true;
+        let rect = self.rects.swap_remove(index);
+        (rect, (index != last).then_some(last))
+    }
+
+    /// Replaces the rect at `index`, keeping its layer, place in the draw order, hit region
+    /// and tag.
+    pub fn set_rect(&mut self, index: usize, rect: Rect) {
+        self.rects[index] = rect;
+        self.changed = true;
+    }
+
+    pub fn layer(&self, index: usize) -> Layer {
+        self.places[index].0
+    }
+
+    /// Moves the rect at `index` to the front of `layer`.
+    pub fn set_layer(&mut self, index: usize, layer: Layer) {
+        let place = self.front_place(layer);
+        self.move_to(index, place);
+    }
+
+    /// Draws the rect at `index` over every other rect in its layer.
+    pub fn bring_to_front(&mut self, index: usize) {
+        self.set_layer(index, self.layer(index));
+    }
+
+    /// Draws the rect at `index` under every other rect in its layer.
+    pub fn send_to_back(&mut self, index: usize) {
+        let layer = self.layer(index);
+        let back = &mut self.ends[layer as usize][0];
+        *back -= 1;
+        let place = (layer, *back);
+        self.move_to(index, place);
+    }
+
+    fn front_place(&mut self, layer: Layer) -> (Layer, i64) {
+        let front = &mut self.ends[layer as usize][1];
+        *front += 1;
+        (layer, *front)
+    }
+
+    fn move_to(&mut self, index: usize, place: (Layer, i64)) {
+        self.order.remove(&self.places[index]);
+        self.order.insert(place, index);
+        self.places[index] = place;
+        self.changed = true;
+    }
+
+    /// Index of the rect added for the node with `key`.
+    pub fn node(&self, key: u64) -> Option<usize> {
+        self.nodes.get(&key).copied()
+    }
+
+    /// Key of the node the rect at `index` was added for by `apply_diff`.
+    pub fn node_key(&self, index: usize) -> Option<u64> {
+        self.node_keys[index]
+    }
+
+    /// Brings the rects added for `old` up to date with `new`, touching only the nodes that
+    /// changed, and returns the operations it applied. `old` must be the description last
+    /// applied, or empty at first.
+    pub fn apply_diff(&mut self, old: &[SceneNode], new: &[SceneNode]) -> Vec<NodeOp> {
+        let ops = Scene::diff(old, new);
+        for op in &ops {
+            self.apply(op);
+        }
+        ops
+    }
+
+    /// Applies one operation from `Scene::diff`. Operations on unknown keys are ignored.
+    pub fn apply(&mut self, op: &NodeOp) {
+        match *op {
+            NodeOp::Insert(node) => {
+                let index = self.push_rect_in(node.layer, node.rect);
+                self.set_node_hit_region(index, node.hit_region);
+                self.set_node_tag(index, node.tag);
+                self.node_keys[index] = Some(node.key);
+                self.nodes.insert(node.key, index);
+            }
+            NodeOp::Update(node) => {
+                let Some(index) = self.node(node.key) else {
+                    return;
+                };
+                self.rects[index] = node.rect;
+                self.set_node_hit_region(index, node.hit_region);
+                self.set_node_tag(index, node.tag);
+                if self.layer(index) != node.layer {
+                    self.set_layer(index, node.layer);
+                }
+                self.changed = true;
+            }
+            NodeOp::Remove(key) => {
+                if let Some(index) = self.node(key) {
+                    self.remove(index);
+                }
+            }
+            NodeOp::BringToFront(key) => {
+                if let Some(index) = self.node(key) {
+                    self.bring_to_front(index);
+                }
+            }
+        }
+    }
+
+    fn set_node_hit_region(&mut self, index: usize, region: Option<HitRegion>) {
+        match region {
+            Some(region) => self.set_hit_region(index, region),
+            None => {
+                self.hit_regions.remove(&index);
+            }
+        }
+    }
+
+    fn set_node_tag(&mut self, index: usize, tag: Option<u64>) {
+        match tag {
+            Some(tag) => self.set_tag(index, tag),
+            None => {
+                self.remove_tag(index);
+            }
+        }
+    }
+
+    /// Sets the cached layer with `key`, replacing any before. It is drawn into its texture
+    /// again before the next frame; layers that aren't set again keep theirs.
+    pub fn set_cached_layer(&mut self, key: u64, layer: CachedLayer) {
+        self.cached_layer_sets += 1;
+        self.cached_layers
+            .insert(key, (layer, self.cached_layer_sets));
+        self.cached_layers_changed = true;
+    }
+
+    pub fn cached_layer(&self, key: u64) -> Option<&CachedLayer> {
+        self.cached_layers.get(&key).map(|(layer, _)| layer)
+    }
+
+    /// Removes the cached layer with `key` and frees its texture.
+    pub fn remove_cached_layer(&mut self, key: u64) -> Option<CachedLayer> {
+        let (layer, _) = self.cached_layers.remove(&key)?;
+        self.cached_layers_changed = true;
+        Some(layer)
+    }
+
+    /// The cached layers by key, each with a number that changes whenever it is set.
+    pub(crate) fn cached_layers(&self) -> impl Iterator<Item = (u64, &CachedLayer, u64)> {
+        self.cached_layers
+            .iter()
+            .map(|(key, (layer, set))| (*key, layer, *set))
+    }
+
+    /// Rect indices from back to front.
+    pub fn draw_order(&self) -> impl DoubleEndedIterator<Item = usize> + '_ {
+        self.order.values().copied()
+    }
+
+    /// The rects from back to front.
+    pub fn ordered_rects(&self) -> Vec<Rect> {
+        self.draw_order().map(|i| self.rects[i]).collect()
+    }
+
+    /// Makes the rect at `index` receive input in `region` instead of where it is drawn.
+    pub fn set_hit_region(&mut self, index: usize, region: HitRegion) {
+        self.hit_regions.insert(index, region);
+    }
+
+    pub fn hit_region(&self, index: usize) -> Option<HitRegion> {
+        self.hit_regions.get(&index).copied()
+    }
+
+    /// Tags the rect at `index` with a number of the application's choosing, e.g. the id of
+    /// the object it draws, to map hits and saved scenes back to it. Tags don't change how
+    /// the rect is drawn, and several rects may share one.
+    pub fn set_tag(&mut self, index: usize, tag: u64) {
+        self.tags.insert(index, tag);
+    }
+
+    pub fn tag(&self, index: usize) -> Option<u64> {
+        self.tags.get(&index).copied()
+    }
+
+    pub fn remove_tag(&mut self, index: usize) -> Option<u64> {
+        self.tags.remove(&index)
+    }
+
+    /// Indices of the rects tagged `tag`, in rect order.
+    pub fn tagged(&self, tag: u64) -> Vec<usize> {
+        let mut indices: Vec<usize> = self
+            .tags
+            .iter()
+            .filter(|(_, t)| **t == tag)
+            .map(|(i, _)| *i)
+            .collect();
+        indices.sort_unstable();
+        indices
+    }
+
+    /// Every hit region with the index of its rect, in rect order.
+    pub fn hit_regions(&self) -> Vec<(usize, HitRegion)> {
+        let mut regions: Vec<(usize, HitRegion)> = self
+            .hit_regions
+            .iter()
+            .map(|(i, region)| (*i, *region))
+            .collect();
+        regions.sort_by_key(|(i, _)| *i);
+        regions
+    }
+
+    /// The rects in draw order, with their layers and hit regions indexed to match, the way
+    /// `Scene` holds them.
+    pub fn to_scene_parts(&self) -> (Vec<Rect>, Vec<(usize, Layer)>, Vec<(usize, HitRegion)>) {
+        let position = self.draw_positions();
+        let layers = self
+            .draw_order()
+            .enumerate()
+            .filter(|(_, i)| self.layer(*i) != Layer::Content)
+            .map(|(new, i)| (new, self.layer(i)))
+            .collect();
+        let mut hit_regions: Vec<(usize, HitRegion)> = self
+            .hit_regions
+            .iter()
+            .map(|(i, region)| (position[*i], *region))
+            .collect();
+        hit_regions.sort_by_key(|(i, _)| *i);
+        (self.ordered_rects(), layers, hit_regions)
+    }
+
+    /// The tags indexed like the rects of `to_scene_parts`.
+    pub fn scene_tags(&self) -> Vec<(usize, u64)> {
+        let position = self.draw_positions();
+        let mut tags: Vec<(usize, u64)> = self
+            .tags
+            .iter()
+            .map(|(i, tag)| (position[*i], *tag))
+            .collect();
+        tags.sort_by_key(|(i, _)| *i);
+        tags
+    }
+
+    /// Each rect's place in the draw order, by index.
+    fn draw_positions(&self) -> Vec<usize> {
+        let mut position = vec![0; self.rects.len()];
+        for (new, old) in self.draw_order().enumerate() {
+            position[old] = new;
+        }
+        position
+    }
+
+    /// Removes the rects with their layers, hit regions, tags and node keys. Cached layers
+    /// stay, so a list rebuilt every frame only sets them again when they change.
+    pub fn clear(&mut self) {
+        *self = Self {
+            cached_layers: std::mem::take(&mut self.cached_layers),
+            cached_layer_sets: self.cached_layer_sets,
+            changed: true,
+            cached_layers_changed: self.cached_layers_changed,
+            origin: self.origin,
+            ..Self::default()
+        };
+    }
+
+    pub fn origin(&self) -> [f64; 2] {
+        self.origin
+    }
+
+    /// Places the rects relative to `origin` in the world rather than to `[0, 0]`. Positions
+    /// are f32, which steps by a sixteenth of a unit around 1e6, so content that far out keeps
+    /// its precision when the origin is set near it and the rects are given relative to that.
+    /// Clearing the draw list keeps the origin.
+    pub fn set_origin(&mut self, origin: [f64; 2]) {
+        if origin != self.origin {
+            self.origin = origin;
+            self.changed = true;
+        }
+    }
+
+    /// `camera` moved to see the rects' coordinates, with the origin taken off its offset in
+    /// f64.
+    pub(crate) fn local_camera(&self, camera: &Camera) -> Camera {
+        Camera {
+            offset: [0, 1].map(|i| camera.offset[i] - self.origin[i]),
+            ..*camera
+        }
+    }
+
+    /// `world` in the rects' coordinates.
+    pub(crate) fn to_local(&self, world: [f64; 2]) -> [f32; 2] {
+        [0, 1].map(|i| (world[i] - self.origin[i]) as f32)
+    }
+
+    /// The rects by index, in the order they were added.
+    pub fn rects(&self) -> &[Rect] {
+        &self.rects
+    }
+
+    pub fn len(&self) -> usize {
+        self.rects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+
+    /// Index of the topmost rect at `point`, tested against its hit region if it has one. See
+    /// `Rect::hit_test`. The highest `z_index` is on top, then the latest in draw order.
+    pub fn rect_at(&self, point: [f32; 2], pixel: f32, tolerance: f32) -> Option<usize> {
+        self.draw_order()
+            .filter(|i| {
+                let rect = &self.rects[*i];
+                match self.hit_regions.get(i) {
+                    Some(region) => region.hit_test(rect, point, pixel, tolerance),
+                    None => rect.hit_test(point, pixel, tolerance),
+                }
+            })
+            // `max_by` keeps the last of equal elements.
+            .max_by(|a, b| self.rects[*a].z_index.total_cmp(&self.rects[*b].z_index))
+    }
+
+    /// Tag of the topmost rect at `point`, as found by `rect_at`.
+    pub fn tag_at(&self, point: [f32; 2], pixel: f32, tolerance: f32) -> Option<u64> {
+        self.tag(self.rect_at(point, pixel, tolerance)?)
+    }
+
+    /// Whether the rects or cached layers changed since they were last taken, without
+    /// resetting it.
+    pub(crate) fn is_changed(&self) -> bool {
+        self.changed || self.cached_layers_changed
+    }
+
+    /// Whether the rects changed since the last call.
+    pub(crate) fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// Whether the cached layers changed since the last call.
+    pub(crate) fn take_cached_layers_changed(&mut self) -> bool {
+        std::mem::take(&mut self.cached_layers_changed)
+    }
+}
+
+impl From<Vec<Rect>> for DrawList {
+    fn from(rects: Vec<Rect>) -> Self {
+        let mut draw_list = Self::new();
+        draw_list.extend(rects);
+        draw_list
+    }
+}
+
+/// Handed to the `run_with_frame` callback whenever the event loop wakes up. With
+/// `RedrawMode::OnChange`, a frame is only drawn when the callback or an event changed
+/// something, so a callback that leaves the draw list alone lets the loop sleep until the next
+/// input or deadline.
+pub struct Frame<'a> {
+    pub draw_list: &'a mut DrawList,
+    /// As of the end of the previous frame.
+    pub stats: FrameStats,
+    /// The window's scale factor, fractional on Wayland at 125% or 150%. Rects are placed in
+    /// physical pixels, so sizes meant in logical pixels are multiplied by it; the scene text
+    /// already is.
+    pub scale_factor: f64,
+    /// The refresh rate of the monitor the window is on, in hertz, when the system reports
+    /// one. It follows the window to other monitors.
+    pub refresh_rate: Option<f64>,
+    /// Seconds since the renderer started, as of when this frame will be shown. With vsync it
+    /// advances by whole refresh intervals, so animations and interpolation timed by it run
+    /// evenly at 120 or 144 Hz too. The scene's glyph effects use the same clock.
+    pub time: f64,
+    /// The quality `RendererConfig::quality_governor` settled on, to scale the callback's own
+    /// detail by. Always `Quality::High` without the governor.
+    pub quality: crate::quality::Quality,
+    /// Whether the window is fullscreen, as of the start of this callback. F11 switches
+    /// between `Windowed` and `Borderless` too.
+    pub window_mode: crate::WindowMode,
+    /// The index of the draw list's rect under the mouse cursor, hit tested against hit
+    /// regions when the cursor last moved, for hover feedback such as `set_cursor`.
+    pub hovered_rect: Option<usize>,
+    /// The colors of the renderer's own panels, as changed in the theme editor, to draw
+    /// `Menus::widget` and the callback's own widgets with.
+    pub theme: crate::theme::Theme,
+    #[cfg(feature = "taskbar")]
+    pub taskbar: &'a mut crate::taskbar::Taskbar,
+    #[cfg(feature = "dialog")]
+    pub dialogs: &'a mut crate::dialog::Dialogs,
+    #[cfg(feature = "menu")]
+    pub menu: &'a mut crate::menu::Menus,
+    /// Clicks on the tray icon and its menu since the previous call.
+    #[cfg(feature = "tray")]
+    pub tray_events: Vec<crate::tray::TrayEvent>,
+    /// The frame read back after `capture_frame` was called, in the callback that follows.
+    pub screenshot: Option<crate::capture::Screenshot>,
+    /// The color read after `pick_color` or with the inspector's eyedropper, in the callback
+    /// that follows: linear RGB like `Fill` colors, with the frame's alpha.
+    pub picked_color: Option<[f32; 4]>,
+    pub(crate) gc: bool,
+    pub(crate) capture: bool,
+    pub(crate) print_job: Option<crate::print::PrintJob>,
+    pub(crate) recording: Option<Recording>,
+    pub(crate) present_mode: Option<crate::PresentMode>,
+    pub(crate) window_mode_change: Option<crate::WindowMode>,
+    pub(crate) cursor: Option<crate::Cursor>,
+    pub(crate) invalidated: bool,
+    pub(crate) deadline: Option<Instant>,
+    pub(crate) images: Vec<(u64, Image)>,
+    pub(crate) color_pick: Option<[f32; 2]>,
+    pub(crate) custom: &'a mut CustomNodes,
+}
+
+/// Counts of work the renderer did or avoided since it started.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    pub frames: u64,
+    /// Frames that laid the text out for the GPU again.
+    pub text_prepares: u64,
+    /// Custom nodes tessellated and uploaded, after being built or moving to a new device.
+    /// Drawing a node with the cache key it already has doesn't add to it.
+    pub custom_uploads: u64,
+    /// How long the GPU took to finish the latest frame it had finished when the last one was
+    /// submitted, measured from that frame's submission. `None` until the GPU finishes one.
+    pub gpu_latency: Option<Duration>,
+}
+
+impl FrameStats {
+    /// Frames that drew the text as laid out before, since neither it nor the window changed.
+    pub fn text_prepares_skipped(&self) -> u64 {
+        self.frames.saturating_sub(self.text_prepares)
+    }
+}
+
+impl Frame<'_> {
+    /// Frees cached resources after this frame, whatever the `CachePolicy`.
+    pub fn gc(&mut self) {
+        self.gc = true;
+    }
+
+    /// Draws a frame even though the draw list didn't change, for changes the renderer can't
+    /// see.
+    pub fn invalidate(&mut self) {
+        self.invalidated = true;
+    }
+
+    /// Draws the frame as it is after this callback and reads it back from the window, for
+    /// `screenshot` in the next callback. egui and the pixel grid are included. Not on wasm32,
+    /// where waiting for the read back would block the browser.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn capture_frame(&mut self) {
+        self.capture = true;
+    }
+
+    /// Reads the color of the pixel at `point`, in physical pixels from the window's top-left
+    /// corner, from the next frame drawn. It's copied on the GPU and read back without
+    /// waiting for it, so `picked_color` has it a frame or two later.
+    pub fn pick_color(&mut self, point: [f32; 2]) {
+        self.color_pick = Some(point);
+    }
+
+    /// Writes the view to `path` as a PDF after this frame; see `print`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn export_pdf(
+        &mut self,
+        path: impl Into<std::path::PathBuf>,
+        config: crate::print::PrintConfig,
+    ) {
+        self.print_job = Some(crate::print::PrintJob {
+            path: Some(path.into()),
+            config,
+        });
+    }
+
+    /// Prints the view after this frame, rendered again at `config.dpi` and split into pages.
+    /// The PDF opens in the system's viewer, whose print dialog picks the printer. Not on
+    /// wasm32, where waiting for the pages to be read back would block the browser.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn print(&mut self, config: crate::print::PrintConfig) {
+        self.print_job = Some(crate::print::PrintJob { path: None, config });
+    }
+
+    /// Streams every frame drawn after this callback into `sink`, e.g. an `FfmpegSink`, until
+    /// `stop_recording` is called or the sink fails. A recording already running is finished
+    /// first. Not on wasm32, where waiting for each frame to be read back would block the
+    /// browser.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn start_recording(&mut self, sink: impl crate::recorder::FrameSink + 'static) {
+        self.recording = Some(Recording::Start(Box::new(sink)));
+    }
+
+    /// Finishes the running recording after this callback, if there is one.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn stop_recording(&mut self) {
+        self.recording = Some(Recording::Stop);
+    }
+
+    /// Switches the window to `mode` from the next frame on, or to its fallback.
+    pub fn set_present_mode(&mut self, mode: crate::PresentMode) {
+        self.present_mode = Some(mode);
+    }
+
+    /// Turns vsync on or off from the next frame on; see `PresentMode::vsync`.
+    pub fn set_vsync(&mut self, vsync: bool) {
+        self.set_present_mode(crate::PresentMode::vsync(vsync));
+    }
+
+    /// Switches the window in or out of fullscreen after this callback. The window reports its
+    /// new size a moment later, sometimes after an animation, and the frame is drawn at it
+    /// from then on.
+    pub fn set_window_mode(&mut self, mode: crate::WindowMode) {
+        self.window_mode_change = Some(mode);
+    }
+
+    /// Shows `cursor` over the window from now on, until it's set again. Setting the one
+    /// already shown costs nothing, so hover feedback can set it in every callback.
+    pub fn set_cursor(&mut self, cursor: crate::Cursor) {
+        self.cursor = Some(cursor);
+    }
+
+    /// Adds `image` for rects to show as `id`, or replaces the one with that id. It is
+    /// uploaded over the next frames within `RendererConfig::upload_budget`. Until all of it
+    /// arrived, rects show the image it replaces, partly written over when both have the same
+    /// size, or their tint alone.
+    pub fn add_image(&mut self, id: u64, image: Image) {
+        self.images.push((id, image));
+    }
+
+    /// Draws `drawable` as the custom node `key`, over the scene's shapes and lines. It is
+    /// built and tessellated again only when its cache key differs from the last one drawn
+    /// as `key`, or after `invalidate_custom`. The node stays drawn until it's removed,
+    /// whether this is called again in later frames or not.
+    pub fn draw_custom(&mut self, key: u64, drawable: &dyn CustomDrawable) {
+        self.custom.draw(key, drawable);
+    }
+
+    /// Builds the custom node `key` again the next time it's drawn, even with the same cache
+    /// key, for changes the key doesn't cover.
+    pub fn invalidate_custom(&mut self, key: u64) {
+        self.custom.invalidate(key);
+    }
+
+    pub fn remove_custom(&mut self, key: u64) {
+        self.custom.remove(key);
+    }
+
+    /// Wakes the event loop at `deadline` to call the callback again and draw a frame, for
+    /// animations that step at their own pace. The earliest of several deadlines wins.
+    pub fn redraw_at(&mut self, deadline: Instant) {
+        self.deadline = Some(self.deadline.map_or(deadline, |d| d.min(deadline)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn origin_keeps_rects_far_out_precise() {
+        let mut draw_list = DrawList::new();
+        draw_list.set_origin([1e6, -1e6]);
+        let camera = Camera {
+            offset: [1e6 - 100.0, -1e6 - 50.0],
+            zoom: 2.0,
+        };
+        // A 1024th of a unit right of the origin, which an f32 position at 1e6 would lose.
+        let x = 1.0 / 1024.0;
+        assert_eq!((1e6 + x) as f32, 1e6);
+        let local = draw_list.to_local([1e6 + x, -1e6]);
+        assert_eq!(local, [x as f32, 0.0]);
+        let screen = draw_list
+            .local_camera(&camera)
+            .to_screen(local.map(f64::from));
+        assert_eq!(screen, [(100.0 + x) * 2.0, 100.0]);
+    }
+
+    fn tagged_list(count: u64) -> DrawList {
+        let mut draw_list = DrawList::new();
+        for tag in 0..count {
+            let index = draw_list.push_rect(Rect {
+                position: [tag as f32, 0.0],
+                ..Default::default()
+            });
+            draw_list.set_tag(index, tag);
+        }
+        draw_list
+    }
+
+    #[test]
+    fn removing_moves_the_last_rect_into_the_gap() {
+        let mut draw_list = tagged_list(4);
+        draw_list.set_hit_region(3, HitRegion::Outset(2.0));
+        draw_list.set_layer(3, Layer::Overlay);
+
+        let (rect, moved) = draw_list.remove(1);
+        assert_eq!(rect.position, [1.0, 0.0]);
+        assert_eq!(moved, Some(3));
+        assert_eq!(draw_list.len(), 3);
+        assert_eq!(draw_list.rects()[1].position, [3.0, 0.0]);
+        assert_eq!(draw_list.tag(1), Some(3));
+        assert_eq!(draw_list.layer(1), Layer::Overlay);
+        assert_eq!(draw_list.hit_region(1), Some(HitRegion::Outset(2.0)));
+
+        assert_eq!(draw_list.remove(2).1, None);
+        assert_eq!(draw_list.len(), 2);
+    }
+
+    #[test]
+    fn removed_nodes_leave_the_others_findable_by_key() {
+        let nodes: Vec<SceneNode> = (0..3)
+            .map(|key| SceneNode {
+                key,
+                rect: Rect {
+                    position: [key as f32, 0.0],
+                    ..Default::default()
+                },
+                layer: Layer::Content,
+                hit_region: None,
+                tag: None,
+            })
+            .collect();
+        let mut draw_list = DrawList::new();
+        draw_list.apply_diff(&[], &nodes);
+        let ops = draw_list.apply_diff(&nodes, &nodes[1..]);
+
+        assert_eq!(ops, [NodeOp::Remove(0)]);
+        assert_eq!(draw_list.node(0), None);
+        for key in 1..3 {
+            let index = draw_list.node(key).unwrap();
+            assert_eq!(draw_list.node_key(index), Some(key));
+            assert_eq!(draw_list.rects()[index].position, [key as f32, 0.0]);
+        }
+    }
+}
@@ -1,53 +1,147 @@
 struct WindowUniform {
 	size: vec2<f32>,
 	scale_factor: f32,
-	padding: f32,
+	// 1.0 when y grows downwards from a top-left origin, -1.0 when it grows upwards
+	y_direction: f32,
 }
 @group(0) @binding(0)
 var<uniform> window: WindowUniform;
 
-struct VertexInput {
-	@location(0) position: vec2<f32>,
-	@location(1) z_index: f32,
-	@location(2) color: vec4<f32>,
-	@location(3) border_radius: f32,
-	@location(4) rect_pos: vec2<f32>,
-	@location(5) rect_size: vec2<f32>,
-	@location(6) softness: f32,
+@group(1) @binding(0)
+var image_atlas: texture_2d<f32>;
+@group(1) @binding(1)
+var image_sampler: sampler;
+
+struct RectStyle {
+	// x is the fill kind (0 solid, 1 radial, 2 conic, 3 image, 4 stroke, 5 pattern, 6 noise), yz
+	// the gradient center relative to the rect, w the radius or start angle. Strokes have their
+	// width in y, patterns their offset in yz and rotation in w, noise its offset in yz and
+	// scale in w.
+	gradient: vec4<f32>,
+	// Patterns have their tile size in xy
+	stop_offsets: vec4<f32>,
+	// The solid color or tint is the first stop
+	stop_colors: array<vec4<f32>, 4>,
+	// left, top, right, bottom of the image in the atlas, or the noise's octaves in x
+	uv: vec4<f32>,
 }
+// Group 2 and load_style come from styles_buffer.wgsl or styles_texture.wgsl, whichever the
+// device can bind
 
-struct VertexOutput {
-	@builtin(position) clip_position: vec4<f32>,
-	@location(0) color: vec4<f32>,
-	@location(1) border_radius: f32,
+struct InstanceInput {
+	// Depth from the instance's place in the paint order, nearer is greater
+	@location(0) z_index: f32,
+	// top-left, top-right, bottom-right, bottom-left in rect coordinates
+	@location(1) border_radius: vec4<f32>,
 	@location(2) rect_pos: vec2<f32>,
 	@location(3) rect_size: vec2<f32>,
 	@location(4) softness: f32,
+	@location(5) rotation: f32,
+	// Smallest and largest corner of the clip, in rect coordinates
+	@location(6) clip: vec4<f32>,
+	// Corner radii of the clip, in the same order as border_radius
+	@location(7) clip_radius: vec4<f32>,
+	// Index into the style table
+	@location(8) style: u32,
+}
+
+// RendererConfig::packed_vertices. The half floats arrive as f32 already.
+struct PackedInstanceInput {
+	@location(0) rect_pos: vec2<f32>,
+	@location(1) rect_size: vec2<f32>,
+	@location(2) rotation: f32,
+	@location(3) z_index: f32,
+	@location(4) border_radius: vec4<f32>,
+	// x is the softness, y is unused
+	@location(5) softness: vec2<f32>,
+	@location(6) clip: vec4<f32>,
+	@location(7) clip_radius: vec4<f32>,
+	@location(8) style: u32,
+}
+
+struct VertexOutput {
+	@builtin(position) clip_position: vec4<f32>,
+	@location(0) border_radius: vec4<f32>,
+	@location(1) rect_pos: vec2<f32>,
+	@location(2) rect_size: vec2<f32>,
+	@location(3) softness: f32,
+	@location(4) rotation: f32,
+	@location(5) @interpolate(flat) style: u32,
+	@location(6) @interpolate(flat) clip: vec4<f32>,
+	@location(7) @interpolate(flat) clip_radius: vec4<f32>,
 };
 
+// Turns from the x axis towards the y axis
+fn rotate(v: vec2<f32>, angle: f32) -> vec2<f32> {
+	var c = cos(angle);
+	var s = sin(angle);
+	return vec2<f32>(v.x * c - v.y * s, v.x * s + v.y * c);
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, model: InstanceInput) -> VertexOutput {
+	return transform_vertex(vertex_index, model);
+}
+
 @vertex
-fn vs_main(model: VertexInput) -> VertexOutput {
+fn vs_packed(
+	@builtin(vertex_index) vertex_index: u32,
+	packed: PackedInstanceInput,
+) -> VertexOutput {
+	var model: InstanceInput;
+	model.z_index = packed.z_index;
+	model.border_radius = packed.border_radius;
+	model.rect_pos = packed.rect_pos;
+	model.rect_size = packed.rect_size;
+	model.softness = packed.softness.x;
+	model.rotation = packed.rotation;
+	model.clip = packed.clip;
+	model.clip_radius = packed.clip_radius;
+	model.style = packed.style;
+	return transform_vertex(vertex_index, model);
+}
+
+fn transform_vertex(vertex_index: u32, model: InstanceInput) -> VertexOutput {
 	var out: VertexOutput;
-	
-	var signs: vec2<f32> = sign(model.position - model.rect_pos);
-	var offset_position = model.position + (signs * model.softness);
+
+	// The unit quad as two triangles, wound the same way as the culled pipeline expects
+	var corners = array<vec2<f32>, 6>(
+		vec2<f32>(1.0, -1.0),
+		vec2<f32>(-1.0, 1.0),
+		vec2<f32>(1.0, 1.0),
+		vec2<f32>(1.0, -1.0),
+		vec2<f32>(-1.0, -1.0),
+		vec2<f32>(-1.0, 1.0),
+	);
+	var direction = corners[vertex_index];
+	// Grow the unrotated corner by the soft edge first, then turn it
+	var corner = direction * (model.rect_size / 2.0 + model.softness);
+	var offset_position = model.rect_pos + rotate(corner, model.rotation);
 	var ndc_position = vec2<f32>(
 		(2.0 * offset_position.x / window.size.x) - 1.0,
-		1.0 - (2.0 * offset_position.y / window.size.y)
+		window.y_direction * (1.0 - (2.0 * offset_position.y / window.size.y))
 	);
 	out.clip_position = vec4<f32>(ndc_position, model.z_index, 1.0);
-	out.color = model.color;
 	out.rect_pos = model.rect_pos;
 	out.rect_size = model.rect_size;
 	out.border_radius = model.border_radius;
 	out.softness = model.softness;
+	out.rotation = model.rotation;
+	out.style = model.style;
+	out.clip = model.clip;
+	out.clip_radius = model.clip_radius;
 	return out;
 }
 
 
-fn rect_sdf(point: vec2<f32>, rect_pos: vec2<f32>, rect_size: vec2<f32>, corner_radius: f32) -> f32 {
+fn rect_sdf(point: vec2<f32>, rect_pos: vec2<f32>, rect_size: vec2<f32>, corner_radii: vec4<f32>) -> f32 {
+
+	var offset: vec2<f32> = point - rect_pos;
+	// Each quadrant is rounded by the radius of the corner it contains.
+	var side: vec2<f32> = select(corner_radii.xw, corner_radii.yz, offset.x > 0.0);
+	var corner_radius: f32 = select(side.x, side.y, offset.y > 0.0);
 
-	var relative_point: vec2<f32> = abs(point - rect_pos);
+	var relative_point: vec2<f32> = abs(offset);
 	
 	var shrunk_corner_position = (rect_size / 2.0) - corner_radius;
 	var point_to_corner = max(vec2<f32>(0.0, 0.0), relative_point - shrunk_corner_position);
@@ -55,15 +149,167 @@ fn rect_sdf(point: vec2<f32>, rect_pos: vec2<f32>, rect_size: vec2<f32>, corner_
 	return length(point_to_corner) - corner_radius;
 }
 
+fn stop_mix(color: vec4<f32>, next: vec4<f32>, t: f32, start: f32, end: f32) -> vec4<f32> {
+	// Coinciding stops make a hard edge
+	return mix(color, next, clamp((t - start) / max(end - start, 1e-6), 0.0, 1.0));
+}
+
+fn image_color(in: VertexOutput, style: RectStyle, point: vec2<f32>) -> vec4<f32> {
+	var relative = (point - in.rect_pos) / in.rect_size + 0.5;
+	// Images keep their top row at the top of the window whichever way y grows
+	if(window.y_direction < 0.0) {
+		relative.y = 1.0 - relative.y;
+	}
+	// Stay half a texel inside the image so filtering never reaches the padding around it
+	var half_texel = 0.5 / vec2<f32>(textureDimensions(image_atlas));
+	var uv = clamp(mix(style.uv.xy, style.uv.zw, relative), style.uv.xy + half_texel, style.uv.zw - half_texel);
+	// The fill kind varies per rect, so sample without the implicit derivatives that would
+	// need uniform control flow. The atlas has no mipmaps anyway.
+	return textureSampleLevel(image_atlas, image_sampler, uv, 0.0) * style.stop_colors[0];
+}
+
+fn pattern_color(in: VertexOutput, style: RectStyle, point: vec2<f32>) -> vec4<f32> {
+	var local = rotate(point - in.rect_pos - style.gradient.yz, -style.gradient.w);
+	var relative = fract(local / max(abs(style.stop_offsets.xy), vec2<f32>(1e-6)));
+	if(window.y_direction < 0.0) {
+		relative.y = 1.0 - relative.y;
+	}
+	// Clamped like image_color, so each tile repeats whole texels without bleeding into the
+	// atlas around it
+	var half_texel = 0.5 / vec2<f32>(textureDimensions(image_atlas));
+	var uv = clamp(mix(style.uv.xy, style.uv.zw, relative), style.uv.xy + half_texel, style.uv.zw - half_texel);
+	return textureSampleLevel(image_atlas, image_sampler, uv, 0.0) * style.stop_colors[0];
+}
+
+// From 0 to 1 for each integer cell. An integer hash, since sin-based ones differ between GPUs
+fn cell_hash(cell: vec2<f32>) -> f32 {
+	var n = bitcast<vec2<u32>>(vec2<i32>(cell));
+	var h = n.x * 0x8da6b343u + n.y * 0xd8163841u;
+	h = (h ^ (h >> 16u)) * 0x7feb352du;
+	h = (h ^ (h >> 15u)) * 0x846ca68bu;
+	h = h ^ (h >> 16u);
+	return f32(h) / 4294967295.0;
+}
+
+fn value_noise(p: vec2<f32>) -> f32 {
+	var cell = floor(p);
+	var f = p - cell;
+	var s = f * f * (3.0 - 2.0 * f);
+	var bottom = mix(cell_hash(cell), cell_hash(cell + vec2<f32>(1.0, 0.0)), s.x);
+	var top = mix(cell_hash(cell + vec2<f32>(0.0, 1.0)), cell_hash(cell + vec2<f32>(1.0, 1.0)), s.x);
+	return mix(bottom, top, s.y);
+}
+
+// Octaves of value noise, each at twice the frequency and half the amplitude of the last,
+// normalized back to 0 to 1
+fn noise(p: vec2<f32>, octaves: u32) -> f32 {
+	var sum = 0.0;
+	var total = 0.0;
+	var amplitude = 1.0;
+	var q = p;
+	for(var i = 0u; i < octaves; i = i + 1u) {
+		sum += amplitude * value_noise(q);
+		total += amplitude;
+		amplitude *= 0.5;
+		// Shifted so the lattices of the octaves don't line up at the origin
+		q = q * 2.0 + vec2<f32>(17.3, 41.9);
+	}
+	return sum / max(total, 1e-6);
+}
+
+fn fill_color(in: VertexOutput, point: vec2<f32>) -> vec4<f32> {
+	var style = load_style(in.style);
+	var kind = style.gradient.x;
+	if(kind > 4.5 && kind < 5.5) {
+		return pattern_color(in, style, point);
+	}
+	if(kind < 0.5 || (kind > 3.5 && kind < 4.5)) {
+		return style.stop_colors[0];
+	}
+	if(kind > 2.5 && kind < 3.5) {
+		return image_color(in, style, point);
+	}
+
+	var offset = point - in.rect_pos - style.gradient.yz;
+	var t: f32;
+	if(kind < 1.5) {
+		t = length(offset) / max(style.gradient.w, 1e-6);
+	} else if(kind < 2.5) {
+		let tau = 6.283185307;
+		t = fract((atan2(offset.y, offset.x) - style.gradient.w) / tau);
+	} else {
+		t = noise(offset / max(style.gradient.w, 1e-6), u32(style.uv.x));
+	}
+
+	// Unused stops repeat the last one, so every segment can be mixed in unconditionally
+	var color = style.stop_colors[0];
+	color = stop_mix(color, style.stop_colors[1], t, style.stop_offsets.x, style.stop_offsets.y);
+	color = stop_mix(color, style.stop_colors[2], t, style.stop_offsets.y, style.stop_offsets.z);
+	color = stop_mix(color, style.stop_colors[3], t, style.stop_offsets.z, style.stop_offsets.w);
+	return color;
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-	var signed_distance = rect_sdf(in.clip_position.xy, in.rect_pos, in.rect_size, in.border_radius);
-	
+	return shade(in);
+}
+
+// Only the fully opaque pixels, which write depth so nothing behind them is shaded later.
+// The edges are left to fs_main, which blends them over whatever turns out to be behind.
+@fragment
+fn fs_opaque(in: VertexOutput) -> @location(0) vec4<f32> {
+	var color = shade(in);
+	if(color.w < 1.0) {
+		discard;
+	}
+	return color;
+}
+
+fn shade(in: VertexOutput) -> vec4<f32> {
+	// clip_position is in framebuffer coordinates, which always start at the top-left
+	var point = in.clip_position.xy;
+	if(window.y_direction < 0.0) {
+		point.y = window.size.y - point.y;
+	}
+	if(any(point < in.clip.xy) || any(point >= in.clip.zw)) {
+		discard;
+	}
+	// Fade out across a pixel at rounded clip corners. Unclipped rects have no radii, and
+	// the center and size of their unbounded clip would overflow.
+	var clip_coverage = 1.0;
+	if(any(in.clip_radius > vec4<f32>(0.0))) {
+		var clip_distance = rect_sdf(point, (in.clip.xy + in.clip.zw) / 2.0, in.clip.zw - in.clip.xy, in.clip_radius);
+		clip_coverage = clamp(0.5 - clip_distance, 0.0, 1.0);
+		if(clip_coverage <= 0.0) {
+			discard;
+		}
+	}
+	// Everything below works in the rect's unrotated frame
+	point = in.rect_pos + rotate(point - in.rect_pos, -in.rotation);
+	var signed_distance = rect_sdf(point, in.rect_pos, in.rect_size, in.border_radius);
+	var color = fill_color(in, point);
+	color.w *= clip_coverage;
+
+	// A stroke only covers a band of its width inside the quad's edge, with the same soft
+	// edge on the inside
+	var style = load_style(in.style);
+	if(style.gradient.x > 3.5 && style.gradient.x < 4.5) {
+		var inner_distance = -signed_distance - style.gradient.y;
+		if(inner_distance > 0.0) {
+			if(in.softness <= 0.0) {
+				discard;
+			}
+			color.w *= 1.0 - smoothstep(0.0, in.softness, inner_distance);
+		}
+	}
 
 	if(signed_distance <= 0.0) {
-		return in.color;
+		return color;
+	} else if(in.softness <= 0.0) {
+		// smoothstep is undefined for an empty edge range
+		discard;
 	} else {
-		return vec4<f32>(in.color.x, in.color.y, in.color.z, (1.0 - smoothstep(0.0, in.softness, signed_distance)) * in.color.w); 
+		return vec4<f32>(color.x, color.y, color.z, (1.0 - smoothstep(0.0, in.softness, signed_distance)) * color.w);
 	}
 
 	// return vec4<f32>(in.color, smoothstep(175.0, 225.0, in.clip_position.x));
@@ -0,0 +1,776 @@
+use std::{fmt, fs, io, path::PathBuf, time::Duration};
+
+use crate::{
+    camera::Camera,
+    rect::{ClipRect, Fill, GradientStop, GradientStops, HitRegion, Rect, Shadow, Stroke},
+    scene::{Layer, Scene},
+    text_effects::GlyphEffect,
+    theme::Theme,
+    RendererConfig,
+};
+
+const MAGIC: &[u8; 4] = b"WGSN";
+const VERSION: u32 = 13;
+
+/// Where and how often the renderer saves a snapshot of its state.
+///
+/// The file only outlives a run that didn't exit cleanly, e.g. after a panic or a lost
+/// device, so finding it at startup means the previous run crashed and its state is restored.
+#[derive(Clone, Debug)]
+pub struct SnapshotConfig {
+    pub path: PathBuf,
+    pub interval: Duration,
+}
+
+impl SnapshotConfig {
+    /// The snapshot a crashed run left behind, if any. One that can't be read is logged and
+    /// ignored.
+    pub fn recover(&self) -> Option<Snapshot> {
+        if !self.path.exists() {
+            return None;
+        }
+        match Snapshot::load(&self.path) {
+            Ok(snapshot) => {
+                log::warn!("Restoring state from {}", self.path.display());
+                Some(snapshot)
+            }
+            Err(e) => {
+                log::error!("Ignoring snapshot {}: {}", self.path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Removes the snapshot after a clean exit.
+    pub fn discard(&self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            if e.kind() != io::ErrorKind::NotFound {
+                log::error!("Failed to remove snapshot {}: {}", self.path.display(), e);
+            }
+        }
+    }
+}
+
+/// The state the user can change while the renderer runs: the retained rects with their
+/// layers, hit regions and tags, the text and its effects, the camera, and the theme as the
+/// theme editor left it. Images, heatmaps, scatter plots and geo layers hold data the
+/// application loaded itself and aren't included.
+#[derive(Clone)]
+pub struct Snapshot {
+    pub rects: Vec<Rect>,
+    pub layers: Vec<(usize, Layer)>,
+    pub hit_regions: Vec<(usize, HitRegion)>,
+    pub tags: Vec<(usize, u64)>,
+    pub text: String,
+    pub text_effects: Vec<GlyphEffect>,
+    pub camera: Camera,
+    /// See `DrawList::set_origin`.
+    pub rect_origin: [f64; 2],
+    pub theme: Theme,
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    /// The file isn't a snapshot this version can read.
+    Format(String),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "{}", e),
+            SnapshotError::Format(message) => write!(f, "invalid snapshot: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> Self {
+        SnapshotError::Io(e)
+    }
+}
+
+fn format_error(message: &str) -> SnapshotError {
+    SnapshotError::Format(message.to_string())
+}
+
+impl Snapshot {
+    /// Replaces the parts of `scene` and `config` the snapshot holds.
+    pub fn apply(self, scene: &mut Scene, config: &mut RendererConfig) {
+        scene.rects = self.rects;
+        scene.layers = self.layers;
+        scene.hit_regions = self.hit_regions;
+        scene.tags = self.tags;
+        scene.text = self.text;
+        scene.text_effects = self.text_effects;
+        config.camera = self.camera;
+        scene.rect_origin = self.rect_origin;
+        config.theme = self.theme;
+    }
+
+    /// Writes the snapshot next to `path` first and then renames it over, so a crash while
+    /// saving leaves the previous snapshot intact.
+    pub fn save(&self, path: impl Into<PathBuf>) -> io::Result<()> {
+        let path = path.into();
+        let partial = path.with_extension("partial");
+        fs::write(&partial, self.to_bytes())?;
+        fs::rename(&partial, &path)
+    }
+
+    pub fn load(path: impl Into<PathBuf>) -> Result<Snapshot, SnapshotError> {
+        Snapshot::from_bytes(&fs::read(path.into())?)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut w = Writer(MAGIC.to_vec());
+        w.u32(VERSION);
+        w.f64s(&self.camera.offset);
+        w.f64s(&[self.camera.zoom]);
+        w.f64s(&self.rect_origin);
+        for token in self.theme.tokens() {
+            w.f32s(&token);
+        }
+
+        w.u32(self.rects.len() as u32);
+        for rect in &self.rects {
+            w.f32s(&rect.position);
+            w.f32s(&rect.size);
+            w.f32s(&rect.border_radius);
+            w.option(rect.fill.as_ref(), |w, fill| match *fill {
+                Fill::Solid { color } => {
+                    w.0.push(0);
+                    w.f32s(&color);
+                }
+                Fill::Radial {
+                    center,
+                    radius,
+                    stops,
+                } => {
+                    w.0.push(1);
+                    w.f32s(&center);
+                    w.f32s(&[radius]);
+                    w.stops(&stops);
+                }
+                Fill::Conic {
+                    center,
+                    start_angle,
+                    stops,
+                } => {
+                    w.0.push(2);
+                    w.f32s(&center);
+                    w.f32s(&[start_angle]);
+                    w.stops(&stops);
+                }
+                Fill::Image { id, tint } => {
+                    w.0.push(3);
+                    w.u64(id);
+                    w.f32s(&tint);
+                }
+                Fill::Pattern {
+                    id,
+                    tile_size,
+                    offset,
+                    rotation,
+                    tint,
+                } => {
+                    w.0.push(4);
+                    w.u64(id);
+                    w.f32s(&tile_size);
+                    w.f32s(&offset);
+                    w.f32s(&[rotation]);
+                    w.f32s(&tint);
+                }
+                Fill::Noise {
+                    scale,
+                    octaves,
+                    offset,
+                    stops,
+                } => {
+                    w.0.push(5);
+                    w.f32s(&[scale]);
+                    w.u32(octaves);
+                    w.f32s(&offset);
+                    w.stops(&stops);
+                }
+            });
+            w.option(rect.stroke.as_ref(), |w, stroke| {
+                w.f32s(&stroke.color);
+                w.f32s(&[stroke.width]);
+            });
+            w.option(rect.shadow.as_ref(), |w, shadow| {
+                w.f32s(&shadow.offset);
+                w.f32s(&[shadow.blur, shadow.spread]);
+                w.f32s(&shadow.color);
+            });
+            w.f32s(&[rect.rotation, rect.z_index, rect.softness]);
+            w.option(rect.clip.as_ref(), |w, clip| {
+                w.f32s(&clip.position);
+                w.f32s(&clip.size);
+                w.f32s(&clip.border_radius);
+            });
+        }
+
+        w.u32(self.layers.len() as u32);
+        for (i, layer) in &self.layers {
+            w.u32(*i as u32);
+            w.0.push(*layer as u8);
+        }
+
+        w.u32(self.hit_regions.len() as u32);
+        for (i, region) in &self.hit_regions {
+            w.u32(*i as u32);
+            match *region {
+                HitRegion::MinSize(min_size) => {
+                    w.0.push(0);
+                    w.f32s(&min_size);
+                }
+                HitRegion::Outset(outset) => {
+                    w.0.push(1);
+                    w.f32s(&[outset]);
+                }
+                HitRegion::Bounds {
+                    position,
+                    size,
+                    border_radius,
+                } => {
+                    w.0.push(2);
+                    w.f32s(&position);
+                    w.f32s(&size);
+                    w.f32s(&border_radius);
+                }
+            }
+        }
+
+        w.u32(self.tags.len() as u32);
+        for (i, tag) in &self.tags {
+            w.u32(*i as u32);
+            w.u64(*tag);
+        }
+
+        w.u32(self.text.len() as u32);
+        w.0.extend_from_slice(self.text.as_bytes());
+
+        w.u32(self.text_effects.len() as u32);
+        for effect in &self.text_effects {
+            match *effect {
+                GlyphEffect::Typewriter {
+                    chars_per_second,
+                    fade,
+                } => {
+                    w.0.push(0);
+                    w.f32s(&[chars_per_second, fade]);
+                }
+                GlyphEffect::Wave {
+                    amplitude,
+                    wavelength,
+                    speed,
+                } => {
+                    w.0.push(1);
+                    w.f32s(&[amplitude, wavelength, speed]);
+                }
+                GlyphEffect::Jitter { amplitude, rate } => {
+                    w.0.push(2);
+                    w.f32s(&[amplitude, rate]);
+                }
+            }
+        }
+        w.0
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Snapshot, SnapshotError> {
+        let mut r = Reader { bytes };
+        if r.take(MAGIC.len())? != MAGIC {
+            return Err(format_error("not a snapshot"));
+        }
+        let version = r.u32()?;
+        if version != VERSION {
+            return Err(SnapshotError::Format(format!(
+                "unsupported version {}",
+                version
+            )));
+        }
+        let camera = Camera {
+            offset: r.f64s()?,
+            zoom: r.f64s::<1>()?[0],
+        };
+        let rect_origin = r.f64s()?;
+        let mut theme = Theme::default();
+        for i in 0..Theme::TOKENS.len() {
+            theme.set_token(i, r.f32s()?);
+        }
+
+        let rect_count = r.u32()?;
+        let mut rects = vec![];
+        for _ in 0..rect_count {
+            let position = r.f32s()?;
+            let size = r.f32s()?;
+            let border_radius = r.f32s()?;
+            let fill = r.option(|r| {
+                Ok(match r.take(1)?[0] {
+                    0 => Fill::Solid { color: r.f32s()? },
+                    1 => Fill::Radial {
+                        center: r.f32s()?,
+                        radius: r.f32s::<1>()?[0],
+                        stops: r.stops()?,
+                    },
+                    2 => Fill::Conic {
+                        center: r.f32s()?,
+                        start_angle: r.f32s::<1>()?[0],
+                        stops: r.stops()?,
+                    },
+                    3 => Fill::Image {
+                        id: r.u64()?,
+                        tint: r.f32s()?,
+                    },
+                    4 => Fill::Pattern {
+                        id: r.u64()?,
+                        tile_size: r.f32s()?,
+                        offset: r.f32s()?,
+                        rotation: r.f32s::<1>()?[0],
+                        tint: r.f32s()?,
+                    },
+                    5 => Fill::Noise {
+                        scale: r.f32s::<1>()?[0],
+                        octaves: r.u32()?,
+                        offset: r.f32s()?,
+                        stops: r.stops()?,
+                    },
+                    _ => return Err(format_error("unknown fill")),
+                })
+            })?;
+            let stroke = r.option(|r| {
+                Ok(Stroke {
+                    color: r.f32s()?,
+                    width: r.f32s::<1>()?[0],
+                })
+            })?;
+            let shadow = r.option(|r| {
+                let offset = r.f32s()?;
+                let [blur, spread] = r.f32s()?;
+                Ok(Shadow {
+                    offset,
+                    blur,
+                    spread,
+                    color: r.f32s()?,
+                })
+            })?;
+            let [rotation, z_index, softness] = r.f32s()?;
+            let clip = r.option(|r| {
+                Ok(ClipRect {
+                    position: r.f32s()?,
+                    size: r.f32s()?,
+                    border_radius: r.f32s()?,
+                })
+            })?;
+            rects.push(Rect {
+                position,
+                size,
+                border_radius,
+                fill,
+                stroke,
+                shadow,
+                rotation,
+                z_index,
+                softness,
+                clip,
+            });
+        }
+
+        let layer_count = r.u32()?;
+        let mut layers = vec![];
+        for _ in 0..layer_count {
+            let i = r.u32()? as usize;
+            let layer = match r.take(1)?[0] {
+                0 => Layer::Background,
+                1 => Layer::Content,
+                2 => Layer::Overlay,
+                3 => Layer::Debug,
+                _ => return Err(format_error("unknown layer")),
+            };
+            layers.push((i, layer));
+        }
+
+        let region_count = r.u32()?;
+        let mut hit_regions = vec![];
+        for _ in 0..region_count {
+            let i = r.u32()? as usize;
+            hit_regions.push((
+                i,
+                match r.take(1)?[0] {
+                    0 => HitRegion::MinSize(r.f32s()?),
+                    1 => HitRegion::Outset(r.f32s::<1>()?[0]),
+                    2 => HitRegion::Bounds {
+                        position: r.f32s()?,
+                        size: r.f32s()?,
+                        border_radius: r.f32s()?,
+                    },
+                    _ => return Err(format_error("unknown hit region")),
+                },
+            ));
+        }
+
+        let tag_count = r.u32()?;
+        let mut tags = vec![];
+        for _ in 0..tag_count {
+            let i = r.u32()? as usize;
+            tags.push((i, r.u64()?));
+        }
+
+        let text_len = r.u32()? as usize;
+        let text = String::from_utf8(r.take(text_len)?.to_vec())
+            .map_err(|_| format_error("text is not UTF-8"))?;
+
+        let effect_count = r.u32()?;
+        let mut text_effects = vec![];
+        for _ in 0..effect_count {
+            text_effects.push(match r.take(1)?[0] {
+                0 => {
+                    let [chars_per_second, fade] = r.f32s()?;
+                    GlyphEffect::Typewriter {
+                        chars_per_second,
+                        fade,
+                    }
+                }
+                1 => {
+                    let [amplitude, wavelength, speed] = r.f32s()?;
+                    GlyphEffect::Wave {
+                        amplitude,
+                        wavelength,
+                        speed,
+                    }
+                }
+                2 => {
+                    let [amplitude, rate] = r.f32s()?;
+                    GlyphEffect::Jitter { amplitude, rate }
+                }
+                _ => return Err(format_error("unknown text effect")),
+            });
+        }
+
+        if !r.bytes.is_empty() {
+            return Err(format_error("trailing bytes"));
+        }
+        Ok(Snapshot {
+            rects,
+            layers,
+            hit_regions,
+            tags,
+            text,
+            text_effects,
+            camera,
+            rect_origin,
+            theme,
+        })
+    }
+}
+
+/// Little-endian encoding of the snapshot fields.
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn f32s(&mut self, values: &[f32]) {
+        for value in values {
+            self.0.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    fn f64s(&mut self, values: &[f64]) {
+        for value in values {
+            self.0.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    fn stops(&mut self, stops: &GradientStops) {
+        self.u32(stops.as_slice().len() as u32);
+        for stop in stops.as_slice() {
+            self.f32s(&[stop.offset]);
+            self.f32s(&stop.color);
+        }
+    }
+
+    fn option<T>(&mut self, value: Option<&T>, write: impl FnOnce(&mut Self, &T)) {
+        match value {
+            Some(value) => {
+                self.0.push(1);
+                write(self, value);
+            }
+            None => self.0.push(0),
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], SnapshotError> {
+        if self.bytes.len() < len {
+            return Err(format_error("unexpected end of file"));
+        }
+        let (taken, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(taken)
+    }
+
+    fn u32(&mut self) -> Result<u32, SnapshotError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64, SnapshotError> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn f32s<const N: usize>(&mut self) -> Result<[f32; N], SnapshotError> {
+        let mut values = [0.0; N];
+        for value in &mut values {
+            *value = f32::from_le_bytes(self.take(4)?.try_into().unwrap());
+        }
+        Ok(values)
+    }
+
+    fn f64s<const N: usize>(&mut self) -> Result<[f64; N], SnapshotError> {
+        let mut values = [0.0; N];
+        for value in &mut values {
+            *value = f64::from_le_bytes(self.take(8)?.try_into().unwrap());
+        }
+        Ok(values)
+    }
+
+    fn stops(&mut self) -> Result<GradientStops, SnapshotError> {
+        let mut stops = vec![];
+        for _ in 0..self.u32()? {
+            stops.push(GradientStop {
+                offset: self.f32s::<1>()?[0],
+                color: self.f32s()?,
+            });
+        }
+        Ok(GradientStops::new(&stops))
+    }
+
+    fn option<T>(
+        &mut self,
+        read: impl FnOnce(&mut Self) -> Result<T, SnapshotError>,
+    ) -> Result<Option<T>, SnapshotError> {
+        match self.take(1)?[0] {
+            0 => Ok(None),
+            1 => read(self).map(Some),
+            _ => Err(format_error("invalid option tag")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stops() -> GradientStops {
+        GradientStops::new(&[
+            GradientStop {
+                offset: 0.0,
+                color: [1.0, 0.0, 0.0, 1.0],
+            },
+            GradientStop {
+                offset: 0.75,
+                color: [0.0, 0.0, 1.0, 0.5],
+            },
+        ])
+    }
+
+    fn snapshot() -> Snapshot {
+        let fills = [
+            Fill::Solid {
+                color: [0.1, 0.2, 0.3, 0.4],
+            },
+            Fill::Radial {
+                center: [0.25, -0.5],
+                radius: 0.8,
+                stops: stops(),
+            },
+            Fill::Conic {
+                center: [0.0, 0.1],
+                start_angle: 1.5,
+                stops: stops(),
+            },
+            Fill::Image {
+                id: u64::MAX,
+                tint: [1.0, 0.5, 0.25, 1.0],
+            },
+            Fill::Pattern {
+                id: 7,
+                tile_size: [16.0, 8.0],
+                offset: [2.0, -3.0],
+                rotation: 0.3,
+                tint: [0.5; 4],
+            },
+            Fill::Noise {
+                scale: 12.5,
+                octaves: 4,
+                offset: [1.0, 2.0],
+                stops: stops(),
+            },
+        ];
+        let mut rects: Vec<Rect> = fills
+            .into_iter()
+            .enumerate()
+            .map(|(i, fill)| Rect {
+                position: [i as f32 * 10.0, -5.5],
+                size: [8.0, 4.0],
+                border_radius: [1.0, 2.0, 3.0, 4.0],
+                fill: Some(fill),
+                rotation: 0.1 * i as f32,
+                z_index: -(i as f32),
+                ..Default::default()
+            })
+            .collect();
+        rects[0].stroke = Some(Stroke {
+            color: [0.9, 0.8, 0.7],
+            width: 1.5,
+        });
+        rects[1].shadow = Some(Shadow {
+            offset: [2.0, 3.0],
+            blur: 4.0,
+            spread: -1.0,
+            color: [0.0, 0.0, 0.0, 0.6],
+        });
+        rects[2].clip = Some(ClipRect {
+            position: [0.0, 0.0],
+            size: [100.0, 50.0],
+            border_radius: [5.0; 4],
+        });
+        rects.push(Rect::default());
+
+        let mut theme = Theme::default();
+        theme.set_token(4, [0.5, 0.25, 0.125, 1.0]);
+        Snapshot {
+            rects,
+            layers: vec![
+                (0, Layer::Background),
+                (2, Layer::Overlay),
+                (3, Layer::Debug),
+                (6, Layer::Content),
+            ],
+            hit_regions: vec![
+                (0, HitRegion::MinSize([44.0, 44.0])),
+                (1, HitRegion::Outset(6.0)),
+                (
+                    5,
+                    HitRegion::Bounds {
+                        position: [1.0, 2.0],
+                        size: [3.0, 4.0],
+                        border_radius: [0.5; 4],
+                    },
+                ),
+            ],
+            tags: vec![(0, 1), (4, u64::MAX)],
+            text: "Grüße, 世界".to_string(),
+            text_effects: vec![
+                GlyphEffect::Typewriter {
+                    chars_per_second: 30.0,
+                    fade: 0.2,
+                },
+                GlyphEffect::Wave {
+                    amplitude: 2.0,
+                    wavelength: 40.0,
+                    speed: 1.5,
+                },
+                GlyphEffect::Jitter {
+                    amplitude: 0.5,
+                    rate: 12.0,
+                },
+            ],
+            camera: Camera {
+                offset: [-1e9, 0.1],
+                zoom: 2.5,
+            },
+            rect_origin: [1e12, -3.0],
+            theme,
+        }
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let snapshot = snapshot();
+        let read = Snapshot::from_bytes(&snapshot.to_bytes()).unwrap();
+        assert_eq!(read.rects, snapshot.rects);
+        assert_eq!(read.layers, snapshot.layers);
+        assert_eq!(read.hit_regions, snapshot.hit_regions);
+        assert_eq!(read.tags, snapshot.tags);
+        assert_eq!(read.text, snapshot.text);
+        assert_eq!(read.text_effects, snapshot.text_effects);
+        assert_eq!(read.camera, snapshot.camera);
+        assert_eq!(read.rect_origin, snapshot.rect_origin);
+        assert_eq!(read.theme, snapshot.theme);
+    }
+
+    #[test]
+    fn truncated_snapshots_are_refused() {
+        let bytes = snapshot().to_bytes();
+        for len in 0..bytes.len() {
+            assert!(
+                matches!(
+                    Snapshot::from_bytes(&bytes[..len]),
+                    Err(SnapshotError::Format(_))
+                ),
+                "{} bytes",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn bad_input_is_refused() {
+        let bytes = snapshot().to_bytes();
+        let refused = |bytes: &[u8], message: &str| match Snapshot::from_bytes(bytes) {
+            Err(SnapshotError::Format(m)) => assert!(m.contains(message), "{}", m),
+            Err(e) => panic!("{}", e),
+            Ok(_) => panic!("accepted, expected {}", message),
+        };
+
+        refused(b"PNG\0\0\0\0\0", "not a snapshot");
+        let mut version = bytes.clone();
+        version[4] += 1;
+        refused(&version, "unsupported version");
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        refused(&trailing, "trailing bytes");
+
+        // The first rect's fill follows the header, the theme, the rect count and its
+        // position, size and corners.
+        let option = 4 + 4 + 8 * 5 + 16 * 10 + 4 + 4 * 8;
+        let mut tag = bytes.clone();
+        assert_eq!(tag[option], 1);
+        tag[option] = 2;
+        refused(&tag, "invalid option tag");
+        let mut fill = bytes;
+        assert_eq!(fill[option + 1], 0);
+        fill[option + 1] = 6;
+        refused(&fill, "unknown fill");
+    }
+
+    #[test]
+    fn saving_replaces_the_previous_snapshot() {
+        let dir = std::env::temp_dir().join(format!("snapshot-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("state.snapshot");
+        let mut snapshot = snapshot();
+        snapshot.save(&path).unwrap();
+        snapshot.text = "after".to_string();
+        snapshot.save(&path).unwrap();
+
+        assert_eq!(Snapshot::load(&path).unwrap().text, "after");
+        assert!(!path.with_extension("partial").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
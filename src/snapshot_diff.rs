@@ -0,0 +1,224 @@
+//! Comparing two snapshots, to tell why a change altered what gets drawn: both are rendered
+//! offscreen and compared pixel by pixel, and their rects field by field.
+
+use std::{collections::HashMap, fmt};
+
+use crate::{
+    capture::{CaptureError, Screenshot},
+    golden::{self, Difference},
+    rect::Rect,
+    scene::Layer,
+    snapshot::Snapshot,
+    Headless, RendererConfig, RendererError, Scene,
+};
+
+/// How a rect of the first snapshot relates to the second. Indices are into the snapshots'
+/// `rects`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RectChange {
+    Added {
+        after: usize,
+    },
+    Removed {
+        before: usize,
+    },
+    /// The same rect with different values, by field name, `layer` included. Rects moved in
+    /// the draw order alone aren't reported.
+    Changed {
+        before: usize,
+        after: usize,
+        fields: Vec<&'static str>,
+    },
+}
+
+/// What differs between two snapshots.
+#[derive(Clone, Debug)]
+pub struct SnapshotDiff {
+    pub rects: Vec<RectChange>,
+    pub text_changed: bool,
+    pub camera_changed: bool,
+    pub before: Screenshot,
+    pub after: Screenshot,
+    /// The pixels that differ at all between `before` and `after`.
+    pub pixels: Difference,
+}
+
+impl fmt::Display for SnapshotDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} pixels differ, by up to {}",
+            self.pixels.pixels, self.pixels.max_channel
+        )?;
+        if self.text_changed {
+            writeln!(f, "text changed")?;
+        }
+        if self.camera_changed {
+            writeln!(f, "camera changed")?;
+        }
+        for change in &self.rects {
+            match change {
+                RectChange::Added { after } => writeln!(f, "rect {} added", after)?,
+                RectChange::Removed { before } => writeln!(f, "rect {} removed", before)?,
+                RectChange::Changed {
+                    before,
+                    after,
+                    fields,
+                } => writeln!(f, "rect {} -> {}: {}", before, after, fields.join(", "))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Why two snapshots couldn't be compared.
+#[derive(Debug)]
+pub enum CompareError {
+    Renderer(RendererError),
+    Render(CaptureError),
+}
+
+impl fmt::Display for CompareError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompareError::Renderer(e) => write!(f, "can't set up the renderer: {}", e),
+            CompareError::Render(e) => write!(f, "can't render the snapshot: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CompareError {}
+
+impl From<RendererError> for CompareError {
+    fn from(e: RendererError) -> Self {
+        CompareError::Renderer(e)
+    }
+}
+
+impl From<CaptureError> for CompareError {
+    fn from(e: CaptureError) -> Self {
+        CompareError::Render(e)
+    }
+}
+
+/// Renders `before` and `after` over `scene`, which provides what snapshots don't hold such as
+/// images and heatmaps, at `size` pixels, and compares them.
+pub async fn compare(
+    before: &Snapshot,
+    after: &Snapshot,
+    scene: &Scene,
+    renderer_config: &RendererConfig,
+    size: [u32; 2],
+) -> Result<SnapshotDiff, CompareError> {
+    let before_frame = render(before, scene, renderer_config, size).await?;
+    let after_frame = render(after, scene, renderer_config, size).await?;
+    let pixels = golden::diff(&after_frame, &before_frame, 0)
+        .expect("both snapshots are rendered at the same size");
+    Ok(SnapshotDiff {
+        rects: diff_rects(before, after),
+        text_changed: before.text != after.text || before.text_effects != after.text_effects,
+        camera_changed: before.camera != after.camera,
+        before: before_frame,
+        after: after_frame,
+        pixels,
+    })
+}
+
+async fn render(
+    snapshot: &Snapshot,
+    scene: &Scene,
+    renderer_config: &RendererConfig,
+    size: [u32; 2],
+) -> Result<Screenshot, CompareError> {
+    let mut scene = scene.clone();
+    let mut renderer_config = renderer_config.clone();
+    snapshot.clone().apply(&mut scene, &mut renderer_config);
+    let mut renderer = Headless::new(renderer_config, scene, size).await?;
+    Ok(renderer.render()?)
+}
+
+/// Pairs the rects of two snapshots and lists what changed. Rects with a tag no other rect of
+/// their snapshot has are paired by tag, wherever they moved; the rest are paired in draw
+/// order.
+pub fn diff_rects(before: &Snapshot, after: &Snapshot) -> Vec<RectChange> {
+    let before_tags = unique_tags(before);
+    let after_tags = unique_tags(after);
+    let mut pairs: Vec<(usize, usize)> = before_tags
+        .iter()
+        .filter_map(|(tag, i)| Some((*i, *after_tags.get(tag)?)))
+        .collect();
+
+    let mut paired_before = vec![false; before.rects.len()];
+    let mut paired_after = vec![false; after.rects.len()];
+    for (b, a) in &pairs {
+        paired_before[*b] = true;
+        paired_after[*a] = true;
+    }
+    let mut rest_before = (0..before.rects.len()).filter(|i| !paired_before[*i]);
+    let mut rest_after = (0..after.rects.len()).filter(|i| !paired_after[*i]);
+    let mut changes = vec![];
+    loop {
+        match (rest_before.next(), rest_after.next()) {
+            (Some(b), Some(a)) => pairs.push((b, a)),
+            (Some(b), None) => changes.push(RectChange::Removed { before: b }),
+            (None, Some(a)) => changes.push(RectChange::Added { after: a }),
+            (None, None) => break,
+        }
+    }
+
+    pairs.sort_unstable();
+    for (b, a) in pairs {
+        let mut fields = changed_fields(&before.rects[b], &after.rects[a]);
+        if layer(before, b) != layer(after, a) {
+            fields.push("layer");
+        }
+        if !fields.is_empty() {
+            changes.push(RectChange::Changed {
+                before: b,
+                after: a,
+                fields,
+            });
+        }
+    }
+    changes
+}
+
+/// Rect indices by tag, for the tags only one rect has.
+fn unique_tags(snapshot: &Snapshot) -> HashMap<u64, usize> {
+    let mut counts: HashMap<u64, (usize, usize)> = HashMap::new();
+    for (i, tag) in &snapshot.tags {
+        counts.entry(*tag).or_insert((*i, 0)).1 += 1;
+    }
+    counts
+        .into_iter()
+        .filter(|(_, (_, count))| *count == 1)
+        .map(|(tag, (i, _))| (tag, i))
+        .collect()
+}
+
+fn layer(snapshot: &Snapshot, index: usize) -> Layer {
+    snapshot
+        .layers
+        .iter()
+        .find(|(i, _)| *i == index)
+        .map_or(Layer::Content, |(_, layer)| *layer)
+}
+
+fn changed_fields(before: &Rect, after: &Rect) -> Vec<&'static str> {
+    [
+        ("position", before.position != after.position),
+        ("size", before.size != after.size),
+        ("border_radius", before.border_radius != after.border_radius),
+        ("fill", before.fill != after.fill),
+        ("stroke", before.stroke != after.stroke),
+        ("shadow", before.shadow != after.shadow),
+        ("rotation", before.rotation != after.rotation),
+        ("z_index", before.z_index != after.z_index),
+        ("softness", before.softness != after.softness),
+        ("clip", before.clip != after.clip),
+    ]
+    .into_iter()
+    .filter(|(_, changed)| *changed)
+    .map(|(field, _)| field)
+    .collect()
+}
@@ -0,0 +1,6 @@
+@group(2) @binding(0)
+var<storage, read> styles: array<RectStyle>;
+
+fn load_style(index: u32) -> RectStyle {
+	return styles[index];
+}
@@ -0,0 +1,18 @@
+// For devices without storage buffers. Each style is seven texels on one row, in the order
+// of RectStyle's fields, and rows hold as many styles as fit.
+@group(2) @binding(0)
+var styles: texture_2d<f32>;
+
+fn load_style(index: u32) -> RectStyle {
+	var per_row = u32(textureDimensions(styles).x) / 7u;
+	var texel = vec2<i32>(i32(index % per_row * 7u), i32(index / per_row));
+	var style: RectStyle;
+	style.gradient = textureLoad(styles, texel, 0);
+	style.stop_offsets = textureLoad(styles, texel + vec2<i32>(1, 0), 0);
+	style.stop_colors[0] = textureLoad(styles, texel + vec2<i32>(2, 0), 0);
+	style.stop_colors[1] = textureLoad(styles, texel + vec2<i32>(3, 0), 0);
+	style.stop_colors[2] = textureLoad(styles, texel + vec2<i32>(4, 0), 0);
+	style.stop_colors[3] = textureLoad(styles, texel + vec2<i32>(5, 0), 0);
+	style.uv = textureLoad(styles, texel + vec2<i32>(6, 0), 0);
+	return style;
+}
@@ -0,0 +1,222 @@
+//! Progress, attention requests and badge counts on the taskbar or dock.
+//!
+//! Progress is shown on the Windows taskbar and badges on the macOS dock. Attention requests
+//! flash the taskbar button on Windows, bounce the dock icon on macOS and set the urgency hint
+//! on X11 and Wayland. Anything a platform can't show is kept, so it can still be queried, but
+//! not displayed.
+
+use winit::window::{UserAttentionType, Window};
+
+/// The progress of a long-running task.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Progress {
+    #[default]
+    None,
+    /// Busy without knowing how far along.
+    Indeterminate,
+    /// Done from 0 to 1.
+    Normal(f32),
+    Paused(f32),
+    Error(f32),
+}
+
+/// How urgently the window asks for the user.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Attention {
+    /// Until the window is focused.
+    Critical,
+    /// Briefly.
+    Informational,
+}
+
+/// The window's taskbar or dock entry, handed to the frame callback as `Frame::taskbar`.
+/// Changes are shown once the callback returns.
+#[derive(Default)]
+pub struct Taskbar {
+    progress: Progress,
+    badge: Option<u32>,
+    /// Requests since the last `apply`, `Some(None)` cancelling one.
+    attention: Option<Option<Attention>>,
+    changed: bool,
+    #[cfg(windows)]
+    list: Option<windows::TaskbarList>,
+}
+
+impl Taskbar {
+    pub fn progress(&self) -> Progress {
+        self.progress
+    }
+
+    pub fn set_progress(&mut self, progress: Progress) {
+        self.changed |= progress != self.progress;
+        self.progress = progress;
+    }
+
+    pub fn badge(&self) -> Option<u32> {
+        self.badge
+    }
+
+    /// Shows `count` on the dock icon, or nothing for `None`.
+    pub fn set_badge(&mut self, count: Option<u32>) {
+        self.changed |= count != self.badge;
+        self.badge = count;
+    }
+
+    /// Asks for the user's attention, or stops asking with `None`. Focusing the window stops it
+    /// too.
+    pub fn request_attention(&mut self, attention: Option<Attention>) {
+        self.attention = Some(attention);
+    }
+
+    /// Shows what changed since the last call on `window`.
+    pub(crate) fn apply(&mut self, window: &Window) {
+        if let Some(attention) = self.attention.take() {
+            window.request_user_attention(attention.map(|attention| match attention {
+                Attention::Critical => UserAttentionType::Critical,
+                Attention::Informational => UserAttentionType::Informational,
+            }));
+        }
+        if !std::mem::take(&mut self.changed) {
+            return;
+        }
+        #[cfg(windows)]
+        {
+            use winit::platform::windows::WindowExtWindows;
+            let list = self.list.get_or_insert_with(windows::TaskbarList::new);
+            list.set_progress(window.hwnd(), self.progress);
+        }
+        #[cfg(target_os = "macos")]
+        macos::set_badge(self.badge);
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::{ffi::c_void, ptr};
+
+    use windows_sys::{
+        core::{GUID, HRESULT},
+        Win32::{
+            Foundation::HWND,
+            System::Com::{
+                CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_APARTMENTTHREADED,
+            },
+        },
+    };
+
+    use super::Progress;
+
+    const CLSID_TASKBAR_LIST: GUID = GUID::from_u128(0x56fdf344_fd6d_11d0_958a_006097c9a090);
+    const IID_TASKBAR_LIST3: GUID = GUID::from_u128(0xea1afb91_9e28_4b86_90e9_9e9f8a5eefaf);
+
+    const TBPF_NOPROGRESS: i32 = 0;
+    const TBPF_INDETERMINATE: i32 = 1;
+    const TBPF_NORMAL: i32 = 2;
+    const TBPF_ERROR: i32 = 4;
+    const TBPF_PAUSED: i32 = 8;
+
+    /// Progress is reported as a fraction of this.
+    const PROGRESS_TOTAL: u64 = 10_000;
+
+    /// The start of `ITaskbarList3`'s vtable, up to the last method used.
+    #[repr(C)]
+    struct Vtable {
+        /// `QueryInterface` and `AddRef`.
+        _unknown: [usize; 2],
+        release: unsafe extern "system" fn(*mut c_void) -> u32,
+        hr_init: unsafe extern "system" fn(*mut c_void) -> HRESULT,
+        /// `AddTab` through `MarkFullscreenWindow`.
+        _tabs: [usize; 5],
+        set_progress_value: unsafe extern "system" fn(*mut c_void, HWND, u64, u64) -> HRESULT,
+        set_progress_state: unsafe extern "system" fn(*mut c_void, HWND, i32) -> HRESULT,
+    }
+
+    /// An `ITaskbarList3`, or nothing when the shell doesn't provide one.
+    pub(super) struct TaskbarList(*mut *const Vtable);
+
+    impl TaskbarList {
+        pub fn new() -> Self {
+            let mut list: *mut c_void = ptr::null_mut();
+            // SAFETY: plain COM calls with valid GUIDs and out pointer. winit initializes COM
+            // on this thread already, which makes this call a harmless no-op.
+            let created = unsafe {
+                CoInitializeEx(ptr::null(), COINIT_APARTMENTTHREADED);
+                CoCreateInstance(
+                    &CLSID_TASKBAR_LIST,
+                    ptr::null_mut(),
+                    CLSCTX_INPROC_SERVER,
+                    &IID_TASKBAR_LIST3,
+                    &mut list,
+                ) >= 0
+            };
+            let list = list as *mut *const Vtable;
+            // SAFETY: `list` is a live ITaskbarList3 when creating it succeeded.
+            if !created || list.is_null() || unsafe { ((**list).hr_init)(list.cast()) } < 0 {
+                log::warn!("Taskbar progress isn't available");
+                return Self(ptr::null_mut());
+            }
+            Self(list)
+        }
+
+        pub fn set_progress(&self, hwnd: HWND, progress: Progress) {
+            if self.0.is_null() {
+                return;
+            }
+            let (state, done) = match progress {
+                Progress::None => (TBPF_NOPROGRESS, None),
+                Progress::Indeterminate => (TBPF_INDETERMINATE, None),
+                Progress::Normal(done) => (TBPF_NORMAL, Some(done)),
+                Progress::Paused(done) => (TBPF_PAUSED, Some(done)),
+                Progress::Error(done) => (TBPF_ERROR, Some(done)),
+            };
+            // SAFETY: `self.0` is a live ITaskbarList3, and `hwnd` the caller's window.
+            unsafe {
+                let vtable = &**self.0;
+                (vtable.set_progress_state)(self.0.cast(), hwnd, state);
+                if let Some(done) = done {
+                    let done = (done.clamp(0.0, 1.0) * PROGRESS_TOTAL as f32) as u64;
+                    (vtable.set_progress_value)(self.0.cast(), hwnd, done, PROGRESS_TOTAL);
+                }
+            }
+        }
+    }
+
+    impl Drop for TaskbarList {
+        fn drop(&mut self) {
+            if !self.0.is_null() {
+                // SAFETY: releases the reference `new` created.
+                unsafe { ((**self.0).release)(self.0.cast()) };
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use objc::{class, msg_send, runtime::Object, sel, sel_impl};
+
+    /// `NSUTF8StringEncoding`.
+    const UTF8: usize = 4;
+
+    pub(super) fn set_badge(count: Option<u32>) {
+        let label = count.map(|count| count.to_string());
+        // SAFETY: messages to AppKit's shared application and its dock tile, from the main
+        // thread the event loop runs on. The label is copied by `setBadgeLabel:`.
+        unsafe {
+            let app: *mut Object = msg_send![class!(NSApplication), sharedApplication];
+            let tile: *mut Object = msg_send![app, dockTile];
+            let string: *mut Object = match &label {
+                Some(label) => {
+                    let (bytes, length) = (label.as_ptr(), label.len());
+                    let string: *mut Object = msg_send![class!(NSString), alloc];
+                    msg_send![string, initWithBytes: bytes length: length encoding: UTF8]
+                }
+                None => std::ptr::null_mut(),
+            };
+            let _: () = msg_send![tile, setBadgeLabel: string];
+            if !string.is_null() {
+                let _: () = msg_send![string, release];
+            }
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! Printing frames to the terminal with the Kitty or Sixel graphics protocol, to look at a
+//! scene over SSH or anywhere else without a display server.
+
+use std::io::{self, Write};
+
+use crate::{capture::Screenshot, Headless, RendererConfig, Scene};
+
+/// Set to `kitty` or `sixel` to choose the protocol instead of guessing it from the terminal.
+pub const PROTOCOL_VAR: &str = "WGPU_TEST_TERMINAL_GRAPHICS";
+
+/// How the terminal is sent images.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Protocol {
+    /// Kitty's graphics protocol, which WezTerm, Ghostty and Konsole understand as well. The
+    /// pixels are sent as they are.
+    Kitty,
+    /// DEC Sixel, which foot, mlterm and xterm started with `-ti vt340` understand. Colors are
+    /// reduced to 216 and translucent pixels are drawn over black.
+    Sixel,
+}
+
+impl Protocol {
+    /// The protocol set with `PROTOCOL_VAR`, or else the one the terminal seems to understand,
+    /// if any.
+    pub fn detect() -> Option<Self> {
+        let var = |name| std::env::var(name).unwrap_or_default().to_lowercase();
+        match var(PROTOCOL_VAR).as_str() {
+            "kitty" => return Some(Protocol::Kitty),
+            "sixel" => return Some(Protocol::Sixel),
+            _ => {}
+        }
+        let term = var("TERM");
+        let program = var("TERM_PROGRAM");
+        if std::env::var_os("KITTY_WINDOW_ID").is_some()
+            || term.contains("kitty")
+            || term.contains("ghostty")
+            || ["wezterm", "ghostty"].contains(&program.as_str())
+        {
+            Some(Protocol::Kitty)
+        } else if term.contains("sixel") || term.starts_with("foot") || term.starts_with("mlterm") {
+            Some(Protocol::Sixel)
+        } else {
+            None
+        }
+    }
+}
+
+/// Renders `scene` offscreen at `size` pixels and prints it to the terminal, with the protocol
+/// `Protocol::detect` finds.
+pub async fn preview(
+    scene: Scene,
+    renderer_config: RendererConfig,
+    size: [u32; 2],
+) -> io::Result<()> {
+    let protocol = Protocol::detect().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "the terminal doesn't seem to show images; set {} to kitty or sixel",
+                PROTOCOL_VAR
+            ),
+        )
+    })?;
+    let frame = Headless::new(renderer_config, scene, size)
+        .await
+        .map_err(|e| io::Error::other(e.to_string()))?
+        .render()
+        .map_err(|e| io::Error::other(e.to_string()))?;
+    print(&frame, protocol)
+}
+
+/// Prints `image` at the cursor and moves the cursor below it.
+pub fn print(image: &Screenshot, protocol: Protocol) -> io::Result<()> {
+    let mut stdout = io::BufWriter::new(io::stdout().lock());
+    write(&mut stdout, image, protocol)?;
+    writeln!(stdout)?;
+    stdout.flush()
+}
+
+/// Writes `image` as the escape sequence that draws it.
+pub fn write(writer: &mut impl Write, image: &Screenshot, protocol: Protocol) -> io::Result<()> {
+    match protocol {
+        Protocol::Kitty => write_kitty(writer, image),
+        Protocol::Sixel => write_sixel(writer, image),
+    }
+}
+
+fn write_kitty(writer: &mut impl Write, image: &Screenshot) -> io::Result<()> {
+    // Escape sequences may carry at most 4096 bytes of the base64 payload each.
+    let payload = base64(&image.pixels);
+    let mut chunks = payload.chunks(4096).peekable();
+    let mut first = true;
+    while let Some(chunk) = chunks.next() {
+        let more = u8::from(chunks.peek().is_some());
+        if first {
+            write!(
+                writer,
+                "\x1b_Ga=T,f=32,s={},v={},m={};",
+                image.width, image.height, more
+            )?;
+            first = false;
+        } else {
+            write!(writer, "\x1b_Gm={};", more)?;
+        }
+        writer.write_all(chunk)?;
+        writer.write_all(b"\x1b\\")?;
+    }
+    Ok(())
+}
+
+fn base64(data: &[u8]) -> Vec<u8> {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = Vec::with_capacity(data.len().div_ceil(3) * 4);
+    for group in data.chunks(3) {
+        let bytes = [
+            group[0],
+            *group.get(1).unwrap_or(&0),
+            *group.get(2).unwrap_or(&0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= group.len() {
+                encoded.push(ALPHABET[((bits >> (18 - 6 * i)) & 63) as usize]);
+            } else {
+                encoded.push(b'=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Levels per channel of the Sixel palette, which has this many cubed colors.
+const SIXEL_LEVELS: u32 = 6;
+const SIXEL_COLORS: usize = (SIXEL_LEVELS * SIXEL_LEVELS * SIXEL_LEVELS) as usize;
+
+fn write_sixel(writer: &mut impl Write, image: &Screenshot) -> io::Result<()> {
+    let width = image.width as usize;
+    let height = image.height as usize;
+    write!(writer, "\x1bPq\"1;1;{};{}", width, height)?;
+    let percent = |level: u32| level * 100 / (SIXEL_LEVELS - 1);
+    for color in 0..SIXEL_COLORS as u32 {
+        let [r, g, b] = [
+            color / (SIXEL_LEVELS * SIXEL_LEVELS),
+            color / SIXEL_LEVELS % SIXEL_LEVELS,
+            color % SIXEL_LEVELS,
+        ];
+        write!(
+            writer,
+            "#{};2;{};{};{}",
+            color,
+            percent(r),
+            percent(g),
+            percent(b)
+        )?;
+    }
+
+    let palette_index = |pixel: &[u8]| {
+        let level = |channel: u8| {
+            let over_black = channel as u32 * pixel[3] as u32 / 255;
+            (over_black * (SIXEL_LEVELS - 1) + 127) / 255
+        };
+        let [r, g, b] = [level(pixel[0]), level(pixel[1]), level(pixel[2])];
+        ((r * SIXEL_LEVELS + g) * SIXEL_LEVELS + b) as usize
+    };
+
+    // Sixels are bands six pixels tall, drawn one color at a time: each column of a color's
+    // row has a bit set for every pixel of the band in that color.
+    let mut bits = vec![0u8; SIXEL_COLORS * width];
+    for band in (0..height).step_by(6) {
+        bits.fill(0);
+        let mut used = [false; SIXEL_COLORS];
+        for row in band..(band + 6).min(height) {
+            let pixels = &image.pixels[row * width * 4..(row + 1) * width * 4];
+            for (x, pixel) in pixels.chunks_exact(4).enumerate() {
+                let color = palette_index(pixel);
+                used[color] = true;
+                bits[color * width + x] |= 1 << (row - band);
+            }
+        }
+        let mut first = true;
+        for color in (0..SIXEL_COLORS).filter(|color| used[*color]) {
+            if !first {
+                // Back to the start of the band for the next color.
+                writer.write_all(b"$")?;
+            }
+            first = false;
+            write!(writer, "#{}", color)?;
+            write_sixel_row(writer, &bits[color * width..(color + 1) * width])?;
+        }
+        writer.write_all(b"-")?;
+    }
+    writer.write_all(b"\x1b\\")
+}
+
+/// Writes a color's row of sixels, with repeats run-length encoded and the empty end left out.
+fn write_sixel_row(writer: &mut impl Write, row: &[u8]) -> io::Result<()> {
+    let end = row.iter().rposition(|bits| *bits != 0).map_or(0, |i| i + 1);
+    let mut rest = &row[..end];
+    while let Some(&bits) = rest.first() {
+        let run = rest.iter().take_while(|b| **b == bits).count();
+        let sixel = 63 + bits;
+        if run > 3 {
+            write!(writer, "!{}", run)?;
+            writer.write_all(&[sixel])?;
+        } else {
+            writer.write_all(&[sixel; 3][..run])?;
+        }
+        rest = &rest[run..];
+    }
+    Ok(())
+}
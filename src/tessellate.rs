@@ -0,0 +1,340 @@
+//! Turns polygon outlines into triangles.
+
+type Point = [f64; 2];
+
+fn signed_area(ring: &[Point]) -> f64 {
+    let mut area = 0.0;
+    for (i, a) in ring.iter().enumerate() {
+        let b = ring[(i + 1) % ring.len()];
+        area += a[0] * b[1] - b[0] * a[1];
+    }
+    area / 2.0
+}
+
+fn cross(o: Point, a: Point, b: Point) -> f64 {
+    (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+}
+
+fn in_triangle(p: Point, a: Point, b: Point, c: Point) -> bool {
+    cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0
+}
+
+/// Drops a repeated closing point and consecutive duplicates.
+fn clean(ring: &[Point]) -> Vec<Point> {
+    let mut out: Vec<Point> = vec![];
+    for &point in ring {
+        if out.last() != Some(&point) && point.iter().all(|v| v.is_finite()) {
+            out.push(point);
+        }
+    }
+    if out.len() > 1 && out.first() == out.last() {
+        out.pop();
+    }
+    out
+}
+
+/// Triangulates a polygon with ear clipping. The first ring is the outline and any others are
+/// holes; either winding is accepted and a repeated closing point is ignored.
+///
+/// Returns the cleaned vertices of every ring, concatenated, and three indices into them per
+/// triangle. Self-intersecting input still produces triangles, just not necessarily the
+/// right ones.
+pub fn fill(rings: &[Vec<Point>]) -> (Vec<Point>, Vec<u32>) {
+    let mut vertices: Vec<Point> = vec![];
+    let mut outline: Vec<u32> = vec![];
+    let mut holes: Vec<Vec<u32>> = vec![];
+
+    for (r, ring) in rings.iter().enumerate() {
+        let mut ring = clean(ring);
+        if ring.len() < 3 {
+            if r == 0 {
+                return (vec![], vec![]);
+            }
+            continue;
+        }
+        // Counter-clockwise outline, clockwise holes.
+        if (signed_area(&ring) < 0.0) == (r == 0) {
+            ring.reverse();
+        }
+        let start = vertices.len() as u32;
+        let indices = (start..start + ring.len() as u32).collect();
+        vertices.extend(ring);
+        if r == 0 {
+            outline = indices;
+        } else {
+            holes.push(indices);
+        }
+    }
+
+    // Holes are bridged into the outline from their rightmost point, rightmost hole first,
+    // so later bridges can't cross earlier ones.
+    let rightmost = |hole: &[u32]| {
+        (0..hole.len())
+            .max_by(|&a, &b| {
+                vertices[hole[a] as usize][0].total_cmp(&vertices[hole[b] as usize][0])
+            })
+            .unwrap()
+    };
+    holes.sort_by(|a, b| {
+        let xa = vertices[a[rightmost(a)] as usize][0];
+        let xb = vertices[b[rightmost(b)] as usize][0];
+        xb.total_cmp(&xa)
+    });
+    for hole in holes {
+        let m = rightmost(&hole);
+        if let Some(bridge) = bridge_target(&vertices, &outline, vertices[hole[m] as usize]) {
+            let mut merged = outline[..=bridge].to_vec();
+            merged.extend(hole[m..].iter().chain(&hole[..=m]));
+            merged.extend(&outline[bridge..]);
+            outline = merged;
+        }
+    }
+
+    let indices = clip_ears(&vertices, outline);
+    (vertices, indices)
+}
+
+/// Finds an outline vertex that `point`, inside the outline, can connect to without crossing
+/// any edge, by casting a ray towards +x.
+fn bridge_target(vertices: &[Point], outline: &[u32], point: Point) -> Option<usize> {
+    let at = |i: usize| vertices[outline[i] as usize];
+    let mut nearest: Option<(f64, usize)> = None;
+    for i in 0..outline.len() {
+        let (a, b) = (at(i), at((i + 1) % outline.len()));
+        if (a[1] > point[1]) == (b[1] > point[1]) {
+            continue;
+        }
+        let x = a[0] + (point[1] - a[1]) / (b[1] - a[1]) * (b[0] - a[0]);
+        if x >= point[0] && !matches!(nearest, Some((nx, _)) if nx <= x) {
+            let end = if a[0] > b[0] {
+                i
+            } else {
+                (i + 1) % outline.len()
+            };
+            nearest = Some((x, end));
+        }
+    }
+    let (x, mut target) = nearest?;
+    let hit = [x, point[1]];
+
+    // A reflex vertex inside the triangle between the ray and the candidate would block the
+    // bridge. The one closest in angle to the ray can always be reached.
+    let candidate = at(target);
+    let mut best_angle = f64::INFINITY;
+    for i in 0..outline.len() {
+        let p = at(i);
+        let prev = at((i + outline.len() - 1) % outline.len());
+        let next = at((i + 1) % outline.len());
+        let reflex = cross(prev, p, next) < 0.0;
+        if i == target || !reflex || p[0] < point[0] {
+            continue;
+        }
+        let inside = in_triangle(p, point, hit, candidate) || in_triangle(p, point, candidate, hit);
+        if inside {
+            let angle = (p[1] - point[1]).abs().atan2(p[0] - point[0]);
+            if angle < best_angle {
+                best_angle = angle;
+                target = i;
+            }
+        }
+    }
+    Some(target)
+}
+
+fn clip_ears(vertices: &[Point], mut polygon: Vec<u32>) -> Vec<u32> {
+    let at = |i: u32| vertices[i as usize];
+    // Positive where the outline turns left at the `i`th vertex, i.e. the vertex is convex.
+    let turn = |polygon: &[u32], i: usize| {
+        let n = polygon.len();
+        cross(
+            at(polygon[(i + n - 1) % n]),
+            at(polygon[i]),
+            at(polygon[(i + 1) % n]),
+        )
+    };
+    // Only vertices that aren't convex can lie inside an ear, so only they are checked.
+    let mut reflex: Vec<bool> = (0..polygon.len())
+        .map(|i| turn(&polygon, i) <= 0.0)
+        .collect();
+    let mut triangles = vec![];
+    let mut i = 0;
+    let mut stalled = 0;
+    while polygon.len() > 3 {
+        let n = polygon.len();
+        let (pa, pb, pc) = (
+            at(polygon[(i + n - 1) % n]),
+            at(polygon[i]),
+            at(polygon[(i + 1) % n]),
+        );
+        let ear = !reflex[i]
+            && !(0..n).any(|j| {
+                let p = at(polygon[j]);
+                // Bridge vertices appear twice, and shared points don't block an ear.
+                reflex[j] && p != pa && p != pb && p != pc && in_triangle(p, pa, pb, pc)
+            });
+
+        let clip = if ear {
+            Some(i)
+        } else if stalled >= n {
+            // No ear is left, as with self-intersecting input. Drop a vertex without area, or
+            // else clip a convex one whatever it covers: clipping a reflex one would wind the
+            // triangle the wrong way, over what's outside the polygon.
+            (0..n)
+                .find(|&j| turn(&polygon, j) == 0.0)
+                .or_else(|| (0..n).find(|&j| !reflex[j]))
+        } else {
+            None
+        };
+        match clip {
+            Some(j) => {
+                if turn(&polygon, j) > 0.0 {
+                    triangles.extend([polygon[(j + n - 1) % n], polygon[j], polygon[(j + 1) % n]]);
+                }
+                polygon.remove(j);
+                reflex.remove(j);
+                let n = polygon.len();
+                for k in [(j + n - 1) % n, j % n] {
+                    reflex[k] = turn(&polygon, k) <= 0.0;
+                }
+                i = j % n;
+                stalled = 0;
+            }
+            // Every vertex left is reflex: what remains winds the wrong way and covers nothing.
+            None if stalled >= n => break,
+            None => {
+                i = (i + 1) % n;
+                stalled += 1;
+            }
+        }
+    }
+    if polygon.len() == 3 && turn(&polygon, 1) > 0.0 {
+        triangles.extend(polygon);
+    }
+    triangles
+}
+
+/// Whether `point` is inside the polygon, with holes, by the even-odd rule.
+pub fn contains(rings: &[Vec<Point>], point: Point) -> bool {
+    let mut inside = false;
+    for ring in rings {
+        for (i, &a) in ring.iter().enumerate() {
+            let b = ring[(i + 1) % ring.len()];
+            if (a[1] > point[1]) != (b[1] > point[1])
+                && point[0] < a[0] + (point[1] - a[1]) / (b[1] - a[1]) * (b[0] - a[0])
+            {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checks that every triangle winds counter-clockwise, and returns their total area.
+    fn area(vertices: &[Point], indices: &[u32]) -> f64 {
+        assert_eq!(indices.len() % 3, 0);
+        indices
+            .chunks(3)
+            .map(|t| {
+                let [a, b, c] = [0, 1, 2].map(|i| vertices[t[i] as usize]);
+                let area = cross(a, b, c) / 2.0;
+                assert!(area > 0.0, "{:?} {:?} {:?}", a, b, c);
+                area
+            })
+            .sum()
+    }
+
+    fn square(center: Point, half: f64) -> Vec<Point> {
+        let [x, y] = center;
+        vec![
+            [x - half, y - half],
+            [x + half, y - half],
+            [x + half, y + half],
+            [x - half, y + half],
+        ]
+    }
+
+    #[test]
+    fn convex_polygons_are_fanned() {
+        let (vertices, indices) = fill(&[square([0.0, 0.0], 1.0)]);
+        assert_eq!(vertices.len(), 4);
+        assert_eq!(indices.len(), 6);
+        assert_eq!(area(&vertices, &indices), 4.0);
+
+        // Clockwise, with the closing point repeated.
+        let mut ring = square([0.0, 0.0], 1.0);
+        ring.reverse();
+        ring.push(ring[0]);
+        let (vertices, indices) = fill(&[ring]);
+        assert_eq!(vertices.len(), 4);
+        assert_eq!(area(&vertices, &indices), 4.0);
+    }
+
+    #[test]
+    fn concave_polygons_stay_inside_their_outline() {
+        // A comb, whose teeth make most vertices reflex.
+        let mut ring = vec![[0.0, 0.0], [7.0, 0.0]];
+        for tooth in (0..4).rev() {
+            let x = tooth as f64 * 2.0;
+            ring.extend([[x + 1.0, 3.0], [x, 3.0]]);
+            if tooth > 0 {
+                ring.extend([[x, 1.0], [x - 1.0, 1.0]]);
+            }
+        }
+        let rings = [ring];
+        let (vertices, indices) = fill(&rings);
+        assert_eq!(indices.len(), 3 * (vertices.len() - 2));
+        assert!((area(&vertices, &indices) - signed_area(&rings[0])).abs() < 1e-9);
+        for t in indices.chunks(3) {
+            let centroid =
+                [0, 1].map(|axis| t.iter().map(|&i| vertices[i as usize][axis]).sum::<f64>() / 3.0);
+            assert!(contains(&rings, centroid), "{:?}", centroid);
+        }
+    }
+
+    #[test]
+    fn holes_are_left_out() {
+        let rings = [
+            square([0.0, 0.0], 4.0),
+            square([-2.0, 0.0], 1.0),
+            square([2.0, 1.0], 1.0),
+        ];
+        let (vertices, indices) = fill(&rings);
+        assert_eq!(vertices.len(), 12);
+        assert!((area(&vertices, &indices) - (64.0 - 4.0 - 4.0)).abs() < 1e-9);
+        for t in indices.chunks(3) {
+            let centroid =
+                [0, 1].map(|axis| t.iter().map(|&i| vertices[i as usize][axis]).sum::<f64>() / 3.0);
+            assert!(contains(&rings, centroid), "{:?}", centroid);
+        }
+    }
+
+    #[test]
+    fn degenerate_input_makes_no_triangles() {
+        assert_eq!(fill(&[]), (vec![], vec![]));
+        assert_eq!(fill(&[vec![[0.0, 0.0], [1.0, 1.0]]]), (vec![], vec![]));
+        assert_eq!(
+            fill(&[vec![[0.0, 0.0], [f64::NAN, 1.0], [1.0, 1.0]]]),
+            (vec![], vec![])
+        );
+
+        let (vertices, indices) = fill(&[vec![[0.0, 0.0], [1.0, 0.0], [2.0, 0.0], [3.0, 0.0]]]);
+        assert_eq!(vertices.len(), 4);
+        assert!(indices.is_empty());
+
+        // A hole too small to have an inside is skipped.
+        let (vertices, indices) = fill(&[square([0.0, 0.0], 1.0), vec![[0.0, 0.0], [0.5, 0.0]]]);
+        assert_eq!(vertices.len(), 4);
+        assert_eq!(area(&vertices, &indices), 4.0);
+    }
+
+    #[test]
+    fn self_intersecting_input_still_terminates() {
+        // A bow tie: only its counter-clockwise half can come out right.
+        let (vertices, indices) = fill(&[vec![[0.0, 0.0], [2.0, 2.0], [2.0, 0.0], [0.0, 2.0]]]);
+        assert!(area(&vertices, &indices) <= 4.0);
+    }
+}
@@ -0,0 +1,695 @@
+use std::ops::Range;
+
+use glyphon::{Attrs, Buffer, Family, FontSystem, Metrics, Shaping};
+
+/// Width and height of `text` when laid out on unbounded lines, in pixels.
+///
+/// Uses the same attributes as the renderer's text, so the result matches what gets drawn.
+pub fn measure(font_system: &mut FontSystem, text: &str, metrics: Metrics) -> [f32; 2] {
+    let mut buffer = Buffer::new(font_system, metrics);
+    buffer.set_size(font_system, f32::MAX, f32::MAX);
+    buffer.set_text(
+        font_system,
+        text,
+        Attrs::new().family(Family::SansSerif),
+        Shaping::Advanced,
+    );
+    buffer.shape_until_scroll(font_system);
+
+    let (width, lines) = buffer
+        .layout_runs()
+        .fold((0.0f32, 0), |(width, lines), run| {
+            (width.max(run.line_w), lines + 1)
+        });
+    [width, lines as f32 * metrics.line_height]
+}
+
+/// A paragraph that wraps to the width of the container it is laid out in.
+///
+/// Shaping is the expensive part of text layout, so the buffer is only re-shaped when the
+/// text or the container width actually changes.
+pub struct TextNode {
+    buffer: Buffer,
+    /// As last set, to tell when it changes.
+    text: String,
+    width: f32,
+}
+
+impl TextNode {
+    pub fn new(font_system: &mut FontSystem, text: &str, metrics: Metrics, width: f32) -> Self {
+        let width = width.max(0.0);
+        let mut buffer = Buffer::new(font_system, metrics);
+        buffer.set_size(font_system, width, f32::MAX);
+        buffer.set_text(
+            font_system,
+            text,
+            Attrs::new().family(Family::SansSerif),
+            Shaping::Advanced,
+        );
+        buffer.shape_until_scroll(font_system);
+        Self {
+            buffer,
+            text: text.to_string(),
+            width,
+        }
+    }
+
+    /// Replaces the text. Returns whether anything was re-shaped, which only happens when it
+    /// differs from the current text.
+    pub fn set_text(&mut self, font_system: &mut FontSystem, text: &str) -> bool {
+        if text == self.text {
+            return false;
+        }
+        self.text = text.to_string();
+        self.buffer.set_text(
+            font_system,
+            text,
+            Attrs::new().family(Family::SansSerif),
+            Shaping::Advanced,
+        );
+        self.buffer.shape_until_scroll(font_system);
+        true
+    }
+
+    /// Rewraps the text for a container `width` pixels wide. Returns whether anything was
+    /// re-shaped.
+    pub fn set_container_width(&mut self, font_system: &mut FontSystem, width: f32) -> bool {
+        let width = width.max(0.0);
+        if width == self.width {
+            return false;
+        }
+        self.width = width;
+        self.buffer.set_size(font_system, width, f32::MAX);
+        self.buffer.shape_until_scroll(font_system);
+        true
+    }
+
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    /// Height of the wrapped text, in pixels.
+    pub fn height(&self) -> f32 {
+        self.buffer.layout_runs().count() as f32 * self.buffer.metrics().line_height
+    }
+
+    pub fn buffer(&self) -> &Buffer {
+        &self.buffer
+    }
+}
+
+/// Vertical metrics of the font the renderer uses, scaled to a font size, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FontMetrics {
+    /// Distance from the baseline up to the top of the tallest glyphs.
+    pub ascent: f32,
+    /// Distance from the baseline down to the bottom of descenders, as a positive number.
+    pub descent: f32,
+    pub cap_height: f32,
+    pub x_height: f32,
+    pub line_gap: f32,
+}
+
+/// Looks up the metrics of the font that `Family::SansSerif` resolves to.
+pub fn font_metrics(font_system: &mut FontSystem, font_size: f32) -> Option<FontMetrics> {
+    // Shaping a character is the simplest way to find out which font the family resolves to.
+    let mut buffer = Buffer::new(font_system, Metrics::new(font_size, font_size));
+    buffer.set_text(
+        font_system,
+        "H",
+        Attrs::new().family(Family::SansSerif),
+        Shaping::Advanced,
+    );
+    buffer.shape_until_scroll(font_system);
+    let font_id = buffer.layout_runs().next()?.glyphs.first()?.font_id;
+
+    let font = font_system.get_font(font_id)?;
+    let face = font.rustybuzz();
+    let scale = font_size / face.units_per_em() as f32;
+    let ascent = face.ascender() as f32 * scale;
+    Some(FontMetrics {
+        ascent,
+        descent: -face.descender() as f32 * scale,
+        // Older fonts lack these, so fall back to the usual proportions.
+        cap_height: face
+            .capital_height()
+            .map_or(ascent * 0.7, |height| height as f32 * scale),
+        x_height: face
+            .x_height()
+            .map_or(ascent * 0.5, |height| height as f32 * scale),
+        line_gap: face.line_gap() as f32 * scale,
+    })
+}
+
+/// Evenly spaced baselines that lines of text snap onto, keeping a vertical rhythm across
+/// blocks with different font sizes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BaselineGrid {
+    /// Y coordinate of the first baseline.
+    pub origin: f32,
+    pub step: f32,
+}
+
+impl BaselineGrid {
+    /// The first baseline at or below `y`.
+    pub fn snap(&self, y: f32) -> f32 {
+        self.origin + ((y - self.origin) / self.step).ceil() * self.step
+    }
+
+    /// `line_height` rounded up to a whole number of grid steps.
+    pub fn line_height(&self, line_height: f32) -> f32 {
+        (line_height / self.step).ceil().max(1.0) * self.step
+    }
+
+    /// Line metrics for `font_size` whose line height keeps every line on the grid.
+    pub fn metrics(&self, font_size: f32, line_height: f32) -> Metrics {
+        Metrics::new(font_size, self.line_height(line_height))
+    }
+
+    /// Where to put the top of a text block so its first baseline lands on the grid, given
+    /// the block's desired top and its first-baseline offset.
+    pub fn top_for(&self, top: f32, first_baseline: f32) -> f32 {
+        self.snap(top + first_baseline) - first_baseline
+    }
+}
+
+impl TextNode {
+    /// Distance from the top of the block to its first baseline.
+    pub fn first_baseline(&self) -> Option<f32> {
+        self.buffer.layout_runs().next().map(|run| run.line_y)
+    }
+}
+
+/// Vertical offsets that line up the first baselines of blocks placed side by side on one
+/// row, such as a large heading next to small body text. The block with the deepest first
+/// baseline gets offset zero.
+pub fn align_first_baselines(nodes: &[&TextNode]) -> Vec<f32> {
+    let baselines: Vec<f32> = nodes
+        .iter()
+        .map(|node| node.first_baseline().unwrap_or(0.0))
+        .collect();
+    let deepest = baselines.iter().copied().fold(0.0, f32::max);
+    baselines
+        .into_iter()
+        .map(|baseline| deepest - baseline)
+        .collect()
+}
+
+/// Expands tabs into spaces up to the next multiple of `tab_size` columns, for monospace
+/// text where every character is one column wide.
+pub fn expand_tabs(line: &str, tab_size: usize) -> String {
+    let tab_size = tab_size.max(1);
+    let mut out = String::with_capacity(line.len());
+    let mut column = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = tab_size - column % tab_size;
+            out.push_str(&" ".repeat(spaces));
+            column += spaces;
+        } else {
+            out.push(c);
+            column += 1;
+        }
+    }
+    out
+}
+
+/// Where tab characters move the text that follows them.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TabStops {
+    /// A stop every this many pixels.
+    Every(f32),
+    /// Stops at these x positions, then every `interval` pixels past the last one.
+    At { positions: Vec<f32>, interval: f32 },
+    /// Elastic tabstops: each column is as wide as its widest cell across the block of
+    /// consecutive lines that share it, plus `padding`.
+    Elastic { padding: f32, min_width: f32 },
+}
+
+impl TabStops {
+    /// The first stop strictly after `x`.
+    fn next_after(&self, x: f32) -> f32 {
+        let every = |origin: f32, interval: f32| {
+            if interval > 0.0 {
+                origin + ((x - origin) / interval).floor() * interval + interval
+            } else {
+                x
+            }
+        };
+        match self {
+            TabStops::Every(interval) => every(0.0, *interval),
+            TabStops::At {
+                positions,
+                interval,
+            } => positions
+                .iter()
+                .copied()
+                .find(|&position| position > x)
+                .unwrap_or_else(|| every(positions.last().copied().unwrap_or(0.0), *interval)),
+            // Elastic columns don't depend on a single cell's position.
+            TabStops::Elastic { .. } => x,
+        }
+    }
+}
+
+/// A run of text between tabs, placed at `x` on `line`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TabCell {
+    pub line: usize,
+    pub column: usize,
+    pub x: f32,
+    pub text: String,
+}
+
+/// Splits `text` at tabs and positions each piece according to `stops`. Draw each cell at
+/// `x` on its line, one line height apart.
+pub fn layout_tabs(
+    font_system: &mut FontSystem,
+    text: &str,
+    metrics: Metrics,
+    stops: &TabStops,
+) -> Vec<TabCell> {
+    let lines: Vec<Vec<&str>> = text
+        .lines()
+        .map(|line| line.split('\t').collect())
+        .collect();
+    let widths: Vec<Vec<f32>> = lines
+        .iter()
+        .map(|cells| {
+            cells
+                .iter()
+                .map(|cell| measure(font_system, cell, metrics)[0])
+                .collect()
+        })
+        .collect();
+
+    // For elastic stops, the width of column `c` on each line that has a tab after it.
+    let mut column_widths: Vec<Vec<f32>> = widths.iter().map(|w| vec![0.0; w.len()]).collect();
+    if let TabStops::Elastic { padding, min_width } = *stops {
+        let columns = lines.iter().map(Vec::len).max().unwrap_or(0);
+        for column in 0..columns {
+            // Only cells followed by a tab belong to a column; the last cell on a line is free.
+            let has_column = |line: usize| lines[line].len() > column + 1;
+            let mut line = 0;
+            while line < lines.len() {
+                if !has_column(line) {
+                    line += 1;
+                    continue;
+                }
+                let start = line;
+                while line < lines.len() && has_column(line) {
+                    line += 1;
+                }
+                let width = (start..line)
+                    .map(|l| widths[l][column] + padding)
+                    .fold(min_width, f32::max);
+                for widths in &mut column_widths[start..line] {
+                    widths[column] = width;
+                }
+            }
+        }
+    }
+
+    let mut cells = vec![];
+    for (line, pieces) in lines.iter().enumerate() {
+        let mut x = 0.0;
+        for (column, piece) in pieces.iter().enumerate() {
+            cells.push(TabCell {
+                line,
+                column,
+                x,
+                text: piece.to_string(),
+            });
+            x = match stops {
+                TabStops::Elastic { .. } => x + column_widths[line][column],
+                _ => stops.next_after(x + widths[line][column]),
+            };
+        }
+    }
+    cells
+}
+
+/// A shaped glyph cluster and where a layout put it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Glyph {
+    /// The characters the glyph was shaped from.
+    pub text: String,
+    /// Byte range of `text` within the whole laid out text.
+    pub range: Range<usize>,
+    /// Top-left corner of the glyph's line box, relative to the top-left of the block.
+    pub position: [f32; 2],
+    /// Advance to the next glyph, including any added spacing.
+    pub width: f32,
+    pub line: usize,
+    /// Position in reading order, counting whitespace.
+    pub index: usize,
+}
+
+/// Byte offset of each paragraph of `buffer` within the text it was given.
+fn paragraph_offsets(buffer: &Buffer) -> Vec<usize> {
+    let mut offset = 0;
+    buffer
+        .lines
+        .iter()
+        .map(|line| {
+            let start = offset;
+            // Paragraphs were split at a newline, which isn't part of either side.
+            offset += line.text().len() + 1;
+            start
+        })
+        .collect()
+}
+
+/// Every glyph of the wrapped `node`, in reading order.
+pub fn glyphs(node: &TextNode) -> Vec<Glyph> {
+    let buffer = node.buffer();
+    let line_height = buffer.metrics().line_height;
+    let offsets = paragraph_offsets(buffer);
+    let mut glyphs = vec![];
+    for (line, run) in buffer.layout_runs().enumerate() {
+        let offset = offsets[run.line_i];
+        for glyph in run.glyphs.iter() {
+            glyphs.push(Glyph {
+                text: run.text[glyph.start..glyph.end].to_string(),
+                range: offset + glyph.start..offset + glyph.end,
+                position: [glyph.x, line as f32 * line_height],
+                width: glyph.w,
+                line,
+                index: glyphs.len(),
+            });
+        }
+    }
+    glyphs
+}
+
+/// Extra space between glyphs, in pixels. Negative values tighten the text.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Spacing {
+    /// Tracking, added after every glyph.
+    pub letter: f32,
+    /// Added after every whitespace glyph, on top of `letter`.
+    pub word: f32,
+}
+
+/// Finds the places a word may be split across lines with a hyphen.
+pub trait Hyphenate {
+    /// Byte offsets within `word` where it may be split.
+    fn breaks(&self, word: &str) -> Vec<usize>;
+}
+
+#[cfg(feature = "hyphenation")]
+impl Hyphenate for hyphenation::Standard {
+    fn breaks(&self, word: &str) -> Vec<usize> {
+        use hyphenation::Hyphenator;
+        self.hyphenate(word).breaks
+    }
+}
+
+/// How `layout_paragraphs` fills lines.
+#[derive(Clone, Copy, Default)]
+pub struct ParagraphStyle<'a> {
+    pub spacing: Spacing,
+    /// Stretches the spaces of every line but a paragraph's last to fill the width.
+    pub justify: bool,
+    /// Splits words that don't fit at the end of a line, if set. With the `hyphenation`
+    /// feature, a dictionary such as `hyphenation::Standard` can be used here.
+    pub hyphenator: Option<&'a dyn Hyphenate>,
+}
+
+/// Lays out `text` with `spacing` applied, wrapping at whitespace to `width` pixels.
+pub fn layout_spaced(
+    font_system: &mut FontSystem,
+    text: &str,
+    metrics: Metrics,
+    width: f32,
+    spacing: Spacing,
+) -> Vec<Glyph> {
+    let style = ParagraphStyle {
+        spacing,
+        ..Default::default()
+    };
+    layout_paragraphs(font_system, text, metrics, width, &style)
+}
+
+/// Lays out `text` wrapped to `width` pixels with the spacing, justification and
+/// hyphenation of `style`.
+///
+/// The shaper knows none of these, so each paragraph is shaped on a single line and broken
+/// into lines here. Draw the result with `EffectText::from_glyphs`.
+pub fn layout_paragraphs(
+    font_system: &mut FontSystem,
+    text: &str,
+    metrics: Metrics,
+    width: f32,
+    style: &ParagraphStyle,
+) -> Vec<Glyph> {
+    let mut buffer = Buffer::new(font_system, metrics);
+    buffer.set_size(font_system, f32::MAX, f32::MAX);
+    buffer.set_text(
+        font_system,
+        text,
+        Attrs::new().family(Family::SansSerif),
+        Shaping::Advanced,
+    );
+    buffer.shape_until_scroll(font_system);
+    let offsets = paragraph_offsets(&buffer);
+    let hyphen_width = match style.hyphenator {
+        Some(_) => measure(font_system, "-", metrics)[0],
+        None => 0.0,
+    };
+
+    let mut glyphs: Vec<Glyph> = vec![];
+    let mut line = 0;
+    for run in buffer.layout_runs() {
+        let offset = offsets[run.line_i];
+        let clusters: Vec<(Range<usize>, f32)> = run
+            .glyphs
+            .iter()
+            .map(|glyph| (glyph.start..glyph.end, glyph.w))
+            .collect();
+        let paragraph = break_paragraph(run.text, &clusters, width, style, hyphen_width);
+        let lines = paragraph.last().map_or(1, |glyph| glyph.line + 1);
+        for mut glyph in paragraph {
+            glyph.range = offset + glyph.range.start..offset + glyph.range.end;
+            glyph.line += line;
+            glyph.position[1] = glyph.line as f32 * metrics.line_height;
+            glyph.index = glyphs.len();
+            glyphs.push(glyph);
+        }
+        line += lines;
+    }
+    glyphs
+}
+
+/// Breaks one paragraph of shaped `clusters`, given as byte range in `text` and advance,
+/// into lines. Lines and ranges count from the start of the paragraph.
+fn break_paragraph(
+    text: &str,
+    clusters: &[(Range<usize>, f32)],
+    width: f32,
+    style: &ParagraphStyle,
+    hyphen_width: f32,
+) -> Vec<Glyph> {
+    let spacing = style.spacing;
+    let is_space = |i: usize| text[clusters[i].0.clone()].trim().is_empty();
+    let advance =
+        |i: usize| clusters[i].1 + spacing.letter + if is_space(i) { spacing.word } else { 0.0 };
+    let glyph = |i: usize, x: f32, line: usize| Glyph {
+        text: text[clusters[i].0.clone()].to_string(),
+        range: clusters[i].0.clone(),
+        position: [x, 0.0],
+        width: advance(i),
+        line,
+        index: 0,
+    };
+
+    let mut glyphs: Vec<Glyph> = vec![];
+    let mut line_start = 0;
+    let mut line = 0;
+    let mut x = 0.0;
+    let mut i = 0;
+    while i < clusters.len() {
+        if is_space(i) {
+            glyphs.push(glyph(i, x, line));
+            x += advance(i);
+            i += 1;
+            continue;
+        }
+
+        let end = (i..clusters.len())
+            .find(|&j| is_space(j))
+            .unwrap_or(clusters.len());
+        // Where the word may be split, as cluster indices.
+        let breaks: Vec<usize> = match style.hyphenator {
+            Some(hyphenator) => {
+                // Right-to-left clusters come in visual order, so the word spans from the
+                // smallest start to the largest end rather than from first to last.
+                let word_start = clusters[i..end].iter().map(|c| c.0.start).min().unwrap();
+                let word_end = clusters[i..end].iter().map(|c| c.0.end).max().unwrap();
+                let word = &text[word_start..word_end];
+                hyphenator
+                    .breaks(word)
+                    .into_iter()
+                    .filter_map(|b| (i + 1..end).find(|&j| clusters[j].0.start == word_start + b))
+                    .collect()
+            }
+            None => vec![],
+        };
+
+        let mut start = i;
+        loop {
+            let word: f32 = (start..end).map(advance).sum::<f32>() - spacing.letter;
+            if x + word <= width {
+                break;
+            }
+            // The longest piece that still fits, hyphen included.
+            let split = breaks.iter().rev().copied().find(|&split| {
+                split > start
+                    && x + (start..split).map(advance).sum::<f32>() + hyphen_width <= width
+            });
+            if let Some(split) = split {
+                for j in start..split {
+                    glyphs.push(glyph(j, x, line));
+                    x += advance(j);
+                }
+                let at = clusters[split].0.start;
+                glyphs.push(Glyph {
+                    text: "-".to_string(),
+                    range: at..at,
+                    position: [x, 0.0],
+                    width: hyphen_width,
+                    line,
+                    index: 0,
+                });
+                finish_line(&mut glyphs[line_start..], width, style.justify);
+                line_start = glyphs.len();
+                line += 1;
+                x = 0.0;
+                start = split;
+            } else if x > 0.0 {
+                finish_line(&mut glyphs[line_start..], width, style.justify);
+                line_start = glyphs.len();
+                line += 1;
+                x = 0.0;
+            } else {
+                // Too long for a line of its own and can't be split any further.
+                break;
+            }
+        }
+        for j in start..end {
+            glyphs.push(glyph(j, x, line));
+            x += advance(j);
+        }
+        i = end;
+    }
+    finish_line(&mut glyphs[line_start..], width, false);
+    glyphs
+}
+
+/// Justifies a finished line by widening its inner spaces.
+fn finish_line(line: &mut [Glyph], width: f32, justify: bool) {
+    let is_space = |glyph: &Glyph| glyph.text.trim().is_empty();
+    // Trailing spaces hang past the edge instead of being stretched.
+    let Some(last) = line.iter().rposition(|glyph| !is_space(glyph)) else {
+        return;
+    };
+    let gaps = line[..last].iter().filter(|glyph| is_space(glyph)).count();
+    if !justify || gaps == 0 {
+        return;
+    }
+    let content = line[last].position[0] + line[last].width;
+    let extra = (width - content).max(0.0) / gaps as f32;
+    let mut shift = 0.0;
+    for (i, glyph) in line.iter_mut().enumerate() {
+        glyph.position[0] += shift;
+        if i < last && is_space(glyph) {
+            glyph.width += extra;
+            shift += extra;
+        }
+    }
+}
+
+/// Top of the caret before byte `offset`, for glyphs from `glyphs` or `layout_spaced`.
+pub fn caret_position(glyphs: &[Glyph], offset: usize) -> [f32; 2] {
+    match glyphs.iter().find(|glyph| glyph.range.end > offset) {
+        Some(glyph) => glyph.position,
+        None => glyphs.last().map_or([0.0; 2], |glyph| {
+            [glyph.position[0] + glyph.width, glyph.position[1]]
+        }),
+    }
+}
+
+/// The byte offset the caret should move to for a click at `point`, relative to the
+/// top-left of the block.
+pub fn offset_at(glyphs: &[Glyph], point: [f32; 2], line_height: f32) -> usize {
+    let line = (point[1] / line_height).floor().max(0.0) as usize;
+    let last_line = glyphs.last().map_or(0, |glyph| glyph.line);
+    let on_line = glyphs
+        .iter()
+        .filter(|glyph| glyph.line == line.min(last_line));
+
+    let mut end = 0;
+    for glyph in on_line {
+        if point[0] < glyph.position[0] + glyph.width / 2.0 {
+            return glyph.range.start;
+        }
+        end = glyph.range.end;
+    }
+    end
+}
+
+/// Highlight boxes, as top-left corner and size, covering the glyphs in the byte `range`.
+/// Each box spans the added spacing too, so there are no gaps between glyphs.
+pub fn selection_boxes(
+    glyphs: &[Glyph],
+    range: Range<usize>,
+    line_height: f32,
+) -> Vec<([f32; 2], [f32; 2])> {
+    let mut boxes: Vec<([f32; 2], [f32; 2])> = vec![];
+    let mut last_line = None;
+    for glyph in glyphs {
+        if glyph.range.start < range.start || glyph.range.end > range.end {
+            continue;
+        }
+        match boxes.last_mut() {
+            Some((position, size)) if last_line == Some(glyph.line) => {
+                size[0] = glyph.position[0] + glyph.width - position[0];
+            }
+            _ => boxes.push((glyph.position, [glyph.width, line_height])),
+        }
+        last_line = Some(glyph.line);
+    }
+    boxes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct SplitAfterTwo;
+
+    impl Hyphenate for SplitAfterTwo {
+        fn breaks(&self, word: &str) -> Vec<usize> {
+            if word.len() > 2 {
+                vec![2]
+            } else {
+                vec![]
+            }
+        }
+    }
+
+    #[test]
+    fn hyphenates_right_to_left_clusters() {
+        // Clusters of a right-to-left word arrive in visual order, last character first.
+        let text = "abcd";
+        let clusters: Vec<(Range<usize>, f32)> = (0..4).rev().map(|i| (i..i + 1, 10.0)).collect();
+        let style = ParagraphStyle {
+            hyphenator: Some(&SplitAfterTwo),
+            ..Default::default()
+        };
+        let glyphs = break_paragraph(text, &clusters, 25.0, &style, 5.0);
+        let mut text: Vec<&str> = glyphs.iter().map(|glyph| glyph.text.as_str()).collect();
+        text.sort_unstable();
+        assert_eq!(text, ["-", "a", "b", "c", "d"]);
+    }
+}
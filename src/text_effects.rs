@@ -0,0 +1,346 @@
+use std::collections::HashMap;
+
+use glyphon::{Attrs, Buffer, Color, Family, FontSystem, Metrics, Shaping, TextArea, TextBounds};
+
+use crate::{
+    config::TextPositioning,
+    text::{glyphs, Glyph, TextNode},
+};
+
+/// How an effect moves, scales and fades one glyph at a point in time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlyphTransform {
+    pub offset: [f32; 2],
+    /// Scale around the center of the glyph's line box.
+    pub scale: f32,
+    pub alpha: f32,
+}
+
+impl Default for GlyphTransform {
+    fn default() -> Self {
+        Self {
+            offset: [0.0; 2],
+            scale: 1.0,
+            alpha: 1.0,
+        }
+    }
+}
+
+impl GlyphTransform {
+    fn then(self, other: GlyphTransform) -> GlyphTransform {
+        GlyphTransform {
+            offset: [
+                self.offset[0] + other.offset[0],
+                self.offset[1] + other.offset[1],
+            ],
+            scale: self.scale * other.scale,
+            alpha: self.alpha * other.alpha,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GlyphEffect {
+    /// Reveals glyphs one after another, each fading and growing in over `fade` seconds.
+    Typewriter { chars_per_second: f32, fade: f32 },
+    /// Bobs glyphs up and down in a wave travelling along the text.
+    Wave {
+        amplitude: f32,
+        /// In pixels.
+        wavelength: f32,
+        /// Wave cycles per second.
+        speed: f32,
+    },
+    /// Shakes every glyph to a new random offset `rate` times per second.
+    Jitter { amplitude: f32, rate: f32 },
+}
+
+impl GlyphEffect {
+    /// The effect's transform for `glyph`, `time` seconds after it started.
+    pub fn transform(&self, glyph: &Glyph, time: f32) -> GlyphTransform {
+        match *self {
+            GlyphEffect::Typewriter {
+                chars_per_second,
+                fade,
+            } => {
+                let appear = glyph.index as f32 / chars_per_second.max(f32::EPSILON);
+                let t = if fade > 0.0 {
+                    ((time - appear) / fade).clamp(0.0, 1.0)
+                } else if time >= appear {
+                    1.0
+                } else {
+                    0.0
+                };
+                GlyphTransform {
+                    scale: 0.5 + 0.5 * t,
+                    alpha: t,
+                    ..Default::default()
+                }
+            }
+            GlyphEffect::Wave {
+                amplitude,
+                wavelength,
+                speed,
+            } => {
+                let center = glyph.position[0] + glyph.width / 2.0;
+                let phase = center / wavelength.max(f32::EPSILON) - speed * time;
+                GlyphTransform {
+                    offset: [0.0, amplitude * (phase * std::f32::consts::TAU).sin()],
+                    ..Default::default()
+                }
+            }
+            GlyphEffect::Jitter { amplitude, rate } => {
+                let tick = (time * rate).floor() as u32;
+                let random = |salt: u32| {
+                    let mut x = (glyph.index as u32)
+                        .wrapping_mul(0x9E37_79B9)
+                        .wrapping_add(tick.wrapping_mul(0x85EB_CA6B))
+                        .wrapping_add(salt);
+                    x ^= x >> 16;
+                    x = x.wrapping_mul(0x7FEB_352D);
+                    x ^= x >> 15;
+                    x as f32 / u32::MAX as f32 * 2.0 - 1.0
+                };
+                GlyphTransform {
+                    offset: [random(1) * amplitude, random(2) * amplitude],
+                    ..Default::default()
+                }
+            }
+        }
+    }
+
+    /// Whether the effect has settled by `time`, so `glyph_count` glyphs no longer need
+    /// redrawing every frame.
+    pub fn finished(&self, glyph_count: usize, time: f32) -> bool {
+        match *self {
+            GlyphEffect::Typewriter {
+                chars_per_second,
+                fade,
+            } => time >= glyph_count as f32 / chars_per_second.max(f32::EPSILON) + fade,
+            GlyphEffect::Wave { .. } | GlyphEffect::Jitter { .. } => false,
+        }
+    }
+}
+
+/// A paragraph drawn glyph by glyph so that effects can transform each one.
+///
+/// Every distinct glyph cluster is shaped into its own small buffer once, and each frame
+/// only repositions those buffers, so animating the effects costs no shaping. Shaped on its
+/// own, a cluster loses what it takes from its neighbours, such as the joined forms of
+/// Arabic letters or the reordered vowels of Indic scripts. Words in such scripts are shaped
+/// whole instead and move as one, with the effects of their first glyph; see
+/// `is_contextual`. Only text in other scripts is animated glyph by glyph.
+pub struct EffectText {
+    glyphs: Vec<Glyph>,
+    /// What is drawn, each with a buffer of its own. Whitespace isn't.
+    pieces: Vec<Piece>,
+    buffers: Vec<Buffer>,
+    line_height: f32,
+    pub effects: Vec<GlyphEffect>,
+    pub color: Color,
+    /// How many times larger glyphs are drawn than laid out, such as the window's scale
+    /// factor. 1 by default.
+    pub scale: f32,
+}
+
+/// A glyph cluster, or a whole word in a contextual script, drawn from one buffer.
+struct Piece {
+    /// Index into `glyphs` of the glyph whose effects move the piece: the first one in
+    /// reading order.
+    glyph: usize,
+    /// Top-left corner of the piece's line box, relative to the top-left of the block.
+    position: [f32; 2],
+    width: f32,
+    buffer: usize,
+}
+
+/// Whether `c` belongs to a script whose glyphs depend on their neighbours: joining scripts
+/// such as Arabic, Syriac, N'Ko and Mongolian, and scripts that reorder or stack marks across
+/// clusters, such as the Indic ones, Tibetan, Myanmar and Khmer. Also true for the zero width
+/// joiner.
+pub fn is_contextual(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0600}'..='\u{074F}'
+            | '\u{0750}'..='\u{077F}'
+            | '\u{07C0}'..='\u{07FF}'
+            | '\u{0840}'..='\u{085F}'
+            | '\u{08A0}'..='\u{0DFF}'
+            | '\u{0F00}'..='\u{109F}'
+            | '\u{1780}'..='\u{18AF}'
+            | '\u{200D}'
+            | '\u{A8E0}'..='\u{A8FF}'
+            | '\u{FB50}'..='\u{FDFF}'
+            | '\u{FE70}'..='\u{FEFF}'
+    )
+}
+
+impl EffectText {
+    pub fn new(font_system: &mut FontSystem, node: &TextNode, effects: Vec<GlyphEffect>) -> Self {
+        Self::from_glyphs(font_system, glyphs(node), node.buffer().metrics(), effects)
+    }
+
+    /// Draws glyphs from any layout, such as `layout_spaced`.
+    pub fn from_glyphs(
+        font_system: &mut FontSystem,
+        glyphs: Vec<Glyph>,
+        metrics: Metrics,
+        effects: Vec<GlyphEffect>,
+    ) -> Self {
+        let mut shaped: HashMap<String, usize> = HashMap::new();
+        let mut buffers = vec![];
+        let mut pieces = vec![];
+        let mut start = 0;
+        while start < glyphs.len() {
+            if glyphs[start].text.trim().is_empty() {
+                start += 1;
+                continue;
+            }
+            // The word: glyphs up to the next whitespace or line.
+            let line = glyphs[start].line;
+            let end = glyphs[start..]
+                .iter()
+                .position(|glyph| glyph.line != line || glyph.text.trim().is_empty())
+                .map_or(glyphs.len(), |len| start + len);
+            let word = &glyphs[start..end];
+            let contextual = word
+                .iter()
+                .any(|glyph| glyph.text.chars().any(is_contextual));
+            let mut piece = |text: String, glyphs: &[Glyph], first: usize| {
+                let buffer = *shaped.entry(text).or_insert_with_key(|text| {
+                    buffers.push(shape_cluster(font_system, text, metrics));
+                    buffers.len() - 1
+                });
+                let left = glyphs
+                    .iter()
+                    .map(|glyph| glyph.position[0])
+                    .fold(f32::INFINITY, f32::min);
+                pieces.push(Piece {
+                    glyph: first,
+                    position: [left, glyphs[0].position[1]],
+                    width: glyphs.iter().map(|glyph| glyph.width).sum(),
+                    buffer,
+                });
+            };
+            if contextual {
+                // Right-to-left runs lay glyphs out in visual order, so the text is put back
+                // in reading order before it is shaped. A cluster shaped into several glyphs
+                // gives its text to each of them.
+                let mut ordered: Vec<usize> = (start..end).collect();
+                ordered.sort_by_key(|&i| glyphs[i].range.start);
+                ordered.dedup_by_key(|i| glyphs[*i].range.start);
+                let text = ordered.iter().map(|&i| glyphs[i].text.as_str()).collect();
+                piece(text, word, ordered[0]);
+            } else {
+                for (i, glyph) in word.iter().enumerate() {
+                    piece(glyph.text.clone(), std::slice::from_ref(glyph), start + i);
+                }
+            }
+            start = end;
+        }
+
+        Self {
+            glyphs,
+            pieces,
+            buffers,
+            line_height: metrics.line_height,
+            effects,
+            color: Color::rgb(255, 255, 255),
+            scale: 1.0,
+        }
+    }
+
+    pub fn glyphs(&self) -> &[Glyph] {
+        &self.glyphs
+    }
+
+    /// Whether every effect has settled by `time`.
+    pub fn finished(&self, time: f32) -> bool {
+        self.effects
+            .iter()
+            .all(|effect| effect.finished(self.glyphs.len(), time))
+    }
+
+    /// One text area per visible piece with the effects applied, for a block whose top-left
+    /// corner is at `left`, `top` in drawn pixels, `time` seconds after the effects started.
+    /// With `TextPositioning::Auto`, glyphs snap to whole pixels once the effects have settled.
+    pub fn text_areas(
+        &self,
+        left: f32,
+        top: f32,
+        time: f32,
+        bounds: TextBounds,
+        positioning: TextPositioning,
+    ) -> Vec<TextArea<'_>> {
+        let snap = positioning.snaps(!self.finished(time));
+        let place = |position: f32| if snap { position.round() } else { position };
+        self.pieces
+            .iter()
+            .filter_map(|piece| {
+                let glyph = &self.glyphs[piece.glyph];
+                let transform = self
+                    .effects
+                    .iter()
+                    .fold(GlyphTransform::default(), |transform, effect| {
+                        transform.then(effect.transform(glyph, time))
+                    });
+                if transform.alpha <= 0.0 {
+                    return None;
+                }
+                // `TextArea` scales around its top-left corner; shift it to scale around the
+                // middle of the piece instead.
+                let shrink = 1.0 - transform.scale;
+                let alpha = (self.color.a() as f32 * transform.alpha.min(1.0)) as u8;
+                Some(TextArea {
+                    buffer: &self.buffers[piece.buffer],
+                    left: place(
+                        left + self.scale
+                            * (piece.position[0]
+                                + transform.offset[0]
+                                + piece.width / 2.0 * shrink),
+                    ),
+                    top: place(
+                        top + self.scale
+                            * (piece.position[1]
+                                + transform.offset[1]
+                                + self.line_height / 2.0 * shrink),
+                    ),
+                    scale: transform.scale * self.scale,
+                    bounds,
+                    default_color: Color::rgba(
+                        self.color.r(),
+                        self.color.g(),
+                        self.color.b(),
+                        alpha,
+                    ),
+                })
+            })
+            .collect()
+    }
+}
+
+fn shape_cluster(font_system: &mut FontSystem, text: &str, metrics: Metrics) -> Buffer {
+    let mut buffer = Buffer::new(font_system, metrics);
+    buffer.set_size(font_system, f32::MAX, f32::MAX);
+    buffer.set_text(
+        font_system,
+        text,
+        Attrs::new().family(Family::SansSerif),
+        Shaping::Advanced,
+    );
+    buffer.shape_until_scroll(font_system);
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn joining_and_reordering_scripts_are_contextual() {
+        assert!("سلام".chars().all(is_contextual));
+        assert!("नमस्ते".chars().all(is_contextual));
+        assert!(!"Hello, wörld".chars().any(is_contextual));
+        assert!(!"日本語".chars().any(is_contextual));
+    }
+}
@@ -0,0 +1,230 @@
+//! The colors of what the renderer draws for itself: the title bar, the inspector and the
+//! theme editor, and the menu bar widget that the frame callback draws from
+//! `Frame::theme`. Each color is a token with a name, which the theme editor edits live.
+//!
+//! Themes are saved as text for designers to pass around, one token per line as
+//! `name = red green blue alpha`, with blank lines and lines starting with `#` ignored.
+
+use std::{fmt, fs, io, path::Path};
+
+use glyphon::Color;
+
+/// Linear RGBA colors like `Fill` colors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Theme {
+    /// Behind the title bar, the menu bar and its open menu.
+    pub bar: [f32; 4],
+    /// Behind the inspector and the theme editor.
+    pub panel: [f32; 4],
+    /// Over the row, menu item or button under the cursor.
+    pub hover: [f32; 4],
+    /// Over the title bar's close button under the cursor.
+    pub danger: [f32; 4],
+    /// Over the selected row, and behind buttons that are switched on.
+    pub selected: [f32; 4],
+    pub button: [f32; 4],
+    /// Glyphs on buttons, checkmarks and separators.
+    pub foreground: [f32; 4],
+    pub text: [f32; 4],
+    /// Around the rect under the cursor, while the inspector is shown.
+    pub hover_outline: [f32; 4],
+    /// Around the rect selected in the inspector.
+    pub selected_outline: [f32; 4],
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            bar: [0.16, 0.16, 0.18, 1.0],
+            panel: [0.08, 0.08, 0.1, 0.92],
+            hover: [1.0, 1.0, 1.0, 0.1],
+            danger: [0.8, 0.15, 0.15, 1.0],
+            selected: [0.25, 0.45, 0.9, 0.45],
+            button: [0.25, 0.25, 0.3, 1.0],
+            foreground: [0.9, 0.9, 0.9, 1.0],
+            text: [0.86, 0.86, 0.86, 1.0],
+            hover_outline: [1.0, 0.8, 0.0, 1.0],
+            selected_outline: [0.3, 0.6, 1.0, 1.0],
+        }
+    }
+}
+
+impl Theme {
+    /// The names of the tokens, in the order `tokens` lists them.
+    pub const TOKENS: [&'static str; 10] = [
+        "bar",
+        "panel",
+        "hover",
+        "danger",
+        "selected",
+        "button",
+        "foreground",
+        "text",
+        "hover_outline",
+        "selected_outline",
+    ];
+
+    /// The colors of the tokens, in the order of `TOKENS`.
+    pub fn tokens(&self) -> [[f32; 4]; 10] {
+        [
+            self.bar,
+            self.panel,
+            self.hover,
+            self.danger,
+            self.selected,
+            self.button,
+            self.foreground,
+            self.text,
+            self.hover_outline,
+            self.selected_outline,
+        ]
+    }
+
+    /// Sets the `i`th token of `TOKENS`.
+    pub fn set_token(&mut self, i: usize, color: [f32; 4]) {
+        if let Some(token) = self.tokens_mut().into_iter().nth(i) {
+            *token = color;
+        }
+    }
+
+    /// `text` as text areas take it. Text is drawn opaque, so its alpha is ignored.
+    pub(crate) fn text_color(&self) -> Color {
+        let [r, g, b, _] = self
+            .text
+            .map(|channel| (channel.clamp(0.0, 1.0) * 255.0) as u8);
+        Color::rgb(r, g, b)
+    }
+
+    /// The theme in the format described in the module documentation.
+    pub fn to_text(&self) -> String {
+        Self::TOKENS
+            .iter()
+            .zip(self.tokens())
+            .map(|(name, [r, g, b, a])| format!("{name} = {r} {g} {b} {a}\n"))
+            .collect()
+    }
+
+    /// Reads a theme written by `to_text`. Tokens it leaves out keep their default colors.
+    pub fn parse(text: &str) -> Result<Theme, ThemeError> {
+        let mut theme = Theme::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |message: &str| ThemeError::Format {
+                line: i + 1,
+                message: message.to_string(),
+            };
+            let (name, color) = line.split_once('=').ok_or_else(|| error("no `=`"))?;
+            let token = Self::TOKENS
+                .iter()
+                .position(|token| *token == name.trim())
+                .ok_or_else(|| error("unknown token"))?;
+            let channels: Vec<f32> = color
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<_, _>>()
+                .map_err(|_| error("channels have to be numbers"))?;
+            let color = channels
+                .try_into()
+                .map_err(|_| error("colors have four channels"))?;
+            theme.set_token(token, color);
+        }
+        Ok(theme)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Theme, ThemeError> {
+        Theme::parse(&fs::read_to_string(path)?)
+    }
+
+    fn tokens_mut(&mut self) -> [&mut [f32; 4]; 10] {
+        [
+            &mut self.bar,
+            &mut self.panel,
+            &mut self.hover,
+            &mut self.danger,
+            &mut self.selected,
+            &mut self.button,
+            &mut self.foreground,
+            &mut self.text,
+            &mut self.hover_outline,
+            &mut self.selected_outline,
+        ]
+    }
+}
+
+/// Why `Theme::load` couldn't read a theme.
+#[derive(Debug)]
+pub enum ThemeError {
+    Io(io::Error),
+    /// A line isn't a token's color.
+    Format {
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThemeError::Io(e) => write!(f, "{}", e),
+            ThemeError::Format { line, message } => {
+                write!(f, "invalid theme at line {}: {}", line, message)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ThemeError {}
+
+impl From<io::Error> for ThemeError {
+    fn from(e: io::Error) -> Self {
+        ThemeError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_round_trips() {
+        let mut theme = Theme::default();
+        theme.set_token(3, [0.1, 0.2, 0.3, 0.4]);
+        theme.text = [1.0 / 3.0, 0.0, 1.0, 0.5];
+        assert_eq!(Theme::parse(&theme.to_text()).unwrap(), theme);
+    }
+
+    #[test]
+    fn left_out_tokens_keep_their_defaults() {
+        let theme = Theme::parse("# From the designers\n\nbar = 1 0 0 1\n").unwrap();
+        assert_eq!(
+            theme,
+            Theme {
+                bar: [1.0, 0.0, 0.0, 1.0],
+                ..Theme::default()
+            }
+        );
+    }
+
+    #[test]
+    fn bad_lines_are_reported_by_number() {
+        for text in [
+            "bar = 1 0 0 1\nbackground = 0 0 0 1",
+            "bar = 1 0 0",
+            "bar = red",
+        ] {
+            match Theme::parse(text) {
+                Err(ThemeError::Format { line, .. }) => {
+                    assert_eq!(line, text.lines().count(), "{}", text)
+                }
+                other => panic!("{}: {:?}", text, other),
+            }
+        }
+    }
+}
@@ -0,0 +1,320 @@
+//! The theme editor: a panel along the left edge of the window, shown with
+//! `RendererConfig::theme_editor` or F9, for designers trying colors out on a running app.
+//!
+//! It lists the tokens of the theme with their colors, and shows a slider for each channel of
+//! the one clicked, which changes the theme as it's dragged: the title bar, the inspector and
+//! the panel itself follow right away, and the frame callback sees it as `Frame::theme`. The
+//! button in the title row saves the theme to `RendererConfig::theme_path`.
+
+use std::path::Path;
+
+use glyphon::{FontSystem, Metrics, TextArea, TextBounds};
+use winit::event::{ElementState, MouseButton, WindowEvent};
+
+use crate::{
+    rect::{Fill, Rect},
+    text::TextNode,
+    theme::Theme,
+};
+
+const PANEL_WIDTH: f32 = 280.0;
+const PADDING: f32 = 8.0;
+const FONT_SIZE: f32 = 14.0;
+/// Every row of the panel is one line of its text.
+const LINE_HEIGHT: f32 = 20.0;
+const BUTTON_SIZE: f32 = 16.0;
+/// Where the sliders start, leaving room for the channel's name and value on their left.
+const SLIDER_OFFSET: f32 = 110.0;
+/// The title row above the tokens.
+const HEADER_ROWS: usize = 1;
+/// The row of the blank line above the selected token's heading.
+const DETAILS_ROW: usize = HEADER_ROWS + Theme::TOKENS.len();
+const CHANNELS: [&str; 4] = ["red", "green", "blue", "alpha"];
+const ROWS: usize = DETAILS_ROW + 2 + CHANNELS.len();
+
+/// A box of the panel, by its top-left corner in window pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Slot {
+    position: [f32; 2],
+    size: [f32; 2],
+}
+
+impl Slot {
+    fn contains(&self, point: [f32; 2]) -> bool {
+        (0..2).all(|i| self.position[i] <= point[i] && point[i] < self.position[i] + self.size[i])
+    }
+
+    fn rect(&self, color: [f32; 4]) -> Rect {
+        Rect {
+            position: [0, 1].map(|i| self.position[i] + self.size[i] / 2.0),
+            size: self.size,
+            fill: Some(Fill::Solid { color }),
+            softness: 0.0,
+            ..Default::default()
+        }
+    }
+}
+
+/// The panel's state between frames. Sizes passed in are the window's, in physical pixels.
+pub(crate) struct ThemeEditor {
+    /// In window pixels from the top-left corner, whatever the origin of rects.
+    cursor: [f32; 2],
+    /// The token whose channels the sliders show.
+    selected: usize,
+    /// The token whose row is under the cursor.
+    hovered: Option<usize>,
+    /// The channel whose slider follows the cursor until the button is released.
+    dragging: Option<usize>,
+    /// What the last save did, shown in the title row.
+    status: String,
+    /// Every row of the panel, one line each.
+    text: TextNode,
+    changed: bool,
+}
+
+impl ThemeEditor {
+    pub fn new(font_system: &mut FontSystem, theme: &Theme) -> Self {
+        let mut editor = Self {
+            cursor: [f32::NEG_INFINITY; 2],
+            selected: 0,
+            hovered: None,
+            dragging: None,
+            status: String::new(),
+            text: TextNode::new(
+                font_system,
+                "",
+                Metrics::new(FONT_SIZE, LINE_HEIGHT),
+                f32::MAX,
+            ),
+            changed: false,
+        };
+        editor.refresh(font_system, theme);
+        editor
+    }
+
+    /// Handles hovering, clicks and dragging the sliders, changing `theme` as they move and
+    /// saving it to `path` when asked to. Returns whether `event` was used.
+    pub fn on_event(
+        &mut self,
+        event: &WindowEvent,
+        theme: &mut Theme,
+        path: &Path,
+        size: [f32; 2],
+    ) -> bool {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = [position.x as f32, position.y as f32];
+                let hovered = self.token_at(size);
+                if hovered != self.hovered {
+                    self.hovered = hovered;
+                    self.changed = true;
+                }
+                if let Some(channel) = self.dragging {
+                    self.slide(theme, channel, size);
+                    return true;
+                }
+                false
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Released,
+                button: MouseButton::Left,
+                ..
+            } => self.dragging.take().is_some(),
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } if panel(size).contains(self.cursor) => {
+                if let Some(token) = self.token_at(size) {
+                    self.selected = token;
+                } else if save_slot(size).contains(self.cursor) {
+                    self.status = match theme.save(path) {
+                        Ok(()) => format!("saved to {}", path.display()),
+                        Err(e) => {
+                            log::error!("Can't save the theme to {}: {}", path.display(), e);
+                            "can't save, see the log".to_string()
+                        }
+                    };
+                } else if let Some(channel) = self.slider_at(size) {
+                    self.dragging = Some(channel);
+                    self.slide(theme, channel, size);
+                }
+                self.changed = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the panel has to be laid out again since the last call, and the theme may have
+    /// changed with it.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// Writes the rows for `theme` as it is now.
+    pub fn refresh(&mut self, font_system: &mut FontSystem, theme: &Theme) {
+        let mut lines = vec![format!("Theme  {}", self.status)];
+        lines.extend(Theme::TOKENS.map(|name| format!("    {name}")));
+        lines.push(String::new());
+        lines.push(Theme::TOKENS[self.selected].to_string());
+        let color = theme.tokens()[self.selected];
+        lines.extend(
+            CHANNELS
+                .iter()
+                .zip(color)
+                .map(|(channel, value)| format!("{channel}  {value:.2}")),
+        );
+        self.text.set_text(font_system, &lines.join("\n"));
+    }
+
+    /// The panel's background, the tokens' swatches, the save button and the sliders, in window
+    /// pixels from the top-left corner.
+    pub fn rects(&self, theme: &Theme, size: [f32; 2]) -> Vec<Rect> {
+        let mut rects = vec![panel(size).rect(theme.panel)];
+        for (i, color) in theme.tokens().into_iter().enumerate() {
+            let slot = row_slot(HEADER_ROWS + i, size);
+            if i == self.selected {
+                rects.push(slot.rect(theme.selected));
+            } else if self.hovered == Some(i) {
+                rects.push(slot.rect(theme.hover));
+            }
+            rects.push(swatch_slot(slot).rect(color));
+        }
+
+        // A bar over a dot, for putting the theme away.
+        let save = save_slot(size);
+        let center = [0, 1].map(|i| save.position[i] + save.size[i] / 2.0);
+        let bar = |offset: [f32; 2], size: [f32; 2]| Slot {
+            position: [
+                center[0] + offset[0] - size[0] / 2.0,
+                center[1] + offset[1] - size[1] / 2.0,
+            ],
+            size,
+        };
+        rects.extend([
+            save.rect(theme.button),
+            bar([0.0, -1.0], [4.0, 4.0]).rect(theme.foreground),
+            bar([0.0, 4.0], [10.0, 2.0]).rect(theme.foreground),
+        ]);
+
+        let color = theme.tokens()[self.selected];
+        let heading = row_slot(DETAILS_ROW + 1, size);
+        rects.push(swatch_slot(heading).rect(color));
+        for (channel, value) in color.into_iter().enumerate() {
+            let track = slider_slot(channel, size);
+            let value = value.clamp(0.0, 1.0);
+            let filled = Slot {
+                size: [track.size[0] * value, track.size[1]],
+                ..track
+            };
+            let knob = Slot {
+                position: [
+                    track.position[0] + filled.size[0] - 2.0,
+                    track.position[1] - 4.0,
+                ],
+                size: [4.0, track.size[1] + 8.0],
+            };
+            rects.extend([
+                track.rect(theme.button),
+                filled.rect(theme.selected),
+                knob.rect(theme.foreground),
+            ]);
+        }
+        rects
+    }
+
+    /// The rows' text, cut off at the panel's edges.
+    pub fn text_area(&self, size: [f32; 2], theme: &Theme) -> TextArea<'_> {
+        let panel = panel(size);
+        TextArea {
+            buffer: self.text.buffer(),
+            left: PADDING,
+            top: PADDING,
+            scale: 1.0,
+            bounds: TextBounds {
+                left: 0,
+                top: 0,
+                right: (panel.size[0] - PADDING) as i32,
+                bottom: panel.size[1] as i32,
+            },
+            default_color: theme.text_color(),
+        }
+    }
+
+    /// Sets `channel` of the selected token from where the cursor is along its slider.
+    fn slide(&mut self, theme: &mut Theme, channel: usize, size: [f32; 2]) {
+        let track = slider_slot(channel, size);
+        let value = ((self.cursor[0] - track.position[0]) / track.size[0]).clamp(0.0, 1.0);
+        let mut color = theme.tokens()[self.selected];
+        // Two decimals, as the panel shows them.
+        color[channel] = (value * 100.0).round() / 100.0;
+        theme.set_token(self.selected, color);
+        self.changed = true;
+    }
+
+    /// The token whose row is under the cursor.
+    fn token_at(&self, size: [f32; 2]) -> Option<usize> {
+        let row = row_at(self.cursor, size)?.checked_sub(HEADER_ROWS)?;
+        (row < Theme::TOKENS.len()).then_some(row)
+    }
+
+    /// The channel whose slider row is under the cursor.
+    fn slider_at(&self, size: [f32; 2]) -> Option<usize> {
+        let channel = row_at(self.cursor, size)?.checked_sub(DETAILS_ROW + 2)?;
+        (channel < CHANNELS.len()).then_some(channel)
+    }
+}
+
+fn panel(size: [f32; 2]) -> Slot {
+    Slot {
+        position: [0.0, 0.0],
+        size: [
+            PANEL_WIDTH.min(size[0]),
+            (2.0 * PADDING + ROWS as f32 * LINE_HEIGHT).min(size[1]),
+        ],
+    }
+}
+
+fn row_slot(row: usize, size: [f32; 2]) -> Slot {
+    Slot {
+        position: [PADDING, PADDING + row as f32 * LINE_HEIGHT],
+        size: [panel(size).size[0] - 2.0 * PADDING, LINE_HEIGHT],
+    }
+}
+
+fn row_at(point: [f32; 2], size: [f32; 2]) -> Option<usize> {
+    if !panel(size).contains(point) || point[1] < PADDING {
+        return None;
+    }
+    Some(((point[1] - PADDING) / LINE_HEIGHT) as usize)
+}
+
+/// A square at the right end of `row`.
+fn swatch_slot(row: Slot) -> Slot {
+    Slot {
+        position: [
+            row.position[0] + row.size[0] - BUTTON_SIZE,
+            row.position[1] + (row.size[1] - BUTTON_SIZE) / 2.0,
+        ],
+        size: [BUTTON_SIZE; 2],
+    }
+}
+
+/// The button that saves the theme, at the right end of the title row.
+fn save_slot(size: [f32; 2]) -> Slot {
+    swatch_slot(row_slot(0, size))
+}
+
+/// The track of the slider for `channel` of the selected token.
+fn slider_slot(channel: usize, size: [f32; 2]) -> Slot {
+    let row = row_slot(DETAILS_ROW + 2 + channel, size);
+    let height = 6.0;
+    Slot {
+        position: [
+            row.position[0] + SLIDER_OFFSET,
+            row.position[1] + (row.size[1] - height) / 2.0,
+        ],
+        size: [(row.size[0] - SLIDER_OFFSET).max(1.0), height],
+    }
+}
@@ -0,0 +1,488 @@
+use glyphon::FontSystem;
+
+use crate::{
+    axis::{Axis, AxisLayout, LabelAngle, Tick},
+    text,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TimeUnit {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Month,
+    Year,
+}
+
+/// The interval between neighbouring ticks on a time axis.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeStep {
+    pub unit: TimeUnit,
+    pub count: u32,
+}
+
+const fn step(unit: TimeUnit, count: u32) -> TimeStep {
+    TimeStep { unit, count }
+}
+
+/// Intervals that land on natural boundaries, finest first.
+const STEPS: &[TimeStep] = &[
+    step(TimeUnit::Second, 1),
+    step(TimeUnit::Second, 5),
+    step(TimeUnit::Second, 15),
+    step(TimeUnit::Second, 30),
+    step(TimeUnit::Minute, 1),
+    step(TimeUnit::Minute, 5),
+    step(TimeUnit::Minute, 15),
+    step(TimeUnit::Minute, 30),
+    step(TimeUnit::Hour, 1),
+    step(TimeUnit::Hour, 3),
+    step(TimeUnit::Hour, 6),
+    step(TimeUnit::Hour, 12),
+    step(TimeUnit::Day, 1),
+    step(TimeUnit::Day, 2),
+    step(TimeUnit::Day, 7),
+    step(TimeUnit::Month, 1),
+    step(TimeUnit::Month, 3),
+    step(TimeUnit::Month, 6),
+    step(TimeUnit::Year, 1),
+    step(TimeUnit::Year, 2),
+    step(TimeUnit::Year, 5),
+    step(TimeUnit::Year, 10),
+    step(TimeUnit::Year, 25),
+    step(TimeUnit::Year, 50),
+    step(TimeUnit::Year, 100),
+];
+
+impl TimeStep {
+    /// Roughly how many seconds the step spans. Months and years vary in length.
+    pub fn approx_seconds(&self) -> f64 {
+        let unit = match self.unit {
+            TimeUnit::Second => 1.0,
+            TimeUnit::Minute => 60.0,
+            TimeUnit::Hour => 3600.0,
+            TimeUnit::Day => 86_400.0,
+            TimeUnit::Month => 2_629_746.0,
+            TimeUnit::Year => 31_556_952.0,
+        };
+        unit * self.count as f64
+    }
+}
+
+/// A calendar date and wall-clock time in the proleptic Gregorian calendar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CivilTime {
+    pub year: i64,
+    /// `1..=12`
+    pub month: u32,
+    /// `1..=31`
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+const MONTH_NAMES: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+impl CivilTime {
+    /// Splits seconds since 1970-01-01T00:00 into calendar fields.
+    pub fn from_unix(seconds: i64) -> Self {
+        let days = seconds.div_euclid(86_400);
+        let time = seconds.rem_euclid(86_400) as u32;
+
+        // Howard Hinnant's civil_from_days, with eras of 400 years starting in March.
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let day_of_era = z.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+        let month = if month_index < 10 {
+            month_index + 3
+        } else {
+            month_index - 9
+        } as u32;
+        let year = year_of_era + era * 400 + (month <= 2) as i64;
+
+        Self {
+            year,
+            month,
+            day,
+            hour: time / 3600,
+            minute: time / 60 % 60,
+            second: time % 60,
+        }
+    }
+
+    /// Seconds since 1970-01-01T00:00. Out of range fields roll over into the next ones.
+    pub fn to_unix(&self) -> i64 {
+        let month0 = self.month as i64 - 1;
+        let year = self.year + month0.div_euclid(12);
+        let month = month0.rem_euclid(12) + 1;
+
+        let y = if month <= 2 { year - 1 } else { year };
+        let era = y.div_euclid(400);
+        let year_of_era = y.rem_euclid(400);
+        let month_index = if month > 2 { month - 3 } else { month + 9 };
+        let day_of_year = (153 * month_index + 2) / 5 + self.day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146_097 + day_of_era - 719_468;
+
+        days * 86_400 + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64
+    }
+
+    /// The latest time at or before `self` that is a multiple of `step` within its parent unit.
+    fn floor(mut self, step: TimeStep) -> Self {
+        let count = step.count;
+        match step.unit {
+            TimeUnit::Second => self.second -= self.second % count,
+            TimeUnit::Minute => {
+                self.second = 0;
+                self.minute -= self.minute % count;
+            }
+            TimeUnit::Hour => {
+                self.second = 0;
+                self.minute = 0;
+                self.hour -= self.hour % count;
+            }
+            TimeUnit::Day => {
+                self = Self {
+                    hour: 0,
+                    minute: 0,
+                    second: 0,
+                    ..self
+                };
+                if count == 7 {
+                    // Weeks start on Monday. 1970-01-01 was a Thursday.
+                    let days = self.to_unix().div_euclid(86_400);
+                    return Self::from_unix((days - (days + 3).rem_euclid(7)) * 86_400);
+                }
+                self.day -= (self.day - 1) % count;
+            }
+            TimeUnit::Month => {
+                self = Self {
+                    day: 1,
+                    hour: 0,
+                    minute: 0,
+                    second: 0,
+                    ..self
+                };
+                self.month -= (self.month - 1) % count;
+            }
+            TimeUnit::Year => {
+                self = Self {
+                    month: 1,
+                    day: 1,
+                    hour: 0,
+                    minute: 0,
+                    second: 0,
+                    ..self
+                };
+                self.year -= self.year.rem_euclid(count as i64);
+            }
+        }
+        self
+    }
+
+    fn advance(self, step: TimeStep) -> Self {
+        let count = step.count;
+        match step.unit {
+            TimeUnit::Month => Self::from_unix(
+                Self {
+                    month: self.month + count,
+                    ..self
+                }
+                .to_unix(),
+            ),
+            TimeUnit::Year => Self {
+                year: self.year + count as i64,
+                ..self
+            },
+            _ => Self::from_unix(self.to_unix() + step.approx_seconds() as i64),
+        }
+    }
+}
+
+/// Converts between UTC and the wall-clock time labels are shown in.
+pub trait TimeZone {
+    /// Seconds to add to the UTC time `utc` to get local time.
+    fn utc_offset(&self, utc: i64) -> i64;
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Utc;
+
+impl TimeZone for Utc {
+    fn utc_offset(&self, _utc: i64) -> i64 {
+        0
+    }
+}
+
+/// A zone with no daylight saving, `0` seconds east of UTC.
+#[derive(Clone, Copy, Debug)]
+pub struct FixedOffset(pub i64);
+
+impl TimeZone for FixedOffset {
+    fn utc_offset(&self, _utc: i64) -> i64 {
+        self.0
+    }
+}
+
+/// Labels a tick with the most significant field that changes there, so the axis reads
+/// `23:00, Mar 4, 01:00, ...` rather than repeating the date on every tick.
+pub fn format_time(time: &CivilTime, step: TimeStep) -> String {
+    if time.second != 0 || step.unit == TimeUnit::Second {
+        format!("{:02}:{:02}:{:02}", time.hour, time.minute, time.second)
+    } else if time.hour != 0 || time.minute != 0 {
+        format!("{:02}:{:02}", time.hour, time.minute)
+    } else if time.day != 1 || step.unit <= TimeUnit::Day {
+        format!("{} {}", MONTH_NAMES[time.month as usize - 1], time.day)
+    } else if time.month != 1 {
+        MONTH_NAMES[time.month as usize - 1].to_string()
+    } else {
+        time.year.to_string()
+    }
+}
+
+/// A horizontal axis over seconds since the Unix epoch, ticked at calendar boundaries in
+/// `zone`.
+pub struct TimeAxis<Z: TimeZone = Utc> {
+    /// Position, length, range, font metrics and label gap. `axis.format` is unused.
+    pub axis: Axis,
+    pub zone: Z,
+    pub format: fn(&CivilTime, TimeStep) -> String,
+}
+
+impl<Z: TimeZone> TimeAxis<Z> {
+    pub fn new(axis: Axis, zone: Z) -> Self {
+        Self {
+            axis,
+            zone,
+            format: format_time,
+        }
+    }
+
+    /// UTC times of every `step` boundary within the range, in local time.
+    pub fn ticks(&self, step: TimeStep) -> Vec<f64> {
+        let [start, end] = self.axis.range;
+        // A reversed range has no ticks: the first boundary is already past `end`.
+        if !(start.is_finite() && end.is_finite()) {
+            return vec![];
+        }
+        let first = start.floor() as i64;
+        let mut local = CivilTime::from_unix(first + self.zone.utc_offset(first)).floor(step);
+        let mut ticks = vec![];
+        while ticks.len() < 10_000 {
+            let local_seconds = local.to_unix();
+            // The offset depends on the UTC time being converted, so estimate it from local
+            // time first.
+            let utc = local_seconds
+                - self
+                    .zone
+                    .utc_offset(local_seconds - self.zone.utc_offset(local_seconds));
+            if utc as f64 > end {
+                break;
+            }
+            if utc as f64 >= start {
+                ticks.push(utc as f64);
+            }
+            local = local.advance(step);
+        }
+        ticks
+    }
+
+    /// Picks the finest calendar step whose labels don't overlap, so zooming in switches to
+    /// smaller units and zooming out to larger ones.
+    pub fn layout(&self, font_system: &mut FontSystem) -> (AxisLayout, TimeStep) {
+        let span = self.axis.range[1] - self.axis.range[0];
+        if !(span > 0.0 && span.is_finite()) {
+            return (
+                AxisLayout {
+                    ticks: vec![],
+                    step: 1.0,
+                    angle: LabelAngle::Horizontal,
+                },
+                STEPS[0],
+            );
+        }
+        let target = span / (self.axis.length / 40.0).max(1.0) as f64;
+        let first = STEPS
+            .iter()
+            .position(|step| step.approx_seconds() >= target)
+            .unwrap_or(STEPS.len() - 1);
+
+        let mut result = None;
+        for &step in &STEPS[first..] {
+            let ticks: Vec<Tick> = self
+                .ticks(step)
+                .into_iter()
+                .map(|value| {
+                    let local =
+                        CivilTime::from_unix(value as i64 + self.zone.utc_offset(value as i64));
+                    let label = (self.format)(&local, step);
+                    Tick {
+                        value,
+                        offset: ((value - self.axis.range[0]) / span * self.axis.length as f64)
+                            as f32,
+                        label_size: text::measure(font_system, &label, self.axis.metrics),
+                        label,
+                    }
+                })
+                .collect();
+
+            let fits = ticks.windows(2).all(|pair| {
+                let gap = pair[1].offset - pair[0].offset;
+                (pair[0].label_size[0] + pair[1].label_size[0]) / 2.0 + self.axis.label_gap <= gap
+            });
+            let layout = AxisLayout {
+                ticks,
+                step: step.approx_seconds(),
+                angle: LabelAngle::Horizontal,
+            };
+            result = Some((layout, step));
+            if fits {
+                break;
+            }
+        }
+        result.unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(year: i64, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> CivilTime {
+        CivilTime {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+        }
+    }
+
+    fn date(year: i64, month: u32, day: u32) -> CivilTime {
+        time(year, month, day, 0, 0, 0)
+    }
+
+    fn time_axis<Z: TimeZone>(range: [f64; 2], zone: Z) -> TimeAxis<Z> {
+        let axis = Axis {
+            position: [0.0, 0.0],
+            length: 800.0,
+            vertical: false,
+            range,
+            format: crate::axis::NumberFormat::Auto,
+            metrics: glyphon::Metrics::new(14.0, 20.0),
+            label_gap: 8.0,
+            rotate_labels: false,
+        };
+        TimeAxis::new(axis, zone)
+    }
+
+    #[test]
+    fn unix_times_convert_both_ways() {
+        for (seconds, civil) in [
+            (0, date(1970, 1, 1)),
+            (-1, time(1969, 12, 31, 23, 59, 59)),
+            (-14_182_940, time(1969, 7, 20, 20, 17, 40)),
+            (951_782_400, date(2000, 2, 29)),
+            (1_709_164_800, date(2024, 2, 29)),
+            (-11_670_912_000, date(1600, 3, 1)),
+        ] {
+            assert_eq!(CivilTime::from_unix(seconds), civil);
+            assert_eq!(civil.to_unix(), seconds);
+        }
+    }
+
+    #[test]
+    fn leap_days_follow_the_gregorian_rules() {
+        let day_after = |civil: CivilTime| CivilTime::from_unix(civil.to_unix() + 86_400);
+        assert_eq!(day_after(date(2024, 2, 28)), date(2024, 2, 29));
+        assert_eq!(day_after(date(2000, 2, 28)), date(2000, 2, 29));
+        assert_eq!(day_after(date(1900, 2, 28)), date(1900, 3, 1));
+        assert_eq!(day_after(date(2023, 2, 28)), date(2023, 3, 1));
+    }
+
+    #[test]
+    fn out_of_range_fields_roll_over() {
+        assert_eq!(date(2023, 13, 1).to_unix(), date(2024, 1, 1).to_unix());
+        assert_eq!(date(2023, 2, 29).to_unix(), date(2023, 3, 1).to_unix());
+        assert_eq!(date(2024, 1, 1).to_unix(), date(2023, 12, 32).to_unix());
+
+        let month = step(TimeUnit::Month, 1);
+        assert_eq!(date(2023, 12, 1).advance(month), date(2024, 1, 1));
+        assert_eq!(
+            date(2023, 11, 1).advance(step(TimeUnit::Month, 3)),
+            date(2024, 2, 1)
+        );
+        assert_eq!(
+            time(2023, 12, 31, 23, 0, 0).advance(step(TimeUnit::Hour, 1)),
+            date(2024, 1, 1)
+        );
+        assert_eq!(
+            date(1969, 12, 31).advance(step(TimeUnit::Day, 1)),
+            date(1970, 1, 1)
+        );
+    }
+
+    #[test]
+    fn floors_land_on_boundaries() {
+        let civil = time(2024, 3, 7, 17, 43, 29);
+        for (step, floor) in [
+            (step(TimeUnit::Second, 15), time(2024, 3, 7, 17, 43, 15)),
+            (step(TimeUnit::Minute, 5), time(2024, 3, 7, 17, 40, 0)),
+            (step(TimeUnit::Hour, 6), time(2024, 3, 7, 12, 0, 0)),
+            (step(TimeUnit::Day, 2), date(2024, 3, 7)),
+            (step(TimeUnit::Month, 3), date(2024, 1, 1)),
+            (step(TimeUnit::Year, 25), date(2000, 1, 1)),
+        ] {
+            assert_eq!(civil.floor(step), floor, "{:?}", step);
+        }
+        assert_eq!(
+            date(-1, 6, 1).floor(step(TimeUnit::Year, 10)),
+            date(-10, 1, 1)
+        );
+    }
+
+    #[test]
+    fn weeks_start_on_monday() {
+        let week = step(TimeUnit::Day, 7);
+        // A Thursday, a Sunday and a Monday.
+        assert_eq!(time(2024, 3, 7, 12, 0, 0).floor(week), date(2024, 3, 4));
+        assert_eq!(date(2024, 3, 10).floor(week), date(2024, 3, 4));
+        assert_eq!(date(2024, 3, 4).floor(week), date(2024, 3, 4));
+        // Across the epoch and a year boundary.
+        assert_eq!(date(1970, 1, 1).floor(week), date(1969, 12, 29));
+    }
+
+    #[test]
+    fn ticks_fall_on_local_boundaries() {
+        let start = date(2024, 2, 29).to_unix();
+        let range = [start as f64, (start + 2 * 86_400) as f64];
+        let day = step(TimeUnit::Day, 1);
+
+        let utc = time_axis(range, Utc).ticks(day);
+        assert_eq!(utc, [0, 1, 2].map(|i| (start + i * 86_400) as f64));
+
+        // Midnight at UTC+05:30 is 18:30 UTC the day before.
+        let india = time_axis(range, FixedOffset(5 * 3600 + 1800)).ticks(day);
+        let midnight = time(2024, 2, 29, 18, 30, 0).to_unix();
+        assert_eq!(india, [0, 1].map(|i| (midnight + i * 86_400) as f64));
+    }
+
+    #[test]
+    fn reversed_ranges_have_no_ticks() {
+        let axis = time_axis([86_400.0 * 10.0, 0.0], Utc);
+        assert!(axis.ticks(step(TimeUnit::Day, 1)).is_empty());
+        assert!(time_axis([0.0, f64::NAN], Utc)
+            .ticks(step(TimeUnit::Day, 1))
+            .is_empty());
+    }
+}
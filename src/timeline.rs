@@ -0,0 +1,225 @@
+use crate::rect::Rect;
+
+/// One scheduled item, spanning `start..end` on the time axis.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimelineItem {
+    pub lane: usize,
+    pub start: f64,
+    pub end: f64,
+    pub label: String,
+    pub color: [f32; 4],
+}
+
+/// A bar's screen placement, for drawing it and its label.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VisibleItem {
+    pub index: usize,
+    /// Top-left corner.
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+}
+
+/// An item being dragged to a new time.
+#[derive(Clone, Copy, Debug)]
+struct Drag {
+    index: usize,
+    /// Time under the cursor when the drag started, minus the item's start.
+    grab: f64,
+}
+
+/// A Gantt-style view of lanes of bars, scrolled and zoomed horizontally over time.
+///
+/// Only items overlapping the visible range are laid out, so timelines with thousands of
+/// items cost about as much as the few dozen on screen.
+#[derive(Clone, Debug)]
+pub struct Timeline {
+    /// Top-left corner.
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    pub lane_height: f32,
+    /// Time at the left edge.
+    pub scroll: f64,
+    /// Pixels per time unit.
+    pub zoom: f64,
+    /// Drawn as a vertical marker line when inside the view.
+    pub today: Option<f64>,
+    /// Dragged items snap to multiples of this, if set.
+    pub snap: Option<f64>,
+    items: Vec<TimelineItem>,
+    /// Indices into `items`, sorted by start time.
+    by_start: Vec<usize>,
+    /// The longest item, which bounds how far before the view an overlapping item can start.
+    longest: f64,
+    drag: Option<Drag>,
+}
+
+impl Timeline {
+    pub fn new(position: [f32; 2], size: [f32; 2], lane_height: f32) -> Self {
+        Self {
+            position,
+            size,
+            lane_height,
+            scroll: 0.0,
+            zoom: 1.0,
+            today: None,
+            snap: None,
+            items: vec![],
+            by_start: vec![],
+            longest: 0.0,
+            drag: None,
+        }
+    }
+
+    pub fn items(&self) -> &[TimelineItem] {
+        &self.items
+    }
+
+    pub fn set_items(&mut self, items: Vec<TimelineItem>) {
+        self.items = items;
+        self.drag = None;
+        self.reindex();
+    }
+
+    fn reindex(&mut self) {
+        self.by_start = (0..self.items.len()).collect();
+        self.by_start
+            .sort_by(|&a, &b| self.items[a].start.total_cmp(&self.items[b].start));
+        self.longest = self
+            .items
+            .iter()
+            .map(|item| item.end - item.start)
+            .fold(0.0, f64::max);
+    }
+
+    pub fn to_screen_x(&self, time: f64) -> f32 {
+        self.position[0] + ((time - self.scroll) * self.zoom) as f32
+    }
+
+    pub fn to_time(&self, x: f32) -> f64 {
+        (x - self.position[0]) as f64 / self.zoom + self.scroll
+    }
+
+    /// Scrolls by `delta` pixels.
+    pub fn pan(&mut self, delta: f32) {
+        self.scroll -= delta as f64 / self.zoom;
+    }
+
+    /// Multiplies the zoom by `factor`, keeping the time under screen `x` in place.
+    pub fn zoom_at(&mut self, x: f32, factor: f64) {
+        let anchor = self.to_time(x);
+        self.zoom = (self.zoom * factor).clamp(1e-9, 1e9);
+        self.scroll = anchor - (x - self.position[0]) as f64 / self.zoom;
+    }
+
+    /// Items overlapping the view, found by binary search over start times. Labels go inside
+    /// each item's box.
+    pub fn visible(&self) -> Vec<VisibleItem> {
+        let view_start = self.scroll;
+        let view_end = self.to_time(self.position[0] + self.size[0]);
+        let lanes = (self.size[1] / self.lane_height).ceil() as usize;
+
+        let first = self
+            .by_start
+            .partition_point(|&i| self.items[i].start < view_start - self.longest);
+        self.by_start[first..]
+            .iter()
+            .map(|&i| (i, &self.items[i]))
+            .take_while(|(_, item)| item.start <= view_end)
+            .filter(|(_, item)| item.end >= view_start && item.lane < lanes)
+            .map(|(index, item)| {
+                let left = self.to_screen_x(item.start.max(view_start));
+                let right = self.to_screen_x(item.end.min(view_end));
+                VisibleItem {
+                    index,
+                    position: [
+                        left,
+                        self.position[1] + item.lane as f32 * self.lane_height + 2.0,
+                    ],
+                    size: [(right - left).max(1.0), self.lane_height - 4.0],
+                }
+            })
+            .collect()
+    }
+
+    /// Bars for the visible items, alternating lane backgrounds and the today marker.
+    pub fn rects(&self, lane_color: [f32; 4], today_color: [f32; 4]) -> Vec<Rect> {
+        let lanes = (self.size[1] / self.lane_height).ceil() as usize;
+        let mut rects: Vec<Rect> = (0..lanes)
+            .step_by(2)
+            .map(|lane| {
+                let top = self.position[1] + lane as f32 * self.lane_height;
+                let height = self.lane_height.min(self.position[1] + self.size[1] - top);
+                Rect::solid(
+                    [self.position[0] + self.size[0] / 2.0, top + height / 2.0],
+                    [self.size[0], height],
+                    lane_color,
+                )
+            })
+            .collect();
+
+        rects.extend(self.visible().into_iter().map(|visible| Rect {
+            border_radius: [3.0; 4],
+            ..Rect::solid(
+                [
+                    visible.position[0] + visible.size[0] / 2.0,
+                    visible.position[1] + visible.size[1] / 2.0,
+                ],
+                visible.size,
+                self.items[visible.index].color,
+            )
+        }));
+
+        if let Some(today) = self.today {
+            let x = self.to_screen_x(today);
+            if (self.position[0]..=self.position[0] + self.size[0]).contains(&x) {
+                rects.push(Rect::solid(
+                    [x, self.position[1] + self.size[1] / 2.0],
+                    [2.0, self.size[1]],
+                    today_color,
+                ));
+            }
+        }
+        rects
+    }
+
+    /// The item under `point`, if any.
+    pub fn item_at(&self, point: [f32; 2]) -> Option<usize> {
+        self.visible()
+            .into_iter()
+            .rev()
+            .find(|visible| {
+                (0.0..=visible.size[0]).contains(&(point[0] - visible.position[0]))
+                    && (0.0..=visible.size[1]).contains(&(point[1] - visible.position[1]))
+            })
+            .map(|visible| visible.index)
+    }
+
+    /// Starts dragging the item under `point`. Returns whether one was grabbed.
+    pub fn begin_drag(&mut self, point: [f32; 2]) -> bool {
+        self.drag = self.item_at(point).map(|index| Drag {
+            index,
+            grab: self.to_time(point[0]) - self.items[index].start,
+        });
+        self.drag.is_some()
+    }
+
+    /// Moves the dragged item so it stays under the cursor, keeping its duration.
+    pub fn drag_to(&mut self, point: [f32; 2]) {
+        let Some(drag) = self.drag else {
+            return;
+        };
+        let mut start = self.to_time(point[0]) - drag.grab;
+        if let Some(snap) = self.snap.filter(|&snap| snap > 0.0) {
+            start = (start / snap).round() * snap;
+        }
+        let item = &mut self.items[drag.index];
+        item.end += start - item.start;
+        item.start = start;
+        self.reindex();
+    }
+
+    /// Finishes a drag, returning the rescheduled item's index.
+    pub fn end_drag(&mut self) -> Option<usize> {
+        self.drag.take().map(|drag| drag.index)
+    }
+}
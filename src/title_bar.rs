@@ -0,0 +1,209 @@
+//! A title bar drawn by the renderer, for `RendererConfig::client_decorations`: Wayland
+//! compositors such as GNOME's leave decorations to the application, and winit's own are
+//! plain.
+//!
+//! The bar lies over the top of the scene with the window's title and buttons to minimize,
+//! maximize and close it, and dragging it moves the window. Its sizes are in logical pixels,
+//! so it keeps its size at fractional scale factors too.
+
+use glyphon::{FontSystem, Metrics, TextArea, TextBounds};
+use winit::{
+    event::{ElementState, MouseButton, WindowEvent},
+    window::Window,
+};
+
+use crate::{
+    rect::{Rect, Stroke},
+    text::TextNode,
+    theme::Theme,
+};
+
+/// In logical pixels, like the sizes below.
+const HEIGHT: f32 = 32.0;
+const BUTTON_WIDTH: f32 = 44.0;
+const PADDING: f32 = 10.0;
+const FONT_SIZE: f32 = 14.0;
+const GLYPH_SIZE: f32 = 10.0;
+
+/// A button of the bar, from the right edge leftwards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Button {
+    Close,
+    Maximize,
+    Minimize,
+}
+
+impl Button {
+    const ALL: [Button; 3] = [Button::Close, Button::Maximize, Button::Minimize];
+}
+
+/// What a click on the bar asks of the window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Action {
+    Drag,
+    Minimize,
+    ToggleMaximized,
+    Close,
+}
+
+/// The bar's state between frames. Sizes passed in are the window's, in physical pixels, and
+/// `scale` is its scale factor.
+pub(crate) struct TitleBar {
+    /// In window pixels from the top-left corner, whatever the origin of rects.
+    cursor: [f32; 2],
+    hovered: Option<Button>,
+    title: String,
+    text: TextNode,
+    changed: bool,
+}
+
+impl TitleBar {
+    pub fn new(font_system: &mut FontSystem, title: &str) -> Self {
+        Self {
+            cursor: [f32::NEG_INFINITY; 2],
+            hovered: None,
+            title: title.to_string(),
+            text: TextNode::new(
+                font_system,
+                title,
+                Metrics::new(FONT_SIZE, HEIGHT),
+                f32::MAX,
+            ),
+            changed: false,
+        }
+    }
+
+    /// Follows the cursor and turns clicks on the bar into actions. Returns whether `event`
+    /// was used, with the action if it asks for one.
+    pub fn on_event(
+        &mut self,
+        event: &WindowEvent,
+        size: [f32; 2],
+        scale: f32,
+    ) -> (bool, Option<Action>) {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor = [position.x as f32, position.y as f32];
+                let hovered = self.button_at(size, scale);
+                if hovered != self.hovered {
+                    self.hovered = hovered;
+                    self.changed = true;
+                }
+                (self.cursor[1] < HEIGHT * scale, None)
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.cursor = [f32::NEG_INFINITY; 2];
+                self.changed |= self.hovered.take().is_some();
+                (false, None)
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } if self.cursor[1] < HEIGHT * scale => {
+                let action = match self.button_at(size, scale) {
+                    Some(Button::Close) => Action::Close,
+                    Some(Button::Maximize) => Action::ToggleMaximized,
+                    Some(Button::Minimize) => Action::Minimize,
+                    None => Action::Drag,
+                };
+                (true, Some(action))
+            }
+            _ => (false, None),
+        }
+    }
+
+    /// Whether the bar has to be drawn again since the last call.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// Shows `window`'s title, when it has changed.
+    pub fn set_title(&mut self, font_system: &mut FontSystem, window: &Window) {
+        let title = window.title();
+        if title != self.title {
+            self.text.set_text(font_system, &title);
+            self.title = title;
+            self.changed = true;
+        }
+    }
+
+    /// The bar, the hovered button's highlight and the buttons' glyphs, in window pixels from
+    /// the top-left corner.
+    pub fn rects(&self, size: [f32; 2], scale: f32, theme: &Theme) -> Vec<Rect> {
+        let height = HEIGHT * scale;
+        let glyph_color = theme.foreground;
+        let mut rects = vec![Rect::solid(
+            [size[0] / 2.0, height / 2.0],
+            [size[0], height],
+            theme.bar,
+        )];
+        for (i, button) in Button::ALL.into_iter().enumerate() {
+            let center = button_center(i, size, scale);
+            if self.hovered == Some(button) {
+                let color = if button == Button::Close {
+                    theme.danger
+                } else {
+                    theme.hover
+                };
+                rects.push(Rect::solid(center, [BUTTON_WIDTH * scale, height], color));
+            }
+            let glyph = GLYPH_SIZE * scale;
+            let line = scale.round().max(1.0);
+            match button {
+                Button::Close => rects.extend([1.0, -1.0].map(|turn| Rect {
+                    rotation: turn * std::f32::consts::FRAC_PI_4,
+                    ..Rect::solid(center, [glyph * 1.4, line], glyph_color)
+                })),
+                Button::Maximize => rects.push(Rect {
+                    fill: None,
+                    stroke: Some(Stroke {
+                        color: [glyph_color[0], glyph_color[1], glyph_color[2]],
+                        width: line,
+                    }),
+                    ..Rect::solid(center, [glyph; 2], glyph_color)
+                }),
+                Button::Minimize => rects.push(Rect::solid(center, [glyph, line], glyph_color)),
+            }
+        }
+        // Hard edges keep the one-pixel glyph lines crisp.
+        for rect in &mut rects {
+            rect.softness = 0.0;
+        }
+        rects
+    }
+
+    /// The title, cut off before the buttons.
+    pub fn text_area(&self, size: [f32; 2], scale: f32, theme: &Theme) -> TextArea<'_> {
+        let buttons = Button::ALL.len() as f32 * BUTTON_WIDTH * scale;
+        TextArea {
+            buffer: self.text.buffer(),
+            left: (PADDING * scale).round(),
+            top: 0.0,
+            scale,
+            bounds: TextBounds {
+                left: 0,
+                top: 0,
+                right: (size[0] - buttons).max(0.0) as i32,
+                bottom: (HEIGHT * scale) as i32,
+            },
+            default_color: theme.text_color(),
+        }
+    }
+
+    fn button_at(&self, size: [f32; 2], scale: f32) -> Option<Button> {
+        if !(0.0..HEIGHT * scale).contains(&self.cursor[1]) {
+            return None;
+        }
+        let from_right = (size[0] - self.cursor[0]) / (BUTTON_WIDTH * scale);
+        Button::ALL.get(from_right.max(0.0) as usize).copied()
+    }
+}
+
+/// The middle of the `i`th button from the right.
+fn button_center(i: usize, size: [f32; 2], scale: f32) -> [f32; 2] {
+    [
+        size[0] - (i as f32 + 0.5) * BUTTON_WIDTH * scale,
+        HEIGHT * scale / 2.0,
+    ]
+}
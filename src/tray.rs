@@ -0,0 +1,162 @@
+//! A system tray icon with a menu, for utility apps that live in the tray rather than on the
+//! taskbar.
+//!
+//! Clicks on the icon and its menu items reach the frame callback as `Frame::tray_events`.
+//! With `TrayConfig::minimize_to_tray`, minimizing or closing the window hides it instead,
+//! clicking the icon brings it back and a Quit item at the end of the menu exits.
+//!
+//! The tray is shown on Windows and macOS. Linux trays need a GTK main loop, which the winit
+//! event loop doesn't run, so there the tray is skipped with a warning.
+
+use winit::window::Window;
+
+/// The id of the Quit item `minimize_to_tray` appends to the menu.
+#[cfg(any(windows, target_os = "macos"))]
+const QUIT_ID: &str = "wgpu_test.tray.quit";
+
+/// The tray icon and menu to create when the window opens; see `RendererConfig::tray`.
+#[derive(Clone, Debug, Default)]
+pub struct TrayConfig {
+    /// Straight alpha RGBA pixels, row by row.
+    pub icon: Vec<u8>,
+    pub icon_size: [u32; 2],
+    /// Shown when hovering the icon.
+    pub tooltip: String,
+    pub items: Vec<TrayItem>,
+    /// Hides the window when it is minimized or closed instead of exiting. Off by default.
+    pub minimize_to_tray: bool,
+}
+
+/// An entry in the tray menu.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TrayItem {
+    /// Reported as `TrayEvent::Item(id)` when chosen.
+    Item {
+        id: String,
+        label: String,
+    },
+    Separator,
+}
+
+/// What the user did with the tray since the previous frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TrayEvent {
+    /// The icon itself was clicked.
+    Clicked,
+    /// The menu item with this id was chosen.
+    Item(String),
+}
+
+/// What the event loop should do after polling the tray.
+#[derive(Default)]
+pub(crate) struct Polled {
+    pub events: Vec<TrayEvent>,
+    pub quit: bool,
+}
+
+/// The live tray icon. Removed from the tray when dropped.
+pub(crate) struct Tray {
+    #[cfg(any(windows, target_os = "macos"))]
+    _icon: tray_icon::TrayIcon,
+    minimize_to_tray: bool,
+}
+
+impl Tray {
+    /// Creates the tray icon, or logs why it can't. macOS only shows icons created once the
+    /// event loop runs.
+    pub(crate) fn new(config: &TrayConfig) -> Option<Self> {
+        #[cfg(any(windows, target_os = "macos"))]
+        {
+            match platform::build(config) {
+                Ok(icon) => Some(Self {
+                    _icon: icon,
+                    minimize_to_tray: config.minimize_to_tray,
+                }),
+                Err(error) => {
+                    log::warn!("The tray icon isn't available: {error}");
+                    None
+                }
+            }
+        }
+        #[cfg(not(any(windows, target_os = "macos")))]
+        {
+            let _ = config;
+            log::warn!("The tray icon isn't supported on this platform");
+            None
+        }
+    }
+
+    pub(crate) fn minimize_to_tray(&self) -> bool {
+        self.minimize_to_tray
+    }
+
+    /// Collects the clicks since the last call, showing the window again when the icon was
+    /// clicked while it hid in the tray. `menu_events` are the ids of the menu items chosen.
+    pub(crate) fn poll(&self, window: &Window, menu_events: Vec<String>) -> Polled {
+        #[cfg(any(windows, target_os = "macos"))]
+        {
+            use tray_icon::{ClickType, TrayIconEvent};
+
+            let mut polled = Polled::default();
+            for event in TrayIconEvent::receiver().try_iter() {
+                if event.click_type != ClickType::Left {
+                    continue;
+                }
+                if self.minimize_to_tray {
+                    window.set_minimized(false);
+                    window.set_visible(true);
+                    window.focus_window();
+                }
+                polled.events.push(TrayEvent::Clicked);
+            }
+            for id in menu_events {
+                if self.minimize_to_tray && id == QUIT_ID {
+                    polled.quit = true;
+                } else {
+                    polled.events.push(TrayEvent::Item(id));
+                }
+            }
+            polled
+        }
+        #[cfg(not(any(windows, target_os = "macos")))]
+        {
+            let _ = (window, menu_events);
+            Polled::default()
+        }
+    }
+}
+
+#[cfg(any(windows, target_os = "macos"))]
+mod platform {
+    use tray_icon::{
+        menu::{Menu, MenuItem, PredefinedMenuItem},
+        Icon, TrayIcon, TrayIconBuilder,
+    };
+
+    use super::{TrayConfig, TrayItem, QUIT_ID};
+
+    pub(super) fn build(config: &TrayConfig) -> Result<TrayIcon, Box<dyn std::error::Error>> {
+        let menu = Menu::new();
+        for item in &config.items {
+            match item {
+                TrayItem::Item { id, label } => {
+                    menu.append(&MenuItem::with_id(id.as_str(), label, true, None))?
+                }
+                TrayItem::Separator => menu.append(&PredefinedMenuItem::separator())?,
+            }
+        }
+        if config.minimize_to_tray {
+            if !config.items.is_empty() {
+                menu.append(&PredefinedMenuItem::separator())?;
+            }
+            menu.append(&MenuItem::with_id(QUIT_ID, "Quit", true, None))?;
+        }
+        let [width, height] = config.icon_size;
+        let icon = Icon::from_rgba(config.icon.clone(), width, height)?;
+        Ok(TrayIconBuilder::new()
+            .with_menu(Box::new(menu))
+            .with_tooltip(&config.tooltip)
+            .with_icon(icon)
+            .build()?)
+    }
+}
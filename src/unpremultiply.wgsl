@@ -0,0 +1,22 @@
+// Copies the premultiplied frame to the surface with straight alpha, for
+// CompositeAlphaMode::PostMultiplied.
+
+@group(0) @binding(0)
+var frame: texture_2d<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+	// One triangle that covers the whole screen.
+	let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+	return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+	let color = textureLoad(frame, vec2<i32>(floor(position.xy)), 0);
+	// Fully transparent pixels have no color left to recover.
+	if (color.a <= 0.0) {
+		return vec4<f32>(0.0);
+	}
+	return vec4<f32>(color.rgb / color.a, color.a);
+}
@@ -0,0 +1,257 @@
+use crate::{
+    line::{LineCap, LineJoin, Polyline},
+    rect::Rect,
+};
+
+/// A stream of values supplied by the host, such as FFT magnitude bins or raw audio samples.
+pub trait SignalSource {
+    /// Replaces the contents of `out` with the latest values.
+    fn read(&mut self, out: &mut Vec<f32>);
+}
+
+impl<F: FnMut(&mut Vec<f32>)> SignalSource for F {
+    fn read(&mut self, out: &mut Vec<f32>) {
+        self(out)
+    }
+}
+
+/// Eases values towards new readings: quickly on the way up, slowly on the way down.
+pub struct Smoother {
+    /// Fraction of the distance covered per update when a value rises, in `0..=1`.
+    pub attack: f32,
+    /// Fraction of the distance covered per update when a value falls, in `0..=1`.
+    pub decay: f32,
+    values: Vec<f32>,
+    /// The last reading from a `SignalSource`, kept for its allocation.
+    input: Vec<f32>,
+}
+
+impl Smoother {
+    pub fn new(attack: f32, decay: f32) -> Self {
+        Self {
+            attack,
+            decay,
+            values: vec![],
+            input: vec![],
+        }
+    }
+
+    /// Reads the latest values from `source` and eases towards them, like `update`.
+    pub fn read(&mut self, source: &mut impl SignalSource) -> &[f32] {
+        let mut input = std::mem::take(&mut self.input);
+        source.read(&mut input);
+        self.update(&input);
+        self.input = input;
+        &self.values
+    }
+
+    pub fn update(&mut self, input: &[f32]) -> &[f32] {
+        self.values.resize(input.len(), 0.0);
+        for (value, &target) in self.values.iter_mut().zip(input) {
+            let rate = if target > *value {
+                self.attack
+            } else {
+                self.decay
+            };
+            *value += (target - *value) * rate;
+        }
+        &self.values
+    }
+}
+
+/// Vertical bars growing up from the bottom edge, one per value in `0..=1`.
+pub struct Bars {
+    /// Top-left corner of the area.
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    pub gap: f32,
+    pub color: [f32; 4],
+}
+
+impl Bars {
+    pub fn rects(&self, values: &[f32]) -> Vec<Rect> {
+        if values.is_empty() {
+            return vec![];
+        }
+        let slot = self.size[0] / values.len() as f32;
+        let width = (slot - self.gap).max(1.0);
+        let bottom = self.position[1] + self.size[1];
+
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                let height = value.clamp(0.0, 1.0) * self.size[1];
+                Rect::solid(
+                    [
+                        self.position[0] + slot * (i as f32 + 0.5),
+                        bottom - height / 2.0,
+                    ],
+                    [width, height],
+                    self.color,
+                )
+            })
+            .collect()
+    }
+}
+
+/// A line through samples in `-1..=1`, centered vertically in the area.
+pub struct Waveform {
+    /// Top-left corner of the area.
+    pub position: [f32; 2],
+    pub size: [f32; 2],
+    /// In pixels, whatever the zoom.
+    pub width: f32,
+    pub color: [f32; 4],
+}
+
+impl Waveform {
+    /// The samples spread evenly across the area, for a custom node; see
+    /// `Frame::draw_custom`. Fewer than two samples draw nothing.
+    pub fn line(&self, samples: &[f32]) -> Polyline {
+        let step = self.size[0] as f64 / (samples.len().max(2) - 1) as f64;
+        let middle = (self.position[1] + self.size[1] / 2.0) as f64;
+        let points = if samples.len() < 2 {
+            vec![]
+        } else {
+            samples
+                .iter()
+                .enumerate()
+                .map(|(i, sample)| {
+                    [
+                        self.position[0] as f64 + step * i as f64,
+                        middle - (sample.clamp(-1.0, 1.0) * self.size[1] / 2.0) as f64,
+                    ]
+                })
+                .collect()
+        };
+        Polyline {
+            points,
+            width: self.width,
+            color: self.color,
+            join: LineJoin::Round,
+            ..Default::default()
+        }
+    }
+}
+
+/// Bins in `0..=1` drawn as rays around a circle, with round ends.
+pub struct RadialSpectrum {
+    pub center: [f32; 2],
+    pub inner_radius: f32,
+    pub max_length: f32,
+    /// In pixels, whatever the zoom.
+    pub width: f32,
+    pub color: [f32; 4],
+}
+
+impl RadialSpectrum {
+    /// One ray per value, for a custom node; see `Frame::draw_custom`. Rays of empty bins
+    /// have no length and draw nothing.
+    pub fn lines(&self, values: &[f32]) -> Vec<Polyline> {
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                let angle = i as f64 / values.len() as f64 * std::f64::consts::TAU;
+                let direction = [angle.cos(), angle.sin()];
+                let start = self.inner_radius as f64;
+                let end = start + (value.clamp(0.0, 1.0) * self.max_length) as f64;
+                let at = |distance: f64| {
+                    [0, 1].map(|axis| self.center[axis] as f64 + direction[axis] * distance)
+                };
+                Polyline {
+                    cap: LineCap::Round,
+                    ..Polyline::line(at(start), at(end), self.width, self.color)
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smoother_rises_fast_and_falls_slowly() {
+        let mut smoother = Smoother::new(0.5, 0.25);
+        assert_eq!(smoother.update(&[1.0, 0.0]), [0.5, 0.0]);
+        assert_eq!(smoother.update(&[1.0, 0.0]), [0.75, 0.0]);
+        assert_eq!(smoother.update(&[0.0]), [0.5625]);
+    }
+
+    #[test]
+    fn smoother_reads_from_a_source() {
+        let mut readings = vec![vec![1.0, 1.0], vec![0.0, 1.0, 1.0]].into_iter();
+        let mut source = |out: &mut Vec<f32>| *out = readings.next().unwrap();
+        let mut smoother = Smoother::new(1.0, 0.5);
+        assert_eq!(smoother.read(&mut source), [1.0, 1.0]);
+        assert_eq!(smoother.read(&mut source), [0.5, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn bars_grow_up_from_the_bottom() {
+        let bars = Bars {
+            position: [10.0, 20.0],
+            size: [40.0, 100.0],
+            gap: 2.0,
+            color: [1.0; 4],
+        };
+        let rects = bars.rects(&[0.5, 2.0]);
+        assert_eq!(rects[0].position, [20.0, 95.0]);
+        assert_eq!(rects[0].size, [18.0, 50.0]);
+        assert_eq!(rects[1].position, [40.0, 70.0]);
+        assert_eq!(rects[1].size, [18.0, 100.0]);
+        assert!(bars.rects(&[]).is_empty());
+    }
+
+    #[test]
+    fn waveform_spans_the_area() {
+        let waveform = Waveform {
+            position: [10.0, 20.0],
+            size: [100.0, 40.0],
+            width: 2.0,
+            color: [1.0; 4],
+        };
+        let line = waveform.line(&[0.0, 1.0, -1.0, 0.5, -3.0]);
+        assert_eq!(
+            line.points,
+            [
+                [10.0, 40.0],
+                [35.0, 20.0],
+                [60.0, 60.0],
+                [85.0, 30.0],
+                [110.0, 60.0]
+            ]
+        );
+        assert_eq!(line.width, 2.0);
+        assert!(waveform.line(&[0.5]).points.is_empty());
+    }
+
+    #[test]
+    fn spectrum_rays_point_outwards() {
+        let spectrum = RadialSpectrum {
+            center: [100.0, 100.0],
+            inner_radius: 10.0,
+            max_length: 20.0,
+            width: 3.0,
+            color: [1.0; 4],
+        };
+        let lines = spectrum.lines(&[1.0, 0.5, 0.0, 0.25]);
+        let ends: Vec<[[i64; 2]; 2]> = lines
+            .iter()
+            .map(|line| [0, 1].map(|i| line.points[i].map(|v| v.round() as i64)))
+            .collect();
+        assert_eq!(
+            ends,
+            [
+                [[110, 100], [130, 100]],
+                [[100, 110], [100, 120]],
+                [[90, 100], [90, 100]],
+                [[100, 90], [100, 85]],
+            ]
+        );
+        assert!(lines.iter().all(|line| line.cap == LineCap::Round));
+    }
+}
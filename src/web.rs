@@ -0,0 +1,64 @@
+//! Running in a web page, on WebGL2. The window is a canvas, either one already in the page or
+//! one added to its body, and the renderer is started with `spawn` rather than awaited, since
+//! the browser's own event loop can't be blocked.
+//!
+//! Pages have no system fonts, so text only shows with the fonts of
+//! `RendererConfig::deterministic`. Scatter plots need compute shaders and aren't drawn.
+
+use wasm_bindgen::JsCast;
+use web_sys::HtmlCanvasElement;
+use winit::{platform::web::WindowExtWebSys, window::Window};
+
+use crate::{run_with_frame, Frame, RendererConfig, Scene};
+
+/// Starts drawing `scene` in the page, calling `frame` as `run_with_frame` does. Errors
+/// setting up the renderer are logged to the browser's console, where panics go too.
+pub fn spawn(
+    renderer_config: RendererConfig,
+    scene: Scene,
+    frame: impl FnMut(&mut Frame) + 'static,
+) {
+    wasm_bindgen_futures::spawn_local(async move {
+        if let Err(e) = run_with_frame(renderer_config, scene, frame).await {
+            log::error!("{}", e);
+        }
+    });
+}
+
+/// Sends log records and panics to the browser's console.
+pub(crate) fn init_logging() {
+    console_error_panic_hook::set_once();
+    // Fails when a logger was set already, which then keeps getting the records.
+    let _ = console_log::init_with_level(log::Level::Info);
+}
+
+/// The canvas in the page with the id `id`.
+pub(crate) fn find_canvas(id: &str) -> Option<HtmlCanvasElement> {
+    let canvas = web_sys::window()?
+        .document()?
+        .get_element_by_id(id)
+        .and_then(|element| element.dyn_into().ok());
+    if canvas.is_none() {
+        log::warn!("no canvas with the id {:?} in the page, adding one", id);
+    }
+    canvas
+}
+
+/// Adds the window's canvas to the page's body, unless it's in the page already.
+pub(crate) fn attach(window: &Window) {
+    let canvas = window.canvas();
+    if canvas.is_connected() {
+        return;
+    }
+    match web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.body())
+    {
+        Some(body) => {
+            if body.append_child(&canvas).is_err() {
+                log::error!("failed to add the canvas to the page");
+            }
+        }
+        None => log::error!("the page has no body to add the canvas to"),
+    }
+}
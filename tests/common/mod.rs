@@ -0,0 +1,30 @@
+use wgpu_test::{
+    capture::Screenshot, Deterministic, Headless, RendererConfig, RendererError, Scene,
+};
+
+/// A renderer for `scene` that leaves out what varies between machines, or `None` where there
+/// is no GPU adapter to render with, in which case the test passes without checking anything.
+pub async fn headless(
+    renderer_config: RendererConfig,
+    scene: Scene,
+    size: [u32; 2],
+) -> Option<Headless> {
+    let renderer_config = RendererConfig {
+        deterministic: Some(Deterministic::new(vec![])),
+        ..renderer_config
+    };
+    match Headless::new(renderer_config, scene, size).await {
+        Ok(renderer) => Some(renderer),
+        Err(RendererError::NoAdapter) => {
+            eprintln!("No graphics adapter, skipping");
+            None
+        }
+        Err(e) => panic!("setting up the renderer: {:?}", e),
+    }
+}
+
+/// The RGBA pixel at `[x, y]`, counted from the top-left.
+pub fn pixel(screenshot: &Screenshot, [x, y]: [u32; 2]) -> [u8; 4] {
+    let i = (y * screenshot.width + x) as usize * 4;
+    screenshot.pixels[i..i + 4].try_into().unwrap()
+}
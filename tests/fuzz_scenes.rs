@@ -0,0 +1,83 @@
+mod common;
+
+use wgpu_test::{
+    fuzz::{check_geometry, SceneFuzzer},
+    rect::{build_geometry, Rect},
+    Camera, Origin, RendererConfig, Scene,
+};
+
+const SEEDS: u64 = 1_000;
+const RENDERED_SEEDS: u64 = 16;
+const SIZE: [u32; 2] = [200, 150];
+const BACKGROUND: [u8; 4] = [0, 0, 0, 255];
+
+fn scene_rects(seed: u64, max_count: usize) -> Vec<Rect> {
+    let count = 1 + (seed as usize * 7919) % max_count;
+    SceneFuzzer::new(seed, SIZE[0] as f32, SIZE[1] as f32).rects(count)
+}
+
+/// Whether the pixel whose center is `point` is far enough from `rect` that nothing of it,
+/// its stroke or its soft edge can land there, whatever its rotation.
+fn out_of_reach(rect: &Rect, point: [f32; 2]) -> bool {
+    let reach = rect.size[0].hypot(rect.size[1]) / 2.0
+        + rect.stroke.map_or(0.0, |stroke| stroke.width)
+        + rect.softness
+        + 2.0;
+    let distance = (point[0] - rect.position[0]).hypot(point[1] - rect.position[1]);
+    distance > reach
+}
+
+#[test]
+fn generated_geometry_is_well_formed() {
+    for seed in 0..SEEDS {
+        let rects = scene_rects(seed, 500);
+        let geometry = build_geometry(&rects, &Camera::default());
+        if let Err(e) = check_geometry(&geometry) {
+            panic!("seed {} ({} rects): {}", seed, rects.len(), e);
+        }
+    }
+}
+
+/// Garbage from NaNs or stray vertices shows up outside the rects, where only the background
+/// may be, or as frames that differ between two renders of the same scene.
+#[tokio::test]
+async fn rendered_scenes_stay_inside_their_rects() {
+    let renderer_config = RendererConfig {
+        origin: Origin::TopLeft,
+        background: [0.0, 0.0, 0.0, 1.0],
+        ..Default::default()
+    };
+    for seed in 0..RENDERED_SEEDS {
+        let rects = scene_rects(seed, 40);
+        let scene = Scene {
+            rects: rects.clone(),
+            ..Default::default()
+        };
+        let Some(mut renderer) = common::headless(renderer_config.clone(), scene, SIZE).await
+        else {
+            return;
+        };
+        let frame = renderer.render().expect("rendering offscreen");
+        assert_eq!(
+            renderer.render().expect("rendering offscreen").pixels,
+            frame.pixels,
+            "seed {} renders differently twice",
+            seed
+        );
+
+        for y in 0..SIZE[1] {
+            for x in 0..SIZE[0] {
+                let center = [x as f32 + 0.5, y as f32 + 0.5];
+                if rects.iter().all(|rect| out_of_reach(rect, center)) {
+                    assert_eq!(
+                        common::pixel(&frame, [x, y]),
+                        BACKGROUND,
+                        "seed {} at {:?}",
+                        seed,
+                        [x, y]
+                    );
+                }
+            }
+        }
+    }
+}
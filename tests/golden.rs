@@ -0,0 +1,42 @@
+mod common;
+
+use wgpu_test::{golden::Golden, rect::Rect, Origin, RendererConfig, Scene};
+
+const SIZE: u32 = 64;
+
+/// Overlapping squares with hard edges on whole pixels and channels of 0 or 1, which every GPU
+/// draws to the same bytes. The yellow one is listed first but raised over the blue one.
+fn scene() -> Scene {
+    let square = |position, size, color| Rect {
+        softness: 0.0,
+        ..Rect::solid(position, size, color)
+    };
+    Scene {
+        rects: vec![
+            Rect {
+                z_index: 1.0,
+                ..square([20.0, 48.0], [16.0, 16.0], [1.0, 1.0, 0.0, 1.0])
+            },
+            square([16.0, 16.0], [24.0, 24.0], [1.0, 0.0, 0.0, 1.0]),
+            square([44.0, 20.0], [24.0, 32.0], [0.0, 1.0, 0.0, 1.0]),
+            square([32.0, 48.0], [48.0, 16.0], [0.0, 0.0, 1.0, 1.0]),
+            square([48.0, 48.0], [16.0, 16.0], [1.0, 1.0, 1.0, 1.0]),
+        ],
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn solid_rects_match_their_reference() {
+    let renderer_config = RendererConfig {
+        origin: Origin::TopLeft,
+        background: [0.0, 0.0, 0.0, 1.0],
+        ..Default::default()
+    };
+    let Some(mut renderer) = common::headless(renderer_config, scene(), [SIZE, SIZE]).await else {
+        return;
+    };
+    Golden::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden"))
+        .check_render("solid_rects", &mut renderer)
+        .unwrap();
+}
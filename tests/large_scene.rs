@@ -0,0 +1,68 @@
+mod common;
+
+use wgpu_test::{
+    fuzz::check_geometry,
+    rect::{build_geometry, Fill, Rect},
+    Camera, Origin, RendererConfig, Scene,
+};
+
+/// Well past the 16,384 quads that u16 indices could address.
+const COUNT: usize = 100_000;
+const COLUMNS: usize = 400;
+const SIZE: [u32; 2] = [COLUMNS as u32 * 4, (COUNT / COLUMNS) as u32 * 4];
+
+const RED: [u8; 4] = [255, 0, 0, 255];
+const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+/// A grid of small gray squares, the first one red and the last one blue.
+fn rects() -> Vec<Rect> {
+    (0..COUNT)
+        .map(|i| {
+            let (column, row) = ((i % COLUMNS) as f32, (i / COLUMNS) as f32);
+            let color = match i {
+                0 => [1.0, 0.0, 0.0, 1.0],
+                i if i == COUNT - 1 => [0.0, 0.0, 1.0, 1.0],
+                _ => [0.5, 0.5, 0.5, 1.0],
+            };
+            Rect {
+                position: [column * 4.0 + 2.0, row * 4.0 + 2.0],
+                size: [3.0, 3.0],
+                border_radius: [0.5; 4],
+                fill: Some(Fill::Solid { color }),
+                softness: 0.0,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn every_quad_lands_on_its_rect() {
+    let rects = rects();
+    let geometry = build_geometry(&rects, &Camera::default());
+    check_geometry(&geometry).unwrap();
+    assert_eq!(geometry.instances.len(), COUNT);
+    // Wrapped indices used to move quads onto other rects.
+    for (i, (rect, instance)) in rects.iter().zip(&geometry.instances).enumerate() {
+        assert_eq!(instance.rect_pos(), rect.position, "instance {}", i);
+    }
+}
+
+#[tokio::test]
+async fn renders_the_first_and_last_rect() {
+    let renderer_config = RendererConfig {
+        origin: Origin::TopLeft,
+        background: [0.0, 0.0, 0.0, 1.0],
+        ..Default::default()
+    };
+    let scene = Scene {
+        rects: rects(),
+        ..Default::default()
+    };
+    let Some(mut renderer) = common::headless(renderer_config, scene, SIZE).await else {
+        return;
+    };
+    let frame = renderer.render().expect("rendering offscreen");
+    assert_eq!(common::pixel(&frame, [2, 2]), RED);
+    assert_eq!(common::pixel(&frame, [SIZE[0] - 2, SIZE[1] - 2]), BLUE);
+}
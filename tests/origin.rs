@@ -0,0 +1,54 @@
+mod common;
+
+use wgpu_test::{
+    rect::{Fill, Rect},
+    Origin, RendererConfig, Scene,
+};
+
+const SIZE: u32 = 64;
+const WHITE: [u8; 4] = [255; 4];
+const BLACK: [u8; 4] = [0, 0, 0, 255];
+
+/// Renders a white square centered on `(0, 0)`, so only the quarter of it in the corner at the
+/// origin is on screen, and returns the top-left and bottom-left pixels.
+async fn corners(origin: Origin) -> Option<[[u8; 4]; 2]> {
+    let square = Rect {
+        size: [32.0, 32.0],
+        fill: Some(Fill::Solid {
+            color: [1.0, 1.0, 1.0, 1.0],
+        }),
+        softness: 0.0,
+        ..Default::default()
+    };
+    let renderer_config = RendererConfig {
+        origin,
+        background: [0.0, 0.0, 0.0, 1.0],
+        ..Default::default()
+    };
+    let scene = Scene {
+        rects: vec![square],
+        ..Default::default()
+    };
+    let mut renderer = common::headless(renderer_config, scene, [SIZE, SIZE]).await?;
+    let frame = renderer.render().expect("rendering offscreen");
+    Some([
+        common::pixel(&frame, [4, 4]),
+        common::pixel(&frame, [4, SIZE - 5]),
+    ])
+}
+
+#[tokio::test]
+async fn top_left_origin_is_the_top_left_corner() {
+    if let Some([top_left, bottom_left]) = corners(Origin::TopLeft).await {
+        assert_eq!(top_left, WHITE);
+        assert_eq!(bottom_left, BLACK);
+    }
+}
+
+#[tokio::test]
+async fn bottom_left_origin_is_the_bottom_left_corner() {
+    if let Some([top_left, bottom_left]) = corners(Origin::BottomLeft).await {
+        assert_eq!(top_left, BLACK);
+        assert_eq!(bottom_left, WHITE);
+    }
+}
@@ -0,0 +1,17 @@
+mod common;
+
+use wgpu_test::{RendererConfig, Scene};
+
+#[tokio::test]
+async fn gpu_latency_is_reported_once_a_frame_finishes() {
+    let Some(mut renderer) =
+        common::headless(RendererConfig::default(), Scene::default(), [16, 16]).await
+    else {
+        return;
+    };
+    assert_eq!(renderer.stats().gpu_latency, None);
+    // Reading the first frame back waits for the GPU, so the second is submitted after it.
+    renderer.render().expect("rendering offscreen");
+    renderer.render().expect("rendering offscreen");
+    assert!(renderer.stats().gpu_latency.is_some());
+}